@@ -123,6 +123,10 @@ clarity_serializable!(u64);
 clarity_serializable!(Contract);
 clarity_serializable!(ContractAnalysis);
 
+/// An account's STX balance, including any PoX lock.
+/// The serde form (an externally-tagged enum, e.g. `{"LockedPoxFour":{"amount_unlocked":..,
+/// "amount_locked":..,"unlock_height":..}}`) is stable, so that wallets can cache a snapshot
+/// and recompute the available balance at a later burn block height offline.
 #[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
 pub enum STXBalance {
     Unlocked {
@@ -1236,6 +1240,20 @@ impl STXBalance {
         }
     }
 
+    /// Project this balance's (unlocked, locked) split at `burn_height`, without canonicalizing
+    /// it: if the lock has expired by then (i.e., `unlock_height() <= burn_height`), the locked
+    /// amount is counted as unlocked. Uses the datastructure's lazy unlock height, so the early
+    /// unlocks of PoX-1, PoX-2 and PoX-3 locks (see `effective_unlock_height`) are not applied.
+    pub fn effective_at(&self, burn_height: u64) -> (u128, u128) {
+        let amount_locked = self.amount_locked();
+        if amount_locked > 0 && self.unlock_height() <= burn_height {
+            // cannot overflow: the total supply of STX fits in a u128
+            (self.amount_unlocked().saturating_add(amount_locked), 0)
+        } else {
+            (self.amount_unlocked(), amount_locked)
+        }
+    }
+
     pub fn get_total_balance(&self) -> Result<u128> {
         let (unlocked, locked) = match self {
             STXBalance::Unlocked { amount } => (*amount, 0),
@@ -1450,3 +1468,84 @@ impl STXBalance {
         )? >= amount)
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn stx_balance_serde_round_trip_unlocked() {
+        let balance = STXBalance::Unlocked { amount: 12345 };
+        let json = serde_json::to_string(&balance).unwrap();
+        assert_eq!(json, r#"{"Unlocked":{"amount":12345}}"#);
+
+        let decoded: STXBalance = serde_json::from_str(&json).unwrap();
+        assert_eq!(decoded, balance);
+        assert_eq!(
+            decoded
+                .get_available_balance_at_burn_block(0, 0, 0, 0)
+                .unwrap(),
+            12345
+        );
+    }
+
+    #[test]
+    fn stx_balance_serde_round_trip_locked() {
+        // more than u64::MAX, so the amounts can't survive by accident
+        let amount_locked = u128::from(u64::MAX) + 1;
+        let balance = STXBalance::LockedPoxFour {
+            amount_unlocked: 100,
+            amount_locked,
+            unlock_height: 2000,
+        };
+        let json = serde_json::to_string(&balance).unwrap();
+        assert_eq!(
+            json,
+            r#"{"LockedPoxFour":{"amount_unlocked":100,"amount_locked":18446744073709551616,"unlock_height":2000}}"#
+        );
+
+        let decoded: STXBalance = serde_json::from_str(&json).unwrap();
+        assert_eq!(decoded, balance);
+        assert_eq!(decoded.unlock_height(), 2000);
+        assert_eq!(decoded.amount_locked(), amount_locked);
+        assert_eq!(decoded.amount_unlocked(), 100);
+
+        // the lock still applies before the unlock height, and not after it
+        assert_eq!(
+            decoded
+                .get_available_balance_at_burn_block(1999, 0, 0, 0)
+                .unwrap(),
+            100
+        );
+        assert_eq!(
+            decoded
+                .get_available_balance_at_burn_block(2000, 0, 0, 0)
+                .unwrap(),
+            amount_locked + 100
+        );
+    }
+
+    #[test]
+    fn stx_balance_effective_at_unlock_height() {
+        let balance = STXBalance::LockedPoxFour {
+            amount_unlocked: 100,
+            amount_locked: 5000,
+            unlock_height: 2000,
+        };
+        assert_eq!(balance.effective_at(1999), (100, 5000));
+        assert_eq!(balance.effective_at(2000), (5100, 0));
+        assert_eq!(balance.effective_at(2001), (5100, 0));
+
+        // the projection does not mutate the balance
+        assert_eq!(balance.unlock_height(), 2000);
+        assert_eq!(balance.amount_locked(), 5000);
+        assert_eq!(balance.amount_unlocked(), 100);
+    }
+
+    #[test]
+    fn stx_balance_effective_at_unlocked() {
+        let balance = STXBalance::Unlocked { amount: 12345 };
+        assert_eq!(balance.effective_at(0), (12345, 0));
+        assert_eq!(balance.effective_at(u64::MAX), (12345, 0));
+    }
+}
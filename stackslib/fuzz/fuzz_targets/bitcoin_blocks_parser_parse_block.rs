@@ -0,0 +1,33 @@
+// Copyright (C) 2025 Stacks Open Internet Foundation
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with this program.  If not, see <http://www.gnu.org/licenses/>.
+
+#![no_main]
+
+use libfuzzer_sys::fuzz_target;
+use stacks::burnchains::bitcoin::blocks::BitcoinBlockParser;
+use stacks::burnchains::bitcoin::BitcoinNetworkType;
+use stacks::burnchains::MagicBytes;
+use stacks::core::StacksEpochId;
+use stacks_common::deps_common::bitcoin::blockdata::block::Block;
+use stacks_common::deps_common::bitcoin::network::serialize::deserialize;
+
+fuzz_target!(|data: &[u8]| {
+    let Ok(block) = deserialize::<Block>(data) else {
+        return;
+    };
+    let parser = BitcoinBlockParser::new(BitcoinNetworkType::Mainnet, MagicBytes::default());
+    let parsed_block = parser.parse_block(&block, 0, StacksEpochId::Epoch31);
+    assert!(parsed_block.txs.len() <= block.txdata.len());
+});
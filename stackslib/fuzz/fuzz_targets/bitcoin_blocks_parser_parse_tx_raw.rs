@@ -0,0 +1,63 @@
+// Copyright (C) 2025 Stacks Open Internet Foundation
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with this program.  If not, see <http://www.gnu.org/licenses/>.
+
+// Input layout:
+//   byte 0     : the parser's network (0 = mainnet, 1 = testnet, 2 = regtest); inputs with any
+//                other value are skipped
+//   bytes 1..3 : the parser's `MagicBytes`
+//   bytes 3..  : the consensus-serialized Bitcoin transaction
+// Letting the fuzzer pick the magic bytes exercises both the magic-mismatch branch and, when
+// they match the tx's OP_RETURN, the rest of the op parsing.
+//
+// Inputs longer than `MAX_INPUT_LENGTH` are skipped. A standard Bitcoin tx is at most 100kB, and
+// a block-commit is a few hundred bytes, so anything bigger only slows each run down without
+// reaching code that real txs can. The seed corpus in
+// `corpus/bitcoin_blocks_parser_parse_tx_raw/` holds real-sized block-commit txs, built from the
+// `LeaderBlockCommitOp` parse fixtures.
+
+#![no_main]
+
+use libfuzzer_sys::fuzz_target;
+use stacks::burnchains::bitcoin::blocks::BitcoinBlockParser;
+use stacks::burnchains::bitcoin::BitcoinNetworkType;
+use stacks::burnchains::{MagicBytes, MAGIC_BYTES_LENGTH};
+use stacks::core::StacksEpochId;
+use stacks_common::deps_common::bitcoin::blockdata::transaction::Transaction;
+use stacks_common::deps_common::bitcoin::network::serialize::deserialize;
+
+/// The network and magic bytes, plus the version and lock time that every tx has
+const MIN_INPUT_LENGTH: usize = 1 + MAGIC_BYTES_LENGTH + 8;
+/// The network and magic bytes, plus the largest tx that Bitcoin Core relays
+/// (`MAX_STANDARD_TX_WEIGHT` / 4)
+const MAX_INPUT_LENGTH: usize = 1 + MAGIC_BYTES_LENGTH + 100_000;
+
+fuzz_target!(|data: &[u8]| {
+    if data.len() < MIN_INPUT_LENGTH || data.len() > MAX_INPUT_LENGTH {
+        return;
+    }
+    let network_id = match data[0] {
+        0 => BitcoinNetworkType::Mainnet,
+        1 => BitcoinNetworkType::Testnet,
+        2 => BitcoinNetworkType::Regtest,
+        _ => return,
+    };
+    let (magic, tx_bytes) = data[1..].split_at(MAGIC_BYTES_LENGTH);
+    let magic_bytes = MagicBytes::from(magic);
+    let Ok(tx) = deserialize::<Transaction>(tx_bytes) else {
+        return;
+    };
+    let parser = BitcoinBlockParser::new(network_id, magic_bytes);
+    let _ = parser.parse_tx(&tx, 0, StacksEpochId::Epoch31);
+});
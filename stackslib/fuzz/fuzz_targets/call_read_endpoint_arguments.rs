@@ -0,0 +1,97 @@
+// Copyright (C) 2025 Stacks Open Internet Foundation
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with this program.  If not, see <http://www.gnu.org/licenses/>.
+
+#![no_main]
+
+use std::net::{IpAddr, Ipv4Addr, SocketAddr};
+
+use libfuzzer_sys::arbitrary::{self, Arbitrary, Unstructured};
+use libfuzzer_sys::fuzz_target;
+use stacks::net::connection::ConnectionOptions;
+use stacks::net::httpcore::{StacksHttp, StacksHttpPreamble};
+use stacks::net::ProtocolFamily;
+use stacks_common::util::hash::to_hex;
+
+/// Senders that parse as principals, so that decoding gets as far as the arguments
+const SENDERS: &[&str] = &[
+    "ST2DS4MSWSGJ3W9FBC6BVT0Y92S345HY8N3T6AV7R",
+    "SP3Q4A5WWZ80REGBN0ZXNE540ECJ9JZ4A765Q5K2Q",
+    "ST2DS4MSWSGJ3W9FBC6BVT0Y92S345HY8N3T6AV7R.hello-world",
+];
+
+/// One entry of the `arguments` array
+#[derive(Debug, Arbitrary)]
+enum FuzzArgument {
+    /// Hex encoding of arbitrary bytes, as a serialized Clarity value would be
+    Hex(Vec<u8>),
+    /// Any string at all, which may not even be hex
+    Raw(String),
+}
+
+impl FuzzArgument {
+    fn into_string(self) -> String {
+        match self {
+            FuzzArgument::Hex(bytes) => to_hex(&bytes),
+            FuzzArgument::Raw(text) => text,
+        }
+    }
+}
+
+#[derive(Debug, Arbitrary)]
+struct CallReadOnlyInput {
+    sender: u8,
+    arguments: Vec<FuzzArgument>,
+}
+
+fuzz_target!(|data: &[u8]| {
+    if std::str::from_utf8(data).is_err() {
+        return;
+    }
+    let Ok(input) = CallReadOnlyInput::arbitrary_take_rest(Unstructured::new(data)) else {
+        return;
+    };
+
+    let sender = SENDERS[usize::from(input.sender) % SENDERS.len()];
+    let arguments: Vec<_> = input
+        .arguments
+        .into_iter()
+        .map(FuzzArgument::into_string)
+        .collect();
+    let body = serde_json::json!({
+        "sender": sender,
+        "arguments": arguments,
+    })
+    .to_string();
+    let request = format!(
+        "POST /v2/contracts/call-read/ST2DS4MSWSGJ3W9FBC6BVT0Y92S345HY8N3T6AV7R/hello-world/ro-test HTTP/1.1\r\n\
+         Host: 127.0.0.1:20443\r\n\
+         Content-Type: application/json\r\n\
+         Content-Length: {}\r\n\
+         \r\n\
+         {body}",
+        body.len()
+    );
+
+    let addr = SocketAddr::new(IpAddr::V4(Ipv4Addr::new(127, 0, 0, 1)), 20443);
+    let mut http = StacksHttp::new(addr, &ConnectionOptions::default());
+    let Ok((preamble, offset)) = http.read_preamble(request.as_bytes()) else {
+        return;
+    };
+    let StacksHttpPreamble::Request(preamble) = preamble else {
+        return;
+    };
+    // errors are fine; panics are not
+    let _ = http.try_parse_request(&preamble, &request.as_bytes()[offset..]);
+});
@@ -81,6 +81,7 @@ pub const ADDRESS_VERSION_TESTNET_MULTISIG: u8 = 196;
 // segwit hrps
 pub const SEGWIT_MAINNET_HRP: &str = "bc";
 pub const SEGWIT_TESTNET_HRP: &str = "tb";
+pub const SEGWIT_REGTEST_HRP: &str = "bcrt";
 
 // segwit witnes versions
 pub const SEGWIT_V0: u8 = 0;
@@ -164,6 +165,15 @@ pub fn segwit_hrp(mainnet: bool) -> &'static str {
     }
 }
 
+/// The bech32 human-readable part of segwit addresses on the given network
+pub fn segwit_network_hrp(network_id: BitcoinNetworkType) -> &'static str {
+    match network_id {
+        BitcoinNetworkType::Mainnet => SEGWIT_MAINNET_HRP,
+        BitcoinNetworkType::Testnet => SEGWIT_TESTNET_HRP,
+        BitcoinNetworkType::Regtest => SEGWIT_REGTEST_HRP,
+    }
+}
+
 impl LegacyBitcoinAddress {
     fn to_versioned_bytes(&self) -> [u8; 21] {
         let mut ret = [0; 21];
@@ -316,6 +326,38 @@ impl SegwitBitcoinAddress {
             None
         }?;
 
+        SegwitBitcoinAddress::from_quintets(s, mainnet, &quintets, variant)
+    }
+
+    /// Decode a bech32 or bech32m segwit address, which must have the human-readable part of
+    /// `network_id` (so `bcrt1...` addresses are only accepted on regtest)
+    pub fn from_bech32_network(
+        network_id: BitcoinNetworkType,
+        s: &str,
+    ) -> Option<SegwitBitcoinAddress> {
+        let (hrp, quintets, variant) = bech32::decode(s)
+            .inspect_err(|_e| {
+                test_debug!("Failed to decode '{s}': {_e:?}");
+            })
+            .ok()?;
+
+        if hrp != segwit_network_hrp(network_id) {
+            test_debug!("Unexpected hrp '{hrp}' for {network_id:?}");
+            return None;
+        }
+
+        let mainnet = network_id == BitcoinNetworkType::Mainnet;
+        SegwitBitcoinAddress::from_quintets(s, mainnet, &quintets, variant)
+    }
+
+    /// Decode the witness version and program of the segwit address `s`, given as bech32
+    /// quintets
+    fn from_quintets(
+        s: &str,
+        mainnet: bool,
+        quintets: &[u5],
+        variant: bech32::Variant,
+    ) -> Option<SegwitBitcoinAddress> {
         if quintets.is_empty() || quintets.len() > 65 {
             test_debug!("Invalid prog length: {}", quintets.len());
             return None;
@@ -14,6 +14,8 @@
 // You should have received a copy of the GNU General Public License
 // along with this program.  If not, see <http://www.gnu.org/licenses/>.
 
+use std::sync::{Arc, Mutex};
+
 use stacks_common::deps_common::bitcoin::blockdata::block::{Block, LoneBlockHeader};
 use stacks_common::deps_common::bitcoin::blockdata::opcodes::All as btc_opcodes;
 use stacks_common::deps_common::bitcoin::blockdata::script::{Instruction, Script};
@@ -59,6 +61,10 @@ impl BurnHeaderIPC for BitcoinHeaderIPC {
     fn header_hash(&self) -> [u8; 32] {
         self.block_header.header.bitcoin_hash().0
     }
+
+    fn parent_header_hash(&self) -> [u8; 32] {
+        self.block_header.header.prev_blockhash.0
+    }
 }
 
 #[derive(Debug, Clone, PartialEq)]
@@ -93,6 +99,66 @@ pub struct BitcoinBlockDownloader {
 pub struct BitcoinBlockParser {
     network_id: BitcoinNetworkType,
     magic_bytes: MagicBytes,
+    unparsed_tx_sampler: Option<UnparsedTxSampler>,
+}
+
+/// Why the parser did not turn a transaction into a burnchain transaction
+#[derive(Debug, Clone, PartialEq)]
+pub enum UnparsedTxReason {
+    /// The tx's OP_RETURN output does not encode a Stacks operation (e.g. it has the wrong magic
+    /// bytes, or is not a single data push)
+    NotStacks(String),
+    /// The tx's OP_RETURN output carries our magic bytes, but the rest of the tx can't be decoded
+    MalformedOp(String),
+}
+
+/// A transaction that the parser skipped, along with why it was skipped
+#[derive(Debug, Clone, PartialEq)]
+pub struct UnparsedTxSample {
+    pub txid: Txid,
+    pub block_height: u64,
+    pub vtxindex: u32,
+    pub reason: UnparsedTxReason,
+}
+
+/// Collects up to `cap` samples of transactions the parser skipped. Clones share the same
+/// samples, so every parser thread of a sync can record into it, and the samples can be read
+/// back once the sync is done.
+///
+/// Only txs whose first output is an OP_RETURN are sampled; almost every Bitcoin tx has no
+/// OP_RETURN at all, and those would just crowd out the interesting ones.
+#[derive(Debug, Clone)]
+pub struct UnparsedTxSampler {
+    cap: usize,
+    samples: Arc<Mutex<Vec<UnparsedTxSample>>>,
+}
+
+impl UnparsedTxSampler {
+    pub fn new(cap: usize) -> UnparsedTxSampler {
+        UnparsedTxSampler {
+            cap,
+            samples: Arc::new(Mutex::new(vec![])),
+        }
+    }
+
+    /// Record a sample, unless we already have `cap` of them
+    pub fn record(&self, sample: UnparsedTxSample) {
+        let mut samples = self
+            .samples
+            .lock()
+            .expect("FATAL: unparsed tx sampler lock poisoned");
+        if samples.len() < self.cap {
+            samples.push(sample);
+        }
+    }
+
+    /// Get a copy of the samples collected so far
+    pub fn samples(&self) -> Vec<UnparsedTxSample> {
+        self.samples
+            .lock()
+            .expect("FATAL: unparsed tx sampler lock poisoned")
+            .clone()
+    }
 }
 
 impl BitcoinBlockDownloader {
@@ -225,9 +291,16 @@ impl BitcoinBlockParser {
         BitcoinBlockParser {
             network_id,
             magic_bytes: magic_bytes.clone(),
+            unparsed_tx_sampler: None,
         }
     }
 
+    /// Record the txs this parser skips into `sampler`
+    pub fn with_unparsed_tx_sampler(mut self, sampler: UnparsedTxSampler) -> BitcoinBlockParser {
+        self.unparsed_tx_sampler = Some(sampler);
+        self
+    }
+
     /// Allow raw inputs?
     fn allow_raw_inputs(epoch_id: StacksEpochId) -> bool {
         epoch_id >= StacksEpochId::Epoch21
@@ -254,89 +327,87 @@ impl BitcoinBlockParser {
         true
     }
 
-    /// Parse the data output to get a byte payload
-    fn parse_data(&self, data_output: &Script) -> Option<(u8, Vec<u8>)> {
+    /// Parse the data output to get a byte payload, or say why it doesn't carry one
+    fn parse_data(&self, data_output: &Script) -> Result<(u8, Vec<u8>), &'static str> {
         if !data_output.is_op_return() {
-            test_debug!("Data output is not an OP_RETURN");
-            return None;
+            return Err("Data output is not an OP_RETURN");
         }
 
         if data_output.len() <= self.magic_bytes.len() {
-            test_debug!("Data output is too short to carry an operation");
-            return None;
+            return Err("Data output is too short to carry an operation");
         }
 
         let script_pieces = bits::parse_script(data_output);
         if script_pieces.len() != 2 {
             // not OP_RETURN <data>
-            test_debug!("Data output does not encode a valid OP_RETURN");
-            return None;
+            return Err("Data output does not encode a valid OP_RETURN");
         }
 
         match (&script_pieces[0], &script_pieces[1]) {
             (Instruction::Op(ref opcode), Instruction::PushBytes(data)) => {
                 if *opcode != btc_opcodes::OP_RETURN {
-                    test_debug!("Data output does not use a standard OP_RETURN");
-                    return None;
+                    return Err("Data output does not use a standard OP_RETURN");
                 }
                 if data.len() <= MAGIC_BYTES_LENGTH {
-                    return None;
+                    return Err("Data output is too short to carry an operation");
                 }
                 if !data.starts_with(self.magic_bytes.as_bytes()) {
-                    test_debug!("Data output does not start with magic bytes");
-                    return None;
+                    return Err("Data output does not start with magic bytes");
                 }
 
                 let opcode = data[MAGIC_BYTES_LENGTH];
-                Some((opcode, data[MAGIC_BYTES_LENGTH + 1..data.len()].to_vec()))
-            }
-            (_, _) => {
-                test_debug!("Data output is not OP_RETURN <data>");
-                None
+                Ok((opcode, data[MAGIC_BYTES_LENGTH + 1..data.len()].to_vec()))
             }
+            (_, _) => Err("Data output is not OP_RETURN <data>"),
         }
     }
 
-    /// Is this an acceptable transaction?  It must have
-    /// * an OP_RETURN output at output 0
-    /// * only p2pkh or p2sh outputs for outputs 1...n
+    /// Is this an acceptable transaction?  See `check_burnchain_tx()`.
+    #[cfg(test)]
     fn maybe_burnchain_tx(&self, tx: &Transaction, epoch_id: StacksEpochId) -> bool {
-        if self.parse_data(&tx.output[0].script_pubkey).is_none() {
-            test_debug!("Tx {:?} has no valid OP_RETURN", tx.txid());
-            return false;
+        match self.check_burnchain_tx(tx, epoch_id) {
+            Ok(()) => true,
+            Err(_reason) => {
+                test_debug!("Tx {:?} is not a burnchain tx: {_reason:?}", tx.txid());
+                false
+            }
         }
+    }
+
+    /// Check that this is an acceptable transaction, and if not, say why not.  It must have
+    /// * an OP_RETURN output at output 0
+    /// * only p2pkh or p2sh outputs for outputs 1...n (pre-2.1), or decodable outputs (2.1+)
+    fn check_burnchain_tx(
+        &self,
+        tx: &Transaction,
+        epoch_id: StacksEpochId,
+    ) -> Result<(), UnparsedTxReason> {
+        let Some(data_output) = tx.output.first() else {
+            return Err(UnparsedTxReason::NotStacks("Tx has no outputs".to_string()));
+        };
+        self.parse_data(&data_output.script_pubkey)
+            .map_err(|reason| UnparsedTxReason::NotStacks(reason.to_string()))?;
 
         for i in 1..tx.output.len() {
-            if epoch_id < StacksEpochId::Epoch21 {
+            let recognized = if epoch_id < StacksEpochId::Epoch21 {
                 // only support legacy addresses pre-2.1
-                if !tx.output[i].script_pubkey.is_p2pkh() && !tx.output[i].script_pubkey.is_p2sh() {
-                    // unrecognized output type
-                    test_debug!(
-                        "Tx {:?} has unrecognized output type in output {}",
-                        tx.txid(),
-                        i
-                    );
-                    return false;
-                }
+                tx.output[i].script_pubkey.is_p2pkh() || tx.output[i].script_pubkey.is_p2sh()
             } else {
                 // in 2.1 and later, support it if the output decodes
-                if BitcoinAddress::from_scriptpubkey(
+                BitcoinAddress::from_scriptpubkey(
                     BitcoinNetworkType::Mainnet,
                     &tx.output[i].script_pubkey.to_bytes(),
                 )
-                .is_none()
-                {
-                    test_debug!(
-                        "Tx {:?} has unrecognized output type in output {}",
-                        tx.txid(),
-                        i
-                    );
-                    return false;
-                }
+                .is_some()
+            };
+            if !recognized {
+                return Err(UnparsedTxReason::MalformedOp(format!(
+                    "Unrecognized output type in output {i}"
+                )));
             }
         }
 
-        return true;
+        Ok(())
     }
 
     /// Parse a transaction's inputs into burnchain tx inputs.
@@ -409,44 +480,78 @@ impl BitcoinBlockParser {
         vtxindex: usize,
         epoch_id: StacksEpochId,
     ) -> Option<BitcoinTransaction> {
-        if !self.maybe_burnchain_tx(tx, epoch_id) {
-            test_debug!("Not a burnchain tx");
-            return None;
+        match self.try_parse_tx(tx, vtxindex, epoch_id) {
+            Ok(bitcoin_tx) => Some(bitcoin_tx),
+            Err(_reason) => {
+                test_debug!("Tx {:?} not parsed: {_reason:?}", tx.txid());
+                None
+            }
         }
+    }
 
-        let data_opt = self.parse_data(&tx.output[0].script_pubkey);
-        if data_opt.is_none() {
-            test_debug!("No OP_RETURN script");
-            return None;
-        }
+    /// Parse a Bitcoin transaction into a Burnchain transaction, like `parse_tx()`, but say why
+    /// the transaction was not parsed if it wasn't.
+    pub fn try_parse_tx(
+        &self,
+        tx: &Transaction,
+        vtxindex: usize,
+        epoch_id: StacksEpochId,
+    ) -> Result<BitcoinTransaction, UnparsedTxReason> {
+        self.check_burnchain_tx(tx, epoch_id)?;
+
+        let (opcode, data) = self
+            .parse_data(&tx.output[0].script_pubkey)
+            .map_err(|reason| UnparsedTxReason::NotStacks(reason.to_string()))?;
 
         let data_amt = tx.output[0].value;
 
-        let (opcode, data) = data_opt.unwrap();
-        let inputs_opt = if BitcoinBlockParser::allow_raw_inputs(epoch_id) {
-            Some(BitcoinBlockParser::parse_inputs_raw(tx))
+        let inputs = if BitcoinBlockParser::allow_raw_inputs(epoch_id) {
+            BitcoinBlockParser::parse_inputs_raw(tx)
         } else {
-            BitcoinBlockParser::parse_inputs_structured(tx)
+            BitcoinBlockParser::parse_inputs_structured(tx).ok_or_else(|| {
+                UnparsedTxReason::MalformedOp("Failed to parse inputs".to_string())
+            })?
         };
-        let outputs_opt = self.parse_outputs(tx, epoch_id);
-
-        match (inputs_opt, outputs_opt) {
-            (Some(inputs), Some(outputs)) => {
-                Some(BitcoinTransaction {
-                    txid: Txid::from_vec_be(tx.txid().as_bytes()).unwrap(), // this *should* panic if it fails
-                    vtxindex: vtxindex as u32,
-                    opcode,
-                    data,
-                    data_amt,
-                    inputs,
-                    outputs,
-                })
-            }
-            (_, _) => {
-                test_debug!("Failed to parse inputs and/or outputs");
-                None
-            }
+        let outputs = self
+            .parse_outputs(tx, epoch_id)
+            .ok_or_else(|| UnparsedTxReason::MalformedOp("Failed to parse outputs".to_string()))?;
+
+        Ok(BitcoinTransaction {
+            txid: Txid::from_vec_be(tx.txid().as_bytes()).unwrap(), // this *should* panic if it fails
+            vtxindex: vtxindex as u32,
+            opcode,
+            data,
+            data_amt,
+            inputs,
+            outputs,
+        })
+    }
+
+    /// Record a skipped tx in the unparsed tx sampler, if we have one and the tx is worth
+    /// sampling (see `UnparsedTxSampler`)
+    fn sample_unparsed_tx(
+        &self,
+        tx: &Transaction,
+        block_height: u64,
+        vtxindex: usize,
+        reason: UnparsedTxReason,
+    ) {
+        let Some(sampler) = self.unparsed_tx_sampler.as_ref() else {
+            return;
+        };
+        let has_op_return = tx
+            .output
+            .first()
+            .is_some_and(|output| output.script_pubkey.is_op_return());
+        if !has_op_return {
+            return;
         }
+        sampler.record(UnparsedTxSample {
+            txid: Txid::from_vec_be(tx.txid().as_bytes()).unwrap(),
+            block_height,
+            vtxindex: vtxindex as u32,
+            reason,
+        });
     }
 
     /// Given a Bitcoin block, extract the transactions that have OP_RETURN <magic>.
@@ -461,12 +566,13 @@ impl BitcoinBlockParser {
         let mut accepted_txs = vec![];
         for i in 0..block.txdata.len() {
             let tx = &block.txdata[i];
-            match self.parse_tx(tx, i, epoch_id) {
-                Some(bitcoin_tx) => {
+            match self.try_parse_tx(tx, i, epoch_id) {
+                Ok(bitcoin_tx) => {
                     accepted_txs.push(bitcoin_tx);
                 }
-                None => {
-                    continue;
+                Err(reason) => {
+                    test_debug!("Tx {:?} not parsed: {reason:?}", tx.txid());
+                    self.sample_unparsed_tx(tx, block_height, i, reason);
                 }
             }
         }
@@ -538,6 +644,7 @@ impl BurnchainBlockParser for BitcoinBlockParser {
 #[cfg(test)]
 mod tests {
     use stacks_common::deps_common::bitcoin::blockdata::block::{Block, LoneBlockHeader};
+    use stacks_common::deps_common::bitcoin::blockdata::script::Script;
     use stacks_common::deps_common::bitcoin::blockdata::transaction::Transaction;
     use stacks_common::deps_common::bitcoin::network::encodable::VarInt;
     use stacks_common::deps_common::bitcoin::network::serialize::deserialize;
@@ -545,7 +652,7 @@ mod tests {
     use stacks_common::types::Address;
     use stacks_common::util::hash::hex_bytes;
 
-    use super::BitcoinBlockParser;
+    use super::{BitcoinBlockParser, UnparsedTxReason, UnparsedTxSample, UnparsedTxSampler};
     use crate::burnchains::bitcoin::address::{BitcoinAddress, LegacyBitcoinAddressType};
     use crate::burnchains::bitcoin::keys::BitcoinPublicKey;
     use crate::burnchains::bitcoin::{
@@ -1233,4 +1340,95 @@ mod tests {
             assert_eq!(parsed_block_opt, block_fixture.result);
         }
     }
+
+    #[test]
+    fn parse_block_samples_unparsed_txs() {
+        // valid NAME_UPDATE
+        let valid_tx = make_tx("010000000320a081bcd1a80d9c1945f863d29dc84278411ed74cb6dcba30541bf8d5770542020000008b483045022100be57031bf2c095945ba2876e97b3f86ee051643a29b908f22ed45ccf58620103022061e056e5f48c5a51c66604a1ca28e4bfaabab1478424c9bbb396cc6afe5c222e0141040fadbbcea0ff3b05f03195b41cd991d7a0af8bd38559943aec99cbdaf0b22cc806b9a4f07579934774cc0c155e781d45c989f94336765e88a66d91cfb9f060b0feffffff20a081bcd1a80d9c1945f863d29dc84278411ed74cb6dcba30541bf8d5770542010000008b483045022100fd9c04b330810694cb4bfef793b193f9cbfaa07325700f217b9cb03e5207005302202f07e7c9c6774c5619a043752444f6da6fd81b9d9d008ec965796d87271598de0141040fadbbcea0ff3b05f03195b41cd991d7a0af8bd38559943aec99cbdaf0b22cc806b9a4f07579934774cc0c155e781d45c989f94336765e88a66d91cfb9f060b0feffffff20a081bcd1a80d9c1945f863d29dc84278411ed74cb6dcba30541bf8d5770542040000008a47304402205e24943a40b8ef876cc218a7e8994f4be7afb7aa02403bb73510fac01b33ead3022033e5fb811c396b2fb50a825cd1d86e82eb83483901a1793d0eb15e3e9f1d1c5b814104c77f262dda02580d65c9069a8a34c56bd77325bba4110b693b90216f5a3edc0bebc8ce28d61aa86b414aa91ecb29823b11aeed06098fcd97fee4bc73d54b1e96feffffff030000000000000000296a2769642bfae543ff5672fb607fe15e16b1c3ef38737c631c7c5d911c6617993c21fba731363f1cfe6c6b0000000000001976a914395f3643cea07ec4eec73b4d9a973dcce56b9bf188acc5120100000000001976a9149f2660e75380675206b6f1e2b4f106ae33266be488ac00000000").unwrap();
+        // OP_RETURN with the wrong magic bytes
+        let bad_magic_tx = make_tx("0100000001d8b97932f097b9fbf0c7584f29515862911ac830826fdfd72d06402c21543e38000000006a47304402202801bc5d11eefddc586b1171bf607cc2be1c661d22e215153f2630316f973a200220628cc08858bba3f0cda661dbef2f007e48f8cb531edc0b54edb573226816f253012103d6967618e0159c9bfcd03ea33d368c8b2a98af5a054364c6b5e7215d7d809169ffffffff030000000000000000356a336469240efa29f955c6ae3bb5037039d89dba5e00000000000000000000000000535441434b5300000000000003e854455354217c150000000000001976a914cfd25e09f2d33e1aec73bfcc5b608ec513bbe6c088ac34460200000000001976a9144cb912533a6935880df7647fd5232e40aca07b8088ac00000000").unwrap();
+        // no OP_RETURN at all, so never sampled
+        let no_op_return_tx = make_tx("0200000003620f7bc1087b0111f76978ef747001e3ae0a12f254cbfb858f028f891c40e5f6010000006a47304402207f5dfc2f7f7329b7cc731df605c83aa6f48ec2218495324bb4ab43376f313b840220020c769655e4bfcc54e55104f6adc723867d9d819266d27e755e098f646f689d0121038c2d1cbe4d731c69e67d16c52682e01cb70b046ead63e90bf793f52f541dafbdfefffffff15fe7d9e0815853738ce47deadee69339e027a1dfcfb6fa887cce3a72626e7b010000006a47304402203202e6c640c063989623fc782ac1c9dc3c6fcaed996d852ec876749ba63db63b02207ef86e262ad4b4bc9cebfadb609f52c35b0105e15d58a5ecbecc5e536d3a8cd8012103dc526ca188418ab128d998bf80942d66f1b3be585d0c89bd61c533bddbdaa729feffffff84e6431db86833897bab333d844486c183dd01e69862edea442e480c2d8cb549010000006a47304402200320bc83f35ceab4a7ef0f8181eedb5f54e3f617626826cc49c8c86efc9be0b302203705889d6aed50f716b81b0f3f5769d72d1b8a6b59d1b0b73bcf94245c283b8001210263591c21ce8ee0d96a617108d7c278e2e715ac6d8afd3fcd158bee472c590068feffffff02ca780a00000000001976a914811fb695e46e2386501bcd70e5c869fe6c0bb33988ac10f59600000000001976a9140f2408a811f6d24ab1833924d98d884c44ecee8888ac6fce0700").unwrap();
+        // our magic bytes, but an output that can't be decoded before 2.1
+        let mut malformed_tx = valid_tx.clone();
+        malformed_tx.output[1].script_pubkey = Script::from(vec![0x51]); // OP_TRUE
+
+        let header = make_block_header("000000209cef4ccd19f4294dd5c762aab6d9577fb4412cd4c0a662a953a8b7969697bc1ddab52e6f053758022fb92f04388eb5fdd87046776e9c406880e728b48e6930aff462fc5bffff7f2000000000").unwrap();
+        let block = Block {
+            header: header.header,
+            txdata: vec![
+                valid_tx.clone(),
+                bad_magic_tx.clone(),
+                no_op_return_tx,
+                malformed_tx.clone(),
+                bad_magic_tx.clone(),
+            ],
+        };
+
+        let sampler = UnparsedTxSampler::new(2);
+        let parser = BitcoinBlockParser::new(BitcoinNetworkType::Testnet, MagicBytes([105, 100])) // "id"
+            .with_unparsed_tx_sampler(sampler.clone());
+        let parsed_block = parser.parse_block(&block, 32, StacksEpochId::Epoch2_05);
+
+        // sampling doesn't change what gets parsed
+        assert_eq!(parsed_block.txs.len(), 1);
+        assert_eq!(
+            parsed_block.txs[0].txid,
+            Txid::from_vec_be(valid_tx.txid().as_bytes()).unwrap()
+        );
+
+        // the second bad-magic tx is past the cap
+        assert_eq!(
+            sampler.samples(),
+            vec![
+                UnparsedTxSample {
+                    txid: Txid::from_vec_be(bad_magic_tx.txid().as_bytes()).unwrap(),
+                    block_height: 32,
+                    vtxindex: 1,
+                    reason: UnparsedTxReason::NotStacks(
+                        "Data output does not start with magic bytes".to_string()
+                    ),
+                },
+                UnparsedTxSample {
+                    txid: Txid::from_vec_be(malformed_tx.txid().as_bytes()).unwrap(),
+                    block_height: 32,
+                    vtxindex: 3,
+                    reason: UnparsedTxReason::MalformedOp(
+                        "Unrecognized output type in output 1".to_string()
+                    ),
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn parse_block_without_outputs_or_txs() {
+        let header = make_block_header("000000209cef4ccd19f4294dd5c762aab6d9577fb4412cd4c0a662a953a8b7969697bc1ddab52e6f053758022fb92f04388eb5fdd87046776e9c406880e728b48e6930aff462fc5bffff7f2000000000").unwrap();
+        let parser = BitcoinBlockParser::new(BitcoinNetworkType::Testnet, MagicBytes([105, 100])); // "id"
+
+        let empty_block = Block {
+            header: header.header,
+            txdata: vec![],
+        };
+        let parsed_block = parser.parse_block(&empty_block, 32, StacksEpochId::Epoch31);
+        assert!(parsed_block.txs.is_empty());
+
+        let no_outputs_tx = Transaction {
+            version: 2,
+            lock_time: 0,
+            input: vec![],
+            output: vec![],
+        };
+        assert_eq!(
+            parser.try_parse_tx(&no_outputs_tx, 0, StacksEpochId::Epoch31),
+            Err(UnparsedTxReason::NotStacks("Tx has no outputs".to_string()))
+        );
+
+        let block = Block {
+            header: header.header,
+            txdata: vec![no_outputs_tx],
+        };
+        let parsed_block = parser.parse_block(&block, 32, StacksEpochId::Epoch31);
+        assert!(parsed_block.txs.is_empty());
+    }
 }
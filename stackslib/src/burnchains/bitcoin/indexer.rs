@@ -35,7 +35,8 @@ use stacks_common::types::chainstate::BurnchainHeaderHash;
 use stacks_common::util::get_epoch_time_secs;
 
 use crate::burnchains::bitcoin::blocks::{
-    BitcoinBlockDownloader, BitcoinBlockParser, BitcoinHeaderIPC,
+    BitcoinBlockDownloader, BitcoinBlockParser, BitcoinHeaderIPC, UnparsedTxSample,
+    UnparsedTxSampler,
 };
 use crate::burnchains::bitcoin::messages::BitcoinMessageHandler;
 use crate::burnchains::bitcoin::spv::*;
@@ -114,6 +115,13 @@ pub struct BitcoinIndexerConfig {
     pub first_block: u64,
     pub magic_bytes: MagicBytes,
     pub epochs: Option<EpochList>,
+    /// Maximum number of blocks to download per second while syncing, if any
+    pub rate_limit: Option<u32>,
+    /// Maximum number of blocks to walk back from the chain tip when searching for a reorg's
+    /// common ancestor, if any
+    pub reorg_lookback: Option<u64>,
+    /// Number of threads that parse downloaded blocks in parallel while syncing
+    pub parse_workers: usize,
 }
 
 #[derive(Debug)]
@@ -133,6 +141,8 @@ pub struct BitcoinIndexer {
     pub config: BitcoinIndexerConfig,
     pub runtime: BitcoinIndexerRuntime,
     pub should_keep_running: Option<Arc<AtomicBool>>,
+    /// If set, the parsers this indexer hands out record samples of the txs they skip
+    pub unparsed_tx_sampler: Option<UnparsedTxSampler>,
 }
 
 impl BitcoinIndexerConfig {
@@ -149,6 +159,9 @@ impl BitcoinIndexerConfig {
             first_block,
             magic_bytes: BLOCKSTACK_MAGIC_MAINNET.clone(),
             epochs: None,
+            rate_limit: None,
+            reorg_lookback: None,
+            parse_workers: 1,
         }
     }
 
@@ -165,6 +178,9 @@ impl BitcoinIndexerConfig {
             first_block: 0,
             magic_bytes: BLOCKSTACK_MAGIC_MAINNET.clone(),
             epochs: None,
+            rate_limit: None,
+            reorg_lookback: None,
+            parse_workers: 1,
         }
     }
 
@@ -182,6 +198,9 @@ impl BitcoinIndexerConfig {
             first_block: 0,
             magic_bytes: BLOCKSTACK_MAGIC_MAINNET.clone(),
             epochs: None,
+            rate_limit: None,
+            reorg_lookback: None,
+            parse_workers: 1,
         }
     }
 }
@@ -214,6 +233,7 @@ impl BitcoinIndexer {
             config,
             runtime,
             should_keep_running,
+            unparsed_tx_sampler: None,
         }
     }
 
@@ -243,6 +263,7 @@ impl BitcoinIndexer {
             ),
             runtime: BitcoinIndexerRuntime::new(BitcoinNetworkType::Regtest),
             should_keep_running: None,
+            unparsed_tx_sampler: None,
         }
     }
 
@@ -251,9 +272,25 @@ impl BitcoinIndexer {
             config: self.config.clone(),
             runtime: BitcoinIndexerRuntime::new(self.runtime.network_id),
             should_keep_running: self.should_keep_running.clone(),
+            unparsed_tx_sampler: self.unparsed_tx_sampler.clone(),
         }
     }
 
+    /// Have the parsers used by subsequent syncs record up to `cap` samples of the txs they
+    /// classify as not being Stacks ops, or as malformed Stacks ops.  Replaces any samples
+    /// collected so far.
+    pub fn collect_unparsed_tx_samples(&mut self, cap: usize) {
+        self.unparsed_tx_sampler = Some(UnparsedTxSampler::new(cap));
+    }
+
+    /// Get the unparsed tx samples collected so far.  Empty if collection isn't enabled.
+    pub fn unparsed_tx_samples(&self) -> Vec<UnparsedTxSample> {
+        self.unparsed_tx_sampler
+            .as_ref()
+            .map(|sampler| sampler.samples())
+            .unwrap_or_default()
+    }
+
     /// (re)connect to our configured network peer.
     /// Sets self.runtime.sock to a new socket referring to our configured
     /// Bitcoin peer.  If we fail to connect, this method sets the socket
@@ -620,6 +657,26 @@ impl BitcoinIndexer {
             error!("Failed to get the last block from {canonical_headers_path}");
         })?;
 
+        // lowest height the common ancestor may have, if the reorg lookback doesn't reach genesis.
+        // Searching stops with an error once a failed batch started below it.
+        let lookback_floor = self
+            .config
+            .reorg_lookback
+            .filter(|lookback| *lookback < canonical_end_block)
+            .map(|lookback| canonical_end_block - lookback);
+        let check_lookback = |height: u64| match lookback_floor {
+            Some(floor) if height < floor => {
+                let lookback = canonical_end_block - floor;
+                warn!(
+                    "No common Bitcoin block ancestor within the last {lookback} blocks";
+                    "canonical_end_block" => canonical_end_block,
+                    "height" => height
+                );
+                Err(btc_error::ReorgDeeperThanLookback(lookback))
+            }
+            _ => Ok(()),
+        };
+
         // bootstrap reorg client
         let mut start_block = canonical_end_block.saturating_sub(REORG_BATCH_SIZE);
         let mut reorg_spv_client =
@@ -648,6 +705,7 @@ impl BitcoinIndexer {
                         start_block,
                         start_block + REORG_BATCH_SIZE
                     );
+                    check_lookback(start_block)?;
                     if start_block == 0 {
                         // reorg all the way back to genesis
                         new_tip = 0;
@@ -694,6 +752,7 @@ impl BitcoinIndexer {
                     start_block,
                     start_block + REORG_BATCH_SIZE
                 );
+                check_lookback(start_block)?;
                 if start_block == 0 {
                     // reorg chain is empty
                     new_tip = 0;
@@ -761,6 +820,7 @@ impl BitcoinIndexer {
                 start_block, max_height
             );
 
+            check_lookback(start_block)?;
             if start_block == 0 {
                 break;
             }
@@ -771,6 +831,9 @@ impl BitcoinIndexer {
                 self.setup_reorg_headers(&orig_spv_client, reorg_headers_path, start_block, false)?;
         }
 
+        // the ancestor may lie below the floor if the last batch straddled it
+        check_lookback(new_tip)?;
+
         if check_chain_work {
             let reorg_total_work = reorg_spv_client.update_chain_work()?;
             let orig_total_work = orig_spv_client.update_chain_work()?;
@@ -1119,7 +1182,19 @@ impl BurnchainIndexer for BitcoinIndexer {
     }
 
     fn parser(&self) -> BitcoinBlockParser {
-        BitcoinBlockParser::new(self.runtime.network_id, self.config.magic_bytes)
+        let parser = BitcoinBlockParser::new(self.runtime.network_id, self.config.magic_bytes);
+        match self.unparsed_tx_sampler.as_ref() {
+            Some(sampler) => parser.with_unparsed_tx_sampler(sampler.clone()),
+            None => parser,
+        }
+    }
+
+    fn parse_workers(&self) -> usize {
+        self.config.parse_workers
+    }
+
+    fn download_rate_limit(&self) -> Option<u32> {
+        self.config.rate_limit
     }
 
     fn reader(&self) -> BitcoinIndexer {
@@ -1369,20 +1444,8 @@ mod test {
         assert_eq!(common_ancestor_height, 0);
     }
 
-    #[test]
-    fn test_indexer_find_bitcoin_reorg_midpoint() {
-        let path_1 = "/tmp/test-indexer-find_bitcoin_reorg_midpoint.dat";
-        let path_2 = "/tmp/test-indexer-find_bitcoin_reorg_midpoint.dat.reorg.bak";
-        let path_reorg = "/tmp/test-indexer-find_bitcoin_reorg_midpoint.dat.reorg";
-
-        if fs::metadata(path_1).is_ok() {
-            fs::remove_file(path_1).unwrap();
-        }
-        if fs::metadata(path_2).is_ok() {
-            fs::remove_file(path_2).unwrap();
-        }
-
-        // two header sets -- both of which build off of same first block
+    /// Two header sets that build off of the same first block, and share their first header
+    fn midpoint_reorg_headers() -> (Vec<LoneBlockHeader>, Vec<LoneBlockHeader>) {
         let headers_1 = vec![
             LoneBlockHeader {
                 header: BlockHeader {
@@ -1491,6 +1554,25 @@ mod test {
             },
         ];
 
+        (headers_1, headers_2)
+    }
+
+    #[test]
+    fn test_indexer_find_bitcoin_reorg_midpoint() {
+        let path_1 = "/tmp/test-indexer-find_bitcoin_reorg_midpoint.dat";
+        let path_2 = "/tmp/test-indexer-find_bitcoin_reorg_midpoint.dat.reorg.bak";
+        let path_reorg = "/tmp/test-indexer-find_bitcoin_reorg_midpoint.dat.reorg";
+
+        if fs::metadata(path_1).is_ok() {
+            fs::remove_file(path_1).unwrap();
+        }
+        if fs::metadata(path_2).is_ok() {
+            fs::remove_file(path_2).unwrap();
+        }
+
+        // two header sets -- both of which build off of same first block
+        let (headers_1, headers_2) = midpoint_reorg_headers();
+
         let mut spv_client =
             SpvClient::new(path_1, 0, None, BitcoinNetworkType::Regtest, true, false).unwrap();
         let mut spv_client_reorg =
@@ -1539,6 +1621,79 @@ mod test {
         assert_eq!(common_ancestor_height, 1);
     }
 
+    #[test]
+    fn test_indexer_find_bitcoin_reorg_deeper_than_lookback() {
+        let path_1 = "/tmp/test-indexer-find_bitcoin_reorg_deeper_than_lookback.dat";
+        let path_2 = "/tmp/test-indexer-find_bitcoin_reorg_deeper_than_lookback.dat.reorg.bak";
+        let path_reorg = "/tmp/test-indexer-find_bitcoin_reorg_deeper_than_lookback.dat.reorg";
+
+        for path in [path_1, path_2, path_reorg] {
+            if fs::metadata(path).is_ok() {
+                fs::remove_file(path).unwrap();
+            }
+        }
+
+        // the chains diverge after block 1, so the reorg is 3 blocks deep
+        let (headers_1, headers_2) = midpoint_reorg_headers();
+
+        let mut spv_client =
+            SpvClient::new(path_1, 0, None, BitcoinNetworkType::Regtest, true, false).unwrap();
+        let mut spv_client_reorg =
+            SpvClient::new(path_2, 0, None, BitcoinNetworkType::Regtest, true, false).unwrap();
+
+        spv_client.insert_block_headers_after(0, headers_1).unwrap();
+        spv_client_reorg
+            .insert_block_headers_after(0, headers_2)
+            .unwrap();
+
+        let mut find_reorg = |reorg_lookback: Option<u64>| {
+            let mut config = BitcoinIndexerConfig::test_default(path_1.to_string());
+            config.reorg_lookback = reorg_lookback;
+            let mut indexer = BitcoinIndexer::new(
+                config,
+                BitcoinIndexerRuntime::new(BitcoinNetworkType::Regtest),
+                None,
+            );
+            let mut batches = 0;
+            let result = indexer.inner_find_bitcoin_reorg(
+                path_1,
+                path_reorg,
+                |ref mut _indexer, ref mut spv_client, start_block, end_block_opt| {
+                    // mock the bitcoind by just copying over the relevant headers from our backup reorg db
+                    batches += 1;
+                    let end_block = end_block_opt.unwrap_or(10000000);
+                    let hdrs = spv_client_reorg
+                        .read_block_headers(start_block, end_block)
+                        .unwrap();
+                    if start_block > 0 {
+                        spv_client
+                            .insert_block_headers_before(start_block - 1, hdrs)
+                            .unwrap();
+                    } else if !hdrs.is_empty() {
+                        spv_client.test_write_block_headers(0, hdrs).unwrap();
+                    }
+                    Ok(())
+                },
+                false,
+            );
+            (result, batches)
+        };
+
+        // the search gives up after the first batch instead of walking back to the ancestor
+        let (result, batches) = find_reorg(Some(1));
+        assert!(matches!(result, Err(btc_error::ReorgDeeperThanLookback(1))));
+        assert_eq!(batches, 1);
+
+        // the ancestor is found once the lookback covers it
+        let (result, batches) = find_reorg(Some(3));
+        assert_eq!(result.unwrap(), 1);
+        assert_eq!(batches, 2);
+
+        // no lookback means an unbounded search
+        let (result, _) = find_reorg(None);
+        assert_eq!(result.unwrap(), 1);
+    }
+
     #[test]
     fn test_indexer_sync_headers() {
         if !env::var("BLOCKSTACK_SPV_BITCOIN_HOST").is_ok() {
@@ -1587,6 +1742,9 @@ mod test {
             first_block: 0,
             magic_bytes: MagicBytes([105, 100]),
             epochs: None,
+            rate_limit: None,
+            reorg_lookback: None,
+            parse_workers: 1,
         };
 
         if fs::metadata(&indexer_conf.spv_headers_path).is_ok() {
@@ -84,6 +84,8 @@ pub enum Error {
     BlockchainHeight,
     /// Request timed out
     TimedOut,
+    /// No common ancestor with the reorg chain within the configured reorg lookback
+    ReorgDeeperThanLookback(u64),
 }
 
 impl fmt::Display for Error {
@@ -109,6 +111,10 @@ impl fmt::Display for Error {
             Error::ConfigError(ref e_str) => fmt::Display::fmt(e_str, f),
             Error::BlockchainHeight => write!(f, "Value is beyond the end of the blockchain"),
             Error::TimedOut => write!(f, "Request timed out"),
+            Error::ReorgDeeperThanLookback(lookback) => write!(
+                f,
+                "Reorg is deeper than the reorg lookback of {lookback} blocks"
+            ),
         }
     }
 }
@@ -136,6 +142,7 @@ impl error::Error for Error {
             Error::ConfigError(ref _e_str) => None,
             Error::BlockchainHeight => None,
             Error::TimedOut => None,
+            Error::ReorgDeeperThanLookback(_) => None,
         }
     }
 }
@@ -14,11 +14,13 @@
 // You should have received a copy of the GNU General Public License
 // along with this program.  If not, see <http://www.gnu.org/licenses/>.
 
+use std::any::Any;
 use std::collections::{HashMap, HashSet};
+use std::panic::{self, AssertUnwindSafe};
 use std::path::PathBuf;
 use std::sync::atomic::{AtomicBool, Ordering};
-use std::sync::mpsc::sync_channel;
-use std::sync::Arc;
+use std::sync::mpsc::{channel, sync_channel, Receiver, RecvTimeoutError};
+use std::sync::{Arc, Mutex};
 use std::time::Duration;
 use std::{fs, thread};
 
@@ -35,14 +37,18 @@ use stacks_common::util::{get_epoch_time_ms, sleep_ms};
 use super::EpochList;
 use crate::burnchains::affirmation::update_pox_affirmation_maps;
 use crate::burnchains::bitcoin::BitcoinTxOutput;
-use crate::burnchains::db::{BurnchainDB, BurnchainHeaderReader};
+use crate::burnchains::db::{
+    BurnchainBlockData, BurnchainBlockSink, BurnchainDB, BurnchainDBSink, BurnchainHeaderReader,
+};
 use crate::burnchains::indexer::{
     BurnBlockIPC, BurnHeaderIPC, BurnchainBlockDownloader, BurnchainBlockParser, BurnchainIndexer,
+    DownloadRateLimiter,
 };
 use crate::burnchains::{
-    Burnchain, BurnchainBlock, BurnchainBlockHeader, BurnchainParameters, BurnchainRecipient,
-    BurnchainSigner, BurnchainStateTransition, BurnchainStateTransitionOps, BurnchainTransaction,
-    Error as burnchain_error, PoxConstants, Txid,
+    BlockLatency, Burnchain, BurnchainBlock, BurnchainBlockHeader, BurnchainParameters,
+    BurnchainRecipient, BurnchainSigner, BurnchainStateTransition, BurnchainStateTransitionOps,
+    BurnchainTransaction, Error as burnchain_error, PoxConstants, SyncOptions, SyncOutcome,
+    SyncReport, SyncStage, SyncStats, SyncTarget, Txid,
 };
 use crate::chainstate::burn::db::sortdb::{SortitionDB, SortitionHandle, SortitionHandleTx};
 use crate::chainstate::burn::distribution::BurnSamplePoint;
@@ -453,6 +459,37 @@ impl BurnchainBlock {
     }
 }
 
+/// A burnchain sync worker thread that sends what it returned (or its panic) over a channel as
+/// the last thing it does, so that `Burnchain::handle_thread_join_timeout` can wait for it with
+/// a bounded wait.
+pub struct WorkerThread<T> {
+    name: String,
+    handle: thread::JoinHandle<()>,
+    done: Receiver<thread::Result<T>>,
+}
+
+impl<T: Send + 'static> WorkerThread<T> {
+    /// Run `work` on a new thread called `name`
+    pub fn spawn<F>(name: &str, work: F) -> std::io::Result<WorkerThread<T>>
+    where
+        F: FnOnce() -> T + Send + 'static,
+    {
+        let (done_sender, done) = sync_channel(1);
+        let handle = thread::Builder::new()
+            .name(name.to_string())
+            .spawn(move || {
+                let result = panic::catch_unwind(AssertUnwindSafe(work));
+                // the receiver is gone if the join already timed out
+                let _ = done_sender.send(result);
+            })?;
+        Ok(WorkerThread {
+            name: name.to_string(),
+            handle,
+            done,
+        })
+    }
+}
+
 impl Burnchain {
     pub fn new(
         working_dir: &str,
@@ -600,6 +637,16 @@ impl Burnchain {
             .block_height_to_reward_cycle(self.first_block_height, block_height)
     }
 
+    /// Like `block_height_to_reward_cycle`, but a height before the first block is a
+    /// `HeightBeforeFirstBlock` error that names it.
+    pub fn reward_cycle_of_block(&self, height: u64) -> Result<u64, burnchain_error> {
+        self.block_height_to_reward_cycle(height)
+            .ok_or(burnchain_error::HeightBeforeFirstBlock {
+                height,
+                first_block_height: self.first_block_height,
+            })
+    }
+
     /// Is this block either the first block in a reward cycle or
     ///  right before the reward phase starts? This is the mod 0 or mod 1
     ///  block. Reward cycle start events (like auto-unlocks) process *after*
@@ -1173,6 +1220,52 @@ impl Burnchain {
         }
     }
 
+    /// Join a worker thread, and get what it returned.
+    /// If the thread panicked, return a `ThreadPanic` error carrying the thread's name and its
+    /// panic message, instead of propagating the panic.
+    pub fn handle_thread_join<T>(handle: thread::JoinHandle<T>) -> Result<T, burnchain_error> {
+        let name = handle.thread().name().unwrap_or("unnamed").to_string();
+        handle
+            .join()
+            .map_err(|payload| Self::thread_panic_error(name, payload))
+    }
+
+    /// Join a worker thread like `handle_thread_join`, but give up after `timeout`.
+    /// `JoinHandle` has no timed join, so this waits on the channel the worker sends its outcome
+    /// over when it's done. If the worker is not done in time, return a `ThreadTimeout` error
+    /// carrying the worker's name; the worker is left to finish on its own.
+    pub fn handle_thread_join_timeout<T>(
+        worker: WorkerThread<T>,
+        timeout: Duration,
+    ) -> Result<T, burnchain_error> {
+        let WorkerThread { name, handle, done } = worker;
+        match done.recv_timeout(timeout) {
+            Ok(result) => {
+                // sending its outcome is the last thing the worker does, so this won't block
+                let _ = handle.join();
+                result.map_err(|payload| Self::thread_panic_error(name, payload))
+            }
+            Err(RecvTimeoutError::Timeout) => {
+                error!("Burnchain thread {name} did not finish within {timeout:?}");
+                Err(burnchain_error::ThreadTimeout { name, timeout })
+            }
+            Err(RecvTimeoutError::Disconnected) => Err(burnchain_error::ThreadChannelError),
+        }
+    }
+
+    /// Make a `ThreadPanic` error out of the panic payload of the thread called `name`
+    fn thread_panic_error(name: String, payload: Box<dyn Any + Send>) -> burnchain_error {
+        let message = if let Some(message) = payload.downcast_ref::<&str>() {
+            message.to_string()
+        } else if let Some(message) = payload.downcast_ref::<String>() {
+            message.clone()
+        } else {
+            "non-string panic payload".to_string()
+        };
+        error!("Burnchain thread {name} panicked: {message}");
+        burnchain_error::ThreadPanic { name, message }
+    }
+
     /// Top-level burnchain sync.
     /// Returns new latest block height.
     pub fn sync<I: BurnchainIndexer + BurnchainHeaderReader + 'static + Send>(
@@ -1395,9 +1488,9 @@ impl Burnchain {
         }
 
         // join up
-        let _ = download_thread.join().unwrap();
-        let _ = parse_thread.join().unwrap();
-        let (block_snapshot, state_transition_opt) = match db_thread.join().unwrap() {
+        let _ = Self::handle_thread_join(download_thread)?;
+        let _ = Self::handle_thread_join(parse_thread)?;
+        let (block_snapshot, state_transition_opt) = match Self::handle_thread_join(db_thread)? {
             Ok(x) => x,
             Err(e) => {
                 warn!("Failed to join burnchain download thread: {:?}", &e);
@@ -1455,6 +1548,8 @@ impl Burnchain {
     /// Returns the burnchain block header for the new burnchain tip, which will be _at least_ as
     /// high as target_block_height_opt (if given), or whatever is currently at the tip of the
     /// burnchain DB.
+    /// Compatibility wrapper around `sync_with_indexer_options` that only returns the new tip, so
+    /// it does not say whether `max_blocks_opt` cut the sync short.
     /// If this method returns Err(burnchain_error::TrySyncAgain), then call this method again.
     pub fn sync_with_indexer<I>(
         &mut self,
@@ -1467,8 +1562,145 @@ impl Burnchain {
     where
         I: BurnchainIndexer + BurnchainHeaderReader + 'static + Send,
     {
+        self.sync_with_indexer_stats(
+            indexer,
+            coord_comm,
+            target_block_height_opt,
+            max_blocks_opt,
+            should_keep_running,
+        )
+        .map(|(header, _stats)| header)
+    }
+
+    /// Like `sync_with_indexer`, but also return the block counts of the sync.
+    /// If this method returns Err(burnchain_error::TrySyncAgain), then call this method again.
+    pub fn sync_with_indexer_stats<I>(
+        &mut self,
+        indexer: &mut I,
+        coord_comm: CoordinatorChannels,
+        target_block_height_opt: Option<u64>,
+        max_blocks_opt: Option<u64>,
+        should_keep_running: Option<Arc<AtomicBool>>,
+    ) -> Result<(BurnchainBlockHeader, SyncStats), burnchain_error>
+    where
+        I: BurnchainIndexer + BurnchainHeaderReader + 'static + Send,
+    {
+        let options = SyncOptions {
+            target: target_block_height_opt.map(SyncTarget::Height),
+            max_blocks: max_blocks_opt,
+            should_keep_running,
+            ..SyncOptions::default()
+        };
+        self.sync_with_indexer_options(indexer, coord_comm, options)
+            .map(|report| (report.outcome.into_header(), report.stats))
+    }
+
+    /// Top-level burnchain sync, with the options and the report that `sync_with_indexer` leaves
+    /// out.
+    /// Downloads, parses, and stores the burnchain blocks needed to reach `options.target` (or
+    /// the burnchain tip), handing them to `options.sink` instead of the burnchain DB if one is
+    /// given.
+    /// The returned report's outcome is SyncOutcome::Complete(..) with the burnchain block
+    /// header for the new burnchain tip, or SyncOutcome::Partial { .. } if `options.max_blocks`
+    /// stopped the sync short of it, along with how many blocks are left to sync. Call this
+    /// method again to continue.
+    /// If the target is a block hash, then the sync stops once that block has been processed,
+    /// and the outcome's header is that block's header. Returns
+    /// Err(burnchain_error::UnknownBlock(..)) if the hash is not among the synced headers.
+    /// A block that fails to download, parse, or store is reported as
+    /// Err(burnchain_error::Contextual { .. }) with the stage and height it failed at, so match on
+    /// the error's `root_cause()` to decide what to do.
+    /// If this method returns Err(burnchain_error::TrySyncAgain), then call this method again.
+    pub fn sync_with_indexer_options<I>(
+        &mut self,
+        indexer: &mut I,
+        coord_comm: CoordinatorChannels,
+        options: SyncOptions,
+    ) -> Result<SyncReport, burnchain_error>
+    where
+        I: BurnchainIndexer + BurnchainHeaderReader + 'static + Send,
+    {
+        let SyncOptions {
+            target,
+            max_blocks,
+            should_keep_running,
+            on_progress,
+            sink,
+        } = options;
         self.setup_chainstate(indexer)?;
-        let (sortdb, mut burnchain_db) = self.connect_db(
+        self.check_indexer_first_block(indexer)?;
+
+        if let Some(sink) = sink {
+            return self.sync_chainstate_into(
+                indexer,
+                coord_comm,
+                target,
+                max_blocks,
+                should_keep_running,
+                sink,
+                on_progress,
+            );
+        }
+
+        Burnchain::setup_chainstate_dirs(&self.working_dir)?;
+        let burnchain_db = BurnchainDB::connect(&self.get_burnchaindb_path(), self, true)?;
+        let sink = BurnchainDBSink::new(self.clone(), burnchain_db, indexer.reader());
+        self.sync_chainstate_into(
+            indexer,
+            coord_comm,
+            target,
+            max_blocks,
+            should_keep_running,
+            sink,
+            on_progress,
+        )
+    }
+
+    /// Check that this burnchain starts at the same block as `indexer`'s headers, so that a
+    /// misconfigured node fails before it syncs anything.
+    /// Returns Err(burnchain_error::FirstBlockMismatch { .. }) if the heights or hashes differ.
+    pub fn check_indexer_first_block<I: BurnchainIndexer>(
+        &self,
+        indexer: &I,
+    ) -> Result<(), burnchain_error> {
+        let indexer_height = indexer.get_first_block_height();
+        let indexer_hash = indexer.get_first_block_header_hash()?;
+        if self.first_block_height != indexer_height || self.first_block_hash != indexer_hash {
+            return Err(burnchain_error::FirstBlockMismatch {
+                configured_height: self.first_block_height,
+                configured_hash: self.first_block_hash.clone(),
+                indexer_height,
+                indexer_hash,
+            });
+        }
+        Ok(())
+    }
+
+    /// Download, parse, and hand off to `sink` the burnchain blocks needed to reach the sync
+    /// target.  The indexer's chainstate must already be set up.
+    /// The returned report also has the pipeline latency of each block handed off to `sink`.
+    /// If given, `on_progress` is called on this thread with `(current_height, target_height)`
+    /// each time a block has been handed off.
+    fn sync_chainstate_into<I, S>(
+        &mut self,
+        indexer: &mut I,
+        coord_comm: CoordinatorChannels,
+        target_opt: Option<SyncTarget>,
+        max_blocks_opt: Option<u64>,
+        should_keep_running: Option<Arc<AtomicBool>>,
+        mut sink: S,
+        mut on_progress: Option<&mut dyn FnMut(u64, u64)>,
+    ) -> Result<SyncReport, burnchain_error>
+    where
+        I: BurnchainIndexer + BurnchainHeaderReader + 'static + Send,
+        S: BurnchainBlockSink + 'static + Send,
+    {
+        let target_block_height_opt = match target_opt {
+            Some(SyncTarget::Height(target_block_height)) => Some(target_block_height),
+            _ => None,
+        };
+
+        let (_, burnchain_db) = self.connect_db(
             true,
             indexer.get_first_block_header_hash()?,
             indexer.get_first_block_header_timestamp()?,
@@ -1515,6 +1747,22 @@ impl Burnchain {
             }
         }
 
+        // a target hash can only be resolved to a height once its header is synced
+        let target_block_height_opt = match target_opt {
+            Some(SyncTarget::Hash(ref target_hash)) => {
+                let Some(target_block_height) =
+                    indexer.find_burnchain_header_height(target_hash)?
+                else {
+                    warn!("Target burnchain block is not among the synced headers";
+                          "target_hash" => %target_hash,
+                          "end_block" => end_block);
+                    return Err(burnchain_error::UnknownBlock(target_hash.clone()));
+                };
+                Some(target_block_height)
+            }
+            _ => target_block_height_opt,
+        };
+
         // check if the db has the parent of sync_height, if not,
         //  start at the highest common ancestor
         // if it does, then start at the minimum of db_height and sync_height
@@ -1553,6 +1801,42 @@ impl Burnchain {
             highest_header_height, end_block, db_height
         );
 
+        if did_reorg {
+            // blocks at and below the common ancestor are still valid, so only the blocks
+            // above it get re-downloaded
+            debug!("Resuming sync after burnchain reorg";
+                   "common_ancestor_height" => start_block,
+                   "reorg_height" => sync_height);
+        }
+        let reorg_depth = if did_reorg {
+            db_height.saturating_sub(start_block)
+        } else {
+            0
+        };
+
+        // report of a sync that stops at `header` without storing any blocks
+        let unsynced_report = |header: BurnchainBlockHeader| SyncReport {
+            stats: SyncStats {
+                start_height: start_block,
+                end_height: header.block_height,
+                reorg_depth,
+                ..SyncStats::default()
+            },
+            outcome: SyncOutcome::Complete(header),
+            block_latencies: vec![],
+        };
+
+        if let (Some(SyncTarget::Hash(target_hash)), Some(target_block_height)) =
+            (target_opt.as_ref(), target_block_height_opt)
+        {
+            if target_block_height <= start_block {
+                // the target block is an ancestor of a block we already have
+                debug!("Target burnchain block {target_hash} at height {target_block_height} already processed");
+                return BurnchainDB::get_burnchain_block(burnchain_db.conn(), target_hash)
+                    .map(|block_data| unsynced_report(block_data.header));
+            }
+        }
+
         if let Some(target_block_height) = target_block_height_opt {
             // `target_block_height` is used as a hint, but could also be completely off
             // in certain situations. This function is directly reading the
@@ -1572,6 +1856,9 @@ impl Burnchain {
             }
         }
 
+        // where the sync would stop if it weren't for `max_blocks_opt`
+        let sync_end_block = end_block;
+
         if let Some(max_blocks) = max_blocks_opt {
             if start_block + max_blocks < end_block {
                 debug!(
@@ -1595,13 +1882,13 @@ impl Burnchain {
                     BurnchainHeaderHash::from_bitcoin_hash(&BitcoinSha256dHash(hdr.header_hash()));
 
                 return BurnchainDB::get_burnchain_block(burnchain_db.conn(), &bhh)
-                    .map(|block_data| block_data.header);
+                    .map(|block_data| unsynced_report(block_data.header));
             }
         }
 
         if start_block == db_height && db_height == end_block {
             // all caught up
-            return Ok(burnchain_tip);
+            return Ok(unsynced_report(burnchain_tip));
         }
 
         let total = sync_height - self.first_block_height;
@@ -1612,12 +1899,21 @@ impl Burnchain {
         );
 
         // synchronize
+        let num_parse_workers = indexer.parse_workers().max(1);
+        let join_timeout = indexer.worker_join_timeout();
         let (downloader_send, downloader_recv) = sync_channel(1);
         let (parser_send, parser_recv) = sync_channel(1);
-        let (db_send, db_recv) = sync_channel(1);
+        let (db_send, db_recv) = sync_channel(num_parse_workers);
+        // unbounded, so the db thread never waits on progress reports
+        let (progress_send, progress_recv) = channel();
+
+        // parse workers take turns pulling downloaded blocks off of the same channel
+        let parser_recv = Arc::new(Mutex::new(parser_recv));
 
         let mut downloader = indexer.downloader();
-        let mut parser = indexer.parser();
+        let mut rate_limiter = indexer
+            .download_rate_limit()
+            .map(|rate| DownloadRateLimiter::with_clock(rate, indexer.sync_clock()));
 
         let myself = self.clone();
         let input_headers = indexer.read_headers(start_block + 1, end_block + 1)?;
@@ -1630,134 +1926,219 @@ impl Burnchain {
 
         // TODO: don't re-process blocks.  See if the block hash is already present in the burn db,
         // and if so, do nothing.
-        let download_thread: thread::JoinHandle<Result<(), burnchain_error>> =
-            thread::Builder::new()
-                .name("burnchain-downloader".to_string())
-                .spawn(move || {
-                    while let Ok(Some(ipc_header)) = downloader_recv.recv() {
-                        debug!("Try recv next header");
-
-                        match should_keep_running {
-                            Some(ref should_keep_running)
-                                if !should_keep_running.load(Ordering::SeqCst) =>
-                            {
-                                return Err(burnchain_error::CoordinatorClosed);
-                            }
-                            _ => {}
-                        };
+        let download_thread: WorkerThread<Result<u64, burnchain_error>> =
+            WorkerThread::spawn("burnchain-downloader", move || {
+                // sequence number of each downloaded block, so the db thread can store
+                // blocks in download order no matter which parse worker handled them
+                let mut download_seq: u64 = 0;
+                while let Ok(Some(ipc_header)) = downloader_recv.recv() {
+                    debug!("Try recv next header");
 
-                        if fault_inject_downloader_on_reorg(did_reorg) {
-                            warn!("Stalling and yielding an error for the reorg";
-                                  "error_ht" => BurnHeaderIPC::height(&ipc_header),
-                                  "sync_ht" => sync_height,
-                                  "start_ht" => start_block,
-                                  "end_ht" => end_block,
-                            );
-                            thread::sleep(Duration::from_secs(10));
-                            return Err(burnchain_error::UnsupportedBurnchain);
+                    match should_keep_running {
+                        Some(ref should_keep_running)
+                            if !should_keep_running.load(Ordering::SeqCst) =>
+                        {
+                            return Err(burnchain_error::CoordinatorClosed);
                         }
-
-                        let download_start = get_epoch_time_ms();
-                        let ipc_block = downloader.download(&ipc_header)?;
-                        let download_end = get_epoch_time_ms();
-
-                        debug!(
-                            "Downloaded block {} in {}ms",
-                            ipc_block.height(),
-                            download_end.saturating_sub(download_start)
+                        _ => {}
+                    };
+
+                    if fault_inject_downloader_on_reorg(did_reorg) {
+                        warn!("Stalling and yielding an error for the reorg";
+                              "error_ht" => BurnHeaderIPC::height(&ipc_header),
+                              "sync_ht" => sync_height,
+                              "start_ht" => start_block,
+                              "end_ht" => end_block,
                         );
-
-                        parser_send
-                            .send(Some(ipc_block))
-                            .map_err(|_e| burnchain_error::ThreadChannelError)?;
+                        thread::sleep(Duration::from_secs(10));
+                        return Err(burnchain_error::UnsupportedBurnchain);
                     }
-                    parser_send
-                        .send(None)
-                        .map_err(|_e| burnchain_error::ThreadChannelError)?;
-                    Ok(())
-                })
-                .unwrap();
 
-        let parse_thread: thread::JoinHandle<Result<(), burnchain_error>> = thread::Builder::new()
-            .name("burnchain-parser".to_string())
-            .spawn(move || {
-                while let Ok(Some(ipc_block)) = parser_recv.recv() {
-                    debug!("Try recv next block");
-
-                    let cur_epoch =
-                        SortitionDB::get_stacks_epoch(sortdb.conn(), ipc_block.height())?
-                            .unwrap_or_else(|| {
-                                panic!("FATAL: no stacks epoch defined for {}", ipc_block.height())
-                            });
+                    if let Some(ref mut rate_limiter) = rate_limiter {
+                        rate_limiter.acquire();
+                    }
 
-                    let parse_start = get_epoch_time_ms();
-                    let burnchain_block = parser.parse(&ipc_block, cur_epoch.epoch_id)?;
-                    let parse_end = get_epoch_time_ms();
+                    let download_start = get_epoch_time_ms();
+                    let ipc_block = downloader.download(&ipc_header).map_err(|e| {
+                        e.with_context(SyncStage::Download, BurnHeaderIPC::height(&ipc_header))
+                    })?;
+                    let download_end = get_epoch_time_ms();
+                    let latency = BlockLatency {
+                        block_height: ipc_block.height(),
+                        download_start_ms: download_start,
+                        download_end_ms: download_end,
+                        ..BlockLatency::default()
+                    };
+
+                    // the block could have changed since its header was read
+                    let block_hash = ipc_block.header().header_hash();
+                    let header_hash = ipc_header.header_hash();
+                    if block_hash != header_hash {
+                        let block_hash =
+                            BurnchainHeaderHash::from_bitcoin_hash(&BitcoinSha256dHash(block_hash));
+                        let header_hash = BurnchainHeaderHash::from_bitcoin_hash(
+                            &BitcoinSha256dHash(header_hash),
+                        );
+                        warn!("Downloaded burnchain block does not match its header";
+                              "height" => ipc_block.height(),
+                              "header_hash" => %header_hash,
+                              "block_hash" => %block_hash,
+                        );
+                        return Err(burnchain_error::BurnchainPeerBroken
+                            .with_context(SyncStage::Download, ipc_block.height()));
+                    }
 
                     debug!(
-                        "Parsed block {} (in epoch {}) in {}ms",
-                        burnchain_block.block_height(),
-                        cur_epoch.epoch_id,
-                        parse_end.saturating_sub(parse_start);
-                        "burn_block_hash" => %burnchain_block.block_hash()
+                        "Downloaded block {} in {}ms",
+                        ipc_block.height(),
+                        download_end.saturating_sub(download_start)
                     );
 
-                    db_send
-                        .send(Some(burnchain_block))
+                    parser_send
+                        .send(Some((download_seq, ipc_block, latency)))
                         .map_err(|_e| burnchain_error::ThreadChannelError)?;
+                    download_seq += 1;
                 }
-                db_send
-                    .send(None)
-                    .map_err(|_e| burnchain_error::ThreadChannelError)?;
-                Ok(())
+                for _ in 0..num_parse_workers {
+                    parser_send
+                        .send(None)
+                        .map_err(|_e| burnchain_error::ThreadChannelError)?;
+                }
+                Ok(download_seq)
             })
             .unwrap();
 
-        let db_thread: thread::JoinHandle<Result<BurnchainBlockHeader, burnchain_error>> =
-            thread::Builder::new()
-                .name("burnchain-db".to_string())
-                .spawn(move || {
-                    let mut last_processed = burnchain_tip;
-                    while let Ok(Some(burnchain_block)) = db_recv.recv() {
-                        debug!("Try recv next parsed block");
-
-                        let block_height = burnchain_block.block_height();
-                        if block_height == 0 {
-                            continue;
-                        }
+        let mut parse_threads = Vec::with_capacity(num_parse_workers);
+        for worker_id in 0..num_parse_workers {
+            let mut parser = indexer.parser();
+            let parser_recv = parser_recv.clone();
+            let db_send = db_send.clone();
+            let epochs = epochs.clone();
+            let parse_thread: WorkerThread<Result<u64, burnchain_error>> =
+                WorkerThread::spawn(&format!("burnchain-parser-{worker_id}"), move || {
+                    let mut num_parsed: u64 = 0;
+                    loop {
+                        // only hold the lock while waiting for the next block, so the other
+                        // workers can pick up blocks while this one parses
+                        let next_block = parser_recv.lock().unwrap().recv();
+                        let Ok(Some((download_seq, ipc_block, mut latency))) = next_block else {
+                            break;
+                        };
+                        debug!("Try recv next block");
 
-                        let epoch_index = StacksEpoch::find_epoch(&epochs, block_height)
+                        let epoch_index = StacksEpoch::find_epoch(&epochs, ipc_block.height())
                             .unwrap_or_else(|| {
-                                panic!("FATAL: no epoch defined for height {}", block_height)
+                                panic!("FATAL: no stacks epoch defined for {}", ipc_block.height())
                             });
+                        let cur_epoch = &epochs[epoch_index];
 
-                        let epoch_id = epochs[epoch_index].epoch_id;
-
-                        let insert_start = get_epoch_time_ms();
-
-                        last_processed = Burnchain::process_block(
-                            &myself,
-                            &mut burnchain_db,
-                            &parser_indexer,
-                            &burnchain_block,
-                            epoch_id,
-                        )?;
-
-                        if !coord_comm.announce_new_burn_block() {
-                            return Err(burnchain_error::CoordinatorClosed);
-                        }
-                        let insert_end = get_epoch_time_ms();
+                        let parse_start = get_epoch_time_ms();
+                        let burnchain_block = parser
+                            .parse(&ipc_block, cur_epoch.epoch_id)
+                            .map_err(|e| e.with_context(SyncStage::Parse, ipc_block.height()))?;
+                        let parse_end = get_epoch_time_ms();
+                        latency.parse_start_ms = parse_start;
+                        latency.parse_end_ms = parse_end;
 
                         debug!(
-                            "Inserted block {} in {}ms",
+                            "Parsed block {} (in epoch {}) in {}ms",
                             burnchain_block.block_height(),
-                            insert_end.saturating_sub(insert_start);
-                            "burn_block_hash" => %burnchain_block.block_hash()
+                            cur_epoch.epoch_id,
+                            parse_end.saturating_sub(parse_start);
+                            "burn_block_hash" => %burnchain_block.block_hash(),
+                            "parse_worker" => worker_id
                         );
+
+                        db_send
+                            .send(Some((download_seq, burnchain_block, latency)))
+                            .map_err(|_e| burnchain_error::ThreadChannelError)?;
+                        num_parsed += 1;
                     }
-                    Ok(last_processed)
+                    db_send
+                        .send(None)
+                        .map_err(|_e| burnchain_error::ThreadChannelError)?;
+                    Ok(num_parsed)
                 })
                 .unwrap();
+            parse_threads.push(parse_thread);
+        }
+        drop(db_send);
+
+        let db_thread: WorkerThread<
+            Result<(BurnchainBlockHeader, Vec<BlockLatency>), burnchain_error>,
+        > = WorkerThread::spawn("burnchain-db", move || {
+            let mut last_processed = burnchain_tip;
+            let mut block_latencies = vec![];
+
+            // parse workers can finish out of order, so hold on to parsed blocks until
+            // all of the blocks downloaded before them have been stored
+            let mut reorder_buffer = HashMap::new();
+            let mut next_seq: u64 = 0;
+            let mut finished_workers = 0;
+            while finished_workers < num_parse_workers {
+                let Ok(parsed) = db_recv.recv() else {
+                    break;
+                };
+                let Some((download_seq, burnchain_block, latency)) = parsed else {
+                    finished_workers += 1;
+                    continue;
+                };
+                reorder_buffer.insert(download_seq, (burnchain_block, latency));
+
+                while let Some((burnchain_block, mut latency)) = reorder_buffer.remove(&next_seq) {
+                    next_seq += 1;
+                    debug!("Try recv next parsed block");
+
+                    let block_height = burnchain_block.block_height();
+                    if block_height == 0 {
+                        continue;
+                    }
+
+                    let epoch_index = StacksEpoch::find_epoch(&epochs, block_height)
+                        .unwrap_or_else(|| {
+                            panic!("FATAL: no epoch defined for height {}", block_height)
+                        });
+
+                    let epoch_id = epochs[epoch_index].epoch_id;
+
+                    let insert_start = get_epoch_time_ms();
+
+                    let header = burnchain_block.header();
+                    let ops = burnchain_db.get_new_burnchain_block_ops(
+                        &myself,
+                        &parser_indexer,
+                        &burnchain_block,
+                        epoch_id,
+                    );
+                    sink.process(BurnchainBlockData {
+                        header: header.clone(),
+                        ops,
+                    })
+                    .map_err(|e| e.with_context(SyncStage::Store, block_height))?;
+                    last_processed = header;
+
+                    if !coord_comm.announce_new_burn_block() {
+                        return Err(burnchain_error::CoordinatorClosed);
+                    }
+                    let insert_end = get_epoch_time_ms();
+                    // the coordinating thread only listens if it has a progress callback
+                    let _ = progress_send.send(block_height);
+                    latency.store_start_ms = insert_start;
+                    latency.store_end_ms = insert_end;
+
+                    debug!(
+                        "Inserted block {} in {}ms",
+                        burnchain_block.block_height(),
+                        insert_end.saturating_sub(insert_start);
+                        "burn_block_hash" => %burnchain_block.block_hash(),
+                        "total_latency_ms" => latency.total_ms()
+                    );
+                    block_latencies.push(latency);
+                }
+            }
+            Ok((last_processed, block_latencies))
+        })
+        .unwrap();
 
         // feed the pipeline!
         let mut downloader_result: Result<(), burnchain_error> = Ok(());
@@ -1776,6 +2157,11 @@ impl Burnchain {
                 downloader_result = Err(burnchain_error::TrySyncAgain);
                 break;
             }
+            if let Some(ref mut on_progress) = on_progress {
+                for height in progress_recv.try_iter() {
+                    on_progress(height, end_block);
+                }
+            }
         }
 
         if downloader_result.is_ok() {
@@ -1785,18 +2171,51 @@ impl Burnchain {
             }
         }
 
-        // join up
-        let _ = download_thread.join().unwrap();
-        let _ = parse_thread.join().unwrap();
-        let block_header = match db_thread.join().unwrap() {
-            Ok(x) => x,
+        if let Some(ref mut on_progress) = on_progress {
+            // ends once the db thread hangs up
+            for height in progress_recv.iter() {
+                on_progress(height, end_block);
+            }
+        }
+
+        // join up, without hanging the sync on a worker that is stuck
+        let download_thread_result =
+            Self::handle_thread_join_timeout(download_thread, join_timeout)?;
+        let mut blocks_parsed = 0;
+        for parse_thread in parse_threads {
+            if let Ok(num_parsed) = Self::handle_thread_join_timeout(parse_thread, join_timeout)? {
+                blocks_parsed += num_parsed;
+            }
+        }
+        let (block_header, block_latencies) =
+            match Self::handle_thread_join_timeout(db_thread, join_timeout)? {
+                Ok(x) => x,
+                Err(e) => {
+                    warn!("Failed to join burnchain download thread: {:?}", &e);
+                    // a block that failed to parse or store keeps the stage and height it failed at
+                    return match e {
+                        burnchain_error::Contextual { .. } | burnchain_error::CoordinatorClosed => {
+                            Err(e)
+                        }
+                        _ => Err(burnchain_error::TrySyncAgain),
+                    };
+                }
+            };
+
+        // a block that failed to download keeps the stage and height it failed at. This is
+        // usually a transient failure of the bitcoin node, so callers that match on the error's
+        // root_cause() try again.
+        let blocks_downloaded = match download_thread_result {
+            Ok(num_downloaded) => num_downloaded,
             Err(e) => {
-                warn!("Failed to join burnchain download thread: {:?}", &e);
-                if let burnchain_error::CoordinatorClosed = e {
-                    return Err(burnchain_error::CoordinatorClosed);
-                } else {
-                    return Err(burnchain_error::TrySyncAgain);
+                if matches!(
+                    e.root_cause(),
+                    burnchain_error::BurnchainPeerBroken | burnchain_error::DownloadError(_)
+                ) {
+                    warn!("Failed to synchronize the burn chain: {e}");
+                    return Err(e);
                 }
+                0
             }
         };
 
@@ -1812,7 +2231,78 @@ impl Burnchain {
             return Err(e);
         }
         update_burnchain_height(block_header.block_height as i64);
-        Ok(block_header)
+        let stats = SyncStats {
+            start_height: start_block,
+            end_height: block_header.block_height,
+            blocks_downloaded,
+            blocks_parsed,
+            blocks_stored: block_latencies.len() as u64,
+            reorg_depth,
+        };
+        let outcome = if end_block < sync_end_block {
+            debug!(
+                "Stopped sync at block {} with {} blocks left to sync",
+                end_block,
+                sync_end_block - end_block
+            );
+            SyncOutcome::Partial {
+                header: block_header,
+                remaining: sync_end_block - end_block,
+            }
+        } else {
+            SyncOutcome::Complete(block_header)
+        };
+        Ok(SyncReport {
+            outcome,
+            block_latencies,
+            stats,
+        })
+    }
+
+    /// Replay the Stacks burn operations in the burnchain blocks at heights
+    /// `start_block..end_block`, in chronological order: by height, and by vtxindex within a
+    /// block.  The blocks are downloaded and parsed with the indexer's downloader and parser,
+    /// just as in `sync_with_indexer`, but their operations are checked against a scratch
+    /// in-memory burnchain DB, so nothing is written to this node's burnchain DB.
+    ///
+    /// The indexer's headers must already be synced through the range.  Operations that spend a
+    /// PreStx operation from before `start_block` can't be matched to their sender, so they're
+    /// dropped, just as they would be by a node that never saw that PreStx.
+    pub fn collect_burn_ops<I>(
+        &self,
+        indexer: &I,
+        start_block: u64,
+        end_block: u64,
+    ) -> Result<Vec<(u64, BlockstackOperationType)>, burnchain_error>
+    where
+        I: BurnchainIndexer + BurnchainHeaderReader,
+    {
+        let epochs = indexer.get_stacks_epochs();
+        let mut scratch_db = BurnchainDB::connect(":memory:", self, true)?;
+        let mut downloader = indexer.downloader();
+        let mut parser = indexer.parser();
+
+        let mut burn_ops = vec![];
+        for ipc_header in indexer.read_headers(start_block, end_block)? {
+            let ipc_block = downloader.download(&ipc_header)?;
+            let block_height = ipc_block.height();
+            let epoch_index = StacksEpoch::find_epoch(&epochs, block_height)
+                .unwrap_or_else(|| panic!("FATAL: no epoch defined for height {}", block_height));
+            let epoch_id = epochs[epoch_index].epoch_id;
+
+            let burnchain_block = parser.parse(&ipc_block, epoch_id)?;
+            let block_ops =
+                scratch_db.store_new_burnchain_block(self, indexer, &burnchain_block, epoch_id)?;
+
+            debug!(
+                "Collected {} burn ops from block {}",
+                block_ops.len(),
+                block_height;
+                "burn_block_hash" => %burnchain_block.block_hash()
+            );
+            burn_ops.extend(block_ops.into_iter().map(|op| (block_height, op)));
+        }
+        Ok(burn_ops)
     }
 }
 
@@ -16,7 +16,7 @@
 
 use std::collections::HashMap;
 use std::path::Path;
-use std::{fs, io};
+use std::{fs, io, iter};
 
 use rusqlite::{params, Connection, OpenFlags, Row, Transaction};
 use serde_json;
@@ -66,6 +66,137 @@ pub trait BurnchainHeaderReader {
         let mut hdrs = self.read_burnchain_headers(height, height.saturating_add(1))?;
         Ok(hdrs.pop())
     }
+
+    /// Iterate over the headers from `start_height` up, reading them `batch_size` at a time so
+    /// that the whole history never has to be held in memory. The iteration ends after a read
+    /// returns fewer than `batch_size` headers, or after the first error.
+    fn iter_burnchain_headers(
+        &self,
+        start_height: u64,
+        batch_size: u64,
+    ) -> impl Iterator<Item = Result<BurnchainBlockHeader, DBError>> + '_
+    where
+        Self: Sized,
+    {
+        let batch_size = batch_size.max(1);
+        let mut next_height = start_height;
+        let mut batch = vec![].into_iter();
+        let mut done = false;
+        iter::from_fn(move || loop {
+            if let Some(header) = batch.next() {
+                return Some(Ok(header));
+            }
+            if done {
+                return None;
+            }
+            let end_height = next_height.saturating_add(batch_size);
+            match self.read_burnchain_headers(next_height, end_height) {
+                Ok(headers) => {
+                    done = (headers.len() as u64) < batch_size;
+                    next_height = end_height;
+                    batch = headers.into_iter();
+                }
+                Err(e) => {
+                    done = true;
+                    return Some(Err(e));
+                }
+            }
+        })
+    }
+}
+
+/// How many of the headers stored in `reader` are not on the chain of `candidate_headers`, i.e.
+/// how many blocks a node that switched to the candidate chain would roll back. This is 0 if the
+/// candidate chain builds on the stored tip, including when it is longer than the stored chain.
+///
+/// `candidate_headers` must be a chain of consecutive heights, in ascending order. Its lowest
+/// header must be on the stored chain or build on a stored header; if not, the divergence is
+/// deeper than the candidate chain reaches, and this returns `DBError::NotFoundError`.
+pub fn detect_reorg_depth(
+    reader: &dyn BurnchainHeaderReader,
+    candidate_headers: &[BurnchainBlockHeader],
+) -> Result<u64, DBError> {
+    let num_headers = reader.get_burnchain_headers_height()?;
+    let (Some(stored_tip_height), Some(lowest_candidate)) =
+        (num_headers.checked_sub(1), candidate_headers.first())
+    else {
+        return Ok(0);
+    };
+
+    // walk back from the highest candidate header that is also stored
+    for candidate in candidate_headers
+        .iter()
+        .rev()
+        .skip_while(|candidate| candidate.block_height > stored_tip_height)
+    {
+        let Some(stored) = reader.read_burnchain_header(candidate.block_height)? else {
+            continue;
+        };
+        if stored.block_hash == candidate.block_hash {
+            return Ok(stored_tip_height - candidate.block_height);
+        }
+    }
+
+    // none of the candidate headers are stored, but the chain may still build on a stored one
+    let Some(parent_height) = lowest_candidate.block_height.checked_sub(1) else {
+        return Err(DBError::NotFoundError);
+    };
+    if parent_height > stored_tip_height {
+        return Err(DBError::NotFoundError);
+    }
+    match reader.read_burnchain_header(parent_height)? {
+        Some(parent) if parent.block_hash == lowest_candidate.parent_block_hash => {
+            Ok(stored_tip_height - parent_height)
+        }
+        _ => Err(DBError::NotFoundError),
+    }
+}
+
+/// Where `Burnchain::sync_with_indexer` sends each burnchain block once its Stacks operations
+/// have been extracted.  Blocks are handed over in burnchain order, parents before children.
+pub trait BurnchainBlockSink {
+    fn process(&mut self, block: BurnchainBlockData) -> Result<(), BurnchainError>;
+}
+
+impl BurnchainBlockSink for Box<dyn BurnchainBlockSink + Send> {
+    fn process(&mut self, block: BurnchainBlockData) -> Result<(), BurnchainError> {
+        (**self).process(block)
+    }
+}
+
+/// The default `BurnchainBlockSink`, which stores each block in the burnchain DB and updates
+/// the PoX affirmation maps at reward cycle boundaries.
+pub struct BurnchainDBSink<B: BurnchainHeaderReader> {
+    burnchain: Burnchain,
+    burnchain_db: BurnchainDB,
+    indexer: B,
+}
+
+impl<B: BurnchainHeaderReader> BurnchainDBSink<B> {
+    pub fn new(burnchain: Burnchain, burnchain_db: BurnchainDB, indexer: B) -> Self {
+        Self {
+            burnchain,
+            burnchain_db,
+            indexer,
+        }
+    }
+}
+
+impl<B: BurnchainHeaderReader> BurnchainBlockSink for BurnchainDBSink<B> {
+    fn process(&mut self, block: BurnchainBlockData) -> Result<(), BurnchainError> {
+        self.burnchain_db.store_new_burnchain_block_ops_unchecked(
+            &self.burnchain,
+            &self.indexer,
+            &block.header,
+            &block.ops,
+        )?;
+        Burnchain::process_affirmation_maps(
+            &self.burnchain,
+            &mut self.burnchain_db,
+            &self.indexer,
+            block.header.block_height,
+        )
+    }
 }
 
 #[derive(Debug, Clone)]
@@ -1421,6 +1552,22 @@ impl BurnchainDB {
         Ok(())
     }
 
+    /// Extracts and validates a newly-parsed burnchain block's operations, without storing
+    /// anything.  PreStx operations spent by the block are looked up in this DB.
+    pub fn get_new_burnchain_block_ops<B: BurnchainHeaderReader>(
+        &self,
+        burnchain: &Burnchain,
+        indexer: &B,
+        block: &BurnchainBlock,
+        epoch_id: StacksEpochId,
+    ) -> Vec<BlockstackOperationType> {
+        let header = block.header();
+        let mut blockstack_ops =
+            self.get_blockstack_transactions(burnchain, indexer, block, &header, epoch_id);
+        apply_blockstack_txs_safety_checks(header.block_height, &mut blockstack_ops);
+        blockstack_ops
+    }
+
     /// Stores a newly-parsed burnchain block's relevant data into the DB.
     /// The given block's operations will be validated.
     pub fn store_new_burnchain_block<B: BurnchainHeaderReader>(
@@ -1435,10 +1582,7 @@ impl BurnchainDB {
               "burn_block_hash" => %header.block_hash,
               "block_height" => header.block_height
         );
-        let mut blockstack_ops =
-            self.get_blockstack_transactions(burnchain, indexer, block, &header, epoch_id);
-        apply_blockstack_txs_safety_checks(header.block_height, &mut blockstack_ops);
-
+        let blockstack_ops = self.get_new_burnchain_block_ops(burnchain, indexer, block, epoch_id);
         self.store_new_burnchain_block_ops_unchecked(burnchain, indexer, &header, &blockstack_ops)?;
         Ok(blockstack_ops)
     }
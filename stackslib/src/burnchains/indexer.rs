@@ -14,11 +14,20 @@
 // You should have received a copy of the GNU General Public License
 // along with this program.  If not, see <http://www.gnu.org/licenses/>.
 
+use std::sync::{Arc, Mutex};
+use std::thread;
+use std::time::{Duration, Instant};
+
+use stacks_common::deps_common::bitcoin::util::hash::Sha256dHash;
 use stacks_common::types::chainstate::BurnchainHeaderHash;
 
+use crate::burnchains::db::BurnchainHeaderReader;
 use crate::burnchains::{BurnchainBlock, Error as burnchain_error, *};
 use crate::core::StacksEpochId;
 
+/// Default for `BurnchainIndexer::worker_join_timeout`
+pub const DEFAULT_WORKER_JOIN_TIMEOUT: Duration = Duration::from_secs(600);
+
 // IPC messages between threads
 pub trait BurnHeaderIPC {
     type H: Send + Sync + Clone;
@@ -26,6 +35,7 @@ pub trait BurnHeaderIPC {
     fn height(&self) -> u64;
     fn header(&self) -> Self::H;
     fn header_hash(&self) -> [u8; 32];
+    fn parent_header_hash(&self) -> [u8; 32];
 }
 
 pub trait BurnBlockIPC {
@@ -67,6 +77,9 @@ pub trait BurnchainIndexer {
     fn get_headers_path(&self) -> String;
     fn get_headers_height(&self) -> Result<u64, burnchain_error>;
     fn get_highest_header_height(&self) -> Result<u64, burnchain_error>;
+    /// Find the height of the highest common ancestor of the stored headers and the canonical
+    /// chain. If the canonical chain has more work, the headers of the orphaned blocks are
+    /// replaced by the canonical chain's headers, so callers don't drop any headers themselves.
     fn find_chain_reorg(&mut self) -> Result<u64, burnchain_error>;
     fn sync_headers(
         &mut self,
@@ -76,12 +89,308 @@ pub trait BurnchainIndexer {
     fn drop_headers(&mut self, new_height: u64) -> Result<(), burnchain_error>;
     /// Return headers that fall within the range. If end_block extends beyond the downloaded header range, then the result is truncated.
     fn read_headers(&self, start_block: u64, end_block: u64) -> Result<Vec<<<<Self as BurnchainIndexer>::P as BurnchainBlockParser>::D as BurnchainBlockDownloader>::H>, burnchain_error>;
+    /// Return up to `count` headers, starting at `from_height` and walking down towards the first
+    /// block. The result is ordered by descending height, and is truncated if it reaches the
+    /// first block before `count` headers are read.
+    fn read_headers_rev(
+        &self,
+        from_height: u64,
+        count: u64,
+    ) -> Result<Vec<BurnchainBlockHeader>, burnchain_error>
+    where
+        Self: BurnchainHeaderReader,
+    {
+        let end_block = from_height.saturating_add(1);
+        let start_block = end_block.saturating_sub(count);
+        let mut headers = self.read_burnchain_headers(start_block, end_block)?;
+        headers.reverse();
+        Ok(headers)
+    }
 
     fn downloader(&self) -> <<Self as BurnchainIndexer>::P as BurnchainBlockParser>::D;
     fn parser(&self) -> Self::P;
 
+    /// Number of threads that parse downloaded blocks in parallel while syncing. Each thread gets
+    /// its own parser, and parsed blocks are still stored in download order.
+    fn parse_workers(&self) -> usize {
+        1
+    }
+
+    /// Maximum number of blocks to download per second while syncing, if any
+    fn download_rate_limit(&self) -> Option<u32> {
+        None
+    }
+
+    /// Clock that the sync pipeline measures the download rate limit with
+    fn sync_clock(&self) -> Box<dyn SyncClock> {
+        Box::new(SystemClock)
+    }
+
+    /// How long the sync pipeline waits for each of its worker threads to finish once every
+    /// header has been handed to the downloader, before giving up on the sync
+    fn worker_join_timeout(&self) -> Duration {
+        DEFAULT_WORKER_JOIN_TIMEOUT
+    }
+
     /// Make an instance of the indexer to be consumed by a burnchain indexer thread, for reading
     /// local state (but not downloading or parsing it).
     /// This is different from `clone()` in that not all state needs to be copied.
     fn reader(&self) -> Self;
 }
+
+/// Source of time for throttling the burnchain sync pipeline
+pub trait SyncClock: Send {
+    fn now(&self) -> Instant;
+    fn sleep(&mut self, duration: Duration);
+}
+
+/// Wall-clock time
+#[derive(Debug, Clone, Default)]
+pub struct SystemClock;
+
+impl SyncClock for SystemClock {
+    fn now(&self) -> Instant {
+        Instant::now()
+    }
+
+    fn sleep(&mut self, duration: Duration) {
+        thread::sleep(duration)
+    }
+}
+
+impl SyncClock for Box<dyn SyncClock> {
+    fn now(&self) -> Instant {
+        (**self).now()
+    }
+
+    fn sleep(&mut self, duration: Duration) {
+        (**self).sleep(duration)
+    }
+}
+
+/// Token bucket that limits how many blocks are downloaded per second. The bucket holds up to
+/// one second's worth of tokens, so a burst of up to `rate` downloads can go out at once before
+/// the rest are spaced `1/rate` seconds apart.
+#[derive(Debug)]
+pub struct DownloadRateLimiter<C: SyncClock> {
+    clock: C,
+    /// Time between downloads once the bucket is empty
+    interval: Duration,
+    /// How far the schedule may run ahead of the clock before a download has to wait
+    burst_allowance: Duration,
+    /// Time at which the bucket will be full again
+    full_at: Option<Instant>,
+}
+
+impl DownloadRateLimiter<SystemClock> {
+    /// Limit downloads to `rate` per second of wall-clock time
+    pub fn new(rate: u32) -> DownloadRateLimiter<SystemClock> {
+        DownloadRateLimiter::with_clock(rate, SystemClock)
+    }
+}
+
+impl<C: SyncClock> DownloadRateLimiter<C> {
+    /// Limit downloads to `rate` per second, as measured by `clock`. A rate of 0 is treated as 1.
+    pub fn with_clock(rate: u32, clock: C) -> DownloadRateLimiter<C> {
+        let rate = rate.max(1);
+        let interval = Duration::from_secs(1) / rate;
+        DownloadRateLimiter {
+            clock,
+            interval,
+            burst_allowance: interval * (rate - 1),
+            full_at: None,
+        }
+    }
+
+    /// Take a token from the bucket, waiting for one to become available if it is empty
+    pub fn acquire(&mut self) {
+        let now = self.clock.now();
+        let full_at = self.full_at.map_or(now, |full_at| full_at.max(now));
+        if let Some(available_at) = full_at.checked_sub(self.burst_allowance) {
+            if available_at > now {
+                self.clock.sleep(available_at - now);
+            }
+        }
+        self.full_at = Some(full_at + self.interval);
+    }
+
+    pub fn clock(&self) -> &C {
+        &self.clock
+    }
+
+    pub fn clock_mut(&mut self) -> &mut C {
+        &mut self.clock
+    }
+}
+
+/// Downloader that checks that each block it downloads builds on the block it downloaded just
+/// before, so an inconsistent indexer is caught before its blocks reach the parser. Only runs of
+/// consecutive heights are checked; downloading a block at any other height starts a new run.
+#[derive(Debug, Clone)]
+pub struct LinkageCheckingDownloader<D: BurnchainBlockDownloader> {
+    inner: D,
+    /// Height and hash of the last block downloaded
+    last_block: Option<(u64, [u8; 32])>,
+}
+
+impl<D: BurnchainBlockDownloader> LinkageCheckingDownloader<D> {
+    pub fn new(inner: D) -> LinkageCheckingDownloader<D> {
+        LinkageCheckingDownloader {
+            inner,
+            last_block: None,
+        }
+    }
+
+    pub fn into_inner(self) -> D {
+        self.inner
+    }
+}
+
+impl<D: BurnchainBlockDownloader> BurnchainBlockDownloader for LinkageCheckingDownloader<D> {
+    type H = D::H;
+    type B = D::B;
+
+    fn download(&mut self, header: &D::H) -> Result<D::B, burnchain_error> {
+        let block = self.inner.download(header)?;
+        let block_header = block.header();
+        let height = block_header.height();
+        if let Some((last_height, last_hash)) = self.last_block.take() {
+            let parent_hash = block_header.parent_header_hash();
+            if last_height + 1 == height && parent_hash != last_hash {
+                let parent_hash = BurnchainHeaderHash::from_bitcoin_hash(&Sha256dHash(parent_hash));
+                let last_hash = BurnchainHeaderHash::from_bitcoin_hash(&Sha256dHash(last_hash));
+                warn!(
+                    "Downloaded burnchain block does not build on the previously downloaded block";
+                    "height" => height,
+                    "parent_hash" => %parent_hash,
+                    "last_hash" => %last_hash,
+                );
+                return Err(burnchain_error::BurnchainPeerBroken);
+            }
+        }
+        self.last_block = Some((height, block_header.header_hash()));
+        Ok(block)
+    }
+}
+
+/// Downloader that retries a download that failed with `DownloadError`, which is usually a
+/// transient bitcoin RPC failure, up to `max_retries` times before giving up. It waits
+/// `base_delay` before the first retry, and twice as long before each retry after that. Any
+/// other error is returned right away.
+#[derive(Debug, Clone)]
+pub struct RetryingDownloader<D: BurnchainBlockDownloader, C: SyncClock = SystemClock> {
+    inner: D,
+    max_retries: u32,
+    base_delay: Duration,
+    clock: C,
+}
+
+impl<D: BurnchainBlockDownloader> RetryingDownloader<D, SystemClock> {
+    pub fn new(inner: D, max_retries: u32, base_delay: Duration) -> RetryingDownloader<D> {
+        RetryingDownloader::with_clock(inner, max_retries, base_delay, SystemClock)
+    }
+}
+
+impl<D: BurnchainBlockDownloader, C: SyncClock> RetryingDownloader<D, C> {
+    /// Like `new`, but wait between retries by sleeping on `clock`
+    pub fn with_clock(
+        inner: D,
+        max_retries: u32,
+        base_delay: Duration,
+        clock: C,
+    ) -> RetryingDownloader<D, C> {
+        RetryingDownloader {
+            inner,
+            max_retries,
+            base_delay,
+            clock,
+        }
+    }
+
+    pub fn into_inner(self) -> D {
+        self.inner
+    }
+
+    pub fn clock(&self) -> &C {
+        &self.clock
+    }
+}
+
+impl<D: BurnchainBlockDownloader, C: SyncClock> BurnchainBlockDownloader
+    for RetryingDownloader<D, C>
+{
+    type H = D::H;
+    type B = D::B;
+
+    fn download(&mut self, header: &D::H) -> Result<D::B, burnchain_error> {
+        let mut delay = self.base_delay;
+        let mut retries = 0;
+        loop {
+            match self.inner.download(header) {
+                Err(burnchain_error::DownloadError(e)) if retries < self.max_retries => {
+                    retries += 1;
+                    warn!(
+                        "Failed to download burnchain block, will retry";
+                        "height" => header.height(),
+                        "retry" => retries,
+                        "delay_ms" => delay.as_millis(),
+                        "err" => %e,
+                    );
+                    self.clock.sleep(delay);
+                    delay = delay.saturating_mul(2);
+                }
+                result => return result,
+            }
+        }
+    }
+}
+
+/// What `InstrumentedParser` records for each block it parses: the block's height, how long
+/// parsing it took, and how many ops it yielded (the transactions the parser kept)
+pub type ParseMetric = (u64, Duration, usize);
+
+/// Parser that records a `ParseMetric` for each block that its inner parser parses
+/// successfully. Clones share the record, so it covers every parse worker of a sync and can be
+/// read through `metrics` once the sync is over.
+#[derive(Debug, Clone)]
+pub struct InstrumentedParser<P: BurnchainBlockParser> {
+    inner: P,
+    metrics: Arc<Mutex<Vec<ParseMetric>>>,
+}
+
+impl<P: BurnchainBlockParser> InstrumentedParser<P> {
+    /// Wrap `inner`, recording into `metrics`
+    pub fn new(inner: P, metrics: Arc<Mutex<Vec<ParseMetric>>>) -> InstrumentedParser<P> {
+        InstrumentedParser { inner, metrics }
+    }
+
+    pub fn into_inner(self) -> P {
+        self.inner
+    }
+
+    pub fn metrics(&self) -> Arc<Mutex<Vec<ParseMetric>>> {
+        self.metrics.clone()
+    }
+}
+
+impl<P: BurnchainBlockParser> BurnchainBlockParser for InstrumentedParser<P> {
+    type D = P::D;
+
+    fn parse(
+        &mut self,
+        block: &<P::D as BurnchainBlockDownloader>::B,
+        epoch_id: StacksEpochId,
+    ) -> Result<BurnchainBlock, burnchain_error> {
+        let start = Instant::now();
+        let parsed = self.inner.parse(block, epoch_id)?;
+        let parse_duration = start.elapsed();
+        let num_ops = match parsed {
+            BurnchainBlock::Bitcoin(ref data) => data.txs.len(),
+        };
+        self.metrics
+            .lock()
+            .expect("FATAL: parse metrics lock poisoned")
+            .push((parsed.block_height(), parse_duration, num_ops));
+        Ok(parsed)
+    }
+}
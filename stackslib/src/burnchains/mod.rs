@@ -14,13 +14,17 @@
 // You should have received a copy of the GNU General Public License
 // along with this program.  If not, see <http://www.gnu.org/licenses/>.
 
-use std::collections::HashMap;
+use std::collections::{BTreeMap, HashMap};
 use std::marker::PhantomData;
+use std::sync::atomic::AtomicBool;
+use std::sync::Arc;
+use std::time::Duration;
 use std::{error, fmt, io};
 
 use rusqlite::Error as sqlite_error;
 use stacks_common::types::chainstate::{BurnchainHeaderHash, ConsensusHash, PoxId};
 pub use stacks_common::types::{Address, PrivateKey, PublicKey};
+use stacks_common::util::serde_serializers::prefix_hex;
 
 use self::bitcoin::indexer::{
     BITCOIN_MAINNET as BITCOIN_NETWORK_ID_MAINNET, BITCOIN_MAINNET_NAME,
@@ -28,6 +32,7 @@ use self::bitcoin::indexer::{
     BITCOIN_TESTNET as BITCOIN_NETWORK_ID_TESTNET, BITCOIN_TESTNET_NAME,
 };
 use self::bitcoin::{BitcoinBlock, BitcoinTransaction, Error as btc_error};
+use self::db::BurnchainBlockSink;
 use crate::chainstate::burn::distribution::BurnSamplePoint;
 use crate::chainstate::burn::operations::leader_block_commit::{
     MissedBlockCommit, OUTPUTS_PER_COMMIT,
@@ -247,15 +252,187 @@ pub enum BurnchainBlock {
     // TODO: fill in some more types as we support them
 }
 
-#[derive(Debug, PartialEq, Clone)]
+/// Serializes to JSON with 0x-prefixed hex hashes, so headers can be captured as test fixtures
+#[derive(Debug, PartialEq, Clone, Serialize, Deserialize)]
 pub struct BurnchainBlockHeader {
     pub block_height: u64,
+    #[serde(with = "prefix_hex")]
     pub block_hash: BurnchainHeaderHash,
+    #[serde(with = "prefix_hex")]
     pub parent_block_hash: BurnchainHeaderHash,
     pub num_txs: u64,
     pub timestamp: u64,
 }
 
+impl_file_io_serde_json!(BurnchainBlockHeader);
+
+/// Where a burnchain sync should stop
+#[derive(Debug, PartialEq, Clone)]
+pub enum SyncTarget {
+    /// Sync up to this burnchain block height. This is a hint, and is ignored if it falls
+    /// outside of the range of blocks to download.
+    Height(u64),
+    /// Sync until the burnchain block with this hash has been processed
+    Hash(BurnchainHeaderHash),
+}
+
+/// A stage of the burnchain sync pipeline
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub enum SyncStage {
+    Download,
+    Parse,
+    Store,
+}
+
+impl fmt::Display for SyncStage {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            SyncStage::Download => write!(f, "download"),
+            SyncStage::Parse => write!(f, "parse"),
+            SyncStage::Store => write!(f, "store"),
+        }
+    }
+}
+
+/// How far a burnchain sync got
+#[derive(Debug, PartialEq, Clone)]
+pub enum SyncOutcome {
+    /// The sync reached its target (or the burnchain tip). This is the new burnchain tip.
+    Complete(BurnchainBlockHeader),
+    /// The sync stopped short of its target because of its `max_blocks` limit, and should be
+    /// called again to fetch the `remaining` blocks.
+    Partial {
+        header: BurnchainBlockHeader,
+        remaining: u64,
+    },
+}
+
+impl SyncOutcome {
+    /// The header of the highest block processed by the sync
+    pub fn header(&self) -> &BurnchainBlockHeader {
+        match self {
+            SyncOutcome::Complete(header) => header,
+            SyncOutcome::Partial { header, .. } => header,
+        }
+    }
+
+    pub fn into_header(self) -> BurnchainBlockHeader {
+        match self {
+            SyncOutcome::Complete(header) => header,
+            SyncOutcome::Partial { header, .. } => header,
+        }
+    }
+
+    pub fn is_complete(&self) -> bool {
+        matches!(self, SyncOutcome::Complete(_))
+    }
+}
+
+/// When a burnchain block passed through each stage of the sync pipeline, in milliseconds since
+/// the epoch
+#[derive(Debug, PartialEq, Clone, Default)]
+pub struct BlockLatency {
+    pub block_height: u64,
+    pub download_start_ms: u64,
+    pub download_end_ms: u64,
+    pub parse_start_ms: u64,
+    pub parse_end_ms: u64,
+    pub store_start_ms: u64,
+    pub store_end_ms: u64,
+}
+
+impl BlockLatency {
+    /// Time spent downloading the block
+    pub fn download_ms(&self) -> u64 {
+        self.download_end_ms.saturating_sub(self.download_start_ms)
+    }
+
+    /// Time spent parsing the block
+    pub fn parse_ms(&self) -> u64 {
+        self.parse_end_ms.saturating_sub(self.parse_start_ms)
+    }
+
+    /// Time spent storing the block
+    pub fn store_ms(&self) -> u64 {
+        self.store_end_ms.saturating_sub(self.store_start_ms)
+    }
+
+    /// Time from the start of the block's download until it was stored. This includes the time
+    /// the block spent waiting between stages.
+    pub fn total_ms(&self) -> u64 {
+        self.store_end_ms.saturating_sub(self.download_start_ms)
+    }
+}
+
+/// Block counts of a burnchain sync
+#[derive(Debug, PartialEq, Clone, Default)]
+pub struct SyncStats {
+    /// Height the sync resumed from: the burnchain DB's tip, or the common ancestor of the
+    /// burnchain DB's tip and the new canonical chain after a reorg
+    pub start_height: u64,
+    /// Height of the burnchain block the sync ended at
+    pub end_height: u64,
+    /// Number of blocks downloaded
+    pub blocks_downloaded: u64,
+    /// Number of blocks parsed
+    pub blocks_parsed: u64,
+    /// Number of blocks stored
+    pub blocks_stored: u64,
+    /// Number of blocks of the burnchain DB's canonical chain that a reorg invalidated, or 0 if
+    /// there was no reorg
+    pub reorg_depth: u64,
+}
+
+/// Options for `Burnchain::sync_with_indexer_options`
+#[derive(Default)]
+pub struct SyncOptions<'a> {
+    /// Where the sync should stop, if not at the burnchain tip
+    pub target: Option<SyncTarget>,
+    /// Maximum number of blocks to sync in one call, if any
+    pub max_blocks: Option<u64>,
+    /// If set, the sync stops with `Error::CoordinatorClosed` once this is false
+    pub should_keep_running: Option<Arc<AtomicBool>>,
+    /// Called on the syncing thread with `(current_height, target_height)` each time a block has
+    /// been stored
+    pub on_progress: Option<&'a mut dyn FnMut(u64, u64)>,
+    /// If set, each processed block is handed to this sink instead of being stored in the
+    /// burnchain DB. The burnchain DB is still read, both to find where to resume the sync and
+    /// to match operations to the PreStx operations they spend.
+    pub sink: Option<Box<dyn BurnchainBlockSink + Send>>,
+}
+
+/// What happened to each burnchain block during a sync
+#[derive(Debug, PartialEq, Clone)]
+pub struct SyncReport {
+    /// Where the sync ended
+    pub outcome: SyncOutcome,
+    /// Latency of each stored block, in the order the blocks were stored
+    pub block_latencies: Vec<BlockLatency>,
+    /// Block counts of the sync
+    pub stats: SyncStats,
+}
+
+impl SyncReport {
+    /// Count the stored blocks by end-to-end latency, in buckets of `bucket_ms` milliseconds.
+    /// Maps the lower bound of each non-empty bucket to its number of blocks.
+    pub fn latency_histogram(&self, bucket_ms: u64) -> BTreeMap<u64, u64> {
+        let bucket_ms = bucket_ms.max(1);
+        let mut histogram = BTreeMap::new();
+        for latency in self.block_latencies.iter() {
+            let bucket = latency.total_ms() / bucket_ms * bucket_ms;
+            *histogram.entry(bucket).or_insert(0) += 1;
+        }
+        histogram
+    }
+
+    /// The block that took the longest to get through the pipeline, if any were stored
+    pub fn slowest_block(&self) -> Option<&BlockLatency> {
+        self.block_latencies
+            .iter()
+            .max_by_key(|latency| latency.total_ms())
+    }
+}
+
 #[derive(Debug, PartialEq, Clone, Serialize, Deserialize)]
 pub struct Burnchain {
     pub peer_version: u32,
@@ -410,8 +587,31 @@ impl PoxConstants {
     }
 
     pub fn reward_slots(&self) -> u32 {
-        (self.reward_cycle_length - self.prepare_length)
-            * u32::try_from(OUTPUTS_PER_COMMIT).expect("FATAL: > 2^32 outputs per commit")
+        self.total_reward_blocks_per_cycle() * self.slots_per_reward_block()
+    }
+
+    /// Number of reward slots paid out by a single reward-phase block commit
+    pub fn slots_per_reward_block(&self) -> u32 {
+        u32::try_from(OUTPUTS_PER_COMMIT).expect("FATAL: > 2^32 outputs per commit")
+    }
+
+    /// Number of blocks in a reward cycle that can pay PoX recipients.
+    /// Prepare-phase blocks always burn, so this excludes them.
+    pub fn total_reward_blocks_per_cycle(&self) -> u32 {
+        self.reward_cycle_length - self.prepare_length
+    }
+
+    /// Estimate how many reward slots a PoX address earns with `amount_ustx` stacked to it, given
+    /// the reward cycle's stacking minimum. As in the reward set, this is `amount_ustx` divided
+    /// by the minimum and rounded down, so any amount below the minimum earns no slots. The
+    /// estimate is capped at `reward_slots()`, and is 0 if the minimum is 0.
+    pub fn estimate_reward_slots(&self, amount_ustx: u128, stacking_minimum: u128) -> u32 {
+        if stacking_minimum == 0 {
+            return 0;
+        }
+        u32::try_from(amount_ustx / stacking_minimum)
+            .unwrap_or(u32::MAX)
+            .min(self.reward_slots())
     }
 
     /// is participating_ustx enough to engage in PoX in the next reward cycle?
@@ -742,6 +942,55 @@ pub enum Error {
     ShutdownInitiated,
     /// No epoch defined at that height
     NoStacksEpoch,
+    /// The burnchain's configured first block is not the indexer's first block
+    FirstBlockMismatch {
+        configured_height: u64,
+        configured_hash: BurnchainHeaderHash,
+        indexer_height: u64,
+        indexer_hash: BurnchainHeaderHash,
+    },
+    /// A block height before the burnchain's first block
+    HeightBeforeFirstBlock {
+        height: u64,
+        first_block_height: u64,
+    },
+    /// A worker thread panicked. Carries the thread's name and its panic message.
+    ThreadPanic {
+        name: String,
+        message: String,
+    },
+    /// A worker thread did not finish within the time it was given to. Carries the thread's name
+    /// and that timeout.
+    ThreadTimeout {
+        name: String,
+        timeout: Duration,
+    },
+    /// An error from the sync pipeline, with the stage and the burnchain block height it
+    /// happened at
+    Contextual {
+        stage: SyncStage,
+        height: u64,
+        source: Box<Error>,
+    },
+}
+
+impl Error {
+    /// Wrap this error with the sync pipeline stage and burnchain block height it happened at
+    pub fn with_context(self, stage: SyncStage, height: u64) -> Error {
+        Error::Contextual {
+            stage,
+            height,
+            source: Box::new(self),
+        }
+    }
+
+    /// The error underneath any `Contextual` wrappers
+    pub fn root_cause(&self) -> &Error {
+        match self {
+            Error::Contextual { source, .. } => source.root_cause(),
+            e => e,
+        }
+    }
 }
 
 impl fmt::Display for Error {
@@ -771,6 +1020,36 @@ impl fmt::Display for Error {
                 f,
                 "No Stacks epoch is defined at the height being evaluated"
             ),
+            Error::FirstBlockMismatch {
+                configured_height,
+                configured_hash,
+                indexer_height,
+                indexer_hash,
+            } => write!(
+                f,
+                "Burnchain is configured to start at block {configured_hash} (height {configured_height}), but the indexer starts at block {indexer_hash} (height {indexer_height})"
+            ),
+            Error::HeightBeforeFirstBlock {
+                height,
+                first_block_height,
+            } => write!(
+                f,
+                "Block height {height} is before the first burnchain block height {first_block_height}"
+            ),
+            Error::ThreadPanic { name, message } => {
+                write!(f, "Thread {name} panicked: {message}")
+            }
+            Error::ThreadTimeout { name, timeout } => {
+                write!(f, "Thread {name} did not finish within {timeout:?}")
+            }
+            Error::Contextual {
+                stage,
+                height,
+                source,
+            } => write!(
+                f,
+                "Failed to {stage} burnchain block at height {height}: {source}"
+            ),
         }
     }
 }
@@ -795,6 +1074,11 @@ impl error::Error for Error {
             Error::CoordinatorClosed => None,
             Error::ShutdownInitiated => None,
             Error::NoStacksEpoch => None,
+            Error::FirstBlockMismatch { .. } => None,
+            Error::HeightBeforeFirstBlock { .. } => None,
+            Error::ThreadPanic { .. } => None,
+            Error::ThreadTimeout { .. } => None,
+            Error::Contextual { ref source, .. } => Some(source.as_ref()),
         }
     }
 }
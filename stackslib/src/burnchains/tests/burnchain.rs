@@ -14,6 +14,8 @@
 // You should have received a copy of the GNU General Public License
 // along with this program.  If not, see <http://www.gnu.org/licenses/>.
 
+use std::time::Duration;
+
 use rand::rngs::ThreadRng;
 use rand::thread_rng;
 use stacks_common::address::AddressHashMode;
@@ -27,7 +29,8 @@ use stacks_common::util::vrf::{VRFPrivateKey, VRFPublicKey};
 
 use crate::burnchains::bitcoin::keys::BitcoinPublicKey;
 use crate::burnchains::bitcoin::*;
-use crate::burnchains::{Txid, *};
+use crate::burnchains::burnchain::WorkerThread;
+use crate::burnchains::{Error as burnchain_error, Txid, *};
 use crate::chainstate::burn::db::sortdb::{SortitionDB, SortitionHandleTx};
 use crate::chainstate::burn::operations::leader_block_commit::BURN_BLOCK_MINED_AT_MODULUS;
 use crate::chainstate::burn::operations::{
@@ -891,3 +894,226 @@ fn test_burn_snapshot_sequence() {
         prev_snapshot = snapshot;
     }
 }
+
+#[test]
+fn test_burnchain_block_header_serde() {
+    let header = BurnchainBlockHeader {
+        block_height: 840_000,
+        block_hash: BurnchainHeaderHash::from_hex(
+            "0000000000000000000320283a032748cef8227873ff4872689bf23f1cda83a5",
+        )
+        .unwrap(),
+        parent_block_hash: BurnchainHeaderHash::from_hex(
+            "0000000000000000000172014ba58d66455762add0512355ad651207918494ab",
+        )
+        .unwrap(),
+        num_txs: 3050,
+        timestamp: 1713571767,
+    };
+
+    let json = serde_json::to_string(&header).unwrap();
+    assert_eq!(
+        json,
+        concat!(
+            r#"{"block_height":840000,"#,
+            r#""block_hash":"0x0000000000000000000320283a032748cef8227873ff4872689bf23f1cda83a5","#,
+            r#""parent_block_hash":"0x0000000000000000000172014ba58d66455762add0512355ad651207918494ab","#,
+            r#""num_txs":3050,"timestamp":1713571767}"#
+        )
+    );
+
+    let decoded: BurnchainBlockHeader = serde_json::from_str(&json).unwrap();
+    assert_eq!(decoded, header);
+    assert_eq!(serde_json::to_string(&decoded).unwrap(), json);
+}
+
+#[test]
+fn test_pox_constants_reward_slot_breakdown() {
+    for pox_constants in [
+        PoxConstants::mainnet_default(),
+        PoxConstants::testnet_default(),
+        PoxConstants::regtest_default(),
+        PoxConstants::test_default(),
+        PoxConstants::test_20_no_sunset(),
+    ] {
+        assert_eq!(
+            pox_constants.slots_per_reward_block() * pox_constants.total_reward_blocks_per_cycle(),
+            pox_constants.reward_slots()
+        );
+        assert_eq!(
+            pox_constants.total_reward_blocks_per_cycle() + pox_constants.prepare_length,
+            pox_constants.reward_cycle_length
+        );
+    }
+
+    // 10-block cycles with 5-block prepare phases leave 5 reward blocks of 2 slots each
+    let pox_constants = PoxConstants::test_default();
+    assert_eq!(pox_constants.slots_per_reward_block(), 2);
+    assert_eq!(pox_constants.total_reward_blocks_per_cycle(), 5);
+    assert_eq!(pox_constants.reward_slots(), 10);
+}
+
+#[test]
+fn test_pox_constants_estimate_reward_slots() {
+    // 10 reward slots
+    let pox_constants = PoxConstants::test_default();
+    let minimum = 1_000;
+
+    // exact multiples of the minimum
+    assert_eq!(pox_constants.estimate_reward_slots(minimum, minimum), 1);
+    assert_eq!(pox_constants.estimate_reward_slots(3 * minimum, minimum), 3);
+
+    // just below a multiple only earns the slots below it
+    assert_eq!(pox_constants.estimate_reward_slots(minimum - 1, minimum), 0);
+    assert_eq!(
+        pox_constants.estimate_reward_slots(3 * minimum - 1, minimum),
+        2
+    );
+    assert_eq!(pox_constants.estimate_reward_slots(0, minimum), 0);
+
+    // never more than the cycle's reward slots
+    assert_eq!(
+        pox_constants.estimate_reward_slots(100 * minimum, minimum),
+        pox_constants.reward_slots()
+    );
+    assert_eq!(
+        pox_constants.estimate_reward_slots(u128::MAX, 1),
+        pox_constants.reward_slots()
+    );
+
+    assert_eq!(pox_constants.estimate_reward_slots(minimum, 0), 0);
+}
+
+#[test]
+fn test_reward_cycle_of_block() {
+    let first_block_height = 100;
+    let mut burnchain =
+        Burnchain::default_unittest(first_block_height, &BurnchainHeaderHash::zero());
+    burnchain.pox_constants = PoxConstants::test_default();
+    let reward_cycle_length = u64::from(burnchain.pox_constants.reward_cycle_length);
+
+    // before the first block
+    let e = burnchain
+        .reward_cycle_of_block(first_block_height - 1)
+        .unwrap_err();
+    match &e {
+        burnchain_error::HeightBeforeFirstBlock {
+            height,
+            first_block_height: first,
+        } => {
+            assert_eq!(*height, first_block_height - 1);
+            assert_eq!(*first, first_block_height);
+        }
+        e => panic!("Expected HeightBeforeFirstBlock error, got {e:?}"),
+    }
+    assert!(e.to_string().contains("99"));
+    assert!(burnchain.block_height_to_reward_cycle(0).is_none());
+
+    // at the first block
+    assert_eq!(
+        burnchain.reward_cycle_of_block(first_block_height).unwrap(),
+        0
+    );
+
+    // mid-chain, agreeing with `block_height_to_reward_cycle`
+    let height = first_block_height + 3 * reward_cycle_length + 4;
+    assert_eq!(burnchain.reward_cycle_of_block(height).unwrap(), 3);
+    assert_eq!(
+        burnchain.block_height_to_reward_cycle(height),
+        Some(burnchain.reward_cycle_of_block(height).unwrap())
+    );
+}
+
+#[test]
+fn test_handle_thread_join() {
+    let handle = std::thread::Builder::new()
+        .name("burnchain-test-ok".to_string())
+        .spawn(|| 42)
+        .unwrap();
+    assert_eq!(Burnchain::handle_thread_join(handle).unwrap(), 42);
+}
+
+#[test]
+fn test_handle_thread_join_panics() {
+    // a `&str` payload
+    let handle = std::thread::Builder::new()
+        .name("burnchain-test-panic".to_string())
+        .spawn(|| -> u64 { panic!("boom") })
+        .unwrap();
+    let e = Burnchain::handle_thread_join(handle).unwrap_err();
+    match &e {
+        burnchain_error::ThreadPanic { name, message } => {
+            assert_eq!(name, "burnchain-test-panic");
+            assert_eq!(message, "boom");
+        }
+        e => panic!("Expected ThreadPanic error, got {e:?}"),
+    }
+    assert!(e.to_string().contains("boom"));
+
+    // a `String` payload, from a formatted panic message
+    let height = 7;
+    let handle = std::thread::Builder::new()
+        .name("burnchain-test-panic-fmt".to_string())
+        .spawn(move || -> u64 { panic!("boom at height {height}") })
+        .unwrap();
+    match Burnchain::handle_thread_join(handle).unwrap_err() {
+        burnchain_error::ThreadPanic { name, message } => {
+            assert_eq!(name, "burnchain-test-panic-fmt");
+            assert_eq!(message, "boom at height 7");
+        }
+        e => panic!("Expected ThreadPanic error, got {e:?}"),
+    }
+}
+
+#[test]
+fn test_handle_thread_join_timeout() {
+    // a thread that is already done
+    let worker = WorkerThread::spawn("burnchain-test-fast", || 42).unwrap();
+    assert_eq!(
+        Burnchain::handle_thread_join_timeout(worker, Duration::from_secs(5)).unwrap(),
+        42
+    );
+
+    // a thread that takes a while, but less than the timeout
+    let worker = WorkerThread::spawn("burnchain-test-slow", || {
+        std::thread::sleep(Duration::from_millis(100));
+        43
+    })
+    .unwrap();
+    assert_eq!(
+        Burnchain::handle_thread_join_timeout(worker, Duration::from_secs(5)).unwrap(),
+        43
+    );
+
+    // a thread that panics is still reported as such
+    let worker = WorkerThread::spawn("burnchain-test-panic", || -> u64 { panic!("boom") }).unwrap();
+    match Burnchain::handle_thread_join_timeout(worker, Duration::from_secs(5)).unwrap_err() {
+        burnchain_error::ThreadPanic { name, message } => {
+            assert_eq!(name, "burnchain-test-panic");
+            assert_eq!(message, "boom");
+        }
+        e => panic!("Expected ThreadPanic error, got {e:?}"),
+    }
+}
+
+#[test]
+fn test_handle_thread_join_timeout_expires() {
+    let worker = WorkerThread::spawn("burnchain-test-hung", || {
+        std::thread::sleep(Duration::from_secs(2));
+        44
+    })
+    .unwrap();
+    let timeout = Duration::from_millis(100);
+    let e = Burnchain::handle_thread_join_timeout(worker, timeout).unwrap_err();
+    match &e {
+        burnchain_error::ThreadTimeout {
+            name,
+            timeout: error_timeout,
+        } => {
+            assert_eq!(name, "burnchain-test-hung");
+            assert_eq!(*error_timeout, timeout);
+        }
+        e => panic!("Expected ThreadTimeout error, got {e:?}"),
+    }
+    assert!(e.to_string().contains("burnchain-test-hung"));
+}
@@ -28,7 +28,8 @@ use crate::burnchains::affirmation::AffirmationMap;
 use crate::burnchains::bitcoin::address::*;
 use crate::burnchains::bitcoin::blocks::*;
 use crate::burnchains::bitcoin::*;
-use crate::burnchains::db::apply_blockstack_txs_safety_checks;
+use crate::burnchains::db::{apply_blockstack_txs_safety_checks, detect_reorg_depth};
+use crate::burnchains::tests::test_doubles::{build_stub_chain, fork_hash, StubBlock};
 use crate::burnchains::{Error as BurnchainError, PoxConstants, BLOCKSTACK_MAGIC_MAINNET};
 use crate::chainstate::burn::operations::leader_block_commit::BURN_BLOCK_MINED_AT_MODULUS;
 use crate::chainstate::burn::*;
@@ -1354,3 +1355,76 @@ fn test_classify_delegate_stx() {
         panic!("EXPECTED to parse a delegate stx op");
     }
 }
+
+/// Headers of the chain `0..=tip_height` that follows the `stub_hash` chain up to
+/// `ancestor_height`, and forks off with `fork_hash(fork_id, ..)` after that
+fn candidate_chain(
+    ancestor_height: u64,
+    tip_height: u64,
+    fork_id: u8,
+) -> Vec<BurnchainBlockHeader> {
+    let mut blocks = build_stub_chain(ancestor_height + 1);
+    for height in ancestor_height + 1..=tip_height {
+        let parent_hash = blocks.last().unwrap().hash.clone();
+        blocks.push(StubBlock::new(
+            height,
+            fork_hash(fork_id, height),
+            parent_hash,
+        ));
+    }
+    blocks.iter().map(StubBlock::header).collect()
+}
+
+#[test]
+fn test_detect_reorg_depth() {
+    let reader = candidate_chain(7, 7, 0);
+
+    // no reorg: the same chain, a suffix of it, or a longer chain building on the stored tip
+    let same_chain = candidate_chain(7, 7, 1);
+    assert_eq!(detect_reorg_depth(&reader, &same_chain).unwrap(), 0);
+    assert_eq!(detect_reorg_depth(&reader, &same_chain[4..]).unwrap(), 0);
+    let longer_chain = candidate_chain(7, 12, 1);
+    assert_eq!(detect_reorg_depth(&reader, &longer_chain).unwrap(), 0);
+    assert_eq!(detect_reorg_depth(&reader, &longer_chain[8..]).unwrap(), 0);
+    assert_eq!(detect_reorg_depth(&reader, &[]).unwrap(), 0);
+
+    // 1-block reorg, whether or not the fork is longer than the stored chain
+    assert_eq!(
+        detect_reorg_depth(&reader, &candidate_chain(6, 7, 1)).unwrap(),
+        1
+    );
+    assert_eq!(
+        detect_reorg_depth(&reader, &candidate_chain(6, 10, 1)).unwrap(),
+        1
+    );
+
+    // deep reorg, including when the candidate headers start right after the fork point
+    let deep_fork = candidate_chain(2, 9, 1);
+    assert_eq!(detect_reorg_depth(&reader, &deep_fork).unwrap(), 5);
+    assert_eq!(detect_reorg_depth(&reader, &deep_fork[3..]).unwrap(), 5);
+
+    // the candidate headers don't reach back to the fork point
+    assert!(matches!(
+        detect_reorg_depth(&reader, &deep_fork[4..]),
+        Err(DBError::NotFoundError)
+    ));
+}
+
+#[test]
+fn test_iter_burnchain_headers() {
+    let reader = candidate_chain(6, 6, 0);
+
+    let headers: Vec<_> = reader
+        .iter_burnchain_headers(0, 3)
+        .collect::<Result<_, _>>()
+        .unwrap();
+    assert_eq!(headers, reader);
+
+    let heights: Vec<_> = reader
+        .iter_burnchain_headers(2, 3)
+        .map(|header| header.unwrap().block_height)
+        .collect();
+    assert_eq!(heights, vec![2, 3, 4, 5, 6]);
+
+    assert_eq!(reader.iter_burnchain_headers(7, 3).count(), 0);
+}
@@ -0,0 +1,269 @@
+// Copyright (C) 2025 Stacks Open Internet Foundation
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with this program.  If not, see <http://www.gnu.org/licenses/>.
+
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+use super::test_doubles::{
+    build_stub_chain, fork_hash, stub_hash, BurnchainIndexerTestDouble, MockDownloader, MockParser,
+    StubBlock,
+};
+use crate::burnchains::bitcoin::{BitcoinTransaction, Error as btc_error};
+use crate::burnchains::indexer::{
+    BurnchainBlockDownloader, BurnchainBlockParser, BurnchainIndexer, DownloadRateLimiter,
+    InstrumentedParser, LinkageCheckingDownloader, RetryingDownloader, SyncClock,
+};
+use crate::burnchains::{Error as burnchain_error, Txid};
+use crate::core::{StacksEpoch, StacksEpochExtension, StacksEpochId};
+
+#[test]
+fn test_build_stub_chain() {
+    let chain = build_stub_chain(10);
+    assert_eq!(chain.len(), 10);
+    for (i, block) in chain.iter().enumerate() {
+        assert_eq!(block.height, u64::try_from(i).unwrap());
+        if i > 0 {
+            assert_eq!(block.parent_hash, chain[i - 1].hash);
+        }
+    }
+    assert!(build_stub_chain(0).is_empty());
+}
+
+#[test]
+fn test_read_headers_rev() {
+    let mut indexer =
+        BurnchainIndexerTestDouble::with_tip_height(6, StacksEpoch::unit_test_2_05(0));
+    indexer.sync_headers(0, None).unwrap();
+
+    let headers = indexer.read_headers_rev(5, 3).unwrap();
+    let heights: Vec<_> = headers.iter().map(|hdr| hdr.block_height).collect();
+    assert_eq!(heights, vec![5, 4, 3]);
+    for hdr in headers.iter() {
+        assert_eq!(hdr.block_hash, stub_hash(hdr.block_height));
+    }
+
+    // stops at the first block
+    let headers = indexer.read_headers_rev(2, 10).unwrap();
+    let heights: Vec<_> = headers.iter().map(|hdr| hdr.block_height).collect();
+    assert_eq!(heights, vec![2, 1, 0]);
+
+    // nothing past the highest synced header
+    indexer.drop_headers(4).unwrap();
+    let headers = indexer.read_headers_rev(5, 3).unwrap();
+    let heights: Vec<_> = headers.iter().map(|hdr| hdr.block_height).collect();
+    assert_eq!(heights, vec![4, 3]);
+
+    assert!(indexer.read_headers_rev(3, 0).unwrap().is_empty());
+}
+
+/// Virtual clock that only moves when slept on, and records each sleep
+struct RecordingClock {
+    start: Instant,
+    elapsed: Duration,
+    sleeps: Vec<Duration>,
+}
+
+impl RecordingClock {
+    fn new() -> RecordingClock {
+        RecordingClock {
+            start: Instant::now(),
+            elapsed: Duration::ZERO,
+            sleeps: vec![],
+        }
+    }
+}
+
+impl SyncClock for RecordingClock {
+    fn now(&self) -> Instant {
+        self.start + self.elapsed
+    }
+
+    fn sleep(&mut self, duration: Duration) {
+        self.elapsed += duration;
+        self.sleeps.push(duration);
+    }
+}
+
+#[test]
+fn test_download_rate_limiter() {
+    let mut rate_limiter = DownloadRateLimiter::with_clock(5, RecordingClock::new());
+
+    // 10 back-to-back downloads at 5 per second: a burst of 5, then one every 200ms
+    let download_times: Vec<_> = (0..10)
+        .map(|_| {
+            rate_limiter.acquire();
+            rate_limiter.clock().elapsed
+        })
+        .collect();
+    let expected_times: Vec<_> = [0, 0, 0, 0, 0, 200, 400, 600, 800, 1000]
+        .into_iter()
+        .map(Duration::from_millis)
+        .collect();
+    assert_eq!(download_times, expected_times);
+    assert_eq!(
+        rate_limiter.clock().sleeps,
+        vec![Duration::from_millis(200); 5]
+    );
+
+    // the bucket refills while idle
+    rate_limiter.clock_mut().sleep(Duration::from_secs(1));
+    let idle_time = rate_limiter.clock().elapsed;
+    for _ in 0..5 {
+        rate_limiter.acquire();
+    }
+    assert_eq!(rate_limiter.clock().elapsed, idle_time);
+    rate_limiter.acquire();
+    assert_eq!(
+        rate_limiter.clock().elapsed,
+        idle_time + Duration::from_millis(200)
+    );
+}
+
+#[test]
+fn test_linkage_checking_downloader() {
+    // block 3 claims a parent that is not block 2
+    let mut blocks = build_stub_chain(5);
+    blocks[3].parent_hash = fork_hash(1, 2);
+    let downloads = Arc::new(Mutex::new(vec![]));
+    let mut downloader =
+        LinkageCheckingDownloader::new(MockDownloader::new(blocks.clone(), downloads.clone()));
+
+    for block in blocks[0..3].iter() {
+        assert_eq!(&downloader.download(block).unwrap(), block);
+    }
+    assert!(matches!(
+        downloader.download(&blocks[3]),
+        Err(burnchain_error::BurnchainPeerBroken)
+    ));
+
+    // the broken link is only detected between consecutive downloads
+    let mut downloader =
+        LinkageCheckingDownloader::new(MockDownloader::new(blocks.clone(), downloads.clone()));
+    downloader.download(&blocks[1]).unwrap();
+    downloader.download(&blocks[3]).unwrap();
+    downloader.download(&blocks[4]).unwrap();
+    assert_eq!(downloads.lock().unwrap().len(), 7);
+}
+
+/// Downloader whose first downloads fail with the given errors, before it starts serving blocks
+struct FlakyDownloader {
+    inner: MockDownloader,
+    failures: Vec<burnchain_error>,
+    attempts: u32,
+}
+
+impl FlakyDownloader {
+    fn new(blocks: Vec<StubBlock>, failures: Vec<burnchain_error>) -> FlakyDownloader {
+        FlakyDownloader {
+            inner: MockDownloader::new(blocks, Arc::new(Mutex::new(vec![]))),
+            failures,
+            attempts: 0,
+        }
+    }
+}
+
+impl BurnchainBlockDownloader for FlakyDownloader {
+    type H = StubBlock;
+    type B = StubBlock;
+
+    fn download(&mut self, header: &StubBlock) -> Result<StubBlock, burnchain_error> {
+        self.attempts += 1;
+        if !self.failures.is_empty() {
+            return Err(self.failures.remove(0));
+        }
+        self.inner.download(header)
+    }
+}
+
+#[test]
+fn test_retrying_downloader() {
+    let blocks = build_stub_chain(3);
+    let transient_error = || burnchain_error::DownloadError(btc_error::ConnectionError);
+
+    // fails twice, then succeeds on the third attempt
+    let flaky = FlakyDownloader::new(blocks.clone(), vec![transient_error(), transient_error()]);
+    let mut downloader =
+        RetryingDownloader::with_clock(flaky, 3, Duration::from_millis(10), RecordingClock::new());
+    assert_eq!(&downloader.download(&blocks[1]).unwrap(), &blocks[1]);
+    assert_eq!(
+        downloader.clock().sleeps,
+        vec![Duration::from_millis(10), Duration::from_millis(20)]
+    );
+    assert_eq!(downloader.into_inner().attempts, 3);
+
+    // gives up once the retries are used up
+    let flaky = FlakyDownloader::new(blocks.clone(), vec![transient_error(), transient_error()]);
+    let mut downloader =
+        RetryingDownloader::with_clock(flaky, 1, Duration::from_millis(10), RecordingClock::new());
+    assert!(matches!(
+        downloader.download(&blocks[1]),
+        Err(burnchain_error::DownloadError(_))
+    ));
+    assert_eq!(downloader.into_inner().attempts, 2);
+
+    // other errors are not retried
+    let flaky = FlakyDownloader::new(blocks.clone(), vec![burnchain_error::BurnchainPeerBroken]);
+    let mut downloader =
+        RetryingDownloader::with_clock(flaky, 3, Duration::from_millis(10), RecordingClock::new());
+    assert!(matches!(
+        downloader.download(&blocks[1]),
+        Err(burnchain_error::BurnchainPeerBroken)
+    ));
+    assert!(downloader.clock().sleeps.is_empty());
+    assert_eq!(downloader.into_inner().attempts, 1);
+}
+
+#[test]
+fn test_instrumented_parser() {
+    let parse_delay = Duration::from_millis(20);
+    let mut blocks = build_stub_chain(4);
+    // block `h` carries `h` txs
+    for block in blocks.iter_mut() {
+        block.txs = (0..block.height)
+            .map(|vtxindex| BitcoinTransaction {
+                txid: Txid([vtxindex as u8; 32]),
+                vtxindex: vtxindex as u32,
+                opcode: 0,
+                data: vec![],
+                data_amt: 0,
+                inputs: vec![],
+                outputs: vec![],
+            })
+            .collect();
+    }
+
+    let metrics = Arc::new(Mutex::new(vec![]));
+    let parser = InstrumentedParser::new(
+        MockParser::new(parse_delay, Arc::new(Mutex::new(vec![]))),
+        metrics.clone(),
+    );
+    // blocks 1-3 are parsed by two workers, which share the metrics
+    let mut workers = vec![parser.clone(), parser];
+    for (i, block) in blocks[1..].iter().enumerate() {
+        let parsed = workers[i % 2]
+            .parse(block, StacksEpochId::Epoch2_05)
+            .unwrap();
+        assert_eq!(parsed.block_height(), block.height);
+    }
+
+    let metrics = metrics.lock().unwrap();
+    let heights: Vec<_> = metrics.iter().map(|(height, ..)| *height).collect();
+    assert_eq!(heights, vec![1, 2, 3]);
+    for (height, parse_duration, num_ops) in metrics.iter() {
+        assert_eq!(*num_ops, *height as usize);
+        assert!(*parse_duration >= parse_delay);
+        assert!(*parse_duration < parse_delay * 50);
+    }
+}
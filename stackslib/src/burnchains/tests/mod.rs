@@ -17,6 +17,9 @@
 pub mod affirmation;
 pub mod burnchain;
 pub mod db;
+pub mod indexer;
+pub mod sync_with_indexer;
+pub mod test_doubles;
 
 use std::collections::HashMap;
 
@@ -0,0 +1,957 @@
+// Copyright (C) 2025 Stacks Open Internet Foundation
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with this program.  If not, see <http://www.gnu.org/licenses/>.
+
+use std::sync::{Arc, Mutex};
+use std::thread;
+use std::time::{Duration, Instant};
+
+use stacks_common::util::hash::Hash160;
+
+use super::test_doubles::{fork_hash, stub_hash, BurnchainIndexerTestDouble};
+use crate::burnchains::bitcoin::address::{
+    BitcoinAddress, LegacyBitcoinAddress, LegacyBitcoinAddressType,
+};
+use crate::burnchains::bitcoin::{
+    BitcoinInputType, BitcoinNetworkType, BitcoinTransaction, BitcoinTxInputStructured,
+    BitcoinTxOutput,
+};
+use crate::burnchains::db::{BurnchainBlockData, BurnchainBlockSink, BurnchainDB};
+use crate::burnchains::indexer::BurnchainIndexer;
+use crate::burnchains::{
+    Burnchain, BurnchainBlockHeader, Error as burnchain_error, SyncOptions, SyncOutcome,
+    SyncReport, SyncStage, SyncStats, SyncTarget, Txid,
+};
+use crate::chainstate::burn::operations::BlockstackOperationType;
+use crate::chainstate::burn::Opcodes;
+use crate::chainstate::coordinator::comm::CoordinatorCommunication;
+use crate::core::{StacksEpoch, StacksEpochExtension, StacksEpochId};
+
+/// Make a test double over a chain of `tip_height + 1` blocks, and a burnchain rooted at its
+/// first block
+fn setup(tip_height: u64) -> (Burnchain, BurnchainIndexerTestDouble) {
+    let indexer =
+        BurnchainIndexerTestDouble::with_tip_height(tip_height, StacksEpoch::unit_test_2_05(0));
+    let burnchain = Burnchain::default_unittest(
+        indexer.get_first_block_height(),
+        &indexer.get_first_block_header_hash().unwrap(),
+    );
+    (burnchain, indexer)
+}
+
+#[test]
+fn test_sync_with_indexer_to_reachable_hash() {
+    let (mut burnchain, mut indexer) = setup(10);
+    let (_receivers, channels) = CoordinatorCommunication::instantiate();
+
+    let target_hash = stub_hash(6);
+    let tip = burnchain
+        .sync_with_indexer_options(
+            &mut indexer,
+            channels.clone(),
+            SyncOptions {
+                target: Some(SyncTarget::Hash(target_hash.clone())),
+                ..SyncOptions::default()
+            },
+        )
+        .unwrap()
+        .outcome
+        .into_header();
+    assert_eq!(tip.block_height, 6);
+    assert_eq!(tip.block_hash, target_hash);
+
+    // syncing to an already-processed block is a no-op
+    let tip = burnchain
+        .sync_with_indexer_options(
+            &mut indexer,
+            channels.clone(),
+            SyncOptions {
+                target: Some(SyncTarget::Hash(stub_hash(3))),
+                ..SyncOptions::default()
+            },
+        )
+        .unwrap()
+        .outcome
+        .into_header();
+    assert_eq!(tip.block_height, 3);
+    assert_eq!(tip.block_hash, stub_hash(3));
+
+    // and the rest of the chain is still there to sync
+    let tip = burnchain
+        .sync_with_indexer_options(&mut indexer, channels, SyncOptions::default())
+        .unwrap()
+        .outcome
+        .into_header();
+    assert_eq!(tip.block_height, 10);
+    assert_eq!(tip.block_hash, stub_hash(10));
+}
+
+#[test]
+fn test_sync_with_indexer_to_unreachable_hash() {
+    let (mut burnchain, mut indexer) = setup(10);
+    let (_receivers, channels) = CoordinatorCommunication::instantiate();
+
+    // past the tip of the burnchain
+    let target_hash = stub_hash(11);
+    let err = burnchain
+        .sync_with_indexer_options(
+            &mut indexer,
+            channels,
+            SyncOptions {
+                target: Some(SyncTarget::Hash(target_hash.clone())),
+                ..SyncOptions::default()
+            },
+        )
+        .unwrap_err();
+    match err {
+        burnchain_error::UnknownBlock(hash) => assert_eq!(hash, target_hash),
+        e => panic!("Expected UnknownBlock error, got {e:?}"),
+    }
+}
+
+#[test]
+fn test_sync_with_indexer_first_block_mismatch() {
+    // the burnchain starts at a different block hash than the indexer
+    let (mut burnchain, mut indexer) = setup(10);
+    burnchain.first_block_hash = stub_hash(1);
+    let (_receivers, channels) = CoordinatorCommunication::instantiate();
+
+    let err = burnchain
+        .sync_with_indexer_options(&mut indexer, channels.clone(), SyncOptions::default())
+        .unwrap_err();
+    match err {
+        burnchain_error::FirstBlockMismatch {
+            configured_height,
+            configured_hash,
+            indexer_height,
+            indexer_hash,
+        } => {
+            assert_eq!(configured_height, 0);
+            assert_eq!(configured_hash, stub_hash(1));
+            assert_eq!(indexer_height, 0);
+            assert_eq!(indexer_hash, stub_hash(0));
+        }
+        e => panic!("Expected FirstBlockMismatch error, got {e:?}"),
+    }
+
+    // the burnchain starts at a different height than the indexer
+    let (mut burnchain, mut indexer) = setup(10);
+    burnchain.first_block_height = 1;
+    let err = burnchain
+        .sync_with_indexer_options(&mut indexer, channels, SyncOptions::default())
+        .unwrap_err();
+    match err {
+        burnchain_error::FirstBlockMismatch {
+            configured_height,
+            indexer_height,
+            ..
+        } => {
+            assert_eq!(configured_height, 1);
+            assert_eq!(indexer_height, 0);
+        }
+        e => panic!("Expected FirstBlockMismatch error, got {e:?}"),
+    }
+}
+
+#[test]
+fn test_sync_with_indexer_parses_blocks_in_their_epoch() {
+    // epoch 2.0 covers heights [0, 4), epoch 2.05 covers [4, 8), and epoch 2.1 starts at 8
+    let epochs = StacksEpoch::unit_test_2_1(0);
+    let mut indexer = BurnchainIndexerTestDouble::with_tip_height(10, epochs.clone());
+    indexer.set_parse_workers(3);
+    let mut burnchain = Burnchain::default_unittest(
+        indexer.get_first_block_height(),
+        &indexer.get_first_block_header_hash().unwrap(),
+    );
+    let (_receivers, channels) = CoordinatorCommunication::instantiate();
+
+    burnchain
+        .sync_with_indexer_options(&mut indexer, channels, SyncOptions::default())
+        .unwrap();
+
+    let parsed_epochs = indexer.take_parsed_epochs();
+    let expected: Vec<_> = (1..=10)
+        .map(|height| (height, epochs.epoch_id_at_height(height).unwrap()))
+        .collect();
+    assert_eq!(parsed_epochs, expected);
+
+    // the first block of each epoch is parsed in that epoch, not the one before it
+    assert_eq!(parsed_epochs[2], (3, StacksEpochId::Epoch20));
+    assert_eq!(parsed_epochs[3], (4, StacksEpochId::Epoch2_05));
+    assert_eq!(parsed_epochs[6], (7, StacksEpochId::Epoch2_05));
+    assert_eq!(parsed_epochs[7], (8, StacksEpochId::Epoch21));
+}
+
+#[test]
+fn test_sync_with_indexer_rejects_block_that_does_not_match_header() {
+    let (mut burnchain, mut indexer) = setup(10);
+    indexer.set_poison_height(Some(6));
+    let (_receivers, channels) = CoordinatorCommunication::instantiate();
+
+    // the error says which stage failed, and at which height
+    let err = burnchain
+        .sync_with_indexer_options(&mut indexer, channels.clone(), SyncOptions::default())
+        .unwrap_err();
+    match err {
+        burnchain_error::Contextual {
+            stage,
+            height,
+            ref source,
+        } => {
+            assert_eq!(stage, SyncStage::Download);
+            assert_eq!(height, 6);
+            assert!(
+                matches!(**source, burnchain_error::BurnchainPeerBroken),
+                "Expected BurnchainPeerBroken, got {source:?}"
+            );
+        }
+        e => panic!("Expected Contextual error, got {e:?}"),
+    }
+    assert!(matches!(
+        err.root_cause(),
+        burnchain_error::BurnchainPeerBroken
+    ));
+    assert_eq!(
+        err.to_string(),
+        "Failed to download burnchain block at height 6: Remote burnchain peer has misbehaved"
+    );
+
+    // the blocks before the mismatched one were stored, and nothing after it was
+    let burnchain_db = burnchain.open_burnchain_db(false).unwrap();
+    let tip = burnchain_db.get_canonical_chain_tip().unwrap();
+    assert_eq!(tip.block_height, 5);
+    assert_eq!(tip.block_hash, stub_hash(5));
+
+    // once the indexer serves the right block again, the sync picks up where it left off
+    indexer.set_poison_height(None);
+    indexer.take_downloads();
+    let tip = burnchain
+        .sync_with_indexer_options(&mut indexer, channels, SyncOptions::default())
+        .unwrap()
+        .outcome
+        .into_header();
+    assert_eq!(tip.block_height, 10);
+    assert_eq!(tip.block_hash, stub_hash(10));
+    let downloaded: Vec<_> = indexer
+        .take_downloads()
+        .iter()
+        .map(|block| block.height)
+        .collect();
+    assert_eq!(downloaded, (6..=10).collect::<Vec<_>>());
+}
+
+#[test]
+fn test_sync_with_indexer_gives_up_on_stuck_worker() {
+    let (mut burnchain, mut indexer) = setup(1);
+    // the downloader is stuck on the only block to sync for far longer than the pipeline waits
+    let join_timeout = Duration::from_millis(100);
+    indexer.set_download_delay(Duration::from_secs(2));
+    indexer.set_worker_join_timeout(join_timeout);
+    let (_receivers, channels) = CoordinatorCommunication::instantiate();
+
+    let sync_start = Instant::now();
+    let err = burnchain
+        .sync_with_indexer_options(&mut indexer, channels, SyncOptions::default())
+        .unwrap_err();
+    assert!(
+        sync_start.elapsed() < Duration::from_secs(2),
+        "Sync waited {:?} on the stuck downloader",
+        sync_start.elapsed()
+    );
+    match err {
+        burnchain_error::ThreadTimeout { name, timeout } => {
+            assert_eq!(name, "burnchain-downloader");
+            assert_eq!(timeout, join_timeout);
+        }
+        e => panic!("Expected ThreadTimeout error, got {e:?}"),
+    }
+}
+
+/// Sync a fresh chain of `tip_height + 1` blocks using `parse_workers` parse threads, which
+/// finish the blocks in `completion_order` if it is given. Returns the heights of the blocks in
+/// the order they finished parsing, and the headers stored in the burnchain DB in height order.
+fn sync_with_parse_workers(
+    tip_height: u64,
+    parse_workers: usize,
+    completion_order: Option<Vec<u64>>,
+) -> (Vec<u64>, Vec<BurnchainBlockHeader>) {
+    let (mut burnchain, mut indexer) = setup(tip_height);
+    indexer.set_parse_workers(parse_workers);
+    indexer.set_completion_order(completion_order);
+    let (_receivers, channels) = CoordinatorCommunication::instantiate();
+
+    // a parser that waits too long for its turn fails the sync instead of hanging it
+    let tip = burnchain
+        .sync_with_indexer_options(&mut indexer, channels, SyncOptions::default())
+        .unwrap_or_else(|e| panic!("Sync with {parse_workers} parse workers failed: {e}"))
+        .outcome
+        .into_header();
+    assert_eq!(tip.block_height, tip_height);
+
+    let burnchain_db = burnchain.open_burnchain_db(false).unwrap();
+    let headers = indexer
+        .blocks()
+        .iter()
+        .map(|block| {
+            BurnchainDB::get_burnchain_block(burnchain_db.conn(), &block.hash)
+                .unwrap()
+                .header
+        })
+        .collect();
+    (indexer.take_parse_order(), headers)
+}
+
+#[test]
+fn test_sync_with_indexer_parse_workers_same_output() {
+    let (_, serial_headers) = sync_with_parse_workers(20, 1, None);
+    let (_, parallel_headers) = sync_with_parse_workers(20, 4, None);
+    assert_eq!(serial_headers.len(), 21);
+    assert_eq!(serial_headers, parallel_headers);
+}
+
+#[test]
+fn test_sync_with_indexer_parse_workers_run_concurrently() {
+    // each block finishes only after the three blocks downloaded after it, so the sync can
+    // only complete if four blocks are being parsed at once
+    let completion_order: Vec<u64> = (0..4)
+        .flat_map(|group| (group * 4 + 1..=group * 4 + 4).rev())
+        .collect();
+    let (serial_order, serial_headers) = sync_with_parse_workers(16, 1, None);
+    let (parallel_order, parallel_headers) =
+        sync_with_parse_workers(16, 4, Some(completion_order.clone()));
+    assert_eq!(serial_order, (1..=16).collect::<Vec<_>>());
+    assert_eq!(parallel_order, completion_order);
+    assert_eq!(serial_headers, parallel_headers);
+}
+
+#[test]
+fn test_sync_with_indexer_report_block_latency() {
+    let (mut burnchain, mut indexer) = setup(5);
+    let download_delay = Duration::from_millis(50);
+    let parse_delay = Duration::from_millis(80);
+    indexer.set_download_delay(download_delay);
+    indexer.set_parse_delay(parse_delay);
+    let (_receivers, channels) = CoordinatorCommunication::instantiate();
+
+    let report = burnchain
+        .sync_with_indexer_options(&mut indexer, channels.clone(), SyncOptions::default())
+        .unwrap();
+    assert_eq!(report.outcome.header().block_height, 5);
+
+    // every block is reported, in the order it was stored
+    let heights: Vec<_> = report
+        .block_latencies
+        .iter()
+        .map(|latency| latency.block_height)
+        .collect();
+    assert_eq!(heights, (1..=5).collect::<Vec<_>>());
+
+    let injected_ms = u64::try_from((download_delay + parse_delay).as_millis()).unwrap();
+    for latency in report.block_latencies.iter() {
+        assert!(latency.download_ms() >= 50, "{latency:?}");
+        assert!(latency.parse_ms() >= 80, "{latency:?}");
+        // the stages happen in order, so the total covers all of them
+        assert!(
+            latency.download_end_ms <= latency.parse_start_ms,
+            "{latency:?}"
+        );
+        assert!(
+            latency.parse_end_ms <= latency.store_start_ms,
+            "{latency:?}"
+        );
+        assert!(
+            latency.total_ms() >= latency.download_ms() + latency.parse_ms() + latency.store_ms()
+        );
+        assert!(latency.total_ms() >= injected_ms, "{latency:?}");
+    }
+
+    // every block lands in a bucket at or above the injected delays
+    let histogram = report.latency_histogram(10);
+    assert_eq!(histogram.values().sum::<u64>(), 5);
+    assert!(histogram.keys().all(|bucket| *bucket + 10 > injected_ms));
+    assert!(report.slowest_block().unwrap().total_ms() >= injected_ms);
+
+    // nothing left to sync means nothing to report
+    let report = burnchain
+        .sync_with_indexer_options(&mut indexer, channels, SyncOptions::default())
+        .unwrap();
+    assert_eq!(
+        report,
+        SyncReport {
+            outcome: SyncOutcome::Complete(indexer.blocks()[5].header()),
+            block_latencies: vec![],
+            stats: SyncStats {
+                start_height: 5,
+                end_height: 5,
+                ..SyncStats::default()
+            },
+        }
+    );
+}
+
+#[test]
+fn test_sync_with_indexer_progress() {
+    let (mut burnchain, mut indexer) = setup(10);
+    let (_receivers, channels) = CoordinatorCommunication::instantiate();
+
+    let test_thread = thread::current().id();
+    let mut calls = vec![];
+    let report = burnchain
+        .sync_with_indexer_options(
+            &mut indexer,
+            channels.clone(),
+            SyncOptions {
+                on_progress: Some(&mut |current, target| {
+                    calls.push((current, target, thread::current().id()))
+                }),
+                ..SyncOptions::default()
+            },
+        )
+        .unwrap();
+    assert_eq!(report.outcome.header().block_height, 10);
+
+    // one call per stored block, on the calling thread, in height order
+    assert_eq!(calls.len(), 10);
+    assert!(calls
+        .iter()
+        .all(|(_, _, thread_id)| *thread_id == test_thread));
+    assert!(calls.iter().all(|(_, target, _)| *target == 10));
+    assert!(calls.windows(2).all(|pair| pair[0].0 < pair[1].0));
+    assert_eq!(
+        calls.last().map(|(current, target, _)| (*current, *target)),
+        Some((10, 10))
+    );
+
+    // nothing left to sync means no progress to report
+    let mut num_calls = 0;
+    burnchain
+        .sync_with_indexer_options(
+            &mut indexer,
+            channels,
+            SyncOptions {
+                on_progress: Some(&mut |_, _| num_calls += 1),
+                ..SyncOptions::default()
+            },
+        )
+        .unwrap();
+    assert_eq!(num_calls, 0);
+}
+
+#[test]
+fn test_sync_with_indexer_download_rate_limit() {
+    let (mut burnchain, mut indexer) = setup(15);
+    indexer.set_download_rate_limit(Some(10));
+    let (_receivers, channels) = CoordinatorCommunication::instantiate();
+
+    let tip = burnchain
+        .sync_with_indexer_options(&mut indexer, channels, SyncOptions::default())
+        .unwrap()
+        .outcome
+        .into_header();
+    // a burst of 10 downloads, then 5 more at 100ms apart
+    assert_eq!(
+        indexer.take_rate_limit_sleeps(),
+        vec![Duration::from_millis(100); 5]
+    );
+    assert_eq!(tip.block_height, 15);
+    assert_eq!(tip.block_hash, stub_hash(15));
+}
+
+#[test]
+fn test_sync_with_indexer_max_blocks_partial() {
+    let (mut burnchain, mut indexer) = setup(10);
+    let (_receivers, channels) = CoordinatorCommunication::instantiate();
+
+    // 10 blocks to sync, 4 at a time
+    let mut outcomes = vec![];
+    loop {
+        let outcome = burnchain
+            .sync_with_indexer_options(
+                &mut indexer,
+                channels.clone(),
+                SyncOptions {
+                    max_blocks: Some(4),
+                    ..SyncOptions::default()
+                },
+            )
+            .unwrap()
+            .outcome;
+        let is_complete = outcome.is_complete();
+        outcomes.push(outcome);
+        if is_complete || outcomes.len() > 3 {
+            break;
+        }
+    }
+    let header_at = |height: usize| indexer.blocks()[height].header();
+    assert_eq!(
+        outcomes,
+        vec![
+            SyncOutcome::Partial {
+                header: header_at(4),
+                remaining: 6,
+            },
+            SyncOutcome::Partial {
+                header: header_at(7),
+                remaining: 3,
+            },
+            SyncOutcome::Complete(header_at(10)),
+        ]
+    );
+}
+
+#[test]
+fn test_sync_with_indexer_max_blocks_partial_to_target() {
+    let (mut burnchain, mut indexer) = setup(10);
+    let (_receivers, channels) = CoordinatorCommunication::instantiate();
+
+    // only the blocks up to the target count as remaining
+    let outcome = burnchain
+        .sync_with_indexer_options(
+            &mut indexer,
+            channels.clone(),
+            SyncOptions {
+                target: Some(SyncTarget::Height(6)),
+                max_blocks: Some(4),
+                ..SyncOptions::default()
+            },
+        )
+        .unwrap()
+        .outcome;
+    assert_eq!(
+        outcome,
+        SyncOutcome::Partial {
+            header: indexer.blocks()[4].header(),
+            remaining: 2,
+        }
+    );
+
+    let outcome = burnchain
+        .sync_with_indexer_options(
+            &mut indexer,
+            channels.clone(),
+            SyncOptions {
+                target: Some(SyncTarget::Height(6)),
+                max_blocks: Some(4),
+                ..SyncOptions::default()
+            },
+        )
+        .unwrap()
+        .outcome;
+    assert_eq!(outcome, SyncOutcome::Complete(indexer.blocks()[6].header()));
+
+    // the compatibility wrapper only returns the header, cut short by max_blocks or not
+    let tip = burnchain
+        .sync_with_indexer(&mut indexer, channels, Some(10), Some(2), None)
+        .unwrap();
+    assert_eq!(tip, indexer.blocks()[8].header());
+}
+
+#[test]
+fn test_sync_with_indexer_max_blocks_per_call() {
+    let (mut burnchain, mut indexer) = setup(5);
+    let (_receivers, channels) = CoordinatorCommunication::instantiate();
+
+    // each call processes exactly 2 blocks, and the next call resumes after them
+    let mut synced = vec![];
+    for expected_height in [2, 4] {
+        let SyncReport { outcome, stats, .. } = burnchain
+            .sync_with_indexer_options(
+                &mut indexer,
+                channels.clone(),
+                SyncOptions {
+                    target: Some(SyncTarget::Height(5)),
+                    max_blocks: Some(2),
+                    ..SyncOptions::default()
+                },
+            )
+            .unwrap();
+        assert!(!outcome.is_complete());
+        assert_eq!(outcome.header().block_height, expected_height);
+        assert_eq!(stats.start_height, expected_height - 2);
+        assert_eq!(stats.blocks_stored, 2);
+        synced.push(
+            indexer
+                .take_downloads()
+                .into_iter()
+                .map(|block| block.height)
+                .collect::<Vec<_>>(),
+        );
+    }
+    assert_eq!(synced, vec![vec![1, 2], vec![3, 4]]);
+
+    let SyncReport { outcome, stats, .. } = burnchain
+        .sync_with_indexer_options(
+            &mut indexer,
+            channels,
+            SyncOptions {
+                target: Some(SyncTarget::Height(5)),
+                max_blocks: Some(2),
+                ..SyncOptions::default()
+            },
+        )
+        .unwrap();
+    assert_eq!(outcome, SyncOutcome::Complete(indexer.blocks()[5].header()));
+    assert_eq!(stats.blocks_stored, 1);
+}
+
+#[test]
+fn test_sync_with_indexer_stats() {
+    let (mut burnchain, mut indexer) = setup(3);
+    let (_receivers, channels) = CoordinatorCommunication::instantiate();
+
+    let (tip, stats) = burnchain
+        .sync_with_indexer_stats(&mut indexer, channels, None, None, None)
+        .unwrap();
+    assert_eq!(tip, indexer.blocks()[3].header());
+    assert_eq!(
+        stats,
+        SyncStats {
+            start_height: 0,
+            end_height: 3,
+            blocks_downloaded: 3,
+            blocks_parsed: 3,
+            blocks_stored: 3,
+            reorg_depth: 0,
+        }
+    );
+}
+
+#[test]
+fn test_sync_with_indexer_resumes_after_reorg() {
+    let (mut burnchain, mut indexer) = setup(6);
+    let (_receivers, channels) = CoordinatorCommunication::instantiate();
+
+    let SyncReport { outcome, stats, .. } = burnchain
+        .sync_with_indexer_options(&mut indexer, channels.clone(), SyncOptions::default())
+        .unwrap();
+    let tip = outcome.into_header();
+    assert_eq!(tip.block_hash, stub_hash(6));
+    assert_eq!(stats.blocks_stored, 6);
+    assert_eq!(stats.reorg_depth, 0);
+    let downloaded: Vec<_> = indexer
+        .take_downloads()
+        .into_iter()
+        .map(|block| block.height)
+        .collect();
+    assert_eq!(downloaded, vec![1, 2, 3, 4, 5, 6]);
+
+    // blocks 4-6 are replaced by a fork off of block 3
+    indexer.fork_at(3, 6, 1);
+    let SyncReport { outcome, stats, .. } = burnchain
+        .sync_with_indexer_options(&mut indexer, channels, SyncOptions::default())
+        .unwrap();
+    let tip = outcome.into_header();
+    assert_eq!(tip.block_height, 6);
+    assert_eq!(tip.block_hash, fork_hash(1, 6));
+    assert_eq!(stats.start_height, 3);
+    assert_eq!(stats.blocks_stored, 3);
+    assert_eq!(stats.reorg_depth, 3);
+
+    // only the fork's blocks were downloaded
+    let downloaded: Vec<_> = indexer
+        .take_downloads()
+        .into_iter()
+        .map(|block| block.hash)
+        .collect();
+    assert_eq!(
+        downloaded,
+        vec![fork_hash(1, 4), fork_hash(1, 5), fork_hash(1, 6)]
+    );
+
+    let burnchain_db = burnchain.open_burnchain_db(false).unwrap();
+    for height in 4..=6 {
+        let header = BurnchainDB::get_burnchain_block(burnchain_db.conn(), &fork_hash(1, height))
+            .unwrap()
+            .header;
+        assert_eq!(header.block_height, height);
+    }
+    let header = BurnchainDB::get_burnchain_block(burnchain_db.conn(), &fork_hash(1, 4))
+        .unwrap()
+        .header;
+    assert_eq!(header.parent_block_hash, stub_hash(3));
+}
+
+#[test]
+fn test_sync_with_indexer_drops_reorged_headers() {
+    let (mut burnchain, mut indexer) = setup(6);
+    let (_receivers, channels) = CoordinatorCommunication::instantiate();
+
+    burnchain
+        .sync_with_indexer_options(&mut indexer, channels.clone(), SyncOptions::default())
+        .unwrap();
+    assert!(indexer.take_dropped_headers().is_empty());
+    indexer.take_header_syncs();
+
+    // blocks 4-6 are replaced by a longer fork off of block 3
+    indexer.fork_at(3, 7, 1);
+    let tip = burnchain
+        .sync_with_indexer_options(&mut indexer, channels, SyncOptions::default())
+        .unwrap()
+        .outcome
+        .into_header();
+    assert_eq!(tip.block_height, 7);
+    assert_eq!(tip.block_hash, fork_hash(1, 7));
+
+    // the indexer replaced the headers above the common ancestor while finding the reorg, so
+    // the sync neither dropped them again nor re-read them from the common ancestor
+    assert_eq!(indexer.take_dropped_headers(), vec![3]);
+    assert_eq!(indexer.take_header_syncs().last(), Some(&7));
+
+    let burnchain_db = burnchain.open_burnchain_db(false).unwrap();
+    let canonical_tip = burnchain_db.get_canonical_chain_tip().unwrap();
+    assert_eq!(canonical_tip, tip);
+}
+
+/// Walk the stored headers from `tip` down to the first block, checking that each one's parent
+/// is the stored header one block below it. Returns the number of headers walked.
+fn assert_headers_parent_linked(burnchain: &Burnchain, tip: &BurnchainBlockHeader) -> u64 {
+    let burnchain_db = burnchain.open_burnchain_db(false).unwrap();
+    let mut header = tip.clone();
+    let mut walked = 1;
+    while header.block_height > burnchain.first_block_height {
+        let parent =
+            BurnchainDB::get_burnchain_block(burnchain_db.conn(), &header.parent_block_hash)
+                .unwrap()
+                .header;
+        assert_eq!(parent.block_height + 1, header.block_height);
+        assert_eq!(header.parent_block_hash, parent.block_hash);
+        header = parent;
+        walked += 1;
+    }
+    assert_eq!(header.block_hash, burnchain.first_block_hash);
+    walked
+}
+
+#[test]
+fn test_sync_with_indexer_stores_parent_linked_headers() {
+    let (mut burnchain, mut indexer) = setup(6);
+    let (_receivers, channels) = CoordinatorCommunication::instantiate();
+
+    // the test double's blocks each name the real hash of the block below them
+    for pair in indexer.blocks().windows(2) {
+        assert_eq!(pair[1].parent_hash, pair[0].hash);
+    }
+
+    let tip = burnchain
+        .sync_with_indexer_options(&mut indexer, channels.clone(), SyncOptions::default())
+        .unwrap()
+        .outcome
+        .into_header();
+    assert_eq!(assert_headers_parent_linked(&burnchain, &tip), 7);
+
+    // and so do a fork's, down through the common ancestor
+    indexer.fork_at(3, 7, 1);
+    let tip = burnchain
+        .sync_with_indexer_options(&mut indexer, channels, SyncOptions::default())
+        .unwrap()
+        .outcome
+        .into_header();
+    assert_eq!(tip.block_hash, fork_hash(1, 7));
+    assert_eq!(assert_headers_parent_linked(&burnchain, &tip), 8);
+}
+
+/// A parsed burnchain tx carrying a Stacks op, which spends output 1 of `spent_txid` and pays
+/// its first output to the P2PKH address `output_hash`
+fn make_op_tx(
+    txid: Txid,
+    vtxindex: u32,
+    opcode: Opcodes,
+    data: Vec<u8>,
+    spent_txid: Txid,
+    output_hash: Hash160,
+) -> BitcoinTransaction {
+    BitcoinTransaction {
+        txid,
+        vtxindex,
+        opcode: opcode as u8,
+        data,
+        data_amt: 0,
+        inputs: vec![BitcoinTxInputStructured {
+            keys: vec![],
+            num_required: 0,
+            in_type: BitcoinInputType::Standard,
+            tx_ref: (spent_txid, 1),
+        }
+        .into()],
+        outputs: vec![BitcoinTxOutput {
+            units: 10,
+            address: BitcoinAddress::Legacy(LegacyBitcoinAddress {
+                addrtype: LegacyBitcoinAddressType::PublicKeyHash,
+                network_id: BitcoinNetworkType::Mainnet,
+                bytes: output_hash,
+            }),
+        }],
+    }
+}
+
+#[test]
+fn test_collect_burn_ops() {
+    // stack-stx ops need epoch 2.1, which starts at height 8
+    let mut indexer =
+        BurnchainIndexerTestDouble::with_tip_height(12, StacksEpoch::unit_test_2_1(0));
+    let burnchain = Burnchain::default_unittest(
+        indexer.get_first_block_height(),
+        &indexer.get_first_block_header_hash().unwrap(),
+    );
+
+    let pre_stx_txid = Txid([5; 32]);
+    indexer.set_block_txs(
+        9,
+        vec![make_op_tx(
+            pre_stx_txid.clone(),
+            0,
+            Opcodes::PreStx,
+            vec![0; 80],
+            Txid([0; 32]),
+            Hash160([1; 20]),
+        )],
+    );
+    indexer.set_block_txs(
+        10,
+        vec![
+            // there's no PreStx to take this one's sender from, so it's dropped
+            make_op_tx(
+                Txid([3; 32]),
+                0,
+                Opcodes::StackStx,
+                vec![1; 80],
+                Txid([0; 32]),
+                Hash160([1; 20]),
+            ),
+            // spends the PreStx from block 9
+            make_op_tx(
+                Txid([4; 32]),
+                1,
+                Opcodes::StackStx,
+                vec![1; 80],
+                pre_stx_txid.clone(),
+                Hash160([2; 20]),
+            ),
+        ],
+    );
+    indexer.set_block_txs(
+        11,
+        vec![make_op_tx(
+            Txid([6; 32]),
+            0,
+            Opcodes::PreStx,
+            vec![0; 80],
+            Txid([0; 32]),
+            Hash160([3; 20]),
+        )],
+    );
+    indexer.sync_headers(0, None).unwrap();
+
+    let ops = burnchain.collect_burn_ops(&indexer, 0, 13).unwrap();
+    let collected: Vec<_> = ops
+        .iter()
+        .map(|(height, op)| (*height, op.txid(), op.vtxindex()))
+        .collect();
+    assert_eq!(
+        collected,
+        vec![
+            (9, pre_stx_txid, 0),
+            (10, Txid([4; 32]), 1),
+            (11, Txid([6; 32]), 0)
+        ]
+    );
+    assert!(matches!(ops[0].1, BlockstackOperationType::PreStx(_)));
+    assert!(matches!(ops[1].1, BlockstackOperationType::StackStx(_)));
+    assert!(matches!(ops[2].1, BlockstackOperationType::PreStx(_)));
+
+    // a range that starts after the PreStx can't resolve the stack-stx's sender
+    let ops = burnchain.collect_burn_ops(&indexer, 10, 12).unwrap();
+    let collected: Vec<_> = ops
+        .iter()
+        .map(|(height, op)| (*height, op.txid()))
+        .collect();
+    assert_eq!(collected, vec![(11, Txid([6; 32]))]);
+
+    // nothing was written to the node's burnchain DB
+    assert!(burnchain.open_burnchain_db(false).is_err());
+}
+
+/// Sink that records the height of every block it receives
+struct CountingSink {
+    heights: Arc<Mutex<Vec<u64>>>,
+}
+
+impl BurnchainBlockSink for CountingSink {
+    fn process(&mut self, block: BurnchainBlockData) -> Result<(), burnchain_error> {
+        self.heights.lock().unwrap().push(block.header.block_height);
+        Ok(())
+    }
+}
+
+#[test]
+fn test_sync_with_indexer_to_sink() {
+    let (mut burnchain, mut indexer) = setup(10);
+    indexer.set_parse_workers(4);
+    let (_receivers, channels) = CoordinatorCommunication::instantiate();
+
+    let heights = Arc::new(Mutex::new(vec![]));
+    let sink = CountingSink {
+        heights: heights.clone(),
+    };
+    let options = SyncOptions {
+        sink: Some(Box::new(sink)),
+        ..SyncOptions::default()
+    };
+    let tip = burnchain
+        .sync_with_indexer_options(&mut indexer, channels, options)
+        .unwrap()
+        .outcome
+        .into_header();
+    assert_eq!(tip.block_height, 10);
+    assert_eq!(tip.block_hash, stub_hash(10));
+
+    // every block after the first one reached the sink, in order
+    assert_eq!(*heights.lock().unwrap(), (1..=10).collect::<Vec<_>>());
+
+    // and none of them were stored in the burnchain DB
+    let burnchain_db = burnchain.open_burnchain_db(false).unwrap();
+    assert_eq!(
+        burnchain_db.get_canonical_chain_tip().unwrap().block_height,
+        0
+    );
+}
+
+#[test]
+fn test_sync_with_indexer_stores_blocks_parsed_out_of_order() {
+    let (mut burnchain, mut indexer) = setup(8);
+    let completion_order = vec![5, 2, 8, 1, 7, 3, 6, 4];
+    indexer.set_parse_workers(completion_order.len());
+    indexer.set_completion_order(Some(completion_order.clone()));
+    let (_receivers, channels) = CoordinatorCommunication::instantiate();
+
+    let heights = Arc::new(Mutex::new(vec![]));
+    let sink = CountingSink {
+        heights: heights.clone(),
+    };
+    let options = SyncOptions {
+        sink: Some(Box::new(sink)),
+        ..SyncOptions::default()
+    };
+    let tip = burnchain
+        .sync_with_indexer_options(&mut indexer, channels, options)
+        .unwrap()
+        .outcome
+        .into_header();
+    assert_eq!(tip.block_height, 8);
+    assert_eq!(tip.block_hash, stub_hash(8));
+
+    // the blocks finished parsing shuffled, but reached the sink in height order
+    assert_eq!(indexer.take_parse_order(), completion_order);
+    assert_eq!(*heights.lock().unwrap(), (1..=8).collect::<Vec<_>>());
+}
@@ -0,0 +1,674 @@
+// Copyright (C) 2025 Stacks Open Internet Foundation
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with this program.  If not, see <http://www.gnu.org/licenses/>.
+
+//! In-memory stand-ins for the burnchain indexer, downloader, and parser, so that the
+//! `Burnchain::sync_with_indexer` pipeline can be driven without a bitcoin node.
+
+use std::sync::{Arc, Condvar, Mutex};
+use std::thread;
+use std::time::{Duration, Instant};
+
+use stacks_common::types::chainstate::BurnchainHeaderHash;
+
+use crate::burnchains::bitcoin::{BitcoinBlock, BitcoinTransaction, Error as btc_error};
+use crate::burnchains::db::BurnchainHeaderReader;
+use crate::burnchains::indexer::{
+    BurnBlockIPC, BurnHeaderIPC, BurnchainBlockDownloader, BurnchainBlockParser, BurnchainIndexer,
+    SyncClock, DEFAULT_WORKER_JOIN_TIMEOUT,
+};
+use crate::burnchains::tests::BURNCHAIN_TEST_BLOCK_TIME;
+use crate::burnchains::{BurnchainBlock, BurnchainBlockHeader, Error as burnchain_error};
+use crate::core::{EpochList, StacksEpochId};
+use crate::util_lib::db::Error as DBError;
+
+/// Headers path reported by the test double. It never exists on disk, so
+/// `Burnchain::setup_chainstate` always asks the test double to sync its headers.
+const TEST_DOUBLE_HEADERS_PATH: &str = "/tmp/stacks-node-tests/burnchain-indexer-test-double";
+
+/// Deterministic block hash for a stub block at the given height
+pub fn stub_hash(height: u64) -> BurnchainHeaderHash {
+    BurnchainHeaderHash::from_hex(&format!("{:064x}", height)).unwrap()
+}
+
+/// Deterministic block hash for a stub block at the given height on fork `fork_id`. Fork 0 is
+/// the chain of `stub_hash`es.
+pub fn fork_hash(fork_id: u8, height: u64) -> BurnchainHeaderHash {
+    BurnchainHeaderHash::from_hex(&format!("{:02x}{:062x}", fork_id, height)).unwrap()
+}
+
+/// A burnchain block, with no transactions unless it's been given some. It doubles as its own
+/// header and as the downloaded block in the sync pipeline's IPC messages.
+#[derive(Debug, Clone, PartialEq)]
+pub struct StubBlock {
+    pub height: u64,
+    pub hash: BurnchainHeaderHash,
+    pub parent_hash: BurnchainHeaderHash,
+    pub timestamp: u64,
+    pub txs: Vec<BitcoinTransaction>,
+}
+
+impl StubBlock {
+    /// Make a block at `height` whose parent is the block hashed `parent_hash`. A chain's first
+    /// block has no parent in the chain, so `build_stub_chain` gives it the zero hash.
+    pub fn new(
+        height: u64,
+        hash: BurnchainHeaderHash,
+        parent_hash: BurnchainHeaderHash,
+    ) -> StubBlock {
+        StubBlock {
+            height,
+            hash,
+            parent_hash,
+            timestamp: BURNCHAIN_TEST_BLOCK_TIME + height,
+            txs: vec![],
+        }
+    }
+
+    pub fn header(&self) -> BurnchainBlockHeader {
+        BurnchainBlockHeader {
+            block_height: self.height,
+            block_hash: self.hash.clone(),
+            parent_block_hash: self.parent_hash.clone(),
+            num_txs: self.txs.len() as u64,
+            timestamp: self.timestamp,
+        }
+    }
+}
+
+/// Make a chain of `len` `StubBlock`s at heights `0..len`, hashed with `stub_hash`, in which
+/// each block's `parent_hash` is the previous block's `hash`
+pub fn build_stub_chain(len: u64) -> Vec<StubBlock> {
+    let mut chain: Vec<StubBlock> = Vec::with_capacity(len.try_into().unwrap());
+    for height in 0..len {
+        let parent_hash = chain
+            .last()
+            .map_or_else(BurnchainHeaderHash::zero, |parent| parent.hash.clone());
+        chain.push(StubBlock::new(height, stub_hash(height), parent_hash));
+    }
+    chain
+}
+
+impl BurnHeaderIPC for StubBlock {
+    type H = StubBlock;
+
+    fn height(&self) -> u64 {
+        self.height
+    }
+
+    fn header(&self) -> StubBlock {
+        self.clone()
+    }
+
+    fn header_hash(&self) -> [u8; 32] {
+        self.hash.to_bitcoin_hash().0
+    }
+
+    fn parent_header_hash(&self) -> [u8; 32] {
+        self.parent_hash.to_bitcoin_hash().0
+    }
+}
+
+impl BurnBlockIPC for StubBlock {
+    type H = StubBlock;
+    type B = StubBlock;
+
+    fn height(&self) -> u64 {
+        self.height
+    }
+
+    fn header(&self) -> StubBlock {
+        self.clone()
+    }
+
+    fn block(&self) -> StubBlock {
+        self.clone()
+    }
+}
+
+/// Downloader that serves blocks out of a fixed in-memory chain, and records each block it
+/// serves
+#[derive(Debug, Clone)]
+pub struct MockDownloader {
+    blocks: Vec<StubBlock>,
+    downloads: Arc<Mutex<Vec<StubBlock>>>,
+    /// If set, the block served at this height has a different hash than its header
+    poison_height: Option<u64>,
+    /// How long each call to `download` takes, to simulate a slow bitcoin node
+    download_delay: Duration,
+}
+
+impl MockDownloader {
+    pub fn new(blocks: Vec<StubBlock>, downloads: Arc<Mutex<Vec<StubBlock>>>) -> MockDownloader {
+        MockDownloader {
+            blocks,
+            downloads,
+            poison_height: None,
+            download_delay: Duration::ZERO,
+        }
+    }
+
+    /// Make each call to `download` take `download_delay`
+    pub fn set_download_delay(&mut self, download_delay: Duration) {
+        self.download_delay = download_delay;
+    }
+
+    /// Serve a block whose hash doesn't match the requested header at `poison_height`, as if
+    /// the block changed between reading its header and downloading it
+    pub fn set_poison_height(&mut self, poison_height: Option<u64>) {
+        self.poison_height = poison_height;
+    }
+}
+
+impl BurnchainBlockDownloader for MockDownloader {
+    type H = StubBlock;
+    type B = StubBlock;
+
+    fn download(&mut self, header: &StubBlock) -> Result<StubBlock, burnchain_error> {
+        if !self.download_delay.is_zero() {
+            thread::sleep(self.download_delay);
+        }
+        let block = self
+            .blocks
+            .iter()
+            .find(|block| block.hash == header.hash)
+            .cloned()
+            .ok_or(burnchain_error::DownloadError(btc_error::MissingHeader))?;
+        let block = if self.poison_height == Some(block.height) {
+            StubBlock {
+                hash: fork_hash(0xff, block.height),
+                ..block
+            }
+        } else {
+            block
+        };
+        self.downloads.lock().unwrap().push(block.clone());
+        Ok(block)
+    }
+}
+
+/// How long a parser waits for its turn to finish a block in a `CompletionOrder`. If the
+/// parsers aren't running concurrently, the blocks before it in the order never finish.
+const COMPLETION_ORDER_TIMEOUT: Duration = Duration::from_secs(30);
+
+/// Makes the parsers that share it finish the blocks at the given heights in the given order.
+/// Parsing a block at any other height is not held up.
+#[derive(Debug, Default)]
+pub struct CompletionOrder {
+    order: Vec<u64>,
+    /// How many of the blocks in `order` have finished parsing
+    finished: Mutex<usize>,
+    finished_changed: Condvar,
+}
+
+impl CompletionOrder {
+    pub fn new(order: Vec<u64>) -> CompletionOrder {
+        CompletionOrder {
+            order,
+            ..CompletionOrder::default()
+        }
+    }
+
+    /// Wait for the blocks before `height` in the order to finish, then run `finish`.
+    /// Panics if they don't all finish within `COMPLETION_ORDER_TIMEOUT`.
+    fn finish_in_turn(&self, height: u64, finish: impl FnOnce()) {
+        let Some(position) = self.order.iter().position(|h| *h == height) else {
+            finish();
+            return;
+        };
+        let (mut finished, wait) = self
+            .finished_changed
+            .wait_timeout_while(
+                self.finished.lock().unwrap(),
+                COMPLETION_ORDER_TIMEOUT,
+                |finished| *finished < position,
+            )
+            .unwrap();
+        if wait.timed_out() {
+            let num_finished = *finished;
+            // don't poison the lock for the other parsers
+            drop(finished);
+            panic!(
+                "Block {height} waited {COMPLETION_ORDER_TIMEOUT:?} for its turn to finish parsing, but only {num_finished} of the {position} blocks before it finished"
+            );
+        }
+        finish();
+        *finished += 1;
+        self.finished_changed.notify_all();
+    }
+}
+
+/// Virtual clock that only moves when slept on. Clones share the same time, and the same record
+/// of every sleep.
+#[derive(Debug, Clone)]
+pub struct SharedVirtualClock {
+    start: Instant,
+    elapsed: Arc<Mutex<Duration>>,
+    sleeps: Arc<Mutex<Vec<Duration>>>,
+}
+
+impl Default for SharedVirtualClock {
+    fn default() -> SharedVirtualClock {
+        SharedVirtualClock {
+            start: Instant::now(),
+            elapsed: Arc::new(Mutex::new(Duration::ZERO)),
+            sleeps: Arc::new(Mutex::new(vec![])),
+        }
+    }
+}
+
+impl SharedVirtualClock {
+    /// Take the record of every sleep so far
+    pub fn take_sleeps(&self) -> Vec<Duration> {
+        std::mem::take(&mut *self.sleeps.lock().unwrap())
+    }
+}
+
+impl SyncClock for SharedVirtualClock {
+    fn now(&self) -> Instant {
+        self.start + *self.elapsed.lock().unwrap()
+    }
+
+    fn sleep(&mut self, duration: Duration) {
+        *self.elapsed.lock().unwrap() += duration;
+        self.sleeps.lock().unwrap().push(duration);
+    }
+}
+
+/// Parser that turns a stub block into a bitcoin block with the stub block's transactions, and
+/// records the epoch each block was parsed in
+#[derive(Debug, Clone, Default)]
+pub struct MockParser {
+    /// How long each call to `parse` takes, to simulate CPU-bound parsing
+    parse_delay: Duration,
+    /// If set, the order that this parser and its clones finish blocks in
+    completion_order: Option<Arc<CompletionOrder>>,
+    parsed_epochs: Arc<Mutex<Vec<(u64, StacksEpochId)>>>,
+}
+
+impl MockParser {
+    pub fn new(
+        parse_delay: Duration,
+        parsed_epochs: Arc<Mutex<Vec<(u64, StacksEpochId)>>>,
+    ) -> MockParser {
+        MockParser {
+            parse_delay,
+            completion_order: None,
+            parsed_epochs,
+        }
+    }
+
+    /// Finish parsing blocks in `completion_order`, as if parse workers had been scheduled that
+    /// way
+    pub fn set_completion_order(&mut self, completion_order: Option<Arc<CompletionOrder>>) {
+        self.completion_order = completion_order;
+    }
+}
+
+impl BurnchainBlockParser for MockParser {
+    type D = MockDownloader;
+
+    fn parse(
+        &mut self,
+        block: &StubBlock,
+        epoch_id: StacksEpochId,
+    ) -> Result<BurnchainBlock, burnchain_error> {
+        if !self.parse_delay.is_zero() {
+            thread::sleep(self.parse_delay);
+        }
+        let record = || {
+            self.parsed_epochs
+                .lock()
+                .unwrap()
+                .push((block.height, epoch_id))
+        };
+        match &self.completion_order {
+            Some(completion_order) => completion_order.finish_in_turn(block.height, record),
+            None => record(),
+        }
+        Ok(BurnchainBlock::Bitcoin(BitcoinBlock::new(
+            block.height,
+            &block.hash,
+            &block.parent_hash,
+            block.txs.clone(),
+            block.timestamp,
+        )))
+    }
+}
+
+/// Burnchain indexer backed by an in-memory chain of `StubBlock`s. `blocks[0]` is the first
+/// block. Headers become visible once `sync_headers` has been called, just like the real
+/// indexer's SPV headers.
+#[derive(Debug, Clone)]
+pub struct BurnchainIndexerTestDouble {
+    blocks: Vec<StubBlock>,
+    epochs: EpochList,
+    /// Height of the highest synced header
+    headers_height: u64,
+    /// If set, the next call to `find_chain_reorg` reports this common ancestor height
+    reorg_height: Option<u64>,
+    /// The height passed to every `drop_headers` call, in call order
+    dropped_headers: Vec<u64>,
+    /// The start height passed to every `sync_headers` call, in call order
+    header_syncs: Vec<u64>,
+    parse_workers: usize,
+    parse_delay: Duration,
+    download_delay: Duration,
+    download_rate_limit: Option<u32>,
+    /// Clock that the sync pipeline measures the download rate limit with
+    sync_clock: SharedVirtualClock,
+    /// How long the sync pipeline waits for each of its worker threads to finish
+    worker_join_timeout: Duration,
+    /// If set, this test double's downloaders serve a wrong-hash block at this height
+    poison_height: Option<u64>,
+    /// If set, the order this test double's parsers finish their blocks in
+    completion_order: Option<Arc<CompletionOrder>>,
+    /// Every block served by this test double's downloaders, in download order
+    downloads: Arc<Mutex<Vec<StubBlock>>>,
+    /// The height and epoch of every block parsed by this test double's parsers, in parse order
+    parsed_epochs: Arc<Mutex<Vec<(u64, StacksEpochId)>>>,
+}
+
+impl BurnchainIndexerTestDouble {
+    pub fn new(blocks: Vec<StubBlock>, epochs: EpochList) -> BurnchainIndexerTestDouble {
+        assert!(!blocks.is_empty(), "BUG: test double needs a first block");
+        BurnchainIndexerTestDouble {
+            blocks,
+            epochs,
+            headers_height: 0,
+            reorg_height: None,
+            dropped_headers: vec![],
+            header_syncs: vec![],
+            parse_workers: 1,
+            parse_delay: Duration::ZERO,
+            download_delay: Duration::ZERO,
+            download_rate_limit: None,
+            sync_clock: SharedVirtualClock::default(),
+            worker_join_timeout: DEFAULT_WORKER_JOIN_TIMEOUT,
+            poison_height: None,
+            completion_order: None,
+            downloads: Arc::new(Mutex::new(vec![])),
+            parsed_epochs: Arc::new(Mutex::new(vec![])),
+        }
+    }
+
+    /// Make a test double over a chain of `StubBlock`s at heights `0..=tip_height`
+    pub fn with_tip_height(tip_height: u64, epochs: EpochList) -> BurnchainIndexerTestDouble {
+        BurnchainIndexerTestDouble::new(build_stub_chain(tip_height + 1), epochs)
+    }
+
+    /// Put the (already-parsed) transactions `txs` into the block at `height`
+    pub fn set_block_txs(&mut self, height: u64, txs: Vec<BitcoinTransaction>) {
+        let block = self
+            .blocks
+            .iter_mut()
+            .find(|block| block.height == height)
+            .expect("BUG: no block at that height");
+        block.txs = txs;
+    }
+
+    /// Have the next `find_chain_reorg` report a common ancestor at `height`
+    pub fn set_reorg_height(&mut self, height: u64) {
+        self.reorg_height = Some(height);
+    }
+
+    /// Replace the blocks above `ancestor_height` with a fork of blocks up to `tip_height`,
+    /// hashed with `fork_hash(fork_id, ..)`, and have the next `find_chain_reorg` report the
+    /// reorg
+    pub fn fork_at(&mut self, ancestor_height: u64, tip_height: u64, fork_id: u8) {
+        self.blocks.retain(|block| block.height <= ancestor_height);
+        for height in ancestor_height + 1..=tip_height {
+            let parent_hash = self
+                .blocks
+                .last()
+                .expect("BUG: no fork ancestor")
+                .hash
+                .clone();
+            self.blocks.push(StubBlock::new(
+                height,
+                fork_hash(fork_id, height),
+                parent_hash,
+            ));
+        }
+        self.set_reorg_height(ancestor_height);
+    }
+
+    /// Have the sync pipeline use `parse_workers` parse threads
+    pub fn set_parse_workers(&mut self, parse_workers: usize) {
+        self.parse_workers = parse_workers;
+    }
+
+    /// Make each parser spend `parse_delay` on every block it parses
+    pub fn set_parse_delay(&mut self, parse_delay: Duration) {
+        self.parse_delay = parse_delay;
+    }
+
+    /// Make each downloader spend `download_delay` on every block it downloads
+    pub fn set_download_delay(&mut self, download_delay: Duration) {
+        self.download_delay = download_delay;
+    }
+
+    /// Have the sync pipeline download at most `rate_limit` blocks per second
+    pub fn set_download_rate_limit(&mut self, rate_limit: Option<u32>) {
+        self.download_rate_limit = rate_limit;
+    }
+
+    /// Have the sync pipeline give up on a worker thread that isn't done within
+    /// `worker_join_timeout`
+    pub fn set_worker_join_timeout(&mut self, worker_join_timeout: Duration) {
+        self.worker_join_timeout = worker_join_timeout;
+    }
+
+    /// Take the record of every sleep the sync pipeline's rate limiter has made so far
+    pub fn take_rate_limit_sleeps(&self) -> Vec<Duration> {
+        self.sync_clock.take_sleeps()
+    }
+
+    /// Have this test double's downloaders serve a block whose hash doesn't match its header at
+    /// `poison_height` (or stop doing so, if `None`)
+    pub fn set_poison_height(&mut self, poison_height: Option<u64>) {
+        self.poison_height = poison_height;
+    }
+
+    /// Have this test double's parsers finish the blocks at the heights in `completion_order` in
+    /// that order, as if the parse workers had been scheduled that way (or stop doing so, if
+    /// `None`). A worker holds on to its block until the blocks before it have finished, so the
+    /// sync needs at least as many parse workers as there are heights in the order.
+    pub fn set_completion_order(&mut self, completion_order: Option<Vec<u64>>) {
+        self.completion_order = completion_order.map(|order| Arc::new(CompletionOrder::new(order)));
+    }
+
+    pub fn blocks(&self) -> &[StubBlock] {
+        &self.blocks
+    }
+
+    /// Take the record of every block downloaded so far
+    pub fn take_downloads(&self) -> Vec<StubBlock> {
+        std::mem::take(&mut *self.downloads.lock().unwrap())
+    }
+
+    /// Take the record of the epoch each block parsed so far was parsed in, sorted by height
+    pub fn take_parsed_epochs(&self) -> Vec<(u64, StacksEpochId)> {
+        let mut parsed_epochs = std::mem::take(&mut *self.parsed_epochs.lock().unwrap());
+        // parse workers can finish out of order
+        parsed_epochs.sort_by_key(|(height, _)| *height);
+        parsed_epochs
+    }
+
+    /// Take the heights of the blocks parsed so far, in the order they finished parsing
+    pub fn take_parse_order(&self) -> Vec<u64> {
+        std::mem::take(&mut *self.parsed_epochs.lock().unwrap())
+            .into_iter()
+            .map(|(height, _)| height)
+            .collect()
+    }
+
+    /// Take the record of every `drop_headers` call so far
+    pub fn take_dropped_headers(&mut self) -> Vec<u64> {
+        std::mem::take(&mut self.dropped_headers)
+    }
+
+    /// Take the record of every `sync_headers` call so far
+    pub fn take_header_syncs(&mut self) -> Vec<u64> {
+        std::mem::take(&mut self.header_syncs)
+    }
+
+    fn tip_height(&self) -> u64 {
+        self.blocks.last().map(|block| block.height).unwrap_or(0)
+    }
+
+    fn synced_blocks(&self) -> impl Iterator<Item = &StubBlock> {
+        let headers_height = self.headers_height;
+        self.blocks
+            .iter()
+            .filter(move |block| block.height <= headers_height)
+    }
+}
+
+impl BurnchainIndexer for BurnchainIndexerTestDouble {
+    type P = MockParser;
+
+    fn connect(&mut self) -> Result<(), burnchain_error> {
+        Ok(())
+    }
+
+    fn get_first_block_height(&self) -> u64 {
+        self.blocks[0].height
+    }
+
+    fn get_first_block_header_hash(&self) -> Result<BurnchainHeaderHash, burnchain_error> {
+        Ok(self.blocks[0].hash.clone())
+    }
+
+    fn get_first_block_header_timestamp(&self) -> Result<u64, burnchain_error> {
+        Ok(self.blocks[0].timestamp)
+    }
+
+    fn get_stacks_epochs(&self) -> EpochList {
+        self.epochs.clone()
+    }
+
+    fn get_headers_path(&self) -> String {
+        TEST_DOUBLE_HEADERS_PATH.to_string()
+    }
+
+    fn get_headers_height(&self) -> Result<u64, burnchain_error> {
+        Ok(self.headers_height + 1)
+    }
+
+    fn get_highest_header_height(&self) -> Result<u64, burnchain_error> {
+        Ok(self.headers_height)
+    }
+
+    fn find_chain_reorg(&mut self) -> Result<u64, burnchain_error> {
+        let Some(reorg_height) = self.reorg_height.take() else {
+            return Ok(self.headers_height);
+        };
+        // like the real indexer, drop the headers of the orphaned blocks, and merge in the
+        // canonical chain's headers in their place
+        self.drop_headers(reorg_height)?;
+        self.headers_height = self.tip_height();
+        Ok(reorg_height)
+    }
+
+    fn sync_headers(
+        &mut self,
+        start_height: u64,
+        end_height: Option<u64>,
+    ) -> Result<u64, burnchain_error> {
+        self.header_syncs.push(start_height);
+        let tip_height = self.tip_height();
+        self.headers_height = end_height.map_or(tip_height, |end| end.min(tip_height));
+        Ok(self.headers_height)
+    }
+
+    fn drop_headers(&mut self, new_height: u64) -> Result<(), burnchain_error> {
+        self.dropped_headers.push(new_height);
+        self.headers_height = self.headers_height.min(new_height);
+        Ok(())
+    }
+
+    fn read_headers(
+        &self,
+        start_block: u64,
+        end_block: u64,
+    ) -> Result<Vec<StubBlock>, burnchain_error> {
+        Ok(self
+            .synced_blocks()
+            .filter(|block| start_block <= block.height && block.height < end_block)
+            .cloned()
+            .collect())
+    }
+
+    fn downloader(&self) -> MockDownloader {
+        let mut downloader = MockDownloader::new(self.blocks.clone(), self.downloads.clone());
+        downloader.set_poison_height(self.poison_height);
+        downloader.set_download_delay(self.download_delay);
+        downloader
+    }
+
+    fn parser(&self) -> MockParser {
+        let mut parser = MockParser::new(self.parse_delay, self.parsed_epochs.clone());
+        parser.set_completion_order(self.completion_order.clone());
+        parser
+    }
+
+    fn parse_workers(&self) -> usize {
+        self.parse_workers
+    }
+
+    fn download_rate_limit(&self) -> Option<u32> {
+        self.download_rate_limit
+    }
+
+    fn sync_clock(&self) -> Box<dyn SyncClock> {
+        Box::new(self.sync_clock.clone())
+    }
+
+    fn worker_join_timeout(&self) -> Duration {
+        self.worker_join_timeout
+    }
+
+    fn reader(&self) -> BurnchainIndexerTestDouble {
+        self.clone()
+    }
+}
+
+impl BurnchainHeaderReader for BurnchainIndexerTestDouble {
+    fn read_burnchain_headers(
+        &self,
+        start_height: u64,
+        end_height: u64,
+    ) -> Result<Vec<BurnchainBlockHeader>, DBError> {
+        Ok(self
+            .synced_blocks()
+            .filter(|block| start_height <= block.height && block.height < end_height)
+            .map(StubBlock::header)
+            .collect())
+    }
+
+    fn get_burnchain_headers_height(&self) -> Result<u64, DBError> {
+        Ok(self.headers_height + 1)
+    }
+
+    fn find_burnchain_header_height(
+        &self,
+        header_hash: &BurnchainHeaderHash,
+    ) -> Result<Option<u64>, DBError> {
+        Ok(self
+            .synced_blocks()
+            .find(|block| &block.hash == header_hash)
+            .map(|block| block.height))
+    }
+}
@@ -451,7 +451,7 @@ impl<T: BlockEventDispatcher> OnChainRewardSetProvider<'_, T> {
             &burnchain.pox_constants,
             &registered_addrs[..],
             liquid_ustx,
-        );
+        )?;
 
         if !burnchain
             .pox_constants
@@ -180,7 +180,7 @@ impl NakamotoSigners {
             pox_constants,
             &reward_slots[..],
             liquid_ustx,
-        );
+        )?;
         let reward_set =
             StacksChainState::make_reward_set(threshold, reward_slots, StacksEpochId::Epoch30);
 
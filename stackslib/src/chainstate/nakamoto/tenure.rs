@@ -62,7 +62,7 @@ use std::ops::DerefMut;
 
 use clarity::vm::types::StacksAddressExtensions;
 use rusqlite::{params, Connection};
-use stacks_common::consts::{FIRST_BURNCHAIN_CONSENSUS_HASH, MINER_REWARD_MATURITY};
+use stacks_common::consts::FIRST_BURNCHAIN_CONSENSUS_HASH;
 use stacks_common::types::chainstate::{BlockHeaderHash, ConsensusHash, StacksBlockId};
 use stacks_common::types::StacksEpochId;
 
@@ -324,13 +324,14 @@ impl NakamotoChainState {
         coinbase_height: u64,
     ) -> Result<Option<MaturedMinerPaymentSchedules>, ChainstateError> {
         let mainnet = chainstate_tx.get_config().mainnet;
+        let miner_reward_maturity = chainstate_tx.get_config().miner_reward_maturity;
 
         // find matured miner rewards, so we can grant them within the Clarity DB tx.
-        if coinbase_height < MINER_REWARD_MATURITY {
+        if coinbase_height < miner_reward_maturity {
             return Ok(Some(MaturedMinerPaymentSchedules::genesis(mainnet)));
         }
 
-        let matured_coinbase_height = coinbase_height - MINER_REWARD_MATURITY;
+        let matured_coinbase_height = coinbase_height - miner_reward_maturity;
         let matured_tenure_block_header = Self::get_header_by_coinbase_height(
             chainstate_tx.deref_mut(),
             tip_index_hash,
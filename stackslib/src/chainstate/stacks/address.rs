@@ -22,11 +22,11 @@ use stacks_common::types::chainstate::StacksAddress;
 use stacks_common::util::hash::{to_hex, Hash160};
 
 use crate::burnchains::bitcoin::address::{
-    legacy_address_type_to_version_byte, legacy_version_byte_to_address_type, to_b58_version_byte,
-    to_c32_version_byte, BitcoinAddress, LegacyBitcoinAddress, LegacyBitcoinAddressType,
-    SegwitBitcoinAddress,
+    legacy_address_type_to_version_byte, legacy_version_byte_to_address_type, segwit_network_hrp,
+    to_b58_version_byte, to_c32_version_byte, BitcoinAddress, LegacyBitcoinAddress,
+    LegacyBitcoinAddressType, SegwitBitcoinAddress,
 };
-use crate::burnchains::bitcoin::BitcoinTxOutput;
+use crate::burnchains::bitcoin::{BitcoinNetworkType, BitcoinTxOutput};
 use crate::burnchains::Address;
 use crate::util_lib::boot::boot_code_addr;
 
@@ -50,6 +50,17 @@ define_u8_enum!(PoxAddressType32 {
     P2TR = 0x06
 });
 
+/// The kind of a PoX address, as identified by the version byte of its Clarity tuple
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PoxAddressType {
+    /// An address with a Stacks representation, using the given hash mode
+    Standard(AddressHashMode),
+    /// A 20-byte address without a Stacks representation
+    Addr20(PoxAddressType20),
+    /// A 32-byte address without a Stacks representation
+    Addr32(PoxAddressType32),
+}
+
 /// A PoX address as seen by the .pox and .pox-2 contracts.
 /// Used by the sortition DB and chains coordinator to extract addresses from the PoX contract to
 /// build the reward set and to validate block-commits.
@@ -122,6 +133,16 @@ impl PoxAddress {
         }
     }
 
+    /// Get the kind of this address.  Returns None for a standard address with an unknown hash
+    /// mode (i.e. one decoded from a legacy Bitcoin address).
+    pub fn address_type(&self) -> Option<PoxAddressType> {
+        match *self {
+            PoxAddress::Standard(_, hashmode) => hashmode.map(PoxAddressType::Standard),
+            PoxAddress::Addr20(_, addrtype, _) => Some(PoxAddressType::Addr20(addrtype)),
+            PoxAddress::Addr32(_, addrtype, _) => Some(PoxAddressType::Addr32(addrtype)),
+        }
+    }
+
     /// Get the data portion of this address.  This does not include the address or witness
     /// version.
     pub fn bytes(&self) -> Vec<u8> {
@@ -254,6 +275,23 @@ impl PoxAddress {
         None
     }
 
+    /// Like `try_from_pox_tuple`, but only accept a tuple whose version byte is that of the
+    /// `expected` address type.
+    pub fn try_from_pox_tuple_expect(
+        mainnet: bool,
+        value: &Value,
+        expected: PoxAddressType,
+    ) -> Result<PoxAddress, String> {
+        let addr = PoxAddress::try_from_pox_tuple(mainnet, value)
+            .ok_or_else(|| "Value is not a valid PoX address tuple".to_string())?;
+        match addr.address_type() {
+            Some(addr_type) if addr_type == expected => Ok(addr),
+            addr_type => Err(format!(
+                "Expected a PoX address of type {expected:?}, but got {addr_type:?}"
+            )),
+        }
+    }
+
     /// Serialize this structure to a string that we can store in the sortition DB
     pub fn to_db_string(&self) -> String {
         serde_json::to_string(self).expect("FATAL: failed to serialize JSON value")
@@ -410,6 +448,58 @@ impl PoxAddress {
         })
     }
 
+    /// Convert this PoxAddress into a legacy (p2pkh or p2sh) base58check Bitcoin address.
+    /// Unlike `to_b58`, this returns None for segwit and taproot addresses, which only have a
+    /// bech32 form.
+    pub fn to_legacy_b58(&self) -> Option<String> {
+        match self {
+            PoxAddress::Standard(addr, _) => Some(addr.clone().to_b58()),
+            PoxAddress::Addr20(..) | PoxAddress::Addr32(..) => None,
+        }
+    }
+
+    /// Convert from a legacy (p2pkh or p2sh) base58check Bitcoin address, on either mainnet or
+    /// testnet. Unlike `from_b58`, this rejects bech32 addresses.
+    pub fn from_legacy_b58(input: &str) -> Option<Self> {
+        let legacy_addr = LegacyBitcoinAddress::from_b58(input).ok()?;
+        Some(PoxAddress::Standard(
+            StacksAddress::from_legacy_bitcoin_address(&legacy_addr),
+            None,
+        ))
+    }
+
+    /// Convert from a bech32 (segwit v0) or bech32m (taproot) address on the given network.
+    /// Returns None if the address is for a different network, or has a witness program of the
+    /// wrong length for its witness version.
+    pub fn from_bech32(network_id: BitcoinNetworkType, input: &str) -> Option<Self> {
+        let segwit_addr = SegwitBitcoinAddress::from_bech32_network(network_id, input)?;
+        PoxAddress::try_from_bitcoin_output(&BitcoinTxOutput {
+            address: BitcoinAddress::Segwit(segwit_addr),
+            units: 0,
+        })
+    }
+
+    /// Convert this PoxAddress into its canonical (lowercase) bech32 or bech32m address on the
+    /// given network. Returns None for legacy addresses, and for addresses of the other network.
+    pub fn to_bech32(&self, network_id: BitcoinNetworkType) -> Option<String> {
+        let segwit_addr = match *self {
+            PoxAddress::Standard(..) => return None,
+            PoxAddress::Addr20(mainnet, PoxAddressType20::P2WPKH, bytes) => {
+                SegwitBitcoinAddress::P2WPKH(mainnet, bytes)
+            }
+            PoxAddress::Addr32(mainnet, PoxAddressType32::P2WSH, bytes) => {
+                SegwitBitcoinAddress::P2WSH(mainnet, bytes)
+            }
+            PoxAddress::Addr32(mainnet, PoxAddressType32::P2TR, bytes) => {
+                SegwitBitcoinAddress::P2TR(mainnet, bytes)
+            }
+        };
+        if segwit_addr.is_mainnet() != (network_id == BitcoinNetworkType::Mainnet) {
+            return None;
+        }
+        Some(segwit_addr.to_bech32_hrp(segwit_network_hrp(network_id)))
+    }
+
     /// Convert this PoxAddress into a Bitcoin tx output
     pub fn to_bitcoin_tx_out(&self, value: u64) -> TxOut {
         match *self {
@@ -524,12 +614,14 @@ impl StacksAddressExtensions for StacksAddress {
 
 #[cfg(test)]
 mod test {
+    use std::collections::{BTreeMap, HashSet};
+
     use clarity::vm::types::BuffData;
+    use stacks_common::deps_common::bech32::{self, ToBase32};
     use stacks_common::util::hash::*;
     use stacks_common::util::secp256k1::Secp256k1PublicKey as PubKey;
 
     use super::*;
-    use crate::burnchains::bitcoin::BitcoinNetworkType;
     use crate::chainstate::stacks::*;
     use crate::net::codec::test::check_codec_and_corruption;
 
@@ -911,6 +1003,49 @@ mod test {
         .is_none());
     }
 
+    #[test]
+    fn test_try_from_pox_tuple_expect() {
+        let p2wsh = PoxAddressType::Addr32(PoxAddressType32::P2WSH);
+        assert_eq!(
+            PoxAddress::try_from_pox_tuple_expect(
+                true,
+                &make_pox_addr_raw(0x05, vec![0x01; 32]),
+                p2wsh
+            )
+            .unwrap(),
+            PoxAddress::Addr32(true, PoxAddressType32::P2WSH, [0x01; 32])
+        );
+        assert_eq!(
+            PoxAddress::try_from_pox_tuple_expect(
+                false,
+                &make_pox_addr_raw(0x01, vec![0x02; 20]),
+                PoxAddressType::Standard(AddressHashMode::SerializeP2SH)
+            )
+            .unwrap(),
+            PoxAddress::Standard(
+                StacksAddress::new(C32_ADDRESS_VERSION_TESTNET_MULTISIG, Hash160([0x02; 20]))
+                    .unwrap(),
+                Some(AddressHashMode::SerializeP2SH)
+            )
+        );
+
+        // valid tuples of the wrong type
+        for (version, len) in [(0x00, 20), (0x04, 20), (0x06, 32)] {
+            let value = make_pox_addr_raw(version, vec![0x03; len]);
+            assert!(PoxAddress::try_from_pox_tuple(true, &value).is_some());
+            assert!(PoxAddress::try_from_pox_tuple_expect(true, &value, p2wsh).is_err());
+        }
+
+        // invalid tuples
+        assert!(PoxAddress::try_from_pox_tuple_expect(
+            true,
+            &make_pox_addr_raw(0x05, vec![0x04; 20]),
+            p2wsh
+        )
+        .is_err());
+        assert!(PoxAddress::try_from_pox_tuple_expect(true, &Value::UInt(5), p2wsh).is_err());
+    }
+
     #[test]
     fn test_as_clarity_tuple() {
         assert_eq!(
@@ -1209,6 +1344,153 @@ mod test {
         }
     }
 
+    #[test]
+    fn test_pox_addr_legacy_b58_round_trip() {
+        let legacy_addrs = [
+            (C32_ADDRESS_VERSION_MAINNET_SINGLESIG, "1"),
+            (C32_ADDRESS_VERSION_TESTNET_SINGLESIG, "m"),
+            (C32_ADDRESS_VERSION_MAINNET_MULTISIG, "3"),
+            (C32_ADDRESS_VERSION_TESTNET_MULTISIG, "2"),
+        ];
+        for (version, b58_prefix) in legacy_addrs {
+            let addr = PoxAddress::Standard(
+                StacksAddress::new(version, Hash160([0x01; 20])).unwrap(),
+                None,
+            );
+            let addr_str = addr.to_legacy_b58().unwrap();
+            assert!(addr_str.starts_with(b58_prefix), "{addr_str}");
+            assert_eq!(PoxAddress::from_legacy_b58(&addr_str).unwrap(), addr);
+        }
+
+        let segwit_addrs = [
+            PoxAddress::Addr20(true, PoxAddressType20::P2WPKH, [0x01; 20]),
+            PoxAddress::Addr20(false, PoxAddressType20::P2WPKH, [0x01; 20]),
+            PoxAddress::Addr32(true, PoxAddressType32::P2WSH, [0x01; 32]),
+            PoxAddress::Addr32(false, PoxAddressType32::P2TR, [0x01; 32]),
+        ];
+        for addr in segwit_addrs {
+            assert_eq!(addr.to_legacy_b58(), None);
+            let bech32_str = addr.clone().to_b58();
+            assert_eq!(PoxAddress::from_b58(&bech32_str).unwrap(), addr);
+            assert_eq!(PoxAddress::from_legacy_b58(&bech32_str), None);
+        }
+
+        assert_eq!(PoxAddress::from_legacy_b58("not an address"), None);
+    }
+
+    #[test]
+    fn test_pox_addr_bech32() {
+        let vectors = [
+            (
+                "bc1qw508d6qejxtdg4y5r3zarvary0c5xw7kv8f3t4",
+                PoxAddress::Addr20(
+                    true,
+                    PoxAddressType20::P2WPKH,
+                    hex_bytes("751e76e8199196d454941c45d1b3a323f1433bd6")
+                        .unwrap()
+                        .try_into()
+                        .unwrap(),
+                ),
+            ),
+            (
+                "bc1qrp33g0q5c5txsp9arysrx4k6zdkfs4nce4xj0gdcccefvpysxf3qccfmv3",
+                PoxAddress::Addr32(
+                    true,
+                    PoxAddressType32::P2WSH,
+                    hex_bytes("1863143c14c5166804bd19203356da136c985678cd4d27a1b8c6329604903262")
+                        .unwrap()
+                        .try_into()
+                        .unwrap(),
+                ),
+            ),
+            (
+                "bc1p5cyxnuxmeuwuvkwfem96lqzszd02n6xdcjrs20cac6yqjjwudpxqkedrcr",
+                PoxAddress::Addr32(
+                    true,
+                    PoxAddressType32::P2TR,
+                    hex_bytes("a60869f0dbcf1dc659c9cecbaf8050135ea9e8cdc487053f1dc6880949dc684c")
+                        .unwrap()
+                        .try_into()
+                        .unwrap(),
+                ),
+            ),
+        ];
+        for (addr_str, addr) in vectors.iter() {
+            assert_eq!(
+                PoxAddress::from_bech32(BitcoinNetworkType::Mainnet, addr_str).as_ref(),
+                Some(addr)
+            );
+            assert_eq!(
+                addr.to_bech32(BitcoinNetworkType::Mainnet).as_deref(),
+                Some(*addr_str)
+            );
+            // wrong network
+            assert_eq!(
+                PoxAddress::from_bech32(BitcoinNetworkType::Testnet, addr_str),
+                None
+            );
+            assert_eq!(addr.to_bech32(BitcoinNetworkType::Testnet), None);
+        }
+
+        // testnet and regtest addresses round-trip with their own prefixes
+        let testnet_addr = PoxAddress::Addr32(false, PoxAddressType32::P2TR, [0x01; 32]);
+        for (network_id, prefix) in [
+            (BitcoinNetworkType::Testnet, "tb1p"),
+            (BitcoinNetworkType::Regtest, "bcrt1p"),
+        ] {
+            let addr_str = testnet_addr.to_bech32(network_id).unwrap();
+            assert!(addr_str.starts_with(prefix), "{addr_str}");
+            assert_eq!(
+                PoxAddress::from_bech32(network_id, &addr_str).unwrap(),
+                testnet_addr
+            );
+        }
+
+        // legacy addresses have no bech32 form
+        let legacy_addr = PoxAddress::Standard(
+            StacksAddress::new(C32_ADDRESS_VERSION_MAINNET_SINGLESIG, Hash160([0x01; 20])).unwrap(),
+            None,
+        );
+        assert_eq!(legacy_addr.to_bech32(BitcoinNetworkType::Mainnet), None);
+        assert_eq!(
+            PoxAddress::from_bech32(
+                BitcoinNetworkType::Mainnet,
+                &legacy_addr.to_legacy_b58().unwrap()
+            ),
+            None
+        );
+
+        // mixed case
+        assert_eq!(
+            PoxAddress::from_bech32(
+                BitcoinNetworkType::Mainnet,
+                "bc1QW508d6qejxtdg4y5r3zarvary0c5xw7kv8f3t4"
+            ),
+            None
+        );
+        // bad checksum
+        assert_eq!(
+            PoxAddress::from_bech32(
+                BitcoinNetworkType::Mainnet,
+                "bc1qw508d6qejxtdg4y5r3zarvary0c5xw7kv8f3t5"
+            ),
+            None
+        );
+        // witness programs of the wrong length for their witness version
+        for (version, program_len, variant) in [
+            (0, 25, bech32::Variant::Bech32),
+            (1, 20, bech32::Variant::Bech32m),
+        ] {
+            let mut quintets = vec![bech32::u5::try_from_u8(version).unwrap()];
+            quintets.extend(vec![0x01u8; program_len].to_base32());
+            let addr_str = bech32::encode("bc", quintets, variant).unwrap();
+            assert_eq!(
+                PoxAddress::from_bech32(BitcoinNetworkType::Mainnet, &addr_str),
+                None
+            );
+        }
+    }
+
     #[test]
     fn test_try_from_bitcoin_output() {
         assert_eq!(
@@ -1286,4 +1568,75 @@ mod test {
             PoxAddress::Addr32(true, PoxAddressType32::P2TR, [0x01; 32])
         );
     }
+
+    #[test]
+    fn test_pox_address_hash_eq() {
+        let addrs = [
+            PoxAddress::Standard(
+                StacksAddress::new(C32_ADDRESS_VERSION_TESTNET_SINGLESIG, Hash160([0x01; 20]))
+                    .unwrap(),
+                Some(AddressHashMode::SerializeP2PKH),
+            ),
+            PoxAddress::Addr20(false, PoxAddressType20::P2WPKH, [0x01; 20]),
+            PoxAddress::Addr32(false, PoxAddressType32::P2WSH, [0x01; 32]),
+            PoxAddress::Addr32(false, PoxAddressType32::P2TR, [0x01; 32]),
+        ];
+
+        for addr in addrs.iter() {
+            // the same address, decoded again from its Clarity tuple
+            let tuple = Value::Tuple(addr.clone().as_clarity_tuple().unwrap());
+            let decoded = PoxAddress::try_from_pox_tuple(false, &tuple).unwrap();
+            assert_eq!(addr, &decoded);
+
+            let set: HashSet<_> = [addr.clone(), decoded].into_iter().collect();
+            assert_eq!(set.len(), 1);
+        }
+
+        // the same hashbytes with a different version or network is a different address
+        let set: HashSet<_> = addrs
+            .iter()
+            .cloned()
+            .chain([
+                PoxAddress::Addr20(true, PoxAddressType20::P2WPKH, [0x01; 20]),
+                PoxAddress::Addr32(true, PoxAddressType32::P2TR, [0x01; 32]),
+            ])
+            .collect();
+        assert_eq!(set.len(), addrs.len() + 2);
+    }
+
+    #[test]
+    fn test_pox_address_ord() {
+        let standard = PoxAddress::Standard(
+            StacksAddress::new(C32_ADDRESS_VERSION_TESTNET_SINGLESIG, Hash160([0x02; 20])).unwrap(),
+            Some(AddressHashMode::SerializeP2PKH),
+        );
+        let p2wpkh_testnet = PoxAddress::Addr20(false, PoxAddressType20::P2WPKH, [0x02; 20]);
+        let p2wpkh_mainnet = PoxAddress::Addr20(true, PoxAddressType20::P2WPKH, [0x01; 20]);
+        let p2wsh = PoxAddress::Addr32(false, PoxAddressType32::P2WSH, [0x02; 32]);
+        let p2tr_low = PoxAddress::Addr32(false, PoxAddressType32::P2TR, [0x01; 32]);
+        let p2tr_high = PoxAddress::Addr32(false, PoxAddressType32::P2TR, [0x02; 32]);
+
+        let expected = vec![
+            standard,
+            p2wpkh_testnet,
+            p2wpkh_mainnet,
+            p2wsh,
+            p2tr_low,
+            p2tr_high,
+        ];
+
+        let mut sorted = expected.clone();
+        sorted.reverse();
+        sorted.sort();
+        assert_eq!(sorted, expected);
+
+        // as map keys, addresses iterate in the same order no matter how they were inserted
+        let map: BTreeMap<_, _> = expected
+            .iter()
+            .rev()
+            .enumerate()
+            .map(|(i, addr)| (addr.clone(), i))
+            .collect();
+        assert_eq!(map.into_keys().collect::<Vec<_>>(), expected);
+    }
 }
@@ -15,7 +15,7 @@
 // along with this program.  If not, see <http://www.gnu.org/licenses/>.
 
 use std::cmp;
-use std::collections::BTreeMap;
+use std::collections::{BTreeMap, HashMap};
 
 use clarity::vm::analysis::CheckErrors;
 use clarity::vm::ast::ASTRules;
@@ -26,14 +26,16 @@ use clarity::vm::errors::Error as VmError;
 use clarity::vm::events::StacksTransactionEvent;
 use clarity::vm::representations::ContractName;
 use clarity::vm::types::{
-    PrincipalData, QualifiedContractIdentifier, StandardPrincipalData, TupleData, Value,
+    OptionalData, PrincipalData, QualifiedContractIdentifier, StandardPrincipalData, TupleData,
+    Value,
 };
 use clarity::vm::{ClarityVersion, Environment, SymbolicExpression};
 use lazy_static::lazy_static;
 use serde::Deserialize;
 use stacks_common::codec::StacksMessageCodec;
-use stacks_common::types::chainstate::{StacksAddress, StacksBlockId};
+use stacks_common::types::chainstate::{SortitionId, StacksAddress, StacksBlockId};
 use stacks_common::util::hash::{hex_bytes, to_hex};
+use stacks_common::util::secp256k1::Secp256k1PublicKey;
 
 use crate::burnchains::{Burnchain, PoxConstants};
 use crate::chainstate::burn::db::sortdb::SortitionDB;
@@ -44,6 +46,7 @@ use crate::clarity_vm::clarity::{ClarityConnection, ClarityTransactionConnection
 use crate::clarity_vm::database::HeadersDBConn;
 use crate::core::{StacksEpochId, CHAIN_ID_MAINNET, POX_MAXIMAL_SCALING, POX_THRESHOLD_STEPS_USTX};
 use crate::util_lib::boot;
+use crate::util_lib::db::Error as DBError;
 
 const BOOT_CODE_POX_BODY: &str = std::include_str!("pox.clar");
 const BOOT_CODE_POX_TESTNET_CONSTS: &str = std::include_str!("pox-testnet.clar");
@@ -144,6 +147,31 @@ pub fn make_contract_id(addr: &StacksAddress, name: &str) -> QualifiedContractId
     )
 }
 
+/// Would pox-4 let a delegate `delegate-stack-stx` a stacker's STX to `proposed_pox_addr`?
+/// `delegation` is the stacker's `delegation-state` entry (as returned by `get-delegation-info`).
+/// If the stacker's `delegate-stx` fixed a `pox-addr`, only that address is allowed; otherwise any
+/// address is.  Pool software can use this to avoid submitting a `delegate-stack-stx` that the
+/// contract would reject.
+pub fn delegate_stack_pox_addr_allowed(
+    delegation: &TupleData,
+    proposed_pox_addr: &PoxAddress,
+) -> bool {
+    let Ok(Value::Optional(OptionalData { data: pox_addr_opt })) = delegation.get("pox-addr")
+    else {
+        warn!("Delegation state has no optional `pox-addr`"; "delegation" => ?delegation);
+        return false;
+    };
+    let Some(fixed_pox_addr) = pox_addr_opt else {
+        return true;
+    };
+    // pox-4 compares the `{ version, hashbytes }` tuples, so an address without one can never
+    // match
+    let Some(proposed_tuple) = proposed_pox_addr.as_clarity_tuple() else {
+        return false;
+    };
+    **fixed_pox_addr == Value::Tuple(proposed_tuple)
+}
+
 #[derive(Clone, Debug)]
 pub struct RawRewardSetEntry {
     pub reward_address: PoxAddress,
@@ -166,6 +194,46 @@ define_named_enum!(PoxVersions {
     Pox4("pox-4"),
 });
 
+impl PoxVersions {
+    /// The PoX contract that is active at `burn_height` in epoch `epoch_id`.
+    ///
+    /// The contract switches over at the activation heights in `pox_constants` (see
+    /// `PoxConstants::active_pox_contract()`), but never to a contract that `epoch_id` doesn't
+    /// have yet. The v2 and v3 unlock heights don't change the active contract: until the next
+    /// contract activates, the unlocked one is still the one that is read.
+    pub fn active_pox_version(
+        epoch_id: StacksEpochId,
+        pox_constants: &PoxConstants,
+        burn_height: u64,
+    ) -> PoxVersions {
+        let by_height = PoxVersions::lookup_by_name(pox_constants.active_pox_contract(burn_height))
+            .expect("FATAL: active PoX contract is not a known PoX version");
+        let by_epoch = match epoch_id {
+            StacksEpochId::Epoch10 | StacksEpochId::Epoch20 | StacksEpochId::Epoch2_05 => {
+                PoxVersions::Pox1
+            }
+            StacksEpochId::Epoch21 | StacksEpochId::Epoch22 | StacksEpochId::Epoch23 => {
+                PoxVersions::Pox2
+            }
+            StacksEpochId::Epoch24 => PoxVersions::Pox3,
+            StacksEpochId::Epoch25 | StacksEpochId::Epoch30 | StacksEpochId::Epoch31 => {
+                PoxVersions::Pox4
+            }
+        };
+        let index = |version: PoxVersions| {
+            PoxVersions::ALL
+                .iter()
+                .position(|v| *v == version)
+                .expect("FATAL: unknown PoX version")
+        };
+        if index(by_height) <= index(by_epoch) {
+            by_height
+        } else {
+            by_epoch
+        }
+    }
+}
+
 #[derive(Debug, PartialEq, Clone, Serialize, Deserialize)]
 pub struct PoxStartCycleInfo {
     /// This data contains the set of principals who missed a reward slot
@@ -209,6 +277,8 @@ pub struct NakamotoSignerEntry {
 pub struct RewardSet {
     pub rewarded_addresses: Vec<PoxAddress>,
     pub start_cycle_state: PoxStartCycleInfo,
+    /// Sorted by compressed signing key, so a signer's index in this list is the same no matter
+    /// the order in which its stacking entries were made. See `RewardSet::signer_index`.
     #[serde(skip_serializing_if = "Option::is_none", default)]
     // only generated for nakamoto reward sets
     pub signers: Option<Vec<NakamotoSignerEntry>>,
@@ -274,6 +344,111 @@ impl RewardSet {
                     .expect("FATAL: Total signer weight > u32::MAX")
             }))
     }
+
+    /// Compare two reward sets, treating the rewarded addresses and the signers as multisets.
+    /// That is, two reward sets with the same entries listed in different orders are equal.
+    pub fn eq_unordered(&self, other: &RewardSet) -> bool {
+        let sorted_addresses = |reward_set: &RewardSet| {
+            let mut addresses: Vec<_> = reward_set.rewarded_addresses.iter().collect();
+            addresses.sort();
+            addresses
+        };
+        let sorted_signers = |reward_set: &RewardSet| {
+            reward_set.signers.as_ref().map(|signers| {
+                let mut signers: Vec<_> = signers.iter().collect();
+                signers.sort_by_key(|entry| (entry.signing_key, entry.stacked_amt, entry.weight));
+                signers
+            })
+        };
+        self.start_cycle_state == other.start_cycle_state
+            && self.pox_ustx_threshold == other.pox_ustx_threshold
+            && sorted_addresses(self) == sorted_addresses(other)
+            && sorted_signers(self) == sorted_signers(other)
+    }
+
+    /// Return the number of sats that `pox_addr` is expected to receive across all reward blocks
+    /// of the cycle, given the `per_block_reward` sats that each reward block's commit splits
+    /// evenly among its `pox_constants.slots_per_reward_block()` outputs.
+    ///
+    /// Each of those outputs pays an entry drawn uniformly at random from the reward set (see
+    /// `SortitionHandleTx::pick_recipients()`), so over the cycle's `pox_constants.reward_slots()`
+    /// outputs, an address holding `n` of the reward set's `len` entries expects
+    /// `reward_slots() * n / len` payouts. For a full reward set, that is one payout per entry.
+    /// The result is rounded down, and is 0 if the reward set is empty.
+    pub fn expected_sats_for(
+        &self,
+        pox_addr: &PoxAddress,
+        pox_constants: &PoxConstants,
+        per_block_reward: u64,
+    ) -> u64 {
+        let num_entries = self.rewarded_addresses.len();
+        if num_entries == 0 {
+            return 0;
+        }
+        let num_held = self
+            .rewarded_addresses
+            .iter()
+            .filter(|addr| *addr == pox_addr)
+            .count();
+        let to_u128 = |n: usize| u128::try_from(n).expect("FATAL: reward set exceeds u128::MAX");
+
+        // reward_slots() * n / len payouts of per_block_reward / slots_per_reward_block() each
+        let expected = to_u128(num_held)
+            * u128::from(pox_constants.reward_slots())
+            * u128::from(per_block_reward)
+            / (to_u128(num_entries) * u128::from(pox_constants.slots_per_reward_block()));
+        u64::try_from(expected).unwrap_or(u64::MAX)
+    }
+
+    /// Return the number of uSTX stacked for each rewarded address, keyed by the address's
+    /// `to_burnchain_repr()`. Each reward slot stands for `pox_ustx_threshold` uSTX, so an address
+    /// with several slots gets their sum, and a reward set without a threshold maps every
+    /// address to 0. Burn slots are all counted under the key `"burn"`.
+    pub fn to_burnchain_payout_map(&self) -> HashMap<String, u128> {
+        let slot_amount = self.pox_ustx_threshold.unwrap_or(0);
+        let mut payouts = HashMap::new();
+        for addr in self.rewarded_addresses.iter() {
+            let key = if addr.is_burn() {
+                "burn".to_string()
+            } else {
+                addr.to_burnchain_repr()
+            };
+            let total: &mut u128 = payouts.entry(key).or_default();
+            *total = total.saturating_add(slot_amount);
+        }
+        payouts
+    }
+
+    /// Return the index of the signer with the given signing key in this reward set's signer
+    /// list, which is how signers identify themselves to each other during the reward cycle.
+    /// Signers are ordered by their compressed signing key, so the index is stable for the
+    /// whole cycle. Returns `None` if this reward set has no signers, or if the key isn't one.
+    pub fn signer_index(&self, signing_key: &Secp256k1PublicKey) -> Option<usize> {
+        let signing_key: [u8; 33] = signing_key.to_bytes_compressed().try_into().ok()?;
+        self.signers
+            .as_ref()?
+            .iter()
+            .position(|entry| entry.signing_key == signing_key)
+    }
+
+    /// Find the signing keys that more than one of the raw reward set `entries` use, which pox-4
+    /// rejects with `ERR_REUSED_SIGNER_KEY`. Returns each such key, in key order, along with the
+    /// indexes of the entries that use it. Entries without a signing key are ignored.
+    /// This takes the raw entries because a `RewardSet` has already merged its signers by key.
+    pub fn find_duplicate_signer_keys(entries: &[RawRewardSetEntry]) -> Vec<(Vec<u8>, Vec<usize>)> {
+        let mut indexes_by_key: BTreeMap<_, Vec<usize>> = BTreeMap::new();
+        for (index, entry) in entries.iter().enumerate() {
+            let Some(signing_key) = entry.signer.as_ref() else {
+                continue;
+            };
+            indexes_by_key.entry(signing_key).or_default().push(index);
+        }
+        indexes_by_key
+            .into_iter()
+            .filter(|(_, indexes)| indexes.len() > 1)
+            .map(|(signing_key, indexes)| (signing_key.to_vec(), indexes))
+            .collect()
+    }
 }
 
 impl RewardSetData {
@@ -730,7 +905,9 @@ impl StacksChainState {
                 .clone()
                 .expect("BUG: signing keys should all be set in reward-sets with any signing keys");
             if let Some(existing_entry) = signer_set.get_mut(&signing_key) {
-                *existing_entry += entry.amount_stacked;
+                *existing_entry = existing_entry
+                    .checked_add(entry.amount_stacked)
+                    .expect("CORRUPTION: Signer stacked > u128 max amount");
             } else {
                 signer_set.insert(signing_key.clone(), entry.amount_stacked);
             };
@@ -878,11 +1055,13 @@ impl StacksChainState {
         }
     }
 
+    /// Compute the reward set's threshold from the total `participation`.  Fails with
+    /// `Error::PoxAmountOverflow` if rounding the threshold up would exceed `u128::MAX`.
     pub fn get_threshold_from_participation(
         liquid_ustx: u128,
         participation: u128,
         reward_slots: u128,
-    ) -> u128 {
+    ) -> Result<u128, Error> {
         // set the lower limit on reward scaling at 25% of liquid_ustx
         //   (i.e., liquid_ustx / POX_MAXIMAL_SCALING)
         let scale_by = cmp::max(participation, liquid_ustx / POX_MAXIMAL_SCALING);
@@ -892,18 +1071,30 @@ impl StacksChainState {
             0 => 0,
             remainder => POX_THRESHOLD_STEPS_USTX - remainder,
         };
-        let threshold = threshold_precise + ceil_amount;
-        return threshold;
+        threshold_precise.checked_add(ceil_amount).ok_or_else(|| {
+            Error::PoxAmountOverflow(format!(
+                "threshold overflowed rounding {} up by {}",
+                threshold_precise, ceil_amount
+            ))
+        })
     }
 
+    /// Compute the reward set's threshold and total participation from the registered
+    /// `addresses`.  Fails with `Error::PoxAmountOverflow` if the stacked amounts sum to more than
+    /// `u128::MAX`, rather than wrapping.
     pub fn get_reward_threshold_and_participation(
         pox_settings: &PoxConstants,
         addresses: &[RawRewardSetEntry],
         liquid_ustx: u128,
-    ) -> (u128, u128) {
-        let participation = addresses
-            .iter()
-            .fold(0, |agg, entry| agg + entry.amount_stacked);
+    ) -> Result<(u128, u128), Error> {
+        let participation = addresses.iter().try_fold(0u128, |agg, entry| {
+            agg.checked_add(entry.amount_stacked).ok_or_else(|| {
+                Error::PoxAmountOverflow(format!(
+                    "total stacked overflowed adding {} to {}",
+                    entry.amount_stacked, agg
+                ))
+            })
+        })?;
 
         assert!(
             participation <= liquid_ustx,
@@ -922,12 +1113,17 @@ impl StacksChainState {
             0 => 0,
             remainder => POX_THRESHOLD_STEPS_USTX - remainder,
         };
-        let threshold = threshold_precise + ceil_amount;
+        let threshold = threshold_precise.checked_add(ceil_amount).ok_or_else(|| {
+            Error::PoxAmountOverflow(format!(
+                "threshold overflowed rounding {} up by {}",
+                threshold_precise, ceil_amount
+            ))
+        })?;
         info!(
             "PoX participation threshold is {}, from {} + {} ({}), participation is {}",
             threshold, threshold_precise, ceil_amount, scale_by, participation
         );
-        (threshold, participation)
+        Ok((threshold, participation))
     }
 
     fn get_reward_addresses_pox_1(
@@ -1262,6 +1458,8 @@ impl StacksChainState {
     /// Get the sequence of reward addresses, as well as the PoX-specified hash mode (which gets
     /// lost in the conversion to StacksAddress)
     /// Each address will have at least (get-stacking-minimum) tokens.
+    /// Fails with `Error::PoxAnchorBlockMismatch` if the sortition DB has recorded an anchor block
+    /// for the reward cycle and `block_id` is neither that block nor one of its descendants.
     pub fn get_reward_addresses(
         &mut self,
         burnchain: &Burnchain,
@@ -1272,9 +1470,80 @@ impl StacksChainState {
         let reward_cycle = burnchain
             .block_height_to_reward_cycle(current_burn_height)
             .ok_or(Error::PoxNoRewardCycle)?;
+        self.check_reward_set_source(sortdb, reward_cycle, block_id)?;
         self.get_reward_addresses_in_cycle(burnchain, sortdb, reward_cycle, block_id)
     }
 
+    /// Check that `block_id` is the anchor block the sortition DB recorded for `reward_cycle`, or
+    /// a descendant of it.  The recorded anchor block is looked up on the burnchain fork that
+    /// holds `block_id`'s sortition.  Nothing is checked if no reward set has been recorded for
+    /// the cycle on that fork yet (e.g. the coordinator is computing it right now), or if no
+    /// anchor block was selected.
+    fn check_reward_set_source(
+        &self,
+        sortdb: &SortitionDB,
+        reward_cycle: u64,
+        block_id: &StacksBlockId,
+    ) -> Result<(), Error> {
+        if reward_cycle == 0 {
+            // no prepare phase, so no anchor block
+            return Ok(());
+        }
+        let Some(header) = StacksChainState::get_stacks_block_header_info_by_index_block_hash(
+            self.db(),
+            block_id,
+        )?
+        else {
+            return Ok(());
+        };
+        let Some(block_sn) =
+            SortitionDB::get_block_snapshot_consensus(sortdb.conn(), &header.consensus_hash)?
+        else {
+            return Ok(());
+        };
+        let reward_cycle_info = match sortdb
+            .get_preprocessed_reward_set_for_reward_cycle(&block_sn.sortition_id, reward_cycle)
+        {
+            Ok((reward_cycle_info, _)) => reward_cycle_info,
+            Err(DBError::NotFoundError) => return Ok(()),
+            Err(e) => return Err(e.into()),
+        };
+        let Some((recorded_anchor, _)) = reward_cycle_info.selected_anchor_block() else {
+            return Ok(());
+        };
+        if *recorded_anchor == header.anchored_header.block_hash() {
+            return Ok(());
+        }
+
+        let anchor_sn = SortitionDB::get_block_snapshot_for_winning_stacks_block(
+            &sortdb.index_conn(),
+            &block_sn.sortition_id,
+            recorded_anchor,
+        )?;
+        let is_descendant = match anchor_sn {
+            Some(anchor_sn) => {
+                let anchor_block_id =
+                    StacksBlockId::new(&anchor_sn.consensus_hash, recorded_anchor);
+                self.index_conn()
+                    .get_ancestor_block_height(&anchor_block_id, block_id)?
+                    .is_some()
+            }
+            None => false,
+        };
+        if !is_descendant {
+            warn!("Reward set source block does not descend from the cycle's anchor block";
+                  "reward_cycle" => reward_cycle,
+                  "recorded_anchor" => %recorded_anchor,
+                  "source_block_id" => %block_id);
+            return Err(Error::PoxAnchorBlockMismatch(
+                reward_cycle,
+                Some(recorded_anchor.clone()),
+                block_id.clone(),
+            ));
+        }
+        Ok(())
+    }
+
     /// Get the sequence of reward addresses, as well as the PoX-specified hash mode (which gets
     /// lost in the conversion to StacksAddress)
     /// Each address will have at least (get-stacking-minimum) tokens.
@@ -1319,6 +1588,44 @@ impl StacksChainState {
         }
     }
 
+    /// Like `get_reward_addresses_in_cycle()`, but reads the reward set from `anchor_block_id`
+    /// only after checking that it is the anchor block the sortition DB selected for
+    /// `reward_cycle`, as of `sortition_tip`.  If the sortition DB and the chainstate disagree
+    /// about the anchor block, this returns `Error::PoxAnchorBlockMismatch` instead of a reward
+    /// set read from the wrong block.
+    pub fn get_reward_addresses_at_anchor(
+        &mut self,
+        burnchain: &Burnchain,
+        sortdb: &SortitionDB,
+        sortition_tip: &SortitionId,
+        reward_cycle: u64,
+        anchor_block_id: &StacksBlockId,
+    ) -> Result<Vec<RawRewardSetEntry>, Error> {
+        let (reward_cycle_info, _) =
+            sortdb.get_preprocessed_reward_set_for_reward_cycle(sortition_tip, reward_cycle)?;
+        let recorded_anchor = reward_cycle_info
+            .selected_anchor_block()
+            .map(|(block_hash, _)| block_hash.clone());
+        let source_anchor = StacksChainState::get_stacks_block_header_info_by_index_block_hash(
+            self.db(),
+            anchor_block_id,
+        )?
+        .map(|header| header.anchored_header.block_hash());
+
+        if recorded_anchor.is_none() || recorded_anchor != source_anchor {
+            warn!("Reward set source block is not the cycle's anchor block";
+                  "reward_cycle" => reward_cycle,
+                  "recorded_anchor" => ?recorded_anchor,
+                  "source_block_id" => %anchor_block_id);
+            return Err(Error::PoxAnchorBlockMismatch(
+                reward_cycle,
+                recorded_anchor,
+                anchor_block_id.clone(),
+            ));
+        }
+        self.get_reward_addresses_in_cycle(burnchain, sortdb, reward_cycle, anchor_block_id)
+    }
+
     /// Get the aggregate public key for a given reward cycle from pox 4
     pub fn get_aggregate_public_key_pox_4(
         &mut self,
@@ -1385,6 +1692,37 @@ pub mod test {
 
     pub const TESTNET_STACKING_THRESHOLD_25: u128 = 8000;
 
+    /// Seed for the signer key a stacker registers with `stack-stx`
+    pub const SIGNER_SEED_STACK: &[u8] = &[0];
+    /// Seed for the signer key a stacker switches to with `stack-extend`
+    pub const SIGNER_SEED_EXTEND: &[u8] = &[1];
+    /// Seed for the signer key a pool operator uses with `delegate-stack-stx`
+    pub const SIGNER_SEED_DELEGATE: &[u8] = &[1, 1, 1];
+    /// Seed for the signer key shared by the multi-step pox-4 scenario tests
+    pub const SIGNER_SEED_SCENARIO: &[u8] = &[1, 3, 3, 7];
+
+    /// Registry of the named signer seeds. Tests that need the same logical signer should
+    /// reuse one of these seeds instead of inventing a new `from_seed` literal.
+    pub const TEST_SIGNER_SEEDS: &[(&str, &[u8])] = &[
+        ("stack", SIGNER_SEED_STACK),
+        ("extend", SIGNER_SEED_EXTEND),
+        ("delegate", SIGNER_SEED_DELEGATE),
+        ("scenario", SIGNER_SEED_SCENARIO),
+    ];
+
+    /// Deterministic signer private key for a registered seed
+    pub fn test_signer_key(seed: &[u8]) -> StacksPrivateKey {
+        StacksPrivateKey::from_seed(seed)
+    }
+
+    /// Deterministic signer private key for a seed in `TEST_SIGNER_SEEDS`, looked up by name
+    pub fn test_signer_key_by_name(name: &str) -> Option<StacksPrivateKey> {
+        TEST_SIGNER_SEEDS
+            .iter()
+            .find(|(seed_name, _)| *seed_name == name)
+            .map(|(_, seed)| test_signer_key(seed))
+    }
+
     /// Extract a PoX address from its tuple representation.
     /// Doesn't work on segwit addresses
     fn tuple_to_pox_addr(tuple_data: TupleData) -> PoxAddress {
@@ -1445,6 +1783,320 @@ pub mod test {
         PoxAddress::Standard(rand_addr(), Some(AddressHashMode::SerializeP2PKH))
     }
 
+    #[test]
+    fn reward_set_eq_unordered() {
+        let threshold = 1_000;
+        let alice = rand_pox_addr();
+        let bob = rand_pox_addr();
+        let alice_signer = [0x01; 33];
+        let bob_signer = [0x02; 33];
+
+        let computed = StacksChainState::make_reward_set(
+            threshold,
+            vec![
+                RawRewardSetEntry {
+                    reward_address: alice.clone(),
+                    amount_stacked: 2_000,
+                    stacker: None,
+                    signer: Some(alice_signer),
+                },
+                RawRewardSetEntry {
+                    reward_address: bob.clone(),
+                    amount_stacked: 1_000,
+                    stacker: None,
+                    signer: Some(bob_signer),
+                },
+            ],
+            StacksEpochId::Epoch30,
+        );
+
+        // the same reward set, listed in a different order
+        let bob_entry = NakamotoSignerEntry {
+            signing_key: bob_signer,
+            stacked_amt: 1_000,
+            weight: 1,
+        };
+        let alice_entry = NakamotoSignerEntry {
+            signing_key: alice_signer,
+            stacked_amt: 2_000,
+            weight: 2,
+        };
+        let mut expected = RewardSet::empty();
+        expected.rewarded_addresses = vec![alice.clone(), bob.clone(), alice.clone()];
+        expected.signers = Some(vec![bob_entry.clone(), alice_entry.clone()]);
+        expected.pox_ustx_threshold = Some(threshold);
+
+        assert_ne!(computed, expected);
+        assert!(computed.eq_unordered(&expected));
+        assert!(expected.eq_unordered(&computed));
+
+        // different multiplicities are not equal
+        let mut different_slots = expected.clone();
+        different_slots.rewarded_addresses = vec![alice.clone(), bob.clone(), bob.clone()];
+        assert!(!computed.eq_unordered(&different_slots));
+
+        // different signer entries are not equal
+        let mut different_signers = expected.clone();
+        different_signers.signers = Some(vec![bob_entry, alice_entry.clone(), alice_entry]);
+        assert!(!computed.eq_unordered(&different_signers));
+
+        let mut no_signers = expected;
+        no_signers.signers = None;
+        assert!(!computed.eq_unordered(&no_signers));
+    }
+
+    #[test]
+    fn reward_set_expected_sats_for() {
+        // 4 reward blocks of 2 outputs each, for 8 reward slots
+        let pox_constants = PoxConstants::new(
+            5,
+            1,
+            1,
+            1,
+            5,
+            5000,
+            10000,
+            u32::MAX,
+            u32::MAX,
+            u32::MAX,
+            u32::MAX,
+        );
+        assert_eq!(pox_constants.reward_slots(), 8);
+
+        let alice = rand_pox_addr();
+        let bob = rand_pox_addr();
+        let carol = rand_pox_addr();
+        let mut reward_set = RewardSet::empty();
+        reward_set.rewarded_addresses = vec![
+            alice.clone(),
+            alice.clone(),
+            alice.clone(),
+            bob.clone(),
+            bob.clone(),
+            alice.clone(),
+            bob.clone(),
+            alice.clone(),
+        ];
+
+        // a full reward set pays each entry once, at half of a reward block's 2_000 sats
+        assert_eq!(
+            reward_set.expected_sats_for(&alice, &pox_constants, 2_000),
+            5_000
+        );
+        assert_eq!(
+            reward_set.expected_sats_for(&bob, &pox_constants, 2_000),
+            3_000
+        );
+        assert_eq!(
+            reward_set.expected_sats_for(&carol, &pox_constants, 2_000),
+            0
+        );
+
+        // with more entries than reward slots, each entry is paid 8/9 of the time
+        reward_set.rewarded_addresses.push(carol.clone());
+        assert_eq!(
+            reward_set.expected_sats_for(&alice, &pox_constants, 2_000),
+            4_444
+        );
+        assert_eq!(
+            reward_set.expected_sats_for(&carol, &pox_constants, 2_000),
+            888
+        );
+
+        // with fewer entries than reward slots, each entry is paid twice
+        reward_set.rewarded_addresses = vec![alice.clone(), alice.clone(), alice, bob.clone()];
+        assert_eq!(
+            reward_set.expected_sats_for(&bob, &pox_constants, 2_000),
+            2_000
+        );
+
+        assert_eq!(
+            RewardSet::empty().expected_sats_for(&bob, &pox_constants, 2_000),
+            0
+        );
+    }
+
+    #[test]
+    fn reward_set_to_burnchain_payout_map() {
+        let alice = rand_pox_addr();
+        let bob = rand_pox_addr();
+        let burn = PoxAddress::standard_burn_address(false);
+        let mut reward_set = RewardSet::empty();
+        reward_set.pox_ustx_threshold = Some(1_000);
+        reward_set.rewarded_addresses = vec![
+            alice.clone(),
+            bob.clone(),
+            alice.clone(),
+            burn.clone(),
+            alice.clone(),
+        ];
+
+        let payouts = reward_set.to_burnchain_payout_map();
+        assert_eq!(payouts.len(), 3);
+        assert_eq!(payouts[&alice.to_burnchain_repr()], 3_000);
+        assert_eq!(payouts[&bob.to_burnchain_repr()], 1_000);
+        assert_eq!(payouts["burn"], 1_000);
+
+        reward_set.pox_ustx_threshold = None;
+        assert!(reward_set
+            .to_burnchain_payout_map()
+            .values()
+            .all(|amount| *amount == 0));
+    }
+
+    #[test]
+    fn reward_set_find_duplicate_signer_keys() {
+        let entry = |signer: Option<[u8; SIGNERS_PK_LEN]>| RawRewardSetEntry {
+            reward_address: rand_pox_addr(),
+            amount_stacked: 1_000,
+            stacker: None,
+            signer,
+        };
+        let entries = vec![
+            entry(Some([1; SIGNERS_PK_LEN])),
+            entry(Some([2; SIGNERS_PK_LEN])),
+            entry(None),
+            entry(Some([1; SIGNERS_PK_LEN])),
+            entry(None),
+            entry(Some([3; SIGNERS_PK_LEN])),
+        ];
+
+        assert_eq!(
+            RewardSet::find_duplicate_signer_keys(&entries),
+            vec![([1; SIGNERS_PK_LEN].to_vec(), vec![0, 3])]
+        );
+        assert!(RewardSet::find_duplicate_signer_keys(&entries[1..]).is_empty());
+    }
+
+    #[test]
+    fn reward_set_signer_index() {
+        let threshold = 1_000;
+        let signer_keys: Vec<_> = TEST_SIGNER_SEEDS
+            .iter()
+            .map(|(_, seed)| Secp256k1PublicKey::from_private(&test_signer_key(seed)))
+            .collect();
+        let entries: Vec<_> = signer_keys
+            .iter()
+            .map(|signer_key| RawRewardSetEntry {
+                reward_address: rand_pox_addr(),
+                amount_stacked: 2_000,
+                stacker: None,
+                signer: Some(signer_key.to_bytes_compressed().try_into().unwrap()),
+            })
+            .collect();
+
+        let reward_set =
+            StacksChainState::make_reward_set(threshold, entries.clone(), StacksEpochId::Epoch30);
+
+        // indices follow the order of the compressed signing keys
+        let mut sorted_keys: Vec<_> = signer_keys
+            .iter()
+            .map(|signer_key| signer_key.to_bytes_compressed())
+            .collect();
+        sorted_keys.sort();
+        for signer_key in signer_keys.iter() {
+            let expected = sorted_keys
+                .iter()
+                .position(|key| *key == signer_key.to_bytes_compressed());
+            assert!(expected.is_some());
+            assert_eq!(reward_set.signer_index(signer_key), expected);
+        }
+
+        // the order in which the stacking entries were made doesn't matter
+        let mut reversed_entries = entries.clone();
+        reversed_entries.reverse();
+        let mut rotated_entries = entries;
+        rotated_entries.rotate_left(1);
+        for reordered in [reversed_entries, rotated_entries] {
+            let reordered_set =
+                StacksChainState::make_reward_set(threshold, reordered, StacksEpochId::Epoch30);
+            for signer_key in signer_keys.iter() {
+                assert_eq!(
+                    reordered_set.signer_index(signer_key),
+                    reward_set.signer_index(signer_key)
+                );
+            }
+        }
+
+        // keys that aren't signers, and reward sets without signers, have no index
+        let not_a_signer = Secp256k1PublicKey::from_private(&StacksPrivateKey::random());
+        assert_eq!(reward_set.signer_index(&not_a_signer), None);
+        assert_eq!(RewardSet::empty().signer_index(&signer_keys[0]), None);
+    }
+
+    #[test]
+    fn estimate_reward_slots_matches_reward_set() {
+        // 5-block cycles with 2-block prepare phases: 6 reward slots
+        let pox_constants = PoxConstants::new(
+            5,
+            2,
+            1,
+            1,
+            5,
+            5000,
+            10000,
+            u32::MAX,
+            u32::MAX,
+            u32::MAX,
+            u32::MAX,
+        );
+        assert_eq!(pox_constants.reward_slots(), 6);
+
+        // 4 stackers with the same amount stacked each fill one of the 6 slots
+        let amount = 1024 * POX_THRESHOLD_STEPS_USTX;
+        let entries: Vec<_> = (0..4)
+            .map(|_| RawRewardSetEntry {
+                reward_address: rand_pox_addr(),
+                amount_stacked: amount,
+                stacker: None,
+                signer: None,
+            })
+            .collect();
+        let liquid = 16 * amount;
+        let (threshold, _) = StacksChainState::get_reward_threshold_and_participation(
+            &pox_constants,
+            &entries,
+            liquid,
+        )
+        .unwrap();
+        assert_eq!(pox_constants.estimate_reward_slots(amount, threshold), 1);
+
+        let reward_set =
+            StacksChainState::make_reward_set(threshold, entries.clone(), StacksEpochId::Epoch25);
+        assert_eq!(reward_set.rewarded_addresses.len(), 4);
+        for entry in entries.iter() {
+            let slots = reward_set
+                .rewarded_addresses
+                .iter()
+                .filter(|addr| **addr == entry.reward_address)
+                .count();
+            assert_eq!(
+                slots,
+                usize::try_from(pox_constants.estimate_reward_slots(amount, threshold)).unwrap()
+            );
+        }
+
+        // just below the threshold earns nothing, and is left out of the reward set
+        assert_eq!(
+            pox_constants.estimate_reward_slots(threshold - 1, threshold),
+            0
+        );
+        let below = RawRewardSetEntry {
+            reward_address: rand_pox_addr(),
+            amount_stacked: threshold - 1,
+            stacker: None,
+            signer: None,
+        };
+        let reward_set = StacksChainState::make_reward_set(
+            threshold,
+            vec![below.clone()],
+            StacksEpochId::Epoch25,
+        );
+        assert!(!reward_set
+            .rewarded_addresses
+            .contains(&below.reward_address));
+    }
+
     #[test]
     fn get_reward_threshold_units() {
         let test_pox_constants = PoxConstants::new(
@@ -1469,6 +2121,7 @@ pub mod test {
                 &[],
                 liquid,
             )
+            .unwrap()
             .0,
             POX_THRESHOLD_STEPS_USTX
         );
@@ -1483,6 +2136,7 @@ pub mod test {
                 }],
                 liquid,
             )
+            .unwrap()
             .0,
             POX_THRESHOLD_STEPS_USTX
         );
@@ -1495,6 +2149,7 @@ pub mod test {
                 &[],
                 liquid,
             )
+            .unwrap()
             .0,
             50_000 * MICROSTACKS_PER_STACKS as u128
         );
@@ -1510,6 +2165,7 @@ pub mod test {
                 }],
                 liquid,
             )
+            .unwrap()
             .0,
             50_000 * MICROSTACKS_PER_STACKS as u128
         );
@@ -1533,6 +2189,7 @@ pub mod test {
                 ],
                 liquid,
             )
+            .unwrap()
             .0,
             60_000 * MICROSTACKS_PER_STACKS as u128
         );
@@ -1557,6 +2214,7 @@ pub mod test {
                 ],
                 liquid,
             )
+            .unwrap()
             .0,
             60_000 * MICROSTACKS_PER_STACKS as u128
         );
@@ -1573,11 +2231,95 @@ pub mod test {
                 }],
                 liquid,
             )
+            .unwrap()
             .0,
             200_000 * MICROSTACKS_PER_STACKS as u128
         );
     }
 
+    #[test]
+    fn get_reward_threshold_overflow() {
+        let test_pox_constants = PoxConstants::new(
+            501,
+            1,
+            1,
+            1,
+            5,
+            5000,
+            10000,
+            u32::MAX,
+            u32::MAX,
+            u32::MAX,
+            u32::MAX,
+        );
+        let entry = |amount_stacked| RawRewardSetEntry {
+            reward_address: rand_pox_addr(),
+            amount_stacked,
+            stacker: None,
+            signer: None,
+        };
+
+        // a total just under u128::MAX is fine
+        let (_threshold, participation) = StacksChainState::get_reward_threshold_and_participation(
+            &test_pox_constants,
+            &[entry(u128::MAX / 2), entry(u128::MAX / 2)],
+            u128::MAX,
+        )
+        .unwrap();
+        assert_eq!(participation, u128::MAX - 1);
+
+        // but one more uSTX is an error, not a wrapped total
+        let err = StacksChainState::get_reward_threshold_and_participation(
+            &test_pox_constants,
+            &[
+                entry(u128::MAX / 2),
+                entry(u128::MAX / 2),
+                entry(1),
+                entry(1),
+            ],
+            u128::MAX,
+        )
+        .unwrap_err();
+        assert!(
+            matches!(err, Error::PoxAmountOverflow(_)),
+            "Expected a PoX amount overflow, got {err:?}"
+        );
+    }
+
+    #[test]
+    fn get_threshold_from_participation_overflow() {
+        // a threshold that is already a multiple of the threshold step needs no rounding
+        let top_step = u128::MAX - u128::MAX % POX_THRESHOLD_STEPS_USTX;
+        assert_eq!(
+            StacksChainState::get_threshold_from_participation(top_step, top_step, 1).unwrap(),
+            top_step
+        );
+
+        // but rounding up past u128::MAX is an error, not a saturated threshold
+        let err = StacksChainState::get_threshold_from_participation(u128::MAX, u128::MAX, 1)
+            .unwrap_err();
+        assert!(
+            matches!(err, Error::PoxAmountOverflow(_)),
+            "Expected a PoX amount overflow, got {err:?}"
+        );
+    }
+
+    #[test]
+    fn test_signer_seeds_are_distinct() {
+        let mut names = HashSet::new();
+        let mut keys = HashSet::new();
+        for (name, seed) in TEST_SIGNER_SEEDS.iter() {
+            assert!(names.insert(*name), "Duplicate signer seed name {name}");
+            let signer_pk = StacksPublicKey::from_private(&test_signer_key(seed));
+            assert!(
+                keys.insert(signer_pk.to_bytes_compressed()),
+                "Signer seed {name} collides with another registered seed"
+            );
+            assert_eq!(test_signer_key_by_name(name), Some(test_signer_key(seed)));
+        }
+        assert!(test_signer_key_by_name("unknown").is_none());
+    }
+
     fn rand_addr() -> StacksAddress {
         key_to_stacks_addr(&StacksPrivateKey::random())
     }
@@ -1947,6 +2689,93 @@ pub mod test {
         make_tx(key, nonce, 0, payload)
     }
 
+    /// Make a `stack-stx` transaction from `key` for each of `lockups`, which are
+    /// `(amount, pox_addr, lock_period, signer_key, signature_opt, max_amount, auth_id)` tuples,
+    /// with consecutive nonces starting at `start_nonce`.
+    pub fn make_pox_4_lockups(
+        key: &StacksPrivateKey,
+        start_nonce: u64,
+        burn_ht: u64,
+        lockups: &[(
+            u128,
+            PoxAddress,
+            u128,
+            StacksPublicKey,
+            Option<Vec<u8>>,
+            u128,
+            u128,
+        )],
+    ) -> Vec<StacksTransaction> {
+        lockups
+            .iter()
+            .zip(start_nonce..)
+            .map(
+                |(
+                    (amount, addr, lock_period, signer_key, signature_opt, max_amount, auth_id),
+                    nonce,
+                )| {
+                    make_pox_4_lockup(
+                        key,
+                        nonce,
+                        *amount,
+                        addr,
+                        *lock_period,
+                        signer_key,
+                        burn_ht,
+                        signature_opt.clone(),
+                        *max_amount,
+                        *auth_id,
+                    )
+                },
+            )
+            .collect()
+    }
+
+    /// Make a `stack-stx` transaction from `key` at its next nonce, starting at `peer`'s
+    /// canonical burnchain tip, with a `StackStx` signature from `signer_key` for the current
+    /// reward cycle. `signer_key` need not be `key`.
+    pub fn make_pox_4_stack_stx_signed(
+        key: &StacksPrivateKey,
+        peer: &mut TestPeer,
+        pox_addr: &PoxAddress,
+        amount: u128,
+        lock_period: u128,
+        signer_key: &StacksPrivateKey,
+        max_amount: u128,
+        auth_id: u128,
+    ) -> StacksTransaction {
+        let tip = SortitionDB::get_canonical_burn_chain_tip(peer.sortdb().conn()).unwrap();
+        let reward_cycle = peer
+            .config
+            .burnchain
+            .block_height_to_reward_cycle(tip.block_height)
+            .unwrap();
+        let nonce = get_account(peer, &key_to_stacks_addr(key).into()).nonce;
+
+        let signature = make_signer_key_signature(
+            pox_addr,
+            signer_key,
+            reward_cycle.into(),
+            &Pox4SignatureTopic::StackStx,
+            lock_period,
+            max_amount,
+            auth_id,
+        );
+
+        make_pox_4_lockup(
+            key,
+            nonce,
+            amount,
+            pox_addr,
+            lock_period,
+            &StacksPublicKey::from_private(signer_key),
+            tip.block_height,
+            Some(signature),
+            max_amount,
+            auth_id,
+        )
+    }
+
     pub fn make_pox_4_lockup_chain_id(
         key: &StacksPrivateKey,
         nonce: u64,
@@ -2850,6 +3679,43 @@ pub mod test {
         }
     }
 
+    #[test]
+    fn test_short_miner_reward_maturity() {
+        let miner_reward_maturity = 2;
+        let mut peer_config = TestPeerConfig::new(function_name!(), 0, 0);
+        peer_config.miner_reward_maturity = miner_reward_maturity;
+        let mut peer = TestPeer::new(peer_config);
+
+        let initial_liquid_ustx = get_liquid_ustx(&mut peer);
+        let mut coinbase_nonce = 0;
+
+        // the first coinbase matures once the chain is `miner_reward_maturity` blocks past the
+        // block that confirms it, i.e. when block `miner_reward_maturity + 2` is processed
+        for stacks_height in 1..=(miner_reward_maturity + 2) {
+            let tip_id = peer.tenure_with_txs(&[], &mut coinbase_nonce);
+            let tip = StacksChainState::get_stacks_block_header_info_by_index_block_hash(
+                peer.chainstate().db(),
+                &tip_id,
+            )
+            .unwrap()
+            .unwrap();
+            assert_eq!(tip.stacks_block_height, stacks_height);
+
+            let liquid_ustx = get_liquid_ustx(&mut peer);
+            if stacks_height <= miner_reward_maturity + 1 {
+                assert_eq!(
+                    liquid_ustx, initial_liquid_ustx,
+                    "no coinbase should have matured at height {stacks_height}"
+                );
+            } else {
+                assert!(
+                    liquid_ustx > initial_liquid_ustx,
+                    "the first coinbase should have matured at height {stacks_height}"
+                );
+            }
+        }
+    }
+
     #[test]
     fn test_lockups() {
         let burnchain = Burnchain::default_unittest(
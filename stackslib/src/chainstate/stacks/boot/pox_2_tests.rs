@@ -15,11 +15,12 @@
 // along with this program.  If not, see <http://www.gnu.org/licenses/>.
 
 use std::collections::{HashMap, HashSet};
+use std::panic::{self, AssertUnwindSafe};
 
 use clarity::vm::clarity::ClarityConnection;
 use clarity::vm::costs::LimitedCostTracker;
 use clarity::vm::database::*;
-use clarity::vm::events::StacksTransactionEvent;
+use clarity::vm::events::{SmartContractEventData, StacksTransactionEvent};
 use clarity::vm::types::{
     BuffData, PrincipalData, SequenceData, StacksAddressExtensions, StandardPrincipalData,
     TupleData, Value,
@@ -30,16 +31,18 @@ use stacks_common::types::Address;
 use stacks_common::util::hash::hex_bytes;
 
 use super::test::*;
-use super::RawRewardSetEntry;
-use crate::burnchains::Burnchain;
+use super::{RawRewardSetEntry, RewardSet};
+use crate::burnchains::{Burnchain, Txid};
 use crate::chainstate::burn::db::sortdb::SortitionDB;
 use crate::chainstate::burn::operations::*;
 use crate::chainstate::burn::BlockSnapshot;
+use crate::chainstate::coordinator::{PoxAnchorBlockStatus, RewardCycleInfo};
 use crate::chainstate::stacks::address::{PoxAddress, PoxAddressType20, PoxAddressType32};
 use crate::chainstate::stacks::boot::{POX_2_NAME, POX_3_NAME};
 use crate::chainstate::stacks::db::{StacksChainState, StacksDBConn};
 use crate::chainstate::stacks::events::TransactionOrigin;
 use crate::chainstate::stacks::tests::make_coinbase;
+use crate::chainstate::stacks::Error as ChainstateError;
 use crate::chainstate::stacks::*;
 use crate::clarity_vm::database::HeadersDBConn;
 use crate::core::*;
@@ -54,7 +57,29 @@ fn get_tip(sortdb: Option<&SortitionDB>) -> BlockSnapshot {
     SortitionDB::get_canonical_burn_chain_tip(sortdb.unwrap().conn()).unwrap()
 }
 
-/// Get the reward set entries if evaluated at the given StacksBlock
+/// Get the StacksBlockId of the anchor block that the sortition DB selected for `reward_cycle`,
+///  as of the canonical sortition tip, if one was selected
+fn get_recorded_anchor_block_id(sortdb: &SortitionDB, reward_cycle: u64) -> Option<StacksBlockId> {
+    let sortition_tip = get_tip(Some(sortdb)).sortition_id;
+    let (reward_cycle_info, _) = sortdb
+        .get_preprocessed_reward_set_for_reward_cycle(&sortition_tip, reward_cycle)
+        .ok()?;
+    let (anchor_block_hash, _) = reward_cycle_info.selected_anchor_block()?;
+    let anchor_snapshot = SortitionDB::get_block_snapshot_for_winning_stacks_block(
+        &sortdb.index_conn(),
+        &sortition_tip,
+        anchor_block_hash,
+    )
+    .unwrap()?;
+    Some(StacksBlockId::new(
+        &anchor_snapshot.consensus_hash,
+        anchor_block_hash,
+    ))
+}
+
+/// Get the reward set entries if evaluated at the given StacksBlock.  Panics if the sortition DB
+///  recorded an anchor block for the reward cycle containing `at_burn_ht` and `tip` does not
+///  descend from it.
 pub fn get_reward_set_entries_at(
     peer: &mut TestPeer,
     tip: &StacksBlockId,
@@ -66,6 +91,34 @@ pub fn get_reward_set_entries_at(
     })
 }
 
+/// Like `get_reward_set_entries_at()`, but fails with `ChainstateError::PoxAnchorBlockMismatch` unless
+///  `anchor_block_id` is the anchor block the sortition DB selected for the reward cycle
+///  containing `at_burn_ht`
+pub fn try_get_reward_set_entries_at_anchor(
+    peer: &mut TestPeer,
+    anchor_block_id: &StacksBlockId,
+    at_burn_ht: u64,
+) -> Result<Vec<RawRewardSetEntry>, ChainstateError> {
+    let burnchain = peer.config.burnchain.clone();
+    let reward_cycle = burnchain
+        .block_height_to_reward_cycle(at_burn_ht)
+        .ok_or(ChainstateError::PoxNoRewardCycle)?;
+    with_sortdb(peer, |ref mut c, sortdb| {
+        let sortition_tip = get_tip(Some(sortdb)).sortition_id;
+        c.get_reward_addresses_at_anchor(
+            &burnchain,
+            sortdb,
+            &sortition_tip,
+            reward_cycle,
+            anchor_block_id,
+        )
+        .map(|mut addrs| {
+            addrs.sort_by_key(|k| k.reward_address.bytes());
+            addrs
+        })
+    })
+}
+
 /// Get the reward set entries if evaluated at the given StacksBlock
 ///  in order of index in the reward-cycle-address-list map
 pub fn get_reward_set_entries_index_order_at(
@@ -94,6 +147,26 @@ pub fn get_stx_account_at(
     })
 }
 
+/// Like `get_stx_account_at()`, but get the balances of all of `accounts` in one read-only
+/// Clarity context. The balances are in the same order as `accounts`.
+pub fn get_stx_accounts_at(
+    peer: &mut TestPeer,
+    tip: &StacksBlockId,
+    accounts: &[PrincipalData],
+) -> Vec<STXBalance> {
+    with_clarity_db_ro(peer, tip, |db| {
+        accounts
+            .iter()
+            .map(|account| {
+                db.get_stx_balance_snapshot(account)
+                    .unwrap()
+                    .canonical_balance_repr()
+                    .unwrap()
+            })
+            .collect()
+    })
+}
+
 /// get the stacking-state entry for an account at the chaintip
 pub fn get_stacking_state_pox(
     peer: &mut TestPeer,
@@ -166,6 +239,48 @@ pub fn check_all_stacker_link_invariants(
     }
 }
 
+/// Like `check_all_stacker_link_invariants()`, but instead of panicking with whatever assertion
+/// failed, returns a message saying which reward cycle's invariants failed, and at what burn
+/// height and chain tip they were checked.  Also rejects an empty cycle range, which would
+/// otherwise silently check nothing.
+pub fn try_check_all_stacker_link_invariants(
+    peer: &mut TestPeer,
+    tip: &StacksBlockId,
+    first_cycle_number: u64,
+    max_cycle_number: u64,
+) -> Result<(), String> {
+    if first_cycle_number > max_cycle_number {
+        return Err(format!(
+            "Invalid PoX invariant range: first cycle {first_cycle_number} is after last cycle {max_cycle_number}"
+        ));
+    }
+
+    let burn_height = StacksChainState::get_stacks_block_header_info_by_index_block_hash(
+        peer.chainstate().db(),
+        tip,
+    )
+    .unwrap()
+    .unwrap()
+    .burn_header_height;
+
+    for cycle in first_cycle_number..(max_cycle_number + 1) {
+        let result = panic::catch_unwind(AssertUnwindSafe(|| {
+            check_all_stacker_link_invariants(peer, tip, cycle, cycle)
+        }));
+        if let Err(payload) = result {
+            let reason = payload
+                .downcast_ref::<String>()
+                .cloned()
+                .or_else(|| payload.downcast_ref::<&str>().map(|s| s.to_string()))
+                .unwrap_or_else(|| "(no panic message)".to_string());
+            return Err(format!(
+                "PoX invariant violated in reward cycle {cycle} at burn height {burn_height} (tip {tip}): {reason}"
+            ));
+        }
+    }
+    Ok(())
+}
+
 pub fn generate_pox_clarity_value(str_hash: &str) -> Value {
     let byte_vec = hex_bytes(str_hash).unwrap();
     let pox_addr_tuple = TupleData::from_data(vec![
@@ -276,6 +391,103 @@ pub fn check_pox_print_event(
     }
 }
 
+/// Fluent alternative to `check_pox_print_event`: accumulates the expected common and
+/// op-specific fields of a pox print event, then checks them against an event with `assert`,
+/// e.g. `PoxEventAssertion::new("stack-increase").stacker(..).field("auth-id", ..).assert(event)`.
+/// Only the fields given are checked.
+pub struct PoxEventAssertion {
+    op_name: String,
+    common_fields: Vec<(&'static str, Value)>,
+    op_fields: Vec<(&'static str, Value)>,
+}
+
+impl PoxEventAssertion {
+    pub fn new(op_name: &str) -> Self {
+        Self {
+            op_name: op_name.to_string(),
+            common_fields: vec![],
+            op_fields: vec![],
+        }
+    }
+
+    pub fn stacker(self, stacker: Value) -> Self {
+        self.common_field("stacker", stacker)
+    }
+
+    pub fn balance(self, balance: Value) -> Self {
+        self.common_field("balance", balance)
+    }
+
+    pub fn locked(self, locked: Value) -> Self {
+        self.common_field("locked", locked)
+    }
+
+    pub fn burnchain_unlock_height(self, burnchain_unlock_height: Value) -> Self {
+        self.common_field("burnchain-unlock-height", burnchain_unlock_height)
+    }
+
+    /// Expect the op-specific field `key`, in the event's `data` tuple, to be `value`
+    pub fn field(mut self, key: &'static str, value: Value) -> Self {
+        self.op_fields.push((key, value));
+        self
+    }
+
+    fn common_field(mut self, key: &'static str, value: Value) -> Self {
+        self.common_fields.push((key, value));
+        self
+    }
+
+    /// Check `event` against the expected fields, panicking with the first mismatched key
+    pub fn assert(&self, event: &StacksTransactionEvent) {
+        let StacksTransactionEvent::SmartContractEvent(data) = event else {
+            panic!("Unexpected event type: {event:?}");
+        };
+        assert_eq!(data.key.1, "print", "Not a print event: {event:?}");
+        let outer_tuple = data
+            .value
+            .clone()
+            .expect_result_ok()
+            .unwrap()
+            .expect_tuple()
+            .unwrap();
+        let name = outer_tuple
+            .data_map
+            .get("name")
+            .expect("The event tuple should have a field named `name`")
+            .clone()
+            .expect_ascii()
+            .unwrap();
+        assert_eq!(name, self.op_name, "Unexpected pox print event {name}");
+        Self::assert_fields(&self.op_name, "", &outer_tuple, &self.common_fields);
+
+        let inner_tuple = outer_tuple
+            .data_map
+            .get("data")
+            .expect("The event tuple should have a field named `data`")
+            .clone()
+            .expect_tuple()
+            .unwrap();
+        Self::assert_fields(&self.op_name, "data.", &inner_tuple, &self.op_fields);
+    }
+
+    fn assert_fields(
+        op_name: &str,
+        prefix: &str,
+        tuple: &TupleData,
+        expected: &[(&'static str, Value)],
+    ) {
+        for (key, expected_value) in expected {
+            match tuple.data_map.get(*key) {
+                Some(value) => assert_eq!(
+                    value, expected_value,
+                    "{op_name} event: mismatched {prefix}{key}"
+                ),
+                None => panic!("{op_name} event: missing {prefix}{key} in {tuple:?}"),
+            }
+        }
+    }
+}
+
 pub struct StackingStateCheckData {
     pub pox_addr: PoxAddress,
     /// this is a map from reward cycle number to the value in reward-set-indexes
@@ -1091,6 +1303,48 @@ fn test_simple_pox_lockup_transition_pox_2() {
     );
 }
 
+fn make_pox_print_event(op_name: &str, auth_id: u128) -> StacksTransactionEvent {
+    let data = TupleData::from_data(vec![("auth-id".into(), Value::UInt(auth_id))]).unwrap();
+    let outer = TupleData::from_data(vec![
+        (
+            "name".into(),
+            Value::string_ascii_from_bytes(op_name.into()).unwrap(),
+        ),
+        ("balance".into(), Value::UInt(100)),
+        ("data".into(), Value::Tuple(data)),
+    ])
+    .unwrap();
+    StacksTransactionEvent::SmartContractEvent(SmartContractEventData {
+        key: (boot_code_id("pox-4", false), "print".into()),
+        value: Value::okay(Value::Tuple(outer)).unwrap(),
+    })
+}
+
+#[test]
+fn pox_event_assertion_matches() {
+    PoxEventAssertion::new("stack-increase")
+        .balance(Value::UInt(100))
+        .field("auth-id", Value::UInt(1))
+        .assert(&make_pox_print_event("stack-increase", 1));
+}
+
+#[test]
+#[should_panic(expected = "stack-increase event: mismatched data.auth-id")]
+fn pox_event_assertion_names_mismatched_key() {
+    PoxEventAssertion::new("stack-increase")
+        .balance(Value::UInt(100))
+        .field("auth-id", Value::UInt(2))
+        .assert(&make_pox_print_event("stack-increase", 1));
+}
+
+#[test]
+#[should_panic(expected = "stack-increase event: missing stacker")]
+fn pox_event_assertion_names_missing_key() {
+    PoxEventAssertion::new("stack-increase")
+        .stacker(Value::UInt(0))
+        .assert(&make_pox_print_event("stack-increase", 1));
+}
+
 #[test]
 fn test_simple_pox_2_auto_unlock_ab() {
     test_simple_pox_2_auto_unlock(true)
@@ -5068,3 +5322,235 @@ fn stack_in_both_pox1_and_pox2() {
         .expect_result_err()
         .unwrap();
 }
+
+#[test]
+#[should_panic(expected = "Invalid PoX invariant range: first cycle 5 is after last cycle 2")]
+fn advance_checked_reports_broken_invariant_range() {
+    let mut burnchain = Burnchain::default_unittest(
+        0,
+        &BurnchainHeaderHash::from_hex(BITCOIN_REGTEST_FIRST_BLOCK_HASH).unwrap(),
+    );
+    burnchain.pox_constants.reward_cycle_length = 5;
+    burnchain.pox_constants.prepare_length = 2;
+    burnchain.pox_constants.anchor_threshold = 1;
+
+    // PoX-2 never publishes, so the invariant checks themselves are skipped: this test is only
+    // about the range they're asked to cover
+    let epochs = StacksEpoch::all(0, 0, 1_000);
+
+    let (mut peer, _keys) = instantiate_pox_peer_with_epoch(
+        &burnchain,
+        "advance_checked_reports_broken_invariant_range",
+        Some(epochs),
+        None,
+    );
+
+    let mut coinbase_nonce = 0;
+
+    // a well-formed range is accepted
+    peer.config.check_pox_invariants = Some((1, 2));
+    peer.advance_checked(&[], &mut coinbase_nonce);
+
+    // a range that ends before it starts would check nothing, so it's reported
+    peer.config.check_pox_invariants = Some((5, 2));
+    peer.advance_checked(&[], &mut coinbase_nonce);
+}
+
+/// A stacker-link invariant that fails is reported with the reward cycle and burn height it was
+///  checked at, along with the failed assertion.
+#[test]
+fn try_check_invariants_reports_violated_cycle() {
+    let EMPTY_SORTITIONS = 25;
+    let EXPECTED_FIRST_V2_CYCLE = 8;
+
+    let mut burnchain = Burnchain::default_unittest(
+        0,
+        &BurnchainHeaderHash::from_hex(BITCOIN_REGTEST_FIRST_BLOCK_HASH).unwrap(),
+    );
+    burnchain.pox_constants.reward_cycle_length = 5;
+    burnchain.pox_constants.prepare_length = 2;
+    burnchain.pox_constants.anchor_threshold = 1;
+    burnchain.pox_constants.v1_unlock_height = 12 + EMPTY_SORTITIONS;
+
+    let epochs = StacksEpoch::all(0, 0, EMPTY_SORTITIONS as u64 + 10);
+
+    let (mut peer, mut keys) =
+        instantiate_pox_peer_with_epoch(&burnchain, function_name!(), Some(epochs), None);
+
+    let alice = keys.pop().unwrap();
+    let mut coinbase_nonce = 0;
+
+    // produce blocks until the epoch switch
+    for _i in 0..10 {
+        peer.tenure_with_txs(&[], &mut coinbase_nonce);
+    }
+
+    // alice locks for cycles 8 through 13
+    let tip = get_tip(peer.sortdb.as_ref());
+    let alice_lockup = make_pox_2_lockup(
+        &alice,
+        0,
+        1024 * POX_THRESHOLD_STEPS_USTX,
+        PoxAddress::from_legacy(
+            AddressHashMode::SerializeP2PKH,
+            key_to_stacks_addr(&alice).destruct().1,
+        ),
+        6,
+        tip.block_height,
+    );
+    let tip_id = peer.tenure_with_txs(&[alice_lockup], &mut coinbase_nonce);
+    let tip_burn_height = StacksChainState::get_stacks_block_header_info_by_index_block_hash(
+        peer.chainstate().db(),
+        &tip_id,
+    )
+    .unwrap()
+    .unwrap()
+    .burn_header_height;
+
+    try_check_all_stacker_link_invariants(
+        &mut peer,
+        &tip_id,
+        EXPECTED_FIRST_V2_CYCLE,
+        EXPECTED_FIRST_V2_CYCLE + 5,
+    )
+    .unwrap();
+
+    // pox-2 recorded alice's unlock height with the burnchain it was booted with.  Checking
+    //  against a burnchain that starts one block later puts the unlock height the stacking-state
+    //  implies one block past the account's, which violates the first invariant checked on
+    //  alice's entry.
+    peer.config.burnchain.first_block_height += 1;
+    let msg = try_check_all_stacker_link_invariants(
+        &mut peer,
+        &tip_id,
+        EXPECTED_FIRST_V2_CYCLE,
+        EXPECTED_FIRST_V2_CYCLE + 5,
+    )
+    .unwrap_err();
+    assert!(
+        msg.starts_with(&format!(
+            "PoX invariant violated in reward cycle {EXPECTED_FIRST_V2_CYCLE} at burn height {tip_burn_height} (tip {tip_id}): "
+        )),
+        "{msg}"
+    );
+    assert!(
+        msg.contains(
+            "Invariant violated: stacking-state and account state have different unlock heights"
+        ),
+        "{msg}"
+    );
+}
+
+/// The sortition DB and the chainstate must agree on a reward cycle's anchor block before its
+///  reward set is read: reading the reward set from any other block is an error, not a stale set.
+#[test]
+fn reward_set_read_requires_recorded_anchor_block() {
+    let mut burnchain = Burnchain::default_unittest(
+        0,
+        &BurnchainHeaderHash::from_hex(BITCOIN_REGTEST_FIRST_BLOCK_HASH).unwrap(),
+    );
+    burnchain.pox_constants.reward_cycle_length = 5;
+    burnchain.pox_constants.prepare_length = 2;
+    burnchain.pox_constants.anchor_threshold = 1;
+
+    let (mut peer, _keys) = instantiate_pox_peer(&burnchain, function_name!());
+    let mut coinbase_nonce = 0;
+    let mut tip_id = peer.tenure_with_txs(&[], &mut coinbase_nonce);
+    for _ in 0..12 {
+        tip_id = peer.tenure_with_txs(&[], &mut coinbase_nonce);
+    }
+
+    // find the anchor block the sortition DB selected for the current reward cycle
+    let sort_tip = get_tip(peer.sortdb.as_ref());
+    let reward_cycle = burnchain
+        .block_height_to_reward_cycle(sort_tip.block_height)
+        .unwrap();
+    let anchor_block_id = get_recorded_anchor_block_id(peer.sortdb(), reward_cycle)
+        .expect("No anchor block selected for the current reward cycle");
+    let anchor_block_hash = StacksChainState::get_stacks_block_header_info_by_index_block_hash(
+        peer.chainstate().db(),
+        &anchor_block_id,
+    )
+    .unwrap()
+    .unwrap()
+    .anchored_header
+    .block_hash();
+    let cycle_start_ht = burnchain.reward_cycle_to_block_height(reward_cycle);
+
+    // reading from the recorded anchor block gives the reward set at that block, and
+    // `get_reward_set_entries_at()` reads it through the same checked path
+    let entries =
+        try_get_reward_set_entries_at_anchor(&mut peer, &anchor_block_id, cycle_start_ht).unwrap();
+    let expected = with_sortdb(&mut peer, |ref mut c, sortdb| {
+        get_reward_set_entries_at_block(c, &burnchain, sortdb, &anchor_block_id, cycle_start_ht)
+            .unwrap()
+    });
+    let to_pairs = |entries: &[RawRewardSetEntry]| {
+        entries
+            .iter()
+            .map(|entry| (entry.reward_address.clone(), entry.amount_stacked))
+            .collect::<Vec<_>>()
+    };
+    assert_eq!(to_pairs(&entries), to_pairs(&expected));
+    assert_eq!(
+        to_pairs(&get_reward_set_entries_at(
+            &mut peer,
+            &anchor_block_id,
+            cycle_start_ht
+        )),
+        to_pairs(&expected)
+    );
+
+    // but reading from any other block, like the chain tip, is a mismatch
+    assert_ne!(tip_id, anchor_block_id);
+    let err = try_get_reward_set_entries_at_anchor(&mut peer, &tip_id, cycle_start_ht).unwrap_err();
+    match err {
+        ChainstateError::PoxAnchorBlockMismatch(cycle, recorded, source) => {
+            assert_eq!(cycle, reward_cycle);
+            assert_eq!(recorded, Some(anchor_block_hash.clone()));
+            assert_eq!(source, tip_id);
+        }
+        e => panic!("Expected an anchor block mismatch, got {e:?}"),
+    }
+
+    // plain reward set reads are checked too: the chain tip descends from the recorded anchor
+    // block, so it can be read from
+    let tip_entries = get_reward_set_entries_at(&mut peer, &tip_id, cycle_start_ht);
+    assert_eq!(tip_entries.len(), expected.len());
+
+    // ...until the sortition DB records a different anchor block for the cycle
+    let other_anchor_block_hash = BlockHeaderHash([0x11; 32]);
+    assert_ne!(other_anchor_block_hash, anchor_block_hash);
+    {
+        let sortdb = peer.sortdb.as_mut().unwrap();
+        let (_, first_prepare_sortition_id) = sortdb
+            .get_preprocessed_reward_set_for_reward_cycle(&sort_tip.sortition_id, reward_cycle)
+            .unwrap();
+        let mut tx = sortdb.tx_begin().unwrap();
+        SortitionDB::store_preprocessed_reward_set(
+            &mut tx,
+            &first_prepare_sortition_id,
+            &RewardCycleInfo {
+                reward_cycle,
+                anchor_status: PoxAnchorBlockStatus::SelectedAndKnown(
+                    other_anchor_block_hash.clone(),
+                    Txid([0x22; 32]),
+                    RewardSet::empty(),
+                ),
+            },
+        )
+        .unwrap();
+        tx.commit().unwrap();
+    }
+    let err = with_sortdb(&mut peer, |ref mut c, sortdb| {
+        get_reward_set_entries_at_block(c, &burnchain, sortdb, &tip_id, cycle_start_ht).unwrap_err()
+    });
+    match err {
+        ChainstateError::PoxAnchorBlockMismatch(cycle, recorded, source) => {
+            assert_eq!(cycle, reward_cycle);
+            assert_eq!(recorded, Some(other_anchor_block_hash));
+            assert_eq!(source, tip_id);
+        }
+        e => panic!("Expected an anchor block mismatch, got {e:?}"),
+    }
+}
@@ -14,7 +14,8 @@
 // You should have received a copy of the GNU General Public License
 // along with this program.  If not, see <http://www.gnu.org/licenses/>.
 
-use std::collections::{HashMap, HashSet};
+use std::collections::{BTreeMap, HashMap, HashSet};
+use std::time::Instant;
 
 use clarity::vm::clarity::ClarityConnection;
 use clarity::vm::costs::LimitedCostTracker;
@@ -36,21 +37,25 @@ use crate::chainstate::burn::BlockSnapshot;
 use crate::chainstate::coordinator::tests::pox_addr_from;
 use crate::chainstate::nakamoto::test_signers::TestSigners;
 use crate::chainstate::nakamoto::tests::node::TestStacker;
-use crate::chainstate::stacks::address::PoxAddress;
+use crate::chainstate::stacks::address::{PoxAddress, PoxAddressType20, PoxAddressType32};
 use crate::chainstate::stacks::boot::pox_2_tests::{
     check_pox_print_event, generate_pox_clarity_value, get_reward_cycle_total,
-    get_reward_set_entries_at, get_stacking_state_pox, get_stx_account_at, with_clarity_db_ro,
-    PoxPrintFields,
+    get_reward_set_entries_at, get_stx_account_at, get_stx_accounts_at, with_clarity_db_ro,
+    PoxEventAssertion, PoxPrintFields,
 };
 use crate::chainstate::stacks::boot::signers_tests::get_signer_index;
-use crate::chainstate::stacks::boot::{PoxVersions, MINERS_NAME};
+use crate::chainstate::stacks::boot::{
+    delegate_stack_pox_addr_allowed, PoxVersions, RawRewardSetEntry, MINERS_NAME,
+};
 use crate::chainstate::stacks::events::{StacksTransactionReceipt, TransactionOrigin};
 use crate::chainstate::stacks::*;
 use crate::core::*;
 use crate::net::test::{TestEventObserver, TestEventObserverBlock, TestPeer, TestPeerConfig};
 use crate::net::tests::NakamotoBootPlan;
 use crate::util_lib::boot::boot_code_id;
-use crate::util_lib::signed_structured_data::pox4::Pox4SignatureTopic;
+use crate::util_lib::signed_structured_data::pox4::{
+    make_signer_key_signatures_batch, Pox4SignatureTopic,
+};
 
 const USTX_PER_HOLDER: u128 = 1_000_000;
 
@@ -78,50 +83,77 @@ fn make_simple_pox_4_lock(
 ) -> StacksTransaction {
     let addr = key_to_stacks_addr(key);
     let pox_addr = PoxAddress::from_legacy(AddressHashMode::SerializeP2PKH, addr.bytes().clone());
-    let signer_pk = StacksPublicKey::from_private(key);
-    let tip = get_tip(peer.sortdb.as_ref());
-    let next_reward_cycle = peer
-        .config
-        .burnchain
-        .block_height_to_reward_cycle(tip.block_height)
-        .unwrap();
-    let nonce = get_account(peer, &addr.into()).nonce;
-    let auth_id = u128::from(nonce);
-
-    let signature = make_signer_key_signature(
-        &pox_addr,
-        key,
-        next_reward_cycle.into(),
-        &Pox4SignatureTopic::StackStx,
-        lock_period,
-        amount,
-        auth_id,
-    );
-
-    make_pox_4_lockup(
+    let auth_id = u128::from(get_account(peer, &addr.into()).nonce);
+    make_pox_4_stack_stx_signed(
         key,
-        nonce,
-        amount,
+        peer,
         &pox_addr,
+        amount,
         lock_period,
-        &signer_pk,
-        tip.block_height,
-        Some(signature),
+        key,
         amount,
         auth_id,
     )
 }
 
+/// Reward cycle length of the `PoxConstants` made by `make_test_epochs_pox_custom`
+const TEST_EPOCHS_REWARD_CYCLE_LENGTH: u32 = 5;
+
+/// Epoch boundaries and pox-4 activation of `make_test_epochs_pox_custom`. Each gap is the
+/// number of burn blocks between the start of the previous epoch and the start of the named
+/// one. The defaults are the boundaries of `make_test_epochs_pox`.
+#[derive(Debug, Clone)]
+pub struct PoxEpochOpts {
+    /// Whether to add epoch 3.0 after epoch 2.5
+    pub use_nakamoto: bool,
+    /// Empty sortitions the sim environment produces before tenures start being tracked
+    pub empty_sortitions: u64,
+    pub epoch_2_1_gap: u64,
+    pub epoch_2_2_gap: u64,
+    pub epoch_2_3_gap: u64,
+    pub epoch_2_4_gap: u64,
+    pub epoch_2_5_gap: u64,
+    pub epoch_3_0_gap: u64,
+    /// Burn blocks between the start of epoch 2.5 and pox-4 activation
+    pub pox_4_activation_offset: u32,
+}
+
+impl Default for PoxEpochOpts {
+    fn default() -> Self {
+        Self {
+            use_nakamoto: false,
+            empty_sortitions: 25,
+            epoch_2_1_gap: 11,
+            epoch_2_2_gap: 14,
+            epoch_2_3_gap: 2,
+            epoch_2_4_gap: 4,
+            epoch_2_5_gap: 44,
+            epoch_3_0_gap: 23,
+            // just over 2 cycles into epoch 2.5, so that pox-4 activates before Epoch 3.0 and
+            // there are no nakamoto blocks
+            pox_4_activation_offset: 1 + 2 * TEST_EPOCHS_REWARD_CYCLE_LENGTH,
+        }
+    }
+}
+
 pub fn make_test_epochs_pox(use_nakamoto: bool) -> (EpochList, PoxConstants) {
-    let EMPTY_SORTITIONS = 25;
-    let EPOCH_2_1_HEIGHT = EMPTY_SORTITIONS + 11; // 36
-    let EPOCH_2_2_HEIGHT = EPOCH_2_1_HEIGHT + 14; // 50
-    let EPOCH_2_3_HEIGHT = EPOCH_2_2_HEIGHT + 2; // 52
-                                                 // epoch-2.4 will start at the first block of cycle 11!
-                                                 //  this means that cycle 11 should also be treated like a "burn"
-    let EPOCH_2_4_HEIGHT = EPOCH_2_3_HEIGHT + 4; // 56
-    let EPOCH_2_5_HEIGHT = EPOCH_2_4_HEIGHT + 44; // 100
-    let EPOCH_3_0_HEIGHT = EPOCH_2_5_HEIGHT + 23; // 123
+    make_test_epochs_pox_custom(PoxEpochOpts {
+        use_nakamoto,
+        ..PoxEpochOpts::default()
+    })
+}
+
+pub fn make_test_epochs_pox_custom(opts: PoxEpochOpts) -> (EpochList, PoxConstants) {
+    let use_nakamoto = opts.use_nakamoto;
+    let EMPTY_SORTITIONS = opts.empty_sortitions;
+    let EPOCH_2_1_HEIGHT = EMPTY_SORTITIONS + opts.epoch_2_1_gap;
+    let EPOCH_2_2_HEIGHT = EPOCH_2_1_HEIGHT + opts.epoch_2_2_gap;
+    let EPOCH_2_3_HEIGHT = EPOCH_2_2_HEIGHT + opts.epoch_2_3_gap;
+    // with the default gaps, epoch-2.4 will start at the first block of cycle 11!
+    //  this means that cycle 11 should also be treated like a "burn"
+    let EPOCH_2_4_HEIGHT = EPOCH_2_3_HEIGHT + opts.epoch_2_4_gap;
+    let EPOCH_2_5_HEIGHT = EPOCH_2_4_HEIGHT + opts.epoch_2_5_gap;
+    let EPOCH_3_0_HEIGHT = EPOCH_2_5_HEIGHT + opts.epoch_3_0_gap;
 
     let mut epochs = EpochList::new(&[
         StacksEpoch {
@@ -199,21 +231,292 @@ pub fn make_test_epochs_pox(use_nakamoto: bool) -> (EpochList, PoxConstants) {
     }
 
     let mut pox_constants = PoxConstants::mainnet_default();
-    pox_constants.reward_cycle_length = 5;
+    pox_constants.reward_cycle_length = TEST_EPOCHS_REWARD_CYCLE_LENGTH;
     pox_constants.prepare_length = 2;
     pox_constants.anchor_threshold = 1;
     pox_constants.v1_unlock_height = (EPOCH_2_1_HEIGHT + 1) as u32;
     pox_constants.v2_unlock_height = (EPOCH_2_2_HEIGHT + 1) as u32;
     pox_constants.v3_unlock_height = (EPOCH_2_5_HEIGHT + 1) as u32;
     pox_constants.pox_3_activation_height = (EPOCH_2_4_HEIGHT + 1) as u32;
-    // Activate pox4 2 cycles into epoch 2.5
-    // Don't use Epoch 3.0 in order to avoid nakamoto blocks
     pox_constants.pox_4_activation_height =
-        (EPOCH_2_5_HEIGHT as u32) + 1 + (2 * pox_constants.reward_cycle_length);
+        (EPOCH_2_5_HEIGHT as u32) + opts.pox_4_activation_offset;
 
     (epochs, pox_constants)
 }
 
+/// Expected `start-cycle-id` and `end-cycle-id` of a pox-4 print event
+pub struct Pox4CycleIds {
+    pub start: u128,
+    pub end: Option<u128>,
+}
+
+/// Expected `data` fields of a `stack-stx` print event
+pub struct StackStxExpectation {
+    pub lock_amount: u128,
+    pub unlock_burn_height: u128,
+    pub pox_addr: PoxAddress,
+    pub start_burn_height: u128,
+    pub lock_period: u128,
+    pub signer_sig: Option<Vec<u8>>,
+    pub signer_key: Secp256k1PublicKey,
+    pub max_amount: u128,
+    pub auth_id: u128,
+}
+
+/// Expected `data` fields of a `stack-extend` print event
+pub struct StackExtendExpectation {
+    pub pox_addr: PoxAddress,
+    pub extend_count: u128,
+    pub unlock_burn_height: u128,
+    pub signer_sig: Option<Vec<u8>>,
+    pub signer_key: Secp256k1PublicKey,
+    pub max_amount: u128,
+    pub auth_id: u128,
+}
+
+/// Expected `data` fields of a `stack-increase` print event
+pub struct StackIncreaseExpectation {
+    pub increase_by: u128,
+    pub total_locked: u128,
+    pub pox_addr: PoxAddress,
+    pub signer_sig: Option<Vec<u8>>,
+    pub signer_key: Secp256k1PublicKey,
+    pub max_amount: u128,
+    pub auth_id: u128,
+}
+
+/// Expected `data` fields of a `delegate-stx` print event
+pub struct DelegateStxExpectation {
+    pub amount_ustx: u128,
+    pub delegate_to: PrincipalData,
+    pub unlock_burn_height: Option<u128>,
+    pub pox_addr: Option<PoxAddress>,
+}
+
+/// Expected `data` fields of a `delegate-stack-stx` print event. The stacker is the one in
+/// the `Pox4OpExpectation`.
+pub struct DelegateStackStxExpectation {
+    pub lock_amount: u128,
+    pub unlock_burn_height: u128,
+    pub pox_addr: PoxAddress,
+    pub start_burn_height: u128,
+    pub lock_period: u128,
+    pub delegator: PrincipalData,
+}
+
+/// Expected `data` fields of a `stack-aggregation-commit` or
+/// `stack-aggregation-commit-indexed` print event. The delegator is the stacker in the
+/// `Pox4OpExpectation`.
+pub struct StackAggregationCommitExpectation {
+    pub pox_addr: PoxAddress,
+    pub reward_cycle: u128,
+    pub amount_ustx: u128,
+    pub signer_sig: Option<Vec<u8>>,
+    pub signer_key: Secp256k1PublicKey,
+    pub max_amount: u128,
+    pub auth_id: u128,
+}
+
+pub enum Pox4OpData {
+    StackStx(StackStxExpectation),
+    StackExtend(StackExtendExpectation),
+    StackIncrease(StackIncreaseExpectation),
+    DelegateStx(DelegateStxExpectation),
+    DelegateStackStx(DelegateStackStxExpectation),
+    StackAggregationCommit(StackAggregationCommitExpectation),
+}
+
+/// Everything a pox-4 print event is expected to report: the stacker's state before the op,
+/// and the op's own data
+pub struct Pox4OpExpectation {
+    pub stacker: PrincipalData,
+    pub balance: u128,
+    pub locked: u128,
+    pub burnchain_unlock_height: u128,
+    /// If `None`, the cycle ids aren't checked
+    pub cycle_ids: Option<Pox4CycleIds>,
+    pub op: Pox4OpData,
+}
+
+fn pox_addr_value(pox_addr: &PoxAddress) -> Value {
+    Value::Tuple(pox_addr.clone().as_clarity_tuple().unwrap())
+}
+
+fn signer_sig_value(signer_sig: &Option<Vec<u8>>) -> Value {
+    match signer_sig {
+        Some(signer_sig) => Value::some(Value::buff_from(signer_sig.clone()).unwrap()).unwrap(),
+        None => Value::none(),
+    }
+}
+
+fn signer_key_value(signer_key: &Secp256k1PublicKey) -> Value {
+    Value::buff_from(signer_key.to_bytes_compressed()).unwrap()
+}
+
+impl Pox4OpExpectation {
+    /// The op names whose print events carry this op's data
+    fn op_names(&self) -> &'static [&'static str] {
+        match self.op {
+            Pox4OpData::StackStx(_) => &["stack-stx"],
+            Pox4OpData::StackExtend(_) => &["stack-extend"],
+            Pox4OpData::StackIncrease(_) => &["stack-increase"],
+            Pox4OpData::DelegateStx(_) => &["delegate-stx"],
+            Pox4OpData::DelegateStackStx(_) => &["delegate-stack-stx"],
+            Pox4OpData::StackAggregationCommit(_) => &[
+                "stack-aggregation-commit",
+                "stack-aggregation-commit-indexed",
+            ],
+        }
+    }
+
+    /// The expected `data` tuple of the print event, as a map of field name to value
+    fn op_data(&self) -> HashMap<&'static str, Value> {
+        let mut op_data = match &self.op {
+            Pox4OpData::StackStx(data) => HashMap::from([
+                ("lock-amount", Value::UInt(data.lock_amount)),
+                ("unlock-burn-height", Value::UInt(data.unlock_burn_height)),
+                ("pox-addr", pox_addr_value(&data.pox_addr)),
+                ("start-burn-height", Value::UInt(data.start_burn_height)),
+                ("lock-period", Value::UInt(data.lock_period)),
+                ("signer-sig", signer_sig_value(&data.signer_sig)),
+                ("signer-key", signer_key_value(&data.signer_key)),
+                ("max-amount", Value::UInt(data.max_amount)),
+                ("auth-id", Value::UInt(data.auth_id)),
+            ]),
+            Pox4OpData::StackExtend(data) => HashMap::from([
+                ("pox-addr", pox_addr_value(&data.pox_addr)),
+                ("extend-count", Value::UInt(data.extend_count)),
+                ("unlock-burn-height", Value::UInt(data.unlock_burn_height)),
+                ("signer-sig", signer_sig_value(&data.signer_sig)),
+                ("signer-key", signer_key_value(&data.signer_key)),
+                ("max-amount", Value::UInt(data.max_amount)),
+                ("auth-id", Value::UInt(data.auth_id)),
+            ]),
+            Pox4OpData::StackIncrease(data) => HashMap::from([
+                ("increase-by", Value::UInt(data.increase_by)),
+                ("total-locked", Value::UInt(data.total_locked)),
+                ("pox-addr", pox_addr_value(&data.pox_addr)),
+                ("signer-sig", signer_sig_value(&data.signer_sig)),
+                ("signer-key", signer_key_value(&data.signer_key)),
+                ("max-amount", Value::UInt(data.max_amount)),
+                ("auth-id", Value::UInt(data.auth_id)),
+            ]),
+            Pox4OpData::DelegateStx(data) => HashMap::from([
+                ("amount-ustx", Value::UInt(data.amount_ustx)),
+                ("delegate-to", Value::Principal(data.delegate_to.clone())),
+                (
+                    "unlock-burn-height",
+                    Value::Optional(OptionalData {
+                        data: data
+                            .unlock_burn_height
+                            .map(|height| Box::new(Value::UInt(height))),
+                    }),
+                ),
+                (
+                    "pox-addr",
+                    Value::Optional(OptionalData {
+                        data: data
+                            .pox_addr
+                            .as_ref()
+                            .map(|pox_addr| Box::new(pox_addr_value(pox_addr))),
+                    }),
+                ),
+            ]),
+            Pox4OpData::DelegateStackStx(data) => HashMap::from([
+                ("lock-amount", Value::UInt(data.lock_amount)),
+                ("unlock-burn-height", Value::UInt(data.unlock_burn_height)),
+                ("pox-addr", pox_addr_value(&data.pox_addr)),
+                ("start-burn-height", Value::UInt(data.start_burn_height)),
+                ("lock-period", Value::UInt(data.lock_period)),
+                ("delegator", Value::Principal(data.delegator.clone())),
+                ("stacker", Value::Principal(self.stacker.clone())),
+            ]),
+            Pox4OpData::StackAggregationCommit(data) => HashMap::from([
+                ("pox-addr", pox_addr_value(&data.pox_addr)),
+                ("reward-cycle", Value::UInt(data.reward_cycle)),
+                ("amount-ustx", Value::UInt(data.amount_ustx)),
+                ("delegator", Value::Principal(self.stacker.clone())),
+                ("signer-sig", signer_sig_value(&data.signer_sig)),
+                ("signer-key", signer_key_value(&data.signer_key)),
+                ("max-amount", Value::UInt(data.max_amount)),
+                ("auth-id", Value::UInt(data.auth_id)),
+            ]),
+        };
+        if let Some(cycle_ids) = &self.cycle_ids {
+            op_data.insert("start-cycle-id", Value::UInt(cycle_ids.start));
+            op_data.insert(
+                "end-cycle-id",
+                Value::Optional(OptionalData {
+                    data: cycle_ids.end.map(|end| Box::new(Value::UInt(end))),
+                }),
+            );
+        }
+        op_data
+    }
+}
+
+/// Check that the first event of `receipt` is the print event of the pox-4 op `op_name`, and
+/// that it reports exactly the fields in `expected` -- no field is missing, wrong, or
+/// unexpected (except for the cycle ids, if `expected.cycle_ids` is `None`).
+pub fn assert_pox_op(
+    receipt: &StacksTransactionReceipt,
+    op_name: &str,
+    expected: Pox4OpExpectation,
+) {
+    assert!(
+        expected.op_names().contains(&op_name),
+        "Expected data for {:?} can't describe a {op_name} event",
+        expected.op_names()
+    );
+    let event = receipt
+        .events
+        .first()
+        .unwrap_or_else(|| panic!("No print event for {op_name}"));
+
+    let op_data = expected.op_data();
+    let mut expected_fields: HashSet<_> = op_data.keys().copied().collect();
+    if expected.cycle_ids.is_none() {
+        expected_fields.extend(["start-cycle-id", "end-cycle-id"]);
+    }
+
+    let common_data = PoxPrintFields {
+        op_name: op_name.to_string(),
+        stacker: Value::Principal(expected.stacker.clone()),
+        balance: Value::UInt(expected.balance),
+        locked: Value::UInt(expected.locked),
+        burnchain_unlock_height: Value::UInt(expected.burnchain_unlock_height),
+    };
+    check_pox_print_event(event, common_data, op_data);
+
+    // check_pox_print_event only looks at the expected fields, so check there are no others
+    let StacksTransactionEvent::SmartContractEvent(event_data) = event else {
+        panic!("Unexpected event type: {event:?}");
+    };
+    let event_tuple = event_data
+        .value
+        .clone()
+        .expect_result_ok()
+        .unwrap()
+        .expect_tuple()
+        .unwrap();
+    let data_tuple = event_tuple
+        .data_map
+        .get("data")
+        .unwrap()
+        .clone()
+        .expect_tuple()
+        .unwrap();
+    let unexpected: Vec<_> = data_tuple
+        .data_map
+        .keys()
+        .filter(|field| !expected_fields.contains(field.as_str()))
+        .collect();
+    assert!(
+        unexpected.is_empty(),
+        "Unexpected fields in {op_name} event: {unexpected:?}"
+    );
+}
+
 #[test]
 fn pox_extend_transition() {
     let EXPECTED_FIRST_V2_CYCLE = 8;
@@ -653,7 +956,7 @@ fn pox_extend_transition() {
     let alice_lockup = make_pox_4_extend(
         &alice,
         3,
-        alice_pox_addr,
+        alice_pox_addr.clone(),
         6,
         alice_signer_key.clone(),
         Some(alice_signature),
@@ -755,43 +1058,30 @@ fn pox_extend_transition() {
     }
 
     // Check that the call to `stack-stx` has a well-formed print event.
-    let stack_tx = &alice_txs
-        .get(&alice_pox_4_lock_nonce)
-        .unwrap()
-        .clone()
-        .events[0];
     let pox_addr_val = generate_pox_clarity_value("ae1593226f85e49a7eaff5b633ff687695438cc9");
-    let stack_op_data = HashMap::from([
-        ("lock-amount", Value::UInt(ALICE_LOCKUP)),
-        (
-            "unlock-burn-height",
-            Value::UInt(alice_first_pox_4_unlock_height.into()),
-        ),
-        (
-            "start-burn-height",
-            Value::UInt(alice_pox_4_start_burn_height.into()),
-        ),
-        ("pox-addr", pox_addr_val.clone()),
-        ("lock-period", Value::UInt(4)),
-        (
-            "signer-sig",
-            Value::some(Value::buff_from(alice_stack_signature).unwrap()).unwrap(),
-        ),
-        (
-            "signer-key",
-            Value::buff_from(alice_stack_signer_key.to_bytes_compressed()).unwrap(),
-        ),
-        ("max-amount", Value::UInt(u128::MAX)),
-        ("auth-id", Value::UInt(1)),
-    ]);
-    let common_data = PoxPrintFields {
-        op_name: "stack-stx".to_string(),
-        stacker: Value::Principal(alice_principal.clone()),
-        balance: Value::UInt(10240000000000),
-        locked: Value::UInt(0),
-        burnchain_unlock_height: Value::UInt(0),
-    };
-    check_pox_print_event(stack_tx, common_data, stack_op_data);
+    assert_eq!(pox_addr_value(&alice_pox_addr), pox_addr_val);
+    assert_pox_op(
+        alice_txs.get(&alice_pox_4_lock_nonce).unwrap(),
+        "stack-stx",
+        Pox4OpExpectation {
+            stacker: alice_principal.clone(),
+            balance: 10240000000000,
+            locked: 0,
+            burnchain_unlock_height: 0,
+            cycle_ids: None,
+            op: Pox4OpData::StackStx(StackStxExpectation {
+                lock_amount: ALICE_LOCKUP,
+                unlock_burn_height: alice_first_pox_4_unlock_height.into(),
+                pox_addr: alice_pox_addr,
+                start_burn_height: alice_pox_4_start_burn_height.into(),
+                lock_period: 4,
+                signer_sig: Some(alice_stack_signature),
+                signer_key: alice_stack_signer_key,
+                max_amount: u128::MAX,
+                auth_id: 1,
+            }),
+        },
+    );
 
     // Check that the call to `stack-extend` has a well-formed print event.
     let stack_extend_tx = &alice_txs
@@ -817,6 +1107,149 @@ fn pox_extend_transition() {
     check_pox_print_event(stack_extend_tx, common_data, stack_ext_op_data);
 }
 
+/// Follow the stackers of `pox_extend_transition` from pox-2 into pox-4, and check that the
+/// reward sets stay consistent at each PoX contract switch along the way
+#[test]
+fn reward_sets_across_pox_transitions() {
+    let (epochs, pox_constants) = make_test_epochs_pox(false);
+
+    let mut burnchain = Burnchain::default_unittest(
+        0,
+        &BurnchainHeaderHash::from_hex(BITCOIN_REGTEST_FIRST_BLOCK_HASH).unwrap(),
+    );
+    burnchain.pox_constants = pox_constants;
+
+    let (mut peer, mut keys) =
+        instantiate_pox_peer_with_epoch(&burnchain, function_name!(), Some(epochs.clone()), None);
+
+    let alice = keys.pop().unwrap();
+    let bob = keys.pop().unwrap();
+    let alice_principal = PrincipalData::from(key_to_stacks_addr(&alice));
+    let bob_principal = PrincipalData::from(key_to_stacks_addr(&bob));
+    let alice_pox_addr = PoxAddress::from_legacy(
+        AddressHashMode::SerializeP2PKH,
+        key_to_stacks_addr(&alice).destruct().1,
+    );
+    let bob_pox_addr = PoxAddress::from_legacy(
+        AddressHashMode::SerializeP2PKH,
+        key_to_stacks_addr(&bob).destruct().1,
+    );
+
+    let mut coinbase_nonce = 0;
+    let ALICE_LOCKUP = 1024 * POX_THRESHOLD_STEPS_USTX;
+    let BOB_LOCKUP = 512 * POX_THRESHOLD_STEPS_USTX;
+
+    // Alice stacks in PoX v1
+    peer.tenure_with_txs(&[], &mut coinbase_nonce);
+    let tip = get_tip(peer.sortdb.as_ref());
+    let alice_lockup = make_pox_lockup(
+        &alice,
+        0,
+        ALICE_LOCKUP,
+        AddressHashMode::SerializeP2PKH,
+        key_to_stacks_addr(&alice).destruct().1,
+        4,
+        tip.block_height,
+    );
+    peer.tenure_with_txs(&[alice_lockup], &mut coinbase_nonce);
+
+    while get_tip(peer.sortdb.as_ref()).block_height < epochs[StacksEpochId::Epoch21].start_height {
+        peer.tenure_with_txs(&[], &mut coinbase_nonce);
+    }
+
+    // Bob stacks in PoX v2, and Alice extends her lock into PoX v2
+    let tip = get_tip(peer.sortdb.as_ref());
+    let bob_lockup = make_pox_2_lockup(
+        &bob,
+        0,
+        BOB_LOCKUP,
+        bob_pox_addr.clone(),
+        3,
+        tip.block_height,
+    );
+    let alice_extend = make_pox_2_extend(&alice, 1, alice_pox_addr.clone(), 6);
+    let mut latest_block = peer.tenure_with_txs(&[bob_lockup, alice_extend], &mut coinbase_nonce);
+
+    // Alice carries over from .pox to .pox-2, and Bob joins her
+    let (outgoing, incoming) = compare_reward_sets_across_versions(
+        &mut peer,
+        &latest_block,
+        u64::from(burnchain.pox_constants.v1_unlock_height),
+    );
+    assert_eq!(outgoing.len(), 1);
+    assert_eq!(outgoing[0].reward_address, alice_pox_addr);
+    assert_eq!(outgoing[0].amount_stacked, ALICE_LOCKUP);
+    let mut incoming_amounts: Vec<_> = incoming
+        .iter()
+        .map(|entry| (entry.reward_address.clone(), entry.amount_stacked))
+        .collect();
+    incoming_amounts.sort();
+    let mut expected_amounts = vec![
+        (alice_pox_addr.clone(), ALICE_LOCKUP),
+        (bob_pox_addr.clone(), BOB_LOCKUP),
+    ];
+    expected_amounts.sort();
+    assert_eq!(incoming_amounts, expected_amounts);
+
+    // Roll to pox4 activation.  Epoch 2.2 unlocked both PoX v2 stackers, and nobody stacks in
+    // PoX v3, so only Alice's new pox-4 lockup is in the first pox-4 reward set.
+    while get_tip(peer.sortdb.as_ref()).block_height
+        < u64::from(burnchain.pox_constants.pox_4_activation_height)
+    {
+        latest_block = peer.tenure_with_txs(&[], &mut coinbase_nonce);
+    }
+    let balances = get_stx_accounts_at(
+        &mut peer,
+        &latest_block,
+        &[alice_principal.clone(), bob_principal.clone()],
+    );
+    assert_eq!(balances[0].amount_locked(), 0);
+    assert_eq!(balances[1].amount_locked(), 0);
+
+    let tip = get_tip(peer.sortdb.as_ref());
+    let alice_signer_private = Secp256k1PrivateKey::random();
+    let alice_signer_key = Secp256k1PublicKey::from_private(&alice_signer_private);
+    let reward_cycle = get_current_reward_cycle(&peer, &burnchain);
+    let auth_id = 1;
+    let alice_signature = make_signer_key_signature(
+        &alice_pox_addr,
+        &alice_signer_private,
+        reward_cycle,
+        &Pox4SignatureTopic::StackStx,
+        4_u128,
+        u128::MAX,
+        auth_id,
+    );
+    let alice_lockup = make_pox_4_lockup(
+        &alice,
+        2,
+        ALICE_LOCKUP,
+        &alice_pox_addr,
+        4,
+        &alice_signer_key,
+        tip.block_height,
+        Some(alice_signature),
+        u128::MAX,
+        auth_id,
+    );
+    latest_block = peer.tenure_with_txs(&[alice_lockup], &mut coinbase_nonce);
+
+    let (outgoing, incoming) = compare_reward_sets_across_versions(
+        &mut peer,
+        &latest_block,
+        u64::from(burnchain.pox_constants.pox_4_activation_height),
+    );
+    assert!(outgoing.is_empty());
+    assert_eq!(incoming.len(), 1);
+    assert_eq!(incoming[0].reward_address, alice_pox_addr);
+    assert_eq!(incoming[0].amount_stacked, ALICE_LOCKUP);
+    assert_eq!(incoming[0].stacker, Some(alice_principal));
+    assert_eq!(
+        incoming[0].signer.map(|signer| signer.to_vec()),
+        Some(alice_signer_key.to_bytes_compressed())
+    );
+}
+
 fn get_burn_pox_addr_info(peer: &mut TestPeer) -> (Vec<PoxAddress>, u128) {
     let tip = get_tip(peer.sortdb.as_ref());
     let tip_index_block = tip.get_canonical_stacks_block_id();
@@ -959,6 +1392,20 @@ fn pox_lock_unlock() {
     info!("Submitting stacking txs");
     let mut latest_block = peer.tenure_with_txs(&txs, &mut coinbase_nonce);
 
+    // the raw reward set is sorted by reward address
+    let mut expected_entries: Vec<_> = keys
+        .iter()
+        .zip(stackers.iter())
+        .map(|(key, pox_addr)| {
+            (
+                pox_addr.bytes(),
+                1024 * POX_THRESHOLD_STEPS_USTX,
+                Some(StacksPublicKey::from_private(key).to_bytes_compressed()),
+            )
+        })
+        .collect();
+    expected_entries.sort_by(|a, b| a.0.cmp(&b.0));
+
     // Advance to start of rewards cycle stackers are participating in
     let target_height = burnchain.pox_constants.pox_4_activation_height + 5;
     while get_tip(peer.sortdb.as_ref()).block_height < u64::from(target_height) {
@@ -971,8 +1418,11 @@ fn pox_lock_unlock() {
     );
 
     // now we should be in the reward phase, produce the reward blocks
-    let reward_blocks =
-        burnchain.pox_constants.reward_cycle_length - burnchain.pox_constants.prepare_length;
+    let reward_blocks = burnchain.pox_constants.total_reward_blocks_per_cycle();
+    // each stacker occupies one slot, so this many reward blocks carry pox outputs
+    let pox_blocks = u32::try_from(stackers.len())
+        .unwrap()
+        .div_ceil(burnchain.pox_constants.slots_per_reward_block());
     let mut rewarded = HashSet::new();
 
     // Check that STX are locked for 2 reward cycles
@@ -989,11 +1439,15 @@ fn pox_lock_unlock() {
         assert!(balances[2].amount_locked() > 0);
         assert!(balances[3].amount_locked() > 0);
 
+        info!("Checking the reward set of cycle {cycle}");
+        let cycle_start = burnchain.reward_cycle_to_block_height(cycle);
+        assert_reward_set_entries(&mut peer, &latest_block, cycle_start, &expected_entries);
+
         info!("Checking we have 2 stackers for cycle {cycle}");
         for i in 0..reward_blocks {
             latest_block = peer.tenure_with_txs(&[], &mut coinbase_nonce);
             // only the first 2 reward blocks contain pox outputs, because there are 6 slots and only 4 are occuppied
-            if i < 2 {
+            if i < pox_blocks {
                 assert_latest_was_pox(&mut peer)
                     .into_iter()
                     .filter(|addr| !addr.is_burn())
@@ -1025,9 +1479,9 @@ fn pox_lock_unlock() {
     let mut rewarded = HashSet::new();
     for i in 0..burnchain.pox_constants.reward_cycle_length {
         latest_block = peer.tenure_with_txs(&[], &mut coinbase_nonce);
-        // only 1 entry in reward set now, but they get 5 slots -- so that's 3 blocks
+        // only 1 entry in reward set now, but they get 5 slots -- so that's every reward block
         info!("Checking {i}th block of next reward cycle");
-        if i < 3 {
+        if i < burnchain.pox_constants.total_reward_blocks_per_cycle() {
             assert_latest_was_pox(&mut peer)
                 .into_iter()
                 .filter(|addr| !addr.is_burn())
@@ -1052,12 +1506,12 @@ fn pox_lock_unlock() {
     assert_eq!(balances[2].amount_locked(), 0);
 }
 
-/// Test that pox3 methods fail once pox4 is activated
+/// Test that a reward cycle whose stackers use p2pkh, p2sh, p2wpkh, p2wsh and p2tr reward
+/// addresses pays each of them, and that the block-commits carry the right output script for
+/// each address type
 #[test]
-fn pox_3_defunct() {
-    // Config for this test
-    // We are going to try locking for 2 reward cycles (10 blocks)
-    let lock_period = 2;
+fn pox_lock_unlock_mixed_address_types() {
+    let lock_period = 1;
     let (epochs, pox_constants) = make_test_epochs_pox(false);
 
     let mut burnchain = Burnchain::default_unittest(
@@ -1066,11 +1520,245 @@ fn pox_3_defunct() {
     );
     burnchain.pox_constants = pox_constants;
 
-    let observer = TestEventObserver::new();
+    let (mut peer, mut keys) =
+        instantiate_pox_peer_with_epoch(&burnchain, function_name!(), Some(epochs.clone()), None);
 
-    let (mut peer, keys) = instantiate_pox_peer_with_epoch(
-        &burnchain,
-        function_name!(),
+    assert_eq!(burnchain.pox_constants.reward_slots(), 6);
+    let mut coinbase_nonce = 0;
+
+    // Advance into pox4
+    let target_height = burnchain.pox_constants.pox_4_activation_height;
+    while get_tip(peer.sortdb.as_ref()).block_height < u64::from(target_height) {
+        peer.tenure_with_txs(&[], &mut coinbase_nonce);
+    }
+
+    // Only four keys are funded, so the four of them fund a fifth stacker. Everyone then has
+    // about 819 steps and stacks 800, which is above the 667-step threshold for 4000 stacked
+    // steps over 6 slots, so each stacker gets exactly one slot.
+    let fifth_key = StacksPrivateKey::from_seed(&[5]);
+    let mut txs: Vec<_> = keys
+        .iter()
+        .map(|key| {
+            make_tx(
+                key,
+                0,
+                0,
+                TransactionPayload::TokenTransfer(
+                    key_to_stacks_addr(&fifth_key).into(),
+                    u64::try_from(205 * POX_THRESHOLD_STEPS_USTX).unwrap(),
+                    TokenTransferMemo([0u8; 34]),
+                ),
+            )
+        })
+        .collect();
+    keys.push(fifth_key);
+
+    let tip_height = get_tip(peer.sortdb.as_ref()).block_height;
+    let reward_cycle = burnchain.block_height_to_reward_cycle(tip_height).unwrap() as u128;
+    // each address type, and the prefix of the output script it must be paid with
+    let stackers: Vec<(PoxAddress, Vec<u8>)> = keys
+        .iter()
+        .enumerate()
+        .map(|(ix, key)| {
+            let hash_bytes = key_to_stacks_addr(key).destruct().1;
+            let (pox_addr, script_prefix) = match ix {
+                0 => (
+                    PoxAddress::from_legacy(AddressHashMode::SerializeP2PKH, hash_bytes),
+                    vec![0x76, 0xa9, 0x14],
+                ),
+                1 => (
+                    PoxAddress::from_legacy(AddressHashMode::SerializeP2SH, hash_bytes),
+                    vec![0xa9, 0x14],
+                ),
+                2 => (
+                    PoxAddress::Addr20(false, PoxAddressType20::P2WPKH, hash_bytes.0),
+                    vec![0x00, 0x14],
+                ),
+                3 => (
+                    PoxAddress::Addr32(false, PoxAddressType32::P2WSH, [0x03; 32]),
+                    vec![0x00, 0x20],
+                ),
+                _ => (
+                    PoxAddress::Addr32(false, PoxAddressType32::P2TR, [0x04; 32]),
+                    vec![0x51, 0x20],
+                ),
+            };
+            let signature = make_signer_key_signature(
+                &pox_addr,
+                key,
+                reward_cycle,
+                &Pox4SignatureTopic::StackStx,
+                lock_period,
+                u128::MAX,
+                1,
+            );
+            // the four funded keys used their first nonce on the transfer
+            let nonce = if ix < 4 { 1 } else { 0 };
+            txs.push(make_pox_4_lockup(
+                key,
+                nonce,
+                800 * POX_THRESHOLD_STEPS_USTX,
+                &pox_addr,
+                lock_period,
+                &StacksPublicKey::from_private(key),
+                tip_height,
+                Some(signature),
+                u128::MAX,
+                1,
+            ));
+            (pox_addr, script_prefix)
+        })
+        .collect();
+
+    info!("Submitting funding and stacking txs");
+    let mut latest_block = peer.tenure_with_txs(&txs, &mut coinbase_nonce);
+
+    // Advance to start of rewards cycle stackers are participating in
+    let target_height = burnchain.pox_constants.pox_4_activation_height + 5;
+    while get_tip(peer.sortdb.as_ref()).block_height < u64::from(target_height) {
+        latest_block = peer.tenure_with_txs(&[], &mut coinbase_nonce);
+    }
+
+    let balances = balances_from_keys(&mut peer, &latest_block, &keys);
+    for balance in balances.iter() {
+        assert_eq!(balance.amount_locked(), 800 * POX_THRESHOLD_STEPS_USTX);
+    }
+
+    // now we should be in the reward phase, produce the reward blocks
+    let reward_blocks = burnchain.pox_constants.total_reward_blocks_per_cycle();
+    // each stacker occupies one slot, so this many reward blocks carry pox outputs
+    let pox_blocks = u32::try_from(stackers.len())
+        .unwrap()
+        .div_ceil(burnchain.pox_constants.slots_per_reward_block());
+    let mut rewarded = HashMap::new();
+    for i in 0..reward_blocks {
+        peer.tenure_with_txs(&[], &mut coinbase_nonce);
+        if i < pox_blocks {
+            assert_latest_was_pox(&mut peer)
+                .into_iter()
+                .filter(|addr| !addr.is_burn())
+                .for_each(|addr| {
+                    rewarded.insert(addr.to_burnchain_repr(), addr);
+                });
+        } else {
+            assert_latest_was_burn(&mut peer);
+        }
+    }
+
+    assert_eq!(rewarded.len(), stackers.len());
+    for (stacker, script_prefix) in stackers.iter() {
+        let paid = rewarded
+            .get(&stacker.to_burnchain_repr())
+            .unwrap_or_else(|| panic!("Reward cycle should include {stacker}"));
+        assert_eq!(paid.address_type(), stacker.address_type());
+
+        let script = paid.to_bitcoin_tx_out(500).script_pubkey.into_bytes();
+        assert_eq!(
+            script,
+            stacker.to_bitcoin_tx_out(500).script_pubkey.into_bytes()
+        );
+        assert!(
+            script.starts_with(script_prefix),
+            "Output script of {stacker} should start with {}",
+            to_hex(script_prefix)
+        );
+    }
+}
+
+/// Test that `make_pox_4_lockups` numbers a stacker's transactions consecutively, so that they
+/// can all be mined in the same tenure
+#[test]
+fn pox_4_lockups_have_consecutive_nonces() {
+    let lock_period = 2;
+    let (epochs, pox_constants) = make_test_epochs_pox(false);
+
+    let mut burnchain = Burnchain::default_unittest(
+        0,
+        &BurnchainHeaderHash::from_hex(BITCOIN_REGTEST_FIRST_BLOCK_HASH).unwrap(),
+    );
+    burnchain.pox_constants = pox_constants;
+
+    let (mut peer, keys) =
+        instantiate_pox_peer_with_epoch(&burnchain, function_name!(), Some(epochs.clone()), None);
+
+    let mut coinbase_nonce = 0;
+
+    // Advance into pox4
+    let target_height = burnchain.pox_constants.pox_4_activation_height;
+    while get_tip(peer.sortdb.as_ref()).block_height < u64::from(target_height) {
+        peer.tenure_with_txs(&[], &mut coinbase_nonce);
+    }
+
+    let tip_height = get_tip(peer.sortdb.as_ref()).block_height;
+    let reward_cycle = burnchain.block_height_to_reward_cycle(tip_height).unwrap() as u128;
+
+    let stacker_key = &keys[0];
+    let stacker_addr = key_to_stacks_addr(stacker_key);
+    let signer_key = StacksPublicKey::from_private(stacker_key);
+    let lockups: Vec<_> = [
+        AddressHashMode::SerializeP2PKH,
+        AddressHashMode::SerializeP2SH,
+        AddressHashMode::SerializeP2WPKH,
+    ]
+    .into_iter()
+    .zip(1..)
+    .map(|(hash_mode, auth_id)| {
+        let pox_addr = PoxAddress::from_legacy(hash_mode, stacker_addr.destruct().1);
+        let signature = make_signer_key_signature(
+            &pox_addr,
+            stacker_key,
+            reward_cycle,
+            &Pox4SignatureTopic::StackStx,
+            lock_period,
+            u128::MAX,
+            auth_id,
+        );
+        (
+            1024 * POX_THRESHOLD_STEPS_USTX,
+            pox_addr,
+            lock_period,
+            signer_key.clone(),
+            Some(signature),
+            u128::MAX,
+            auth_id,
+        )
+    })
+    .collect();
+
+    let start_nonce = get_account(&mut peer, &stacker_addr.clone().into()).nonce;
+    let txs = make_pox_4_lockups(stacker_key, start_nonce, tip_height, &lockups);
+    let nonces: Vec<_> = txs.iter().map(|tx| tx.get_origin_nonce()).collect();
+    assert_eq!(nonces, vec![start_nonce, start_nonce + 1, start_nonce + 2]);
+
+    // all of them are mined in the same tenure, even though only the first one can lock STX
+    let latest_block = peer.tenure_with_txs(&txs, &mut coinbase_nonce);
+    assert_eq!(
+        get_account(&mut peer, &stacker_addr.into()).nonce,
+        start_nonce + 3
+    );
+    let balances = balances_from_keys(&mut peer, &latest_block, &keys[0..1]);
+    assert_eq!(balances[0].amount_locked(), 1024 * POX_THRESHOLD_STEPS_USTX);
+}
+
+/// Test that pox3 methods fail once pox4 is activated
+#[test]
+fn pox_3_defunct() {
+    // Config for this test
+    // We are going to try locking for 2 reward cycles (10 blocks)
+    let lock_period = 2;
+    let (epochs, pox_constants) = make_test_epochs_pox(false);
+
+    let mut burnchain = Burnchain::default_unittest(
+        0,
+        &BurnchainHeaderHash::from_hex(BITCOIN_REGTEST_FIRST_BLOCK_HASH).unwrap(),
+    );
+    burnchain.pox_constants = pox_constants;
+
+    let observer = TestEventObserver::new();
+
+    let (mut peer, keys) = instantiate_pox_peer_with_epoch(
+        &burnchain,
+        function_name!(),
         Some(epochs.clone()),
         Some(&observer),
     );
@@ -1555,25 +2243,9 @@ fn pox_4_check_cycle_id_range_in_print_events_pool() {
     let in_prepare_phase = burnchain.is_in_prepare_phase(tip.block_height);
     assert!(!in_prepare_phase);
 
-    let blocks = observer.get_blocks();
-    let mut steph_txs = HashMap::new();
-    let mut alice_txs = HashMap::new();
-    let mut bob_txs = HashMap::new();
-
-    for b in blocks.into_iter() {
-        for r in b.receipts.into_iter() {
-            if let TransactionOrigin::Stacks(ref t) = r.transaction {
-                let addr = t.auth.origin().address_testnet();
-                if addr == steph_address {
-                    steph_txs.insert(t.auth.get_origin_nonce(), r);
-                } else if addr == alice_address {
-                    alice_txs.insert(t.auth.get_origin_nonce(), r);
-                } else if addr == bob_address {
-                    bob_txs.insert(t.auth.get_origin_nonce(), r);
-                }
-            }
-        }
-    }
+    let steph_txs = observer.receipts_by_sender(steph_address);
+    let alice_txs = observer.receipts_by_sender(alice_address);
+    let bob_txs = observer.receipts_by_sender(bob_address);
 
     assert_eq!(steph_txs.len() as u64, 3);
     assert_eq!(alice_txs.len() as u64, 1);
@@ -1950,25 +2622,9 @@ fn pox_4_check_cycle_id_range_in_print_events_pool_in_prepare_phase() {
     let in_prepare_phase = burnchain.is_in_prepare_phase(tip.block_height);
     assert!(in_prepare_phase);
 
-    let blocks = observer.get_blocks();
-    let mut steph_txs = HashMap::new();
-    let mut alice_txs = HashMap::new();
-    let mut bob_txs = HashMap::new();
-
-    for b in blocks.into_iter() {
-        for r in b.receipts.into_iter() {
-            if let TransactionOrigin::Stacks(ref t) = r.transaction {
-                let addr = t.auth.origin().address_testnet();
-                if addr == steph_address {
-                    steph_txs.insert(t.auth.get_origin_nonce(), r);
-                } else if addr == alice_address {
-                    alice_txs.insert(t.auth.get_origin_nonce(), r);
-                } else if addr == bob_address {
-                    bob_txs.insert(t.auth.get_origin_nonce(), r);
-                }
-            }
-        }
-    }
+    let steph_txs = observer.receipts_by_sender(steph_address);
+    let alice_txs = observer.receipts_by_sender(alice_address);
+    let bob_txs = observer.receipts_by_sender(bob_address);
 
     assert_eq!(steph_txs.len() as u64, 3);
     assert_eq!(alice_txs.len() as u64, 1);
@@ -2290,22 +2946,8 @@ fn pox_4_check_cycle_id_range_in_print_events_pool_in_prepare_phase_skip_cycle()
     let in_prepare_phase = burnchain.is_in_prepare_phase(tip.block_height);
     assert!(in_prepare_phase);
 
-    let blocks = observer.get_blocks();
-    let mut alice_txs = HashMap::new();
-    let mut bob_txs = HashMap::new();
-
-    for b in blocks.into_iter() {
-        for r in b.receipts.into_iter() {
-            if let TransactionOrigin::Stacks(ref t) = r.transaction {
-                let addr = t.auth.origin().address_testnet();
-                if addr == alice_address {
-                    alice_txs.insert(t.auth.get_origin_nonce(), r);
-                } else if addr == bob_address {
-                    bob_txs.insert(t.auth.get_origin_nonce(), r);
-                }
-            }
-        }
-    }
+    let alice_txs = observer.receipts_by_sender(alice_address);
+    let bob_txs = observer.receipts_by_sender(bob_address);
 
     assert_eq!(alice_txs.len() as u64, 1);
     assert_eq!(bob_txs.len() as u64, 2);
@@ -3279,62 +3921,691 @@ fn verify_signer_key_signatures() {
     assert_eq!(result, Value::okay_true());
 }
 
-#[apply(nakamoto_cases)]
-fn stack_stx_verify_signer_sig(use_nakamoto: bool) {
-    let lock_period = 2;
+/// The signature period for each topic in `verify_signer_key_signatures_all_topics`: the lock
+/// period for `stack-stx` and `stack-increase`, the extend count for `stack-extend`, and 1 for
+/// the aggregation topics. The match has no wildcard arm, so a new topic can't be added without
+/// a row here.
+fn signature_topic_test_period(topic: &Pox4SignatureTopic) -> u128 {
+    match topic {
+        Pox4SignatureTopic::StackStx => 2,
+        Pox4SignatureTopic::AggregationCommit => 1,
+        Pox4SignatureTopic::AggregationIncrease => 1,
+        Pox4SignatureTopic::StackExtend => 3,
+        Pox4SignatureTopic::StackIncrease => 2,
+    }
+}
+
+#[test]
+fn verify_signer_key_signatures_all_topics() {
+    let (epochs, pox_constants) = make_test_epochs_pox(false);
+
+    let mut burnchain = Burnchain::default_unittest(
+        0,
+        &BurnchainHeaderHash::from_hex(BITCOIN_REGTEST_FIRST_BLOCK_HASH).unwrap(),
+    );
+    burnchain.pox_constants = pox_constants;
+
     let observer = TestEventObserver::new();
-    let (burnchain, mut peer, keys, latest_block, block_height, coinbase_nonce, mut test_signers) =
-        prepare_pox4_test(function_name!(), Some(&observer), use_nakamoto);
 
-    let mut coinbase_nonce = coinbase_nonce;
+    let (mut peer, mut keys) = instantiate_pox_peer_with_epoch(
+        &burnchain,
+        function_name!(),
+        Some(epochs),
+        Some(&observer),
+    );
 
-    let mut stacker_nonce = 0;
-    let stacker_key = &keys[0];
-    let min_ustx = get_stacking_minimum(&mut peer, &latest_block);
-    let stacker_addr = key_to_stacks_addr(stacker_key);
-    let signer_key = &keys[1];
-    let signer_public_key = StacksPublicKey::from_private(signer_key);
-    let pox_addr = pox_addr_from(stacker_key);
+    let mut coinbase_nonce = 0;
+    let mut latest_block;
 
-    let second_stacker = &keys[2];
-    let second_stacker_addr = key_to_stacks_addr(second_stacker);
-    let second_stacker_pox_addr = PoxAddress::from_legacy(
+    let signer = keys.pop().unwrap();
+    let signer_public_key = StacksPublicKey::from_private(&signer);
+    let pox_addr = PoxAddress::from_legacy(
         AddressHashMode::SerializeP2PKH,
-        second_stacker_addr.bytes().clone(),
+        key_to_stacks_addr(&signer).bytes().clone(),
     );
 
-    let reward_cycle = get_current_reward_cycle(&peer, &burnchain);
+    // Advance into pox4
+    let target_height = burnchain.pox_constants.pox_4_activation_height;
+    while get_tip(peer.sortdb.as_ref()).block_height < u64::from(target_height) {
+        latest_block = peer.tenure_with_txs(&[], &mut coinbase_nonce);
+    }
+    latest_block = peer.tenure_with_txs(&[], &mut coinbase_nonce);
 
-    let topic = Pox4SignatureTopic::StackStx;
+    let reward_cycle = get_current_reward_cycle(&peer, &burnchain);
+    let expected_error = Value::error(Value::Int(35)).unwrap();
 
-    // Test 1: invalid reward cycle
-    let signature = make_signer_key_signature(
-        &pox_addr,
-        signer_key,
-        reward_cycle - 1,
-        &topic,
-        lock_period,
-        u128::MAX,
-        1,
-    );
-    let invalid_cycle_nonce = stacker_nonce;
-    let invalid_cycle_stack = make_pox_4_lockup(
-        stacker_key,
-        stacker_nonce,
-        min_ustx,
-        &pox_addr,
-        lock_period,
-        &signer_public_key,
-        block_height,
-        Some(signature),
-        u128::MAX,
-        1,
-    );
+    for topic in Pox4SignatureTopic::ALL.iter() {
+        let period = signature_topic_test_period(topic);
 
-    // test 2: invalid pox addr
-    stacker_nonce += 1;
-    let signature = make_signer_key_signature(
-        &second_stacker_pox_addr,
+        // valid signature
+        let signature = make_signer_key_signature(
+            &pox_addr,
+            &signer,
+            reward_cycle,
+            topic,
+            period,
+            u128::MAX,
+            1,
+        );
+        let result = verify_signer_key_sig(
+            &signature,
+            &signer_public_key,
+            &pox_addr,
+            &mut peer,
+            &latest_block,
+            reward_cycle,
+            period,
+            topic,
+            1,
+            u128::MAX,
+            1,
+        );
+        assert_eq!(
+            result,
+            Value::okay_true(),
+            "Valid {} signature should be accepted",
+            topic.get_name_str()
+        );
+
+        // signature for the wrong reward cycle
+        let signature = make_signer_key_signature(
+            &pox_addr,
+            &signer,
+            reward_cycle - 1,
+            topic,
+            period,
+            u128::MAX,
+            1,
+        );
+        let result = verify_signer_key_sig(
+            &signature,
+            &signer_public_key,
+            &pox_addr,
+            &mut peer,
+            &latest_block,
+            reward_cycle,
+            period,
+            topic,
+            1,
+            u128::MAX,
+            1,
+        );
+        assert_eq!(
+            result,
+            expected_error,
+            "{} signature for the wrong reward cycle should be rejected",
+            topic.get_name_str()
+        );
+    }
+}
+
+/// Test that `make_signer_key_signatures_batch` produces, in order, signatures that pox-4
+/// accepts for several topics across several reward cycles
+#[test]
+fn verify_signer_key_signatures_batch() {
+    let (epochs, pox_constants) = make_test_epochs_pox(false);
+
+    let mut burnchain = Burnchain::default_unittest(
+        0,
+        &BurnchainHeaderHash::from_hex(BITCOIN_REGTEST_FIRST_BLOCK_HASH).unwrap(),
+    );
+    burnchain.pox_constants = pox_constants;
+
+    let (mut peer, mut keys) =
+        instantiate_pox_peer_with_epoch(&burnchain, function_name!(), Some(epochs), None);
+
+    let mut coinbase_nonce = 0;
+    let mut latest_block;
+
+    let signer = keys.pop().unwrap();
+    let signer_public_key = StacksPublicKey::from_private(&signer);
+    let pox_addr = PoxAddress::from_legacy(
+        AddressHashMode::SerializeP2PKH,
+        key_to_stacks_addr(&signer).bytes().clone(),
+    );
+
+    // Advance into pox4
+    let target_height = burnchain.pox_constants.pox_4_activation_height;
+    while get_tip(peer.sortdb.as_ref()).block_height < u64::from(target_height) {
+        latest_block = peer.tenure_with_txs(&[], &mut coinbase_nonce);
+    }
+    latest_block = peer.tenure_with_txs(&[], &mut coinbase_nonce);
+
+    let reward_cycle = get_current_reward_cycle(&peer, &burnchain);
+    let mut entries = vec![];
+    for cycle in reward_cycle..reward_cycle + 3 {
+        for topic in [
+            Pox4SignatureTopic::StackStx,
+            Pox4SignatureTopic::StackExtend,
+            Pox4SignatureTopic::AggregationCommit,
+        ] {
+            let period = signature_topic_test_period(&topic);
+            let auth_id = u128::try_from(entries.len()).unwrap();
+            entries.push((pox_addr.clone(), cycle, topic, period, u128::MAX, auth_id));
+        }
+    }
+
+    let signatures =
+        make_signer_key_signatures_batch(&signer, CHAIN_ID_TESTNET, entries.clone()).unwrap();
+    assert_eq!(signatures.len(), entries.len());
+
+    for (signature, (pox_addr, cycle, topic, period, max_amount, auth_id)) in
+        signatures.iter().zip(entries.iter())
+    {
+        // the same signature as signing the entry alone
+        assert_eq!(
+            signature,
+            &make_signer_key_signature(
+                pox_addr,
+                &signer,
+                *cycle,
+                topic,
+                *period,
+                *max_amount,
+                *auth_id
+            )
+        );
+        let result = verify_signer_key_sig(
+            signature,
+            &signer_public_key,
+            pox_addr,
+            &mut peer,
+            &latest_block,
+            *cycle,
+            *period,
+            topic,
+            1,
+            *max_amount,
+            *auth_id,
+        );
+        assert_eq!(
+            result,
+            Value::okay_true(),
+            "{} signature for cycle {cycle} should be accepted",
+            topic.get_name_str()
+        );
+    }
+
+    // a zero period fails the whole batch
+    entries[4].3 = 0;
+    let err = make_signer_key_signatures_batch(&signer, CHAIN_ID_TESTNET, entries).unwrap_err();
+    assert!(err.contains("Entry 4"), "Unexpected error: {err}");
+    assert!(err.contains("zero period"), "Unexpected error: {err}");
+}
+
+/// Count the distinct signer key authorizations of `signer_key` that were consumed for
+/// `reward_cycle`, as of `tip`.
+///
+/// `used-signer-key-authorizations` can't be enumerated, so the candidate authorizations come
+/// from the pox-4 events of the transactions in `blocks`. A candidate is counted if the map has
+/// it marked as used at `tip`, so blocks that are not ancestors of `tip` don't count.
+pub fn count_used_authorizations(
+    peer: &mut TestPeer,
+    blocks: &[TestEventObserverBlock],
+    tip: &StacksBlockId,
+    signer_key: &Secp256k1PublicKey,
+    reward_cycle: u128,
+) -> u128 {
+    let pox_contract_id = boot_code_id(boot::POX_4_NAME, false);
+    let signer_key_val = Value::buff_from(signer_key.to_bytes_compressed()).unwrap();
+
+    // `Value` isn't `Hash`, so dedupe by hand
+    let mut candidates: Vec<Value> = vec![];
+    let events = blocks
+        .iter()
+        .flat_map(|block| block.receipts.iter())
+        .flat_map(|receipt| receipt.events.iter());
+    for event in events {
+        let StacksTransactionEvent::SmartContractEvent(event_data) = event else {
+            continue;
+        };
+        if event_data.key.0 != pox_contract_id || event_data.key.1 != "print" {
+            continue;
+        }
+        let Some(event_tuple) = event_data
+            .value
+            .clone()
+            .expect_result_ok()
+            .ok()
+            .and_then(|value| value.expect_tuple().ok())
+        else {
+            continue;
+        };
+        let Some(name) = event_tuple
+            .get("name")
+            .ok()
+            .and_then(|name| name.clone().expect_ascii().ok())
+        else {
+            continue;
+        };
+        let Some(data) = event_tuple
+            .get("data")
+            .ok()
+            .and_then(|data| data.clone().expect_tuple().ok())
+        else {
+            continue;
+        };
+        if data.get("signer-key").ok() != Some(&signer_key_val) {
+            continue;
+        }
+
+        // the topic and period each function consumes the authorization with
+        let (topic, periods) = match name.as_str() {
+            "stack-stx" => (
+                Pox4SignatureTopic::StackStx,
+                vec![data.get("lock-period").unwrap().clone()],
+            ),
+            "stack-extend" => (
+                Pox4SignatureTopic::StackExtend,
+                vec![data.get("extend-count").unwrap().clone()],
+            ),
+            // the period is the stacker's lock period at the time, which the event doesn't have
+            "stack-increase" => (
+                Pox4SignatureTopic::StackIncrease,
+                (1..=12).map(Value::UInt).collect(),
+            ),
+            "stack-aggregation-commit" | "stack-aggregation-commit-indexed" => {
+                (Pox4SignatureTopic::AggregationCommit, vec![Value::UInt(1)])
+            }
+            "stack-aggregation-increase" => (
+                Pox4SignatureTopic::AggregationIncrease,
+                vec![Value::UInt(1)],
+            ),
+            _ => continue,
+        };
+        for period in periods {
+            let key = TupleData::from_data(vec![
+                ("signer-key".into(), signer_key_val.clone()),
+                ("reward-cycle".into(), Value::UInt(reward_cycle)),
+                ("period".into(), period),
+                (
+                    "topic".into(),
+                    Value::string_ascii_from_bytes(topic.get_name_str().into()).unwrap(),
+                ),
+                ("pox-addr".into(), data.get("pox-addr").unwrap().clone()),
+                ("auth-id".into(), data.get("auth-id").unwrap().clone()),
+                ("max-amount".into(), data.get("max-amount").unwrap().clone()),
+            ])
+            .unwrap();
+            let key = Value::Tuple(key);
+            if !candidates.contains(&key) {
+                candidates.push(key);
+            }
+        }
+    }
+
+    let used = with_clarity_db_ro(peer, tip, |db| {
+        let epoch = db.get_clarity_epoch_version().unwrap();
+        candidates
+            .iter()
+            .filter(|key| {
+                db.fetch_entry_unknown_descriptor(
+                    &pox_contract_id,
+                    "used-signer-key-authorizations",
+                    key,
+                    &epoch,
+                )
+                .unwrap()
+                .expect_optional()
+                .unwrap()
+                .is_some()
+            })
+            .count()
+    });
+    u128::try_from(used).unwrap()
+}
+
+/// List the `signer-key-authorizations` entries of `signer_key` at `tip`, as
+/// `(pox_addr, reward_cycle, topic, period, max_amount, auth_id, enabled)`.
+///
+/// `signer-key-authorizations` can't be enumerated, so the candidate entries come from the
+/// `set-signer-key-authorization` calls in `blocks`. Each candidate is listed once, with its
+/// value at `tip`, and candidates the map doesn't have at `tip` are left out.
+pub fn list_signer_key_authorizations_for_signer(
+    peer: &mut TestPeer,
+    blocks: &[TestEventObserverBlock],
+    tip: &StacksBlockId,
+    signer_key: &StacksPublicKey,
+) -> Vec<(PoxAddress, u64, Pox4SignatureTopic, u128, u128, u128, bool)> {
+    let pox_contract_id = boot_code_id(boot::POX_4_NAME, false);
+    let signer_key_val = Value::buff_from(signer_key.to_bytes_compressed()).unwrap();
+
+    let mut candidates = vec![];
+    let contract_calls = blocks
+        .iter()
+        .flat_map(|block| block.receipts.iter())
+        .filter_map(|receipt| match &receipt.transaction {
+            TransactionOrigin::Stacks(tx) => match &tx.payload {
+                TransactionPayload::ContractCall(contract_call) => Some(contract_call),
+                _ => None,
+            },
+            _ => None,
+        });
+    for contract_call in contract_calls {
+        if contract_call.to_clarity_contract_id() != pox_contract_id
+            || contract_call.function_name.as_str() != "set-signer-key-authorization"
+        {
+            continue;
+        }
+        // (pox-addr, period, reward-cycle, topic, signer-key, allowed, max-amount, auth-id)
+        let [pox_addr, period, reward_cycle, topic, call_signer_key, _, max_amount, auth_id] =
+            contract_call.function_args.as_slice()
+        else {
+            continue;
+        };
+        if call_signer_key != &signer_key_val {
+            continue;
+        }
+        let (
+            Some(pox_addr),
+            Value::UInt(period),
+            Value::UInt(reward_cycle),
+            Some(topic),
+            Value::UInt(max_amount),
+            Value::UInt(auth_id),
+        ) = (
+            PoxAddress::try_from_pox_tuple(false, pox_addr),
+            period,
+            reward_cycle,
+            topic
+                .clone()
+                .expect_ascii()
+                .ok()
+                .and_then(|name| Pox4SignatureTopic::lookup_by_name(&name)),
+            max_amount,
+            auth_id,
+        )
+        else {
+            continue;
+        };
+        let candidate = (
+            pox_addr,
+            *reward_cycle,
+            topic,
+            *period,
+            *max_amount,
+            *auth_id,
+        );
+        if !candidates.contains(&candidate) {
+            candidates.push(candidate);
+        }
+    }
+
+    with_clarity_db_ro(peer, tip, |db| {
+        let epoch = db.get_clarity_epoch_version().unwrap();
+        candidates
+            .into_iter()
+            .filter_map(
+                |(pox_addr, reward_cycle, topic, period, max_amount, auth_id)| {
+                    let lookup_tuple = make_signer_key_authorization_lookup_key(
+                        &pox_addr,
+                        reward_cycle,
+                        &topic,
+                        period,
+                        signer_key,
+                        max_amount,
+                        auth_id,
+                    );
+                    let enabled = db
+                        .fetch_entry_unknown_descriptor(
+                            &pox_contract_id,
+                            "signer-key-authorizations",
+                            &lookup_tuple,
+                            &epoch,
+                        )
+                        .unwrap()
+                        .expect_optional()
+                        .unwrap()?
+                        .expect_bool()
+                        .unwrap();
+                    Some((
+                        pox_addr,
+                        reward_cycle,
+                        topic,
+                        period,
+                        max_amount,
+                        auth_id,
+                        enabled,
+                    ))
+                },
+            )
+            .collect()
+    })
+}
+
+/// Test that `count_used_authorizations` counts each authorization a signer key had consumed in
+/// a reward cycle
+#[apply(nakamoto_cases)]
+fn count_used_authorizations_of_signer(use_nakamoto: bool) {
+    let observer = TestEventObserver::new();
+    let (
+        burnchain,
+        mut peer,
+        keys,
+        latest_block,
+        block_height,
+        mut coinbase_nonce,
+        mut test_signers,
+    ) = prepare_pox4_test(function_name!(), Some(&observer), use_nakamoto);
+
+    let min_ustx = get_stacking_minimum(&mut peer, &latest_block);
+    let signer_key = &keys[1];
+    let signer_public_key = StacksPublicKey::from_private(signer_key);
+    let reward_cycle = get_current_reward_cycle(&peer, &burnchain);
+    let lock_period = 2;
+
+    // two stackers use the same signer key, each with its own authorization
+    let txs: Vec<_> = [(&keys[0], 1), (&keys[2], 2)]
+        .into_iter()
+        .map(|(stacker_key, auth_id)| {
+            let pox_addr = pox_addr_from(stacker_key);
+            let signature = make_signer_key_signature(
+                &pox_addr,
+                signer_key,
+                reward_cycle,
+                &Pox4SignatureTopic::StackStx,
+                lock_period,
+                u128::MAX,
+                auth_id,
+            );
+            make_pox_4_lockup(
+                stacker_key,
+                0,
+                min_ustx,
+                &pox_addr,
+                lock_period,
+                &signer_public_key,
+                block_height,
+                Some(signature),
+                u128::MAX,
+                auth_id,
+            )
+        })
+        .collect();
+
+    let latest_block = tenure_with_txs(&mut peer, &txs, &mut coinbase_nonce, &mut test_signers);
+    let blocks = observer.get_blocks();
+
+    assert_eq!(
+        count_used_authorizations(
+            &mut peer,
+            &blocks,
+            &latest_block,
+            &signer_public_key,
+            reward_cycle
+        ),
+        2
+    );
+    // nothing was consumed for the next cycle
+    assert_eq!(
+        count_used_authorizations(
+            &mut peer,
+            &blocks,
+            &latest_block,
+            &signer_public_key,
+            reward_cycle + 1
+        ),
+        0
+    );
+    // nor for another signer key
+    assert_eq!(
+        count_used_authorizations(
+            &mut peer,
+            &blocks,
+            &latest_block,
+            &StacksPublicKey::from_private(&keys[3]),
+            reward_cycle
+        ),
+        0
+    );
+}
+
+/// Test that `list_signer_key_authorizations_for_signer` lists every authorization set for a
+/// signer key, with its enabled flag
+#[apply(nakamoto_cases)]
+fn list_signer_key_authorizations_of_signer(use_nakamoto: bool) {
+    let observer = TestEventObserver::new();
+    let (burnchain, mut peer, keys, _, _, mut coinbase_nonce, mut test_signers) =
+        prepare_pox4_test(function_name!(), Some(&observer), use_nakamoto);
+
+    let signer_key = &keys[1];
+    let signer_public_key = StacksPublicKey::from_private(signer_key);
+    let other_signer_key = &keys[2];
+    let pox_addr = pox_addr_from(signer_key);
+    let reward_cycle = get_current_reward_cycle(&peer, &burnchain);
+    let lock_period = 2;
+
+    let txs = vec![
+        make_pox_4_set_signer_key_auth(
+            &pox_addr,
+            signer_key,
+            reward_cycle,
+            &Pox4SignatureTopic::StackStx,
+            lock_period,
+            true,
+            0,
+            None,
+            u128::MAX,
+            1,
+        ),
+        make_pox_4_set_signer_key_auth(
+            &pox_addr,
+            signer_key,
+            reward_cycle + 1,
+            &Pox4SignatureTopic::AggregationCommit,
+            1,
+            true,
+            1,
+            None,
+            1_000_000,
+            2,
+        ),
+        // an authorization for another signer key isn't listed
+        make_pox_4_set_signer_key_auth(
+            &pox_addr_from(other_signer_key),
+            other_signer_key,
+            reward_cycle,
+            &Pox4SignatureTopic::StackStx,
+            lock_period,
+            true,
+            0,
+            None,
+            u128::MAX,
+            3,
+        ),
+    ];
+
+    let latest_block = tenure_with_txs(&mut peer, &txs, &mut coinbase_nonce, &mut test_signers);
+    let blocks = observer.get_blocks();
+
+    let authorizations = list_signer_key_authorizations_for_signer(
+        &mut peer,
+        &blocks,
+        &latest_block,
+        &signer_public_key,
+    );
+    let reward_cycle = u64::try_from(reward_cycle).unwrap();
+    assert_eq!(
+        authorizations,
+        vec![
+            (
+                pox_addr.clone(),
+                reward_cycle,
+                Pox4SignatureTopic::StackStx,
+                lock_period,
+                u128::MAX,
+                1,
+                true
+            ),
+            (
+                pox_addr,
+                reward_cycle + 1,
+                Pox4SignatureTopic::AggregationCommit,
+                1,
+                1_000_000,
+                2,
+                true
+            ),
+        ]
+    );
+}
+
+#[apply(nakamoto_cases)]
+fn stack_stx_verify_signer_sig(use_nakamoto: bool) {
+    let lock_period = 2;
+    let observer = TestEventObserver::new();
+    let (burnchain, mut peer, keys, latest_block, block_height, coinbase_nonce, mut test_signers) =
+        prepare_pox4_test(function_name!(), Some(&observer), use_nakamoto);
+
+    let mut coinbase_nonce = coinbase_nonce;
+
+    let mut stacker_nonce = 0;
+    let stacker_key = &keys[0];
+    let min_ustx = get_stacking_minimum(&mut peer, &latest_block);
+    let stacker_addr = key_to_stacks_addr(stacker_key);
+    let signer_key = &keys[1];
+    let signer_public_key = StacksPublicKey::from_private(signer_key);
+    let pox_addr = pox_addr_from(stacker_key);
+
+    let second_stacker = &keys[2];
+    let second_stacker_addr = key_to_stacks_addr(second_stacker);
+    let second_stacker_pox_addr = PoxAddress::from_legacy(
+        AddressHashMode::SerializeP2PKH,
+        second_stacker_addr.bytes().clone(),
+    );
+
+    let reward_cycle = get_current_reward_cycle(&peer, &burnchain);
+
+    let topic = Pox4SignatureTopic::StackStx;
+
+    // Test 1: invalid reward cycle
+    let signature = make_signer_key_signature(
+        &pox_addr,
+        signer_key,
+        reward_cycle - 1,
+        &topic,
+        lock_period,
+        u128::MAX,
+        1,
+    );
+    let invalid_cycle_nonce = stacker_nonce;
+    let invalid_cycle_stack = make_pox_4_lockup(
+        stacker_key,
+        stacker_nonce,
+        min_ustx,
+        &pox_addr,
+        lock_period,
+        &signer_public_key,
+        block_height,
+        Some(signature),
+        u128::MAX,
+        1,
+    );
+
+    // test 2: invalid pox addr
+    stacker_nonce += 1;
+    let signature = make_signer_key_signature(
+        &second_stacker_pox_addr,
         signer_key,
         reward_cycle,
         &topic,
@@ -3590,7 +4861,7 @@ fn stack_stx_verify_signer_sig(use_nakamoto: bool) {
         &mut peer,
         &latest_block,
         &pox_addr,
-        reward_cycle.try_into().unwrap(),
+        reward_cycle,
         &topic,
         lock_period,
         &signer_public_key,
@@ -3842,7 +5113,7 @@ fn stack_extend_verify_sig() {
         &mut peer,
         &latest_block,
         &pox_addr,
-        reward_cycle.try_into().unwrap(),
+        reward_cycle,
         &topic,
         lock_period,
         &signer_public_key,
@@ -4175,7 +5446,7 @@ fn stack_agg_commit_verify_sig() {
         &mut peer,
         &latest_block,
         &pox_addr,
-        next_reward_cycle.try_into().unwrap(),
+        next_reward_cycle,
         &topic,
         1,
         &signer_pk,
@@ -4255,6 +5526,131 @@ fn advance_to_block_height(
     (latest_block, tx_block, tx_block_receipts)
 }
 
+/// A point in a reward cycle for `advance_to_reward_phase` to stop at
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum RewardPhaseTarget {
+    /// The first burn block of the cycle's reward phase, i.e. its mod 1 block
+    CycleStart(u64),
+    /// The first burn block of the prepare phase that precedes the cycle
+    PrepareStart(u64),
+    /// The given number of burn blocks past the cycle's first (mod 0) block
+    BlocksIntoCycle(u64, u64),
+}
+
+impl RewardPhaseTarget {
+    /// The burn height of this target
+    pub fn burn_height(&self, burnchain: &Burnchain) -> u64 {
+        match self {
+            Self::CycleStart(cycle) => burnchain.reward_cycle_to_block_height(*cycle),
+            Self::PrepareStart(cycle) => {
+                let prepare_length = u64::from(burnchain.pox_constants.prepare_length);
+                // the prepare phase ends with the cycle's mod 0 block
+                (burnchain.nakamoto_first_block_of_cycle(*cycle) + 1)
+                    .checked_sub(prepare_length)
+                    .expect("Prepare phase starts before the first burn block")
+            }
+            Self::BlocksIntoCycle(cycle, blocks) => {
+                assert!(
+                    *blocks < u64::from(burnchain.pox_constants.reward_cycle_length),
+                    "{blocks} blocks is past the end of cycle {cycle}"
+                );
+                burnchain.nakamoto_first_block_of_cycle(*cycle) + blocks
+            }
+        }
+    }
+}
+
+/// Mine empty tenures until the burnchain tip is at `target`, and return the resulting Stacks
+/// tip. Panics if the burnchain tip is already at or past `target`.
+pub fn advance_to_reward_phase(
+    peer: &mut TestPeer,
+    burnchain: &Burnchain,
+    coinbase_nonce: &mut usize,
+    target: RewardPhaseTarget,
+    test_signers: &mut Option<TestSigners>,
+) -> StacksBlockId {
+    let target_height = target.burn_height(burnchain);
+    let mut latest_block = None;
+    while get_tip(peer.sortdb.as_ref()).block_height < target_height {
+        latest_block = Some(tenure_with_txs(peer, &[], coinbase_nonce, test_signers));
+    }
+    latest_block.unwrap_or_else(|| {
+        panic!("Burnchain tip is already at or past {target:?} (height {target_height})")
+    })
+}
+
+#[apply(nakamoto_cases)]
+fn advance_to_reward_phase_targets(use_nakamoto: bool) {
+    let (burnchain, mut peer, _, _, _, mut coinbase_nonce, mut test_signers) =
+        prepare_pox4_test(function_name!(), None, use_nakamoto);
+    // far enough ahead that the tip can't already be in its prepare phase
+    let cycle = u64::try_from(get_current_reward_cycle(&peer, &burnchain)).unwrap() + 2;
+    let tip_height = |peer: &TestPeer| get_tip(peer.sortdb.as_ref()).block_height;
+
+    advance_to_reward_phase(
+        &mut peer,
+        &burnchain,
+        &mut coinbase_nonce,
+        RewardPhaseTarget::PrepareStart(cycle),
+        &mut test_signers,
+    );
+    let height = tip_height(&peer);
+    assert!(burnchain.is_in_prepare_phase(height));
+    assert!(!burnchain.is_in_prepare_phase(height - 1));
+    assert_eq!(
+        burnchain.block_height_to_reward_cycle(height),
+        Some(cycle - 1)
+    );
+
+    advance_to_reward_phase(
+        &mut peer,
+        &burnchain,
+        &mut coinbase_nonce,
+        RewardPhaseTarget::CycleStart(cycle),
+        &mut test_signers,
+    );
+    let height = tip_height(&peer);
+    assert!(burnchain.is_reward_cycle_start(height));
+    assert!(!burnchain.is_in_prepare_phase(height));
+    assert!(burnchain.is_in_prepare_phase(height - 1));
+    assert_eq!(burnchain.block_height_to_reward_cycle(height), Some(cycle));
+
+    // the first block of a cycle is the last block of its prepare phase
+    advance_to_reward_phase(
+        &mut peer,
+        &burnchain,
+        &mut coinbase_nonce,
+        RewardPhaseTarget::BlocksIntoCycle(cycle + 1, 0),
+        &mut test_signers,
+    );
+    let height = tip_height(&peer);
+    assert!(burnchain.is_in_prepare_phase(height));
+    assert_eq!(
+        burnchain.block_height_to_reward_cycle(height),
+        Some(cycle + 1)
+    );
+
+    let latest_block = advance_to_reward_phase(
+        &mut peer,
+        &burnchain,
+        &mut coinbase_nonce,
+        RewardPhaseTarget::BlocksIntoCycle(cycle + 1, 2),
+        &mut test_signers,
+    );
+    let height = tip_height(&peer);
+    assert_eq!(
+        height,
+        burnchain.reward_cycle_to_block_height(cycle + 1) + 1
+    );
+    assert!(!burnchain.is_in_prepare_phase(height));
+    assert_eq!(
+        burnchain.block_height_to_reward_cycle(height),
+        Some(cycle + 1)
+    );
+    // the returned block can be read from
+    assert!(get_stacking_minimum(&mut peer, &latest_block) > 0);
+}
+
 #[test]
 /// Test for verifying that the stacker aggregation works as expected
 ///   with new signature parameters. In this test Alice is the service signer,
@@ -5090,6 +6486,116 @@ fn stack_increase_different_signer_keys(use_nakamoto: bool) {
     assert_eq!(increase_result, Value::error(Value::Int(40)).unwrap())
 }
 
+#[apply(nakamoto_cases)]
+/// Verify that a `stack-increase` signed for the period computed by
+/// `compute_stack_increase_period` succeeds after a `stack-stx` and a `stack-extend`
+fn stack_increase_after_extend_with_computed_period(use_nakamoto: bool) {
+    let lock_period = 1;
+    let observer = TestEventObserver::new();
+    let (burnchain, mut peer, keys, latest_block, block_height, coinbase_nonce, mut test_signers) =
+        prepare_pox4_test(function_name!(), Some(&observer), use_nakamoto);
+
+    let mut coinbase_nonce = coinbase_nonce;
+
+    let mut stacker_nonce = 0;
+    let stacker_key = &keys[0];
+    let min_ustx = get_stacking_minimum(&mut peer, &latest_block);
+    let stacker_addr = key_to_stacks_addr(stacker_key);
+    let signer_sk = &keys[1];
+    let signer_pk = StacksPublicKey::from_private(signer_sk);
+    let pox_addr = pox_addr_from(signer_sk);
+
+    let reward_cycle = get_current_reward_cycle(&peer, &burnchain);
+
+    // Setup: stack-stx and stack-extend, with the same signer
+    let signature = make_signer_key_signature(
+        &pox_addr,
+        signer_sk,
+        reward_cycle,
+        &Pox4SignatureTopic::StackStx,
+        lock_period,
+        u128::MAX,
+        1,
+    );
+    let stack_tx = make_pox_4_lockup(
+        stacker_key,
+        stacker_nonce,
+        min_ustx,
+        &pox_addr,
+        lock_period,
+        &signer_pk,
+        block_height,
+        Some(signature),
+        u128::MAX,
+        1,
+    );
+
+    stacker_nonce += 1;
+    let signature = make_signer_key_signature(
+        &pox_addr,
+        signer_sk,
+        reward_cycle,
+        &Pox4SignatureTopic::StackExtend,
+        lock_period,
+        u128::MAX,
+        2,
+    );
+    let extend_tx = make_pox_4_extend(
+        stacker_key,
+        stacker_nonce,
+        pox_addr.clone(),
+        lock_period,
+        signer_pk.clone(),
+        Some(signature),
+        u128::MAX,
+        2,
+    );
+
+    let latest_block = tenure_with_txs(
+        &mut peer,
+        &[stack_tx, extend_tx],
+        &mut coinbase_nonce,
+        &mut test_signers,
+    );
+
+    let stacking_state =
+        get_stacking_state_pox_4(&mut peer, &latest_block, &stacker_addr.clone().into())
+            .expect("No stacking state, stack-stx failed")
+            .expect_tuple()
+            .unwrap();
+    // 1 cycle from stack-stx, and 1 from stack-extend
+    assert_eq!(compute_stack_increase_period(&stacking_state), 2);
+
+    stacker_nonce += 1;
+    let reward_cycle = get_current_reward_cycle(&peer, &burnchain);
+    let stack_increase = make_pox_4_stack_increase_for_state(
+        stacker_key,
+        stacker_nonce,
+        &stacking_state,
+        reward_cycle,
+        min_ustx,
+        signer_sk,
+        u128::MAX,
+        3,
+    );
+
+    tenure_with_txs(
+        &mut peer,
+        &[stack_increase],
+        &mut coinbase_nonce,
+        &mut test_signers,
+    );
+
+    // the stack-increase is the only tx from the stacker in the last block
+    let txs = get_last_block_sender_transactions(&observer, stacker_addr);
+    assert_eq!(txs.len(), 1);
+    txs[0]
+        .result
+        .clone()
+        .expect_result_ok()
+        .expect("Expected ok result from stack-increase");
+}
+
 pub fn assert_latest_was_burn(peer: &mut TestPeer) {
     let tip = get_tip(peer.sortdb.as_ref());
     let tip_index_block = tip.get_canonical_stacks_block_id();
@@ -5123,43 +6629,280 @@ pub fn assert_latest_was_burn(peer: &mut TestPeer) {
     }
 }
 
-fn assert_latest_was_pox(peer: &mut TestPeer) -> Vec<PoxAddress> {
-    let tip = get_tip(peer.sortdb.as_ref());
-    let tip_index_block = tip.get_canonical_stacks_block_id();
-    let burn_height = tip.block_height - 1;
+fn assert_latest_was_pox(peer: &mut TestPeer) -> Vec<PoxAddress> {
+    let tip = get_tip(peer.sortdb.as_ref());
+    let tip_index_block = tip.get_canonical_stacks_block_id();
+    let burn_height = tip.block_height - 1;
+
+    let conn = peer.sortdb().conn();
+
+    // check the *parent* burn block, because that's what we'll be
+    //  checking with get_burn_pox_addr_info
+    let mut burn_ops =
+        SortitionDB::get_block_commits_by_block(conn, &tip.parent_sortition_id).unwrap();
+    assert_eq!(burn_ops.len(), 1);
+    let commit = burn_ops.pop().unwrap();
+    assert!(!commit.all_outputs_burn());
+    let commit_addrs = commit.commit_outs;
+
+    let (addrs, payout) = get_burn_pox_addr_info(peer);
+    info!(
+        "Checking pox outputs at burn_height = {burn_height}, commit_addrs = {commit_addrs:?}, fetch_addrs = {addrs:?}"
+    );
+    assert_eq!(addrs.len(), 2);
+    assert_eq!(payout, 500);
+    assert!(commit_addrs.contains(&addrs[0]));
+    assert!(commit_addrs.contains(&addrs[1]));
+    addrs
+}
+
+fn balances_from_keys(
+    peer: &mut TestPeer,
+    tip: &StacksBlockId,
+    keys: &[Secp256k1PrivateKey],
+) -> Vec<STXBalance> {
+    let principals: Vec<_> = keys
+        .iter()
+        .map(key_to_stacks_addr)
+        .map(PrincipalData::from)
+        .collect();
+    get_stx_accounts_at(peer, tip, &principals)
+}
+
+#[apply(nakamoto_cases)]
+fn make_pox_4_stack_stx_signed_p2wsh(use_nakamoto: bool) {
+    let lock_period = 2;
+    let (
+        burnchain,
+        mut peer,
+        keys,
+        latest_block,
+        block_height,
+        mut coinbase_nonce,
+        mut test_signers,
+    ) = prepare_pox4_test(function_name!(), None, use_nakamoto);
+
+    let alice_key = &keys[0];
+    let alice_principal = PrincipalData::from(key_to_stacks_addr(alice_key));
+    let signer_key = StacksPrivateKey::from_seed(&[3]);
+    let pox_addr = PoxAddress::Addr32(false, PoxAddressType32::P2WSH, [0x05; 32]);
+    let min_ustx = get_stacking_minimum(&mut peer, &latest_block);
+    let next_reward_cycle = burnchain
+        .block_height_to_reward_cycle(block_height)
+        .unwrap()
+        + 1;
+
+    let stack_stx = make_pox_4_stack_stx_signed(
+        alice_key,
+        &mut peer,
+        &pox_addr,
+        min_ustx,
+        lock_period,
+        &signer_key,
+        u128::MAX,
+        1,
+    );
+    let latest_block = tenure_with_txs(
+        &mut peer,
+        &[stack_stx],
+        &mut coinbase_nonce,
+        &mut test_signers,
+    );
+
+    let alice_account = get_stx_account_at(&mut peer, &latest_block, &alice_principal);
+    assert_eq!(alice_account.amount_locked(), min_ustx);
+    let expected_unlock_height = burnchain
+        .reward_cycle_to_block_height(next_reward_cycle + u64::try_from(lock_period).unwrap())
+        - 1;
+    assert_eq!(alice_account.unlock_height(), expected_unlock_height);
+
+    // the reward set entry carries the signer key, not the stacker's key
+    let reward_cycle_ht = burnchain.reward_cycle_to_block_height(next_reward_cycle);
+    let reward_set = get_reward_set_entries_at(&mut peer, &latest_block, reward_cycle_ht);
+    let reward_entry = reward_set
+        .iter()
+        .find(|entry| entry.reward_address == pox_addr)
+        .expect("No reward entry found");
+    let signer_bytes = StacksPublicKey::from_private(&signer_key).to_bytes_compressed();
+    assert_eq!(&reward_entry.signer.unwrap(), &signer_bytes.as_slice());
+}
+
+#[test]
+fn get_stx_accounts_at_matches_get_stx_account_at() {
+    let (epochs, pox_constants) = make_test_epochs_pox(false);
+    let mut burnchain = Burnchain::default_unittest(
+        0,
+        &BurnchainHeaderHash::from_hex(BITCOIN_REGTEST_FIRST_BLOCK_HASH).unwrap(),
+    );
+    burnchain.pox_constants = pox_constants;
+
+    let (mut peer, keys) =
+        instantiate_pox_peer_with_epoch(&burnchain, function_name!(), Some(epochs), None);
+    let mut coinbase_nonce = 0;
+    let mut latest_block = peer.tenure_with_txs(&[], &mut coinbase_nonce);
 
-    let conn = peer.sortdb().conn();
+    // move some STX around, so that the balances differ
+    let transfer = make_tx(
+        &keys[0],
+        0,
+        1000,
+        TransactionPayload::TokenTransfer(
+            key_to_stacks_addr(&keys[1]).into(),
+            12345,
+            TokenTransferMemo([0u8; 34]),
+        ),
+    );
+    latest_block = peer.tenure_with_txs(&[transfer], &mut coinbase_nonce);
 
-    // check the *parent* burn block, because that's what we'll be
-    //  checking with get_burn_pox_addr_info
-    let mut burn_ops =
-        SortitionDB::get_block_commits_by_block(conn, &tip.parent_sortition_id).unwrap();
-    assert_eq!(burn_ops.len(), 1);
-    let commit = burn_ops.pop().unwrap();
-    assert!(!commit.all_outputs_burn());
-    let commit_addrs = commit.commit_outs;
+    // the funded accounts, some accounts that were never funded, and a repeat
+    let mut principals: Vec<PrincipalData> = keys
+        .iter()
+        .map(|key| key_to_stacks_addr(key).into())
+        .collect();
+    principals.extend((0..5).map(|_| key_to_stacks_addr(&StacksPrivateKey::random()).into()));
+    principals.push(principals[1].clone());
+    assert_eq!(principals.len(), 10);
 
-    let (addrs, payout) = get_burn_pox_addr_info(peer);
-    info!(
-        "Checking pox outputs at burn_height = {burn_height}, commit_addrs = {commit_addrs:?}, fetch_addrs = {addrs:?}"
+    let one_by_one_start = Instant::now();
+    let one_by_one: Vec<_> = principals
+        .iter()
+        .map(|principal| get_stx_account_at(&mut peer, &latest_block, principal))
+        .collect();
+    let one_by_one_time = one_by_one_start.elapsed();
+
+    let batched_start = Instant::now();
+    let batched = get_stx_accounts_at(&mut peer, &latest_block, &principals);
+    let batched_time = batched_start.elapsed();
+
+    eprintln!(
+        "Read {} balances one by one in {one_by_one_time:?}, batched in {batched_time:?}",
+        principals.len()
     );
-    assert_eq!(addrs.len(), 2);
-    assert_eq!(payout, 500);
-    assert!(commit_addrs.contains(&addrs[0]));
-    assert!(commit_addrs.contains(&addrs[1]));
-    addrs
+    assert_eq!(batched, one_by_one);
+    assert_eq!(
+        batched[1].amount_unlocked(),
+        1024 * POX_THRESHOLD_STEPS_USTX + 12345
+    );
+    assert_eq!(batched[9], batched[1]);
+    assert!(batched[4..9]
+        .iter()
+        .all(|balance| balance.amount_unlocked() == 0));
 }
 
-fn balances_from_keys(
+/// Assert that the raw reward set for the reward cycle starting at `cycle_start` has exactly the
+/// `expected` entries, in order. Each entry is `(reward address bytes, amount stacked, signer
+/// key)`.
+fn assert_reward_set_entries(
     peer: &mut TestPeer,
     tip: &StacksBlockId,
-    keys: &[Secp256k1PrivateKey],
-) -> Vec<STXBalance> {
-    keys.iter()
-        .map(key_to_stacks_addr)
-        .map(PrincipalData::from)
-        .map(|principal| get_stx_account_at(peer, tip, &principal))
-        .collect()
+    cycle_start: u64,
+    expected: &[(Vec<u8>, u128, Option<Vec<u8>>)],
+) {
+    let cycle = peer
+        .config
+        .burnchain
+        .block_height_to_reward_cycle(cycle_start)
+        .unwrap();
+    let actual: Vec<_> = get_reward_set_entries_at(peer, tip, cycle_start)
+        .into_iter()
+        .map(|entry| {
+            (
+                entry.reward_address.bytes(),
+                entry.amount_stacked,
+                entry.signer.map(|signer| signer.to_vec()),
+            )
+        })
+        .collect();
+
+    for (index, (expected_entry, actual_entry)) in expected.iter().zip(actual.iter()).enumerate() {
+        assert_eq!(
+            expected_entry, actual_entry,
+            "Reward set entry {index} of cycle {cycle} differs: expected {expected_entry:?}, got {actual_entry:?}"
+        );
+    }
+    assert_eq!(
+        expected.len(),
+        actual.len(),
+        "Reward set of cycle {cycle} has {} entries instead of {}: expected {expected:?}, got {actual:?}",
+        actual.len(),
+        expected.len()
+    );
+}
+
+/// Compare the reward sets on either side of the PoX contract switch at `boundary_height`, as of
+///  `tip`: the set the outgoing contract computed for the last cycle it pays out, and the set the
+///  incoming contract computed for the first cycle it pays out.
+/// Checks that each set adds up to its contract's `reward-cycle-total-stacked` with no PoX address
+///  listed twice, and that every outgoing PoX address whose stacker is still locked when the
+///  incoming cycle starts is in the incoming set with the same amount.
+/// Returns the outgoing and incoming reward sets.
+fn compare_reward_sets_across_versions(
+    peer: &mut TestPeer,
+    tip: &StacksBlockId,
+    boundary_height: u64,
+) -> (Vec<RawRewardSetEntry>, Vec<RawRewardSetEntry>) {
+    let burnchain = peer.config.burnchain.clone();
+    let outgoing_cycle = burnchain
+        .block_height_to_reward_cycle(boundary_height)
+        .unwrap();
+    let incoming_cycle = outgoing_cycle + 1;
+    let outgoing_start = burnchain.reward_cycle_to_block_height(outgoing_cycle);
+    let incoming_start = burnchain.reward_cycle_to_block_height(incoming_cycle);
+
+    let outgoing_contract = burnchain.pox_constants.active_pox_contract(outgoing_start);
+    let incoming_contract = burnchain.pox_constants.active_pox_contract(incoming_start);
+    assert_ne!(
+        outgoing_contract, incoming_contract,
+        "No PoX contract switch at burn height {boundary_height}"
+    );
+
+    let outgoing = get_reward_set_entries_at(peer, tip, outgoing_start);
+    let incoming = get_reward_set_entries_at(peer, tip, incoming_start);
+
+    let mut amounts_by_cycle = vec![];
+    for (cycle, contract, entries) in [
+        (outgoing_cycle, outgoing_contract, &outgoing),
+        (incoming_cycle, incoming_contract, &incoming),
+    ] {
+        let mut amounts = BTreeMap::new();
+        for entry in entries.iter() {
+            assert!(
+                amounts
+                    .insert(entry.reward_address.clone(), entry.amount_stacked)
+                    .is_none(),
+                "{contract} lists {} more than once in cycle {cycle}",
+                entry.reward_address
+            );
+        }
+        assert_eq!(
+            amounts.values().sum::<u128>(),
+            get_reward_cycle_total(peer, tip, cycle),
+            "{contract} reward set for cycle {cycle} does not add up to its total stacked"
+        );
+        amounts_by_cycle.push(amounts);
+    }
+    let incoming_amounts = amounts_by_cycle.pop().unwrap();
+
+    for entry in outgoing.iter() {
+        // .pox entries carry no stacker, but stackers in these tests pay to their own address
+        let stacker = match (&entry.stacker, &entry.reward_address) {
+            (Some(stacker), _) => stacker.clone(),
+            (None, PoxAddress::Standard(addr, _)) => PrincipalData::from(addr.clone()),
+            (None, _) => continue,
+        };
+        let balance = get_stx_account_at(peer, tip, &stacker);
+        if balance.amount_locked() == 0 || balance.unlock_height() <= incoming_start {
+            continue;
+        }
+        assert_eq!(
+            incoming_amounts.get(&entry.reward_address),
+            Some(&entry.amount_stacked),
+            "{stacker} is locked through cycle {incoming_cycle}, but {incoming_contract} did not carry over {}",
+            entry.reward_address
+        );
+    }
+
+    (outgoing, incoming)
 }
 
 #[apply(nakamoto_cases)]
@@ -5499,6 +7242,197 @@ fn stack_agg_commit_signer_auth(use_nakamoto: bool) {
         .expect("Expected ok result from stack-agg-commit tx");
 }
 
+/// Pool operator's plan for committing its delegators' partial stacks in a reward cycle.
+/// It produces the `delegate-stack-stx` txs for each delegator, followed by a single
+/// `stack-aggregation-commit-indexed` signed by the signer key for that cycle.
+#[derive(Debug, Clone)]
+struct AggregationCommitPlan {
+    pox_addr: PoxAddress,
+    signer_sk: StacksPrivateKey,
+    reward_cycle: u128,
+    auth_id: u128,
+    /// (delegator, amount) pairs to partially stack
+    partial_stacks: Vec<(PrincipalData, u128)>,
+}
+
+impl AggregationCommitPlan {
+    fn new(pox_addr: PoxAddress, signer_sk: &StacksPrivateKey, reward_cycle: u128) -> Self {
+        Self {
+            pox_addr,
+            signer_sk: signer_sk.clone(),
+            reward_cycle,
+            auth_id: 1,
+            partial_stacks: vec![],
+        }
+    }
+
+    fn auth_id(mut self, auth_id: u128) -> Self {
+        self.auth_id = auth_id;
+        self
+    }
+
+    fn partial_stack(mut self, stacker: PrincipalData, amount: u128) -> Self {
+        self.partial_stacks.push((stacker, amount));
+        self
+    }
+
+    fn total_ustx(&self) -> u128 {
+        self.partial_stacks
+            .iter()
+            .fold(0, |total, (_, amount)| total.checked_add(*amount).unwrap())
+    }
+
+    /// Make the `delegate-stack-stx` txs for each delegator, sent by `delegate_key`
+    /// with consecutive nonces starting at `start_nonce`
+    fn make_delegate_stack_txs(
+        &self,
+        delegate_key: &StacksPrivateKey,
+        start_nonce: u64,
+        start_burn_height: u64,
+        lock_period: u128,
+    ) -> Vec<StacksTransaction> {
+        self.partial_stacks
+            .iter()
+            .zip(start_nonce..)
+            .map(|((stacker, amount), nonce)| {
+                make_pox_4_delegate_stack_stx(
+                    delegate_key,
+                    nonce,
+                    stacker.clone(),
+                    *amount,
+                    self.pox_addr.clone(),
+                    start_burn_height.into(),
+                    lock_period,
+                )
+            })
+            .collect()
+    }
+
+    /// Make the signed `stack-aggregation-commit-indexed` tx, sent by `delegate_key`.
+    /// Fails if the total partially stacked amount is below `min_ustx`.
+    fn make_commit_tx(
+        &self,
+        delegate_key: &StacksPrivateKey,
+        nonce: u64,
+        min_ustx: u128,
+    ) -> Result<StacksTransaction, String> {
+        let total_ustx = self.total_ustx();
+        if total_ustx < min_ustx {
+            return Err(format!(
+                "Aggregation commit of {total_ustx} uSTX is below the stacking minimum of {min_ustx} uSTX"
+            ));
+        }
+        let signature = make_signer_key_signature(
+            &self.pox_addr,
+            &self.signer_sk,
+            self.reward_cycle,
+            &Pox4SignatureTopic::AggregationCommit,
+            1_u128,
+            total_ustx,
+            self.auth_id,
+        );
+        Ok(make_pox_4_aggregation_commit_indexed(
+            delegate_key,
+            nonce,
+            &self.pox_addr,
+            self.reward_cycle,
+            Some(signature),
+            &StacksPublicKey::from_private(&self.signer_sk),
+            total_ustx,
+            self.auth_id,
+        ))
+    }
+}
+
+#[apply(nakamoto_cases)]
+/// Test that an `AggregationCommitPlan` over two delegators commits successfully
+/// and produces a single reward set entry for the pool
+fn stack_agg_commit_plan(use_nakamoto: bool) {
+    let lock_period = 2;
+    let observer = TestEventObserver::new();
+    let (burnchain, mut peer, keys, latest_block, block_height, coinbase_nonce, mut test_signers) =
+        prepare_pox4_test(function_name!(), Some(&observer), use_nakamoto);
+
+    let mut coinbase_nonce = coinbase_nonce;
+    let min_ustx = get_stacking_minimum(&mut peer, &latest_block);
+
+    let alice_key = &keys[0];
+    let bob_key = &keys[1];
+    let signer_sk = &keys[1];
+    let signer_pk = StacksPublicKey::from_private(signer_sk);
+
+    let delegate_key = &keys[2];
+    let delegate_addr = key_to_stacks_addr(delegate_key);
+    let pox_addr = pox_addr_from(delegate_key);
+
+    let next_reward_cycle = 1 + burnchain
+        .block_height_to_reward_cycle(block_height)
+        .unwrap() as u128;
+
+    let alice_amount = min_ustx / 2;
+    let bob_amount = min_ustx - alice_amount;
+
+    let plan = AggregationCommitPlan::new(pox_addr.clone(), signer_sk, next_reward_cycle)
+        .partial_stack(key_to_stacks_addr(alice_key).into(), alice_amount)
+        .partial_stack(key_to_stacks_addr(bob_key).into(), bob_amount);
+    assert_eq!(plan.total_ustx(), min_ustx);
+
+    // a plan short of the minimum is rejected before any tx is made
+    let short_plan = AggregationCommitPlan::new(pox_addr.clone(), signer_sk, next_reward_cycle)
+        .partial_stack(key_to_stacks_addr(alice_key).into(), alice_amount);
+    assert!(short_plan
+        .make_commit_tx(delegate_key, 0, min_ustx)
+        .is_err());
+
+    let mut txs = vec![
+        make_pox_4_delegate_stx(
+            alice_key,
+            0,
+            alice_amount,
+            delegate_addr.clone().into(),
+            None,
+            None,
+        ),
+        make_pox_4_delegate_stx(
+            bob_key,
+            0,
+            bob_amount,
+            delegate_addr.clone().into(),
+            None,
+            None,
+        ),
+    ];
+    txs.extend(plan.make_delegate_stack_txs(delegate_key, 0, block_height, lock_period));
+    let commit_nonce = plan.partial_stacks.len() as u64;
+    txs.push(
+        plan.make_commit_tx(delegate_key, commit_nonce, min_ustx)
+            .unwrap(),
+    );
+
+    let latest_block = tenure_with_txs(&mut peer, &txs, &mut coinbase_nonce, &mut test_signers);
+
+    let delegate_txs = get_last_block_sender_transactions(&observer, delegate_addr);
+    delegate_txs
+        .get(commit_nonce as usize)
+        .unwrap()
+        .result
+        .clone()
+        .expect_result_ok()
+        .expect("Expected ok result from stack-agg-commit tx");
+
+    let cycle_start = burnchain.reward_cycle_to_block_height(next_reward_cycle as u64);
+    let pool_entries: Vec<_> = get_reward_set_entries_at(&mut peer, &latest_block, cycle_start)
+        .into_iter()
+        .filter(|entry| entry.reward_address == pox_addr)
+        .collect();
+    assert_eq!(pool_entries.len(), 1);
+    assert_eq!(pool_entries[0].amount_stacked, min_ustx);
+    assert_eq!(
+        pool_entries[0].signer,
+        Some(signer_pk.to_bytes_compressed().try_into().unwrap())
+    );
+}
+
 #[apply(nakamoto_cases)]
 /// Test `stack-extend` using signer key authorization
 /// instead of signatures
@@ -5740,7 +7674,7 @@ fn test_set_signer_key_auth(use_nakamoto: bool) {
         &mut peer,
         &latest_block,
         &pox_addr,
-        current_reward_cycle.clone() as u64,
+        current_reward_cycle,
         &Pox4SignatureTopic::StackStx,
         lock_period,
         &signer_public_key,
@@ -5777,7 +7711,7 @@ fn test_set_signer_key_auth(use_nakamoto: bool) {
         &mut peer,
         &latest_block,
         &pox_addr,
-        current_reward_cycle.clone() as u64,
+        current_reward_cycle,
         &Pox4SignatureTopic::StackStx,
         lock_period,
         &signer_public_key,
@@ -5814,7 +7748,7 @@ fn test_set_signer_key_auth(use_nakamoto: bool) {
         &mut peer,
         &latest_block,
         &pox_addr,
-        current_reward_cycle.clone() as u64,
+        current_reward_cycle,
         &Pox4SignatureTopic::StackStx,
         lock_period,
         &signer_public_key,
@@ -5845,8 +7779,8 @@ fn stack_extend_signer_key(use_nakamoto: bool) {
     let pox_addr = pox_addr_from(stacker_key);
     let pox_addr_val = Value::Tuple(pox_addr.as_clarity_tuple().unwrap());
 
-    let signer_sk = Secp256k1PrivateKey::from_seed(&[0]);
-    let signer_extend_sk = Secp256k1PrivateKey::from_seed(&[1]);
+    let signer_sk = test_signer_key(SIGNER_SEED_STACK);
+    let signer_extend_sk = test_signer_key(SIGNER_SEED_EXTEND);
 
     let signer_key = Secp256k1PublicKey::from_private(&signer_sk);
     let signer_bytes = signer_key.to_bytes_compressed();
@@ -5992,7 +7926,7 @@ fn delegate_stack_stx_signer_key(use_nakamoto: bool) {
     //                          (pox-addr (optional { version: (buff 1), hashbytes: (buff 32) })))
     let pox_addr = pox_addr_from(stacker_key);
     let pox_addr_val = Value::Tuple(pox_addr.as_clarity_tuple().unwrap());
-    let signer_sk = Secp256k1PrivateKey::from_seed(&[1, 1, 1]);
+    let signer_sk = test_signer_key(SIGNER_SEED_DELEGATE);
     let signer_key = Secp256k1PublicKey::from_private(&signer_sk);
     let signer_key_val = Value::buff_from(signer_key.to_bytes_compressed()).unwrap();
     let min_ustx = get_stacking_minimum(&mut peer, &latest_block);
@@ -6053,36 +7987,160 @@ fn delegate_stack_stx_signer_key(use_nakamoto: bool) {
     let delegation_state = get_delegation_state_pox_4(
         &mut peer,
         &latest_block,
-        &key_to_stacks_addr(stacker_key).to_account_principal(),
+        &key_to_stacks_addr(stacker_key).to_account_principal(),
+    )
+    .expect("No delegation state, delegate-stx failed")
+    .expect_tuple();
+
+    let stacking_state = get_stacking_state_pox_4(
+        &mut peer,
+        &latest_block,
+        &key_to_stacks_addr(stacker_key).to_account_principal(),
+    )
+    .expect("No stacking state, delegate-stack-stx failed")
+    .expect_tuple();
+
+    let reward_cycle_ht = burnchain.reward_cycle_to_block_height(next_reward_cycle);
+    let reward_set = get_reward_set_entries_at(&mut peer, &latest_block, reward_cycle_ht);
+    assert_eq!(reward_set.len(), {
+        if use_nakamoto {
+            2
+        } else {
+            1
+        }
+    });
+    let reward_entry = reward_set
+        .iter()
+        .find(|entry| entry.reward_address == pox_addr)
+        .expect("No reward entry found");
+    assert_eq!(
+        &reward_entry.signer.unwrap(),
+        signer_key.to_bytes_compressed().as_slice()
+    );
+}
+
+#[test]
+fn typed_stacking_state_pox_4() {
+    let lock_period = 2;
+    let (
+        burnchain,
+        mut peer,
+        keys,
+        latest_block,
+        block_height,
+        mut coinbase_nonce,
+        mut test_signers,
+    ) = prepare_pox4_test(function_name!(), None, false);
+
+    let min_ustx = get_stacking_minimum(&mut peer, &latest_block);
+    let reward_cycle = get_current_reward_cycle(&peer, &burnchain);
+    let next_reward_cycle = 1 + burnchain
+        .block_height_to_reward_cycle(block_height)
+        .unwrap();
+
+    // Alice stacks on her own
+    let alice_key = &keys[0];
+    let alice_signer_key = &keys[1];
+    let alice_pox_addr = pox_addr_from(alice_key);
+    let alice_pox_addr_val = Value::Tuple(alice_pox_addr.as_clarity_tuple().unwrap());
+    let signature = make_signer_key_signature(
+        &alice_pox_addr,
+        alice_signer_key,
+        reward_cycle,
+        &Pox4SignatureTopic::StackStx,
+        lock_period,
+        u128::MAX,
+        1,
+    );
+    let alice_signer_key_val =
+        Value::buff_from(StacksPublicKey::from_private(alice_signer_key).to_bytes_compressed())
+            .unwrap();
+
+    // Bob delegates to Carol, who stacks for him but does not commit, so Bob has no reward slots
+    let bob_key = &keys[2];
+    let carol_key = &keys[3];
+    let carol_principal = PrincipalData::from(key_to_stacks_addr(carol_key));
+    let bob_pox_addr = pox_addr_from(bob_key);
+    let bob_pox_addr_val = Value::Tuple(bob_pox_addr.as_clarity_tuple().unwrap());
+
+    let txs = vec![
+        make_pox_4_contract_call(
+            alice_key,
+            0,
+            "stack-stx",
+            vec![
+                Value::UInt(min_ustx),
+                alice_pox_addr_val.clone(),
+                Value::UInt(block_height as u128),
+                Value::UInt(lock_period),
+                Value::some(Value::buff_from(signature).unwrap()).unwrap(),
+                alice_signer_key_val,
+                Value::UInt(u128::MAX),
+                Value::UInt(1),
+            ],
+        ),
+        make_pox_4_contract_call(
+            bob_key,
+            0,
+            "delegate-stx",
+            vec![
+                Value::UInt(min_ustx),
+                carol_principal.clone().into(),
+                Value::none(),
+                Value::some(bob_pox_addr_val.clone()).unwrap(),
+            ],
+        ),
+        make_pox_4_contract_call(
+            carol_key,
+            0,
+            "delegate-stack-stx",
+            vec![
+                PrincipalData::from(key_to_stacks_addr(bob_key)).into(),
+                Value::UInt(min_ustx),
+                bob_pox_addr_val.clone(),
+                Value::UInt(block_height as u128),
+                Value::UInt(lock_period),
+            ],
+        ),
+    ];
+    let latest_block = tenure_with_txs(&mut peer, &txs, &mut coinbase_nonce, &mut test_signers);
+
+    let alice_state = get_typed_stacking_state_pox_4(
+        &mut peer,
+        &latest_block,
+        &key_to_stacks_addr(alice_key).to_account_principal(),
     )
-    .expect("No delegation state, delegate-stx failed")
-    .expect_tuple();
+    .expect("No stacking state, stack-stx failed");
+    assert_eq!(
+        alice_state,
+        Pox4StackingState {
+            pox_addr: PoxAddress::try_from_pox_tuple(false, &alice_pox_addr_val).unwrap(),
+            lock_period: 2,
+            first_reward_cycle: next_reward_cycle,
+            reward_set_indexes: vec![0, 0],
+            delegated_to: None,
+        }
+    );
 
-    let stacking_state = get_stacking_state_pox_4(
+    let bob_state = get_typed_stacking_state_pox_4(
         &mut peer,
         &latest_block,
-        &key_to_stacks_addr(stacker_key).to_account_principal(),
+        &key_to_stacks_addr(bob_key).to_account_principal(),
     )
-    .expect("No stacking state, delegate-stack-stx failed")
-    .expect_tuple();
-
-    let reward_cycle_ht = burnchain.reward_cycle_to_block_height(next_reward_cycle);
-    let reward_set = get_reward_set_entries_at(&mut peer, &latest_block, reward_cycle_ht);
-    assert_eq!(reward_set.len(), {
-        if use_nakamoto {
-            2
-        } else {
-            1
-        }
-    });
-    let reward_entry = reward_set
-        .iter()
-        .find(|entry| entry.reward_address == pox_addr)
-        .expect("No reward entry found");
+    .expect("No stacking state, delegate-stack-stx failed");
     assert_eq!(
-        &reward_entry.signer.unwrap(),
-        signer_key.to_bytes_compressed().as_slice()
+        bob_state,
+        Pox4StackingState {
+            pox_addr: PoxAddress::try_from_pox_tuple(false, &bob_pox_addr_val).unwrap(),
+            lock_period: 2,
+            first_reward_cycle: next_reward_cycle,
+            reward_set_indexes: vec![],
+            delegated_to: Some(carol_principal),
+        }
     );
+
+    // a malformed entry is rejected rather than misread
+    assert!(Pox4StackingState::try_from(Value::UInt(1)).is_err());
 }
 
 // In this test case, Alice delegates to Bob.
@@ -6111,8 +8169,8 @@ fn delegate_stack_stx_extend_signer_key(use_nakamoto: bool) {
     let bob_delegate_private_key = &keys[1];
     let bob_delegate_principal = PrincipalData::from(key_to_stacks_addr(bob_delegate_private_key));
 
-    let signer_sk = Secp256k1PrivateKey::from_seed(&[0]);
-    let signer_extend_sk = Secp256k1PrivateKey::from_seed(&[1]);
+    let signer_sk = test_signer_key(SIGNER_SEED_STACK);
+    let signer_extend_sk = test_signer_key(SIGNER_SEED_EXTEND);
 
     let signer_key = Secp256k1PublicKey::from_private(&signer_sk);
     let signer_bytes = signer_key.to_bytes_compressed();
@@ -6413,19 +8471,6 @@ fn stack_increase(use_nakamoto: bool) {
     ))
     .unwrap();
 
-    let increase_op_data = HashMap::from([
-        (
-            "signer-sig",
-            Value::some(Value::buff_from(signature).unwrap()).unwrap(),
-        ),
-        (
-            "signer-key",
-            Value::buff_from(signing_pk.to_bytes_compressed()).unwrap(),
-        ),
-        ("max-amount", Value::UInt(u128::MAX)),
-        ("auth-id", Value::UInt(1)),
-    ]);
-
     let alice_expected_balance = alice_balance - min_ustx;
 
     // Compute the expected unlock height because the 3.0 and 2.5 cases
@@ -6443,15 +8488,22 @@ fn stack_increase(use_nakamoto: bool) {
     let expected_unlock_height =
         unlock_cycle * (burnchain.pox_constants.reward_cycle_length as u64);
 
-    let common_data = PoxPrintFields {
-        op_name: "stack-increase".to_string(),
-        stacker: Value::Principal(PrincipalData::from(alice_address.clone())),
-        balance: Value::UInt(alice_expected_balance),
-        locked: Value::UInt(min_ustx),
-        burnchain_unlock_height: Value::UInt(expected_unlock_height as u128),
-    };
-
-    check_pox_print_event(increase_event, common_data, increase_op_data);
+    PoxEventAssertion::new("stack-increase")
+        .stacker(Value::Principal(PrincipalData::from(alice_address.clone())))
+        .balance(Value::UInt(alice_expected_balance))
+        .locked(Value::UInt(min_ustx))
+        .burnchain_unlock_height(Value::UInt(expected_unlock_height as u128))
+        .field(
+            "signer-sig",
+            Value::some(Value::buff_from(signature).unwrap()).unwrap(),
+        )
+        .field(
+            "signer-key",
+            Value::buff_from(signing_pk.to_bytes_compressed()).unwrap(),
+        )
+        .field("max-amount", Value::UInt(u128::MAX))
+        .field("auth-id", Value::UInt(1))
+        .assert(increase_event);
 
     // Testing stack_increase response is equal to expected response
     // Test is straightforward because 'stack-increase' in PoX-4 is the same as PoX-3
@@ -6503,7 +8555,7 @@ fn delegate_stack_increase(use_nakamoto: bool) {
     let bob_delegate_key = &keys[1];
     let bob_delegate_address = PrincipalData::from(key_to_stacks_addr(bob_delegate_key));
     let min_ustx = get_stacking_minimum(&mut peer, &latest_block);
-    let signer_sk = StacksPrivateKey::from_seed(&[1, 3, 3, 7]);
+    let signer_sk = test_signer_key(SIGNER_SEED_SCENARIO);
     let signer_pk = StacksPublicKey::from_private(&signer_sk);
     let signer_pk_bytes = signer_pk.to_bytes_compressed();
     let signer_key_val = Value::buff_from(signer_pk_bytes.clone()).unwrap();
@@ -8804,7 +10856,7 @@ fn delegate_stack_increase_err(use_nakamoto: bool) {
     let bob_delegate_key = &keys[1];
     let bob_delegate_address = PrincipalData::from(key_to_stacks_addr(bob_delegate_key));
     let min_ustx = get_stacking_minimum(&mut peer, &latest_block);
-    let signer_sk = StacksPrivateKey::from_seed(&[1, 3, 3, 7]);
+    let signer_sk = test_signer_key(SIGNER_SEED_SCENARIO);
     let signer_pk = StacksPublicKey::from_private(&signer_sk);
     let signer_pk_bytes = signer_pk.to_bytes_compressed();
     let signer_key_val = Value::buff_from(signer_pk_bytes).unwrap();
@@ -8916,9 +10968,132 @@ pub fn get_stacking_state_pox_4(
     })
 }
 
+/// A pox-4 `stacking-state` map entry
+#[derive(Debug, Clone, PartialEq)]
+pub struct Pox4StackingState {
+    pub pox_addr: PoxAddress,
+    pub lock_period: u64,
+    pub first_reward_cycle: u64,
+    pub reward_set_indexes: Vec<u128>,
+    pub delegated_to: Option<PrincipalData>,
+}
+
+impl TryFrom<Value> for Pox4StackingState {
+    type Error = String;
+
+    /// Parse a `stacking-state` tuple. Test peers run on testnet, so its PoX address is read as
+    /// a testnet address.
+    fn try_from(value: Value) -> Result<Self, Self::Error> {
+        let tuple = value
+            .expect_tuple()
+            .map_err(|e| format!("stacking-state is not a tuple: {e:?}"))?;
+        let field = |name: &str| {
+            tuple
+                .get(name)
+                .cloned()
+                .map_err(|e| format!("stacking-state has no {name}: {e:?}"))
+        };
+        let u64_field = |name: &str| {
+            let value = field(name)?
+                .expect_u128()
+                .map_err(|e| format!("stacking-state {name} is not a uint: {e:?}"))?;
+            u64::try_from(value).map_err(|_| format!("stacking-state {name} exceeds u64::MAX"))
+        };
+
+        let pox_addr = PoxAddress::try_from_pox_tuple(false, &field("pox-addr")?)
+            .ok_or_else(|| "stacking-state pox-addr is not a PoX address".to_string())?;
+        let reward_set_indexes = field("reward-set-indexes")?
+            .expect_list()
+            .map_err(|e| format!("stacking-state reward-set-indexes is not a list: {e:?}"))?
+            .into_iter()
+            .map(|index| {
+                index
+                    .expect_u128()
+                    .map_err(|e| format!("stacking-state reward set index is not a uint: {e:?}"))
+            })
+            .collect::<Result<_, _>>()?;
+        let delegated_to = field("delegated-to")?
+            .expect_optional()
+            .map_err(|e| format!("stacking-state delegated-to is not optional: {e:?}"))?
+            .map(|delegate| {
+                delegate
+                    .expect_principal()
+                    .map_err(|e| format!("stacking-state delegated-to is not a principal: {e:?}"))
+            })
+            .transpose()?;
+
+        Ok(Pox4StackingState {
+            pox_addr,
+            lock_period: u64_field("lock-period")?,
+            first_reward_cycle: u64_field("first-reward-cycle")?,
+            reward_set_indexes,
+            delegated_to,
+        })
+    }
+}
+
+/// Like `get_stacking_state_pox_4`, but parse the entry. Panics if the entry is malformed.
+pub fn get_typed_stacking_state_pox_4(
+    peer: &mut TestPeer,
+    tip: &StacksBlockId,
+    account: &PrincipalData,
+) -> Option<Pox4StackingState> {
+    get_stacking_state_pox_4(peer, tip, account).map(|value| {
+        Pox4StackingState::try_from(value)
+            .unwrap_or_else(|e| panic!("Malformed pox-4 stacking-state: {e}"))
+    })
+}
+
+/// The `period` that a `stack-increase` signer key signature must be made over: the stacker's
+/// whole current lock period, as recorded in its pox-4 `stacking_state`. `stack-extend` rewrites
+/// this period to cover the extended cycles, so it is not the `stack-stx` lock period.
+pub fn compute_stack_increase_period(stacking_state: &TupleData) -> u128 {
+    stacking_state
+        .get("lock-period")
+        .unwrap()
+        .clone()
+        .expect_u128()
+        .unwrap()
+}
+
+/// Make a `stack-increase` of `amount` from `key`, whose pox-4 stacking state is
+/// `stacking_state`, with a signature from `signer_sk` for `reward_cycle` over the stacking
+/// state's PoX address and the period from `compute_stack_increase_period`
+pub fn make_pox_4_stack_increase_for_state(
+    key: &StacksPrivateKey,
+    nonce: u64,
+    stacking_state: &TupleData,
+    reward_cycle: u128,
+    amount: u128,
+    signer_sk: &StacksPrivateKey,
+    max_amount: u128,
+    auth_id: u128,
+) -> StacksTransaction {
+    let pox_addr_tuple = stacking_state.get("pox-addr").unwrap().clone();
+    let pox_addr = PoxAddress::try_from_pox_tuple(false, &pox_addr_tuple).unwrap();
+    let signature = make_signer_key_signature(
+        &pox_addr,
+        signer_sk,
+        reward_cycle,
+        &Pox4SignatureTopic::StackIncrease,
+        compute_stack_increase_period(stacking_state),
+        max_amount,
+        auth_id,
+    );
+    make_pox_4_stack_increase(
+        key,
+        nonce,
+        amount,
+        &StacksPublicKey::from_private(signer_sk),
+        Some(signature),
+        max_amount,
+        auth_id,
+    )
+}
+
 pub fn make_signer_key_authorization_lookup_key(
     pox_addr: &PoxAddress,
-    reward_cycle: u64,
+    reward_cycle: u128,
     topic: &Pox4SignatureTopic,
     period: u128,
     signer_key: &StacksPublicKey,
@@ -8930,7 +11105,7 @@ pub fn make_signer_key_authorization_lookup_key(
             "pox-addr".into(),
             pox_addr.as_clarity_tuple().unwrap().into(),
         ),
-        ("reward-cycle".into(), Value::UInt(reward_cycle.into())),
+        ("reward-cycle".into(), Value::UInt(reward_cycle)),
         (
             "topic".into(),
             Value::string_ascii_from_bytes(topic.get_name_str().into()).unwrap(),
@@ -8951,7 +11126,7 @@ pub fn get_signer_key_authorization_pox_4(
     peer: &mut TestPeer,
     tip: &StacksBlockId,
     pox_addr: &PoxAddress,
-    reward_cycle: u64,
+    reward_cycle: u128,
     topic: &Pox4SignatureTopic,
     period: u128,
     signer_key: &StacksPublicKey,
@@ -8989,7 +11164,7 @@ pub fn get_signer_key_authorization_used_pox_4(
     peer: &mut TestPeer,
     tip: &StacksBlockId,
     pox_addr: &PoxAddress,
-    reward_cycle: u64,
+    reward_cycle: u128,
     topic: &Pox4SignatureTopic,
     period: u128,
     signer_key: &StacksPublicKey,
@@ -9308,20 +11483,115 @@ pub fn get_last_block_sender_transactions(
     observer: &TestEventObserver,
     address: StacksAddress,
 ) -> Vec<StacksTransactionReceipt> {
-    observer
-        .get_blocks()
-        .last()
-        .unwrap()
-        .clone()
-        .receipts
-        .into_iter()
-        .filter(|receipt| {
-            if let TransactionOrigin::Stacks(ref transaction) = receipt.transaction {
-                return transaction.auth.origin().address_testnet() == address;
-            }
-            false
-        })
-        .collect::<Vec<_>>()
+    observer.receipts_in_last_block_by_sender(address)
+}
+
+#[test]
+fn make_test_epochs_pox_custom_activation() {
+    let (default_epochs, default_pox_constants) = make_test_epochs_pox(false);
+    let (epochs, pox_constants) = make_test_epochs_pox_custom(PoxEpochOpts {
+        pox_4_activation_offset: 0,
+        ..PoxEpochOpts::default()
+    });
+
+    // only pox-4 activation moves, to the first block of epoch 2.5
+    let epoch_2_5_start = epochs[StacksEpochId::Epoch25].start_height;
+    assert_eq!(
+        u64::from(pox_constants.pox_4_activation_height),
+        epoch_2_5_start
+    );
+    assert_eq!(epochs, default_epochs);
+    let mut moved_pox_constants = default_pox_constants.clone();
+    moved_pox_constants.pox_4_activation_height = pox_constants.pox_4_activation_height;
+    assert_eq!(pox_constants, moved_pox_constants);
+    assert!(epochs.get(StacksEpochId::Epoch30).is_none());
+
+    // the gaps set the epoch boundaries
+    let (epochs, pox_constants) = make_test_epochs_pox_custom(PoxEpochOpts {
+        use_nakamoto: true,
+        epoch_2_5_gap: 20,
+        pox_4_activation_offset: 0,
+        ..PoxEpochOpts::default()
+    });
+    assert_eq!(epochs[StacksEpochId::Epoch25].start_height, 56 + 20);
+    assert_eq!(epochs[StacksEpochId::Epoch30].start_height, 56 + 20 + 23);
+    assert_eq!(pox_constants.pox_4_activation_height, 56 + 20);
+    assert_eq!(pox_constants.v3_unlock_height, 56 + 20 + 1);
+}
+
+#[test]
+fn active_pox_version_at_boundaries() {
+    let (epochs, pox_constants) = make_test_epochs_pox(false);
+    let v1_unlock_height = u64::from(pox_constants.v1_unlock_height);
+    let v2_unlock_height = u64::from(pox_constants.v2_unlock_height);
+    let v3_unlock_height = u64::from(pox_constants.v3_unlock_height);
+    let pox_3_activation_height = u64::from(pox_constants.pox_3_activation_height);
+    let pox_4_activation_height = u64::from(pox_constants.pox_4_activation_height);
+
+    let active_version = |burn_height: u64| {
+        let epoch_id = epochs.epoch_id_at_height(burn_height).unwrap();
+        PoxVersions::active_pox_version(epoch_id, &pox_constants, burn_height)
+    };
+
+    // each contract takes over in the block after its activation height
+    let expected = [
+        (0, PoxVersions::Pox1),
+        (
+            epochs[StacksEpochId::Epoch21].start_height,
+            PoxVersions::Pox1,
+        ),
+        (v1_unlock_height, PoxVersions::Pox1),
+        (v1_unlock_height + 1, PoxVersions::Pox2),
+        (v2_unlock_height, PoxVersions::Pox2),
+        (v2_unlock_height + 1, PoxVersions::Pox2),
+        (
+            epochs[StacksEpochId::Epoch24].start_height,
+            PoxVersions::Pox2,
+        ),
+        (pox_3_activation_height, PoxVersions::Pox2),
+        (pox_3_activation_height + 1, PoxVersions::Pox3),
+        (
+            epochs[StacksEpochId::Epoch25].start_height,
+            PoxVersions::Pox3,
+        ),
+        (v3_unlock_height, PoxVersions::Pox3),
+        (v3_unlock_height + 1, PoxVersions::Pox3),
+        (pox_4_activation_height, PoxVersions::Pox3),
+        (pox_4_activation_height + 1, PoxVersions::Pox4),
+    ];
+    for (burn_height, version) in expected {
+        assert_eq!(
+            active_version(burn_height),
+            version,
+            "Wrong PoX version at burn height {burn_height}"
+        );
+    }
+
+    // the version agrees with the contract name used to read PoX state
+    for burn_height in 0..=pox_4_activation_height + 1 {
+        assert_eq!(
+            active_version(burn_height).get_name_str(),
+            pox_constants.active_pox_contract(burn_height)
+        );
+    }
+
+    // an epoch that doesn't have a contract yet never reads it
+    assert_eq!(
+        PoxVersions::active_pox_version(
+            StacksEpochId::Epoch21,
+            &pox_constants,
+            pox_4_activation_height + 1
+        ),
+        PoxVersions::Pox2
+    );
+    assert_eq!(
+        PoxVersions::active_pox_version(
+            StacksEpochId::Epoch24,
+            &pox_constants,
+            pox_4_activation_height + 1
+        ),
+        PoxVersions::Pox3
+    );
 }
 
 /// In this test case, two Stackers, Alice and Bob stack in PoX 4. Alice stacks enough
@@ -9334,9 +11604,10 @@ fn missed_slots_no_unlock() {
     //  tenures start being tracked.
     let EMPTY_SORTITIONS = 25;
 
-    let (epochs, mut pox_constants) = make_test_epochs_pox(false);
-    pox_constants.pox_4_activation_height =
-        u32::try_from(epochs[StacksEpochId::Epoch25].start_height).unwrap() + 1;
+    let (epochs, pox_constants) = make_test_epochs_pox_custom(PoxEpochOpts {
+        pox_4_activation_offset: 1,
+        ..PoxEpochOpts::default()
+    });
 
     let mut burnchain = Burnchain::default_unittest(
         0,
@@ -9384,24 +11655,24 @@ fn missed_slots_no_unlock() {
     let txs = [alice_lockup, bob_lockup];
     let mut latest_block = peer.tenure_with_txs(&txs, &mut coinbase_nonce);
 
+    let expected_entries = [
+        (
+            bob_address.bytes().0.to_vec(),
+            POX_THRESHOLD_STEPS_USTX,
+            Some(StacksPublicKey::from_private(&bob).to_bytes_compressed()),
+        ),
+        (
+            alice_address.bytes().0.to_vec(),
+            1024 * POX_THRESHOLD_STEPS_USTX,
+            Some(StacksPublicKey::from_private(&alice).to_bytes_compressed()),
+        ),
+    ];
+
     // check that the "raw" reward set will contain entries for alice and bob
     //  for the pox-4 cycles
     for cycle_number in first_v4_cycle..first_v4_cycle + 6 {
         let cycle_start = burnchain.reward_cycle_to_block_height(cycle_number);
-        let reward_set_entries = get_reward_set_entries_at(&mut peer, &latest_block, cycle_start);
-        assert_eq!(
-            reward_set_entries.len(),
-            2,
-            "Reward set should contain two entries in cycle {cycle_number}"
-        );
-        assert_eq!(
-            reward_set_entries[0].reward_address.bytes(),
-            bob_address.bytes().0.to_vec()
-        );
-        assert_eq!(
-            reward_set_entries[1].reward_address.bytes(),
-            alice_address.bytes().0.to_vec()
-        );
+        assert_reward_set_entries(&mut peer, &latest_block, cycle_start, &expected_entries);
     }
 
     // we'll produce blocks until the next reward cycle gets through the "handled start" code
@@ -9424,16 +11695,7 @@ fn missed_slots_no_unlock() {
     // check that the "raw" reward sets for all cycles contain entries for alice and bob still!
     for cycle_number in first_v4_cycle..(first_v4_cycle + 6) {
         let cycle_start = burnchain.reward_cycle_to_block_height(cycle_number);
-        let reward_set_entries = get_reward_set_entries_at(&mut peer, &latest_block, cycle_start);
-        assert_eq!(reward_set_entries.len(), 2);
-        assert_eq!(
-            reward_set_entries[0].reward_address.bytes(),
-            bob_address.bytes().0.to_vec()
-        );
-        assert_eq!(
-            reward_set_entries[1].reward_address.bytes(),
-            alice_address.bytes().0.to_vec()
-        );
+        assert_reward_set_entries(&mut peer, &latest_block, cycle_start, &expected_entries);
     }
 
     let expected_unlock_height = burnchain.reward_cycle_to_block_height(first_v4_cycle + 6) - 1;
@@ -9463,29 +11725,21 @@ fn missed_slots_no_unlock() {
     }
 
     // check that bob's stacking-state is gone and alice's stacking-state is correct
-    let bob_state = get_stacking_state_pox(
+    let bob_state = get_typed_stacking_state_pox_4(
         &mut peer,
         &latest_block,
         &bob_address.to_account_principal(),
-        PoxVersions::Pox4.get_name_str(),
     )
-    .expect("Bob should have stacking-state entry")
-    .expect_tuple()
-    .unwrap();
-    let reward_indexes_str = bob_state.get("reward-set-indexes").unwrap().to_string();
-    assert_eq!(reward_indexes_str, "(u1 u1 u1 u1 u1 u1)");
+    .expect("Bob should have stacking-state entry");
+    assert_eq!(bob_state.reward_set_indexes, vec![1; 6]);
 
-    let alice_state = get_stacking_state_pox(
+    let alice_state = get_typed_stacking_state_pox_4(
         &mut peer,
         &latest_block,
         &alice_address.to_account_principal(),
-        PoxVersions::Pox4.get_name_str(),
     )
-    .expect("Alice should have stacking-state entry")
-    .expect_tuple()
-    .unwrap();
-    let reward_indexes_str = alice_state.get("reward-set-indexes").unwrap().to_string();
-    assert_eq!(reward_indexes_str, "(u0 u0 u0 u0 u0 u0)");
+    .expect("Alice should have stacking-state entry");
+    assert_eq!(alice_state.reward_set_indexes, vec![0; 6]);
 
     // check that bob is still locked at next block
     latest_block = peer.tenure_with_txs(&[], &mut coinbase_nonce);
@@ -9584,9 +11838,10 @@ fn no_lockups_2_5() {
     //  tenures start being tracked.
     let EMPTY_SORTITIONS = 25;
 
-    let (epochs, mut pox_constants) = make_test_epochs_pox(false);
-    pox_constants.pox_4_activation_height =
-        u32::try_from(epochs[StacksEpochId::Epoch25].start_height).unwrap() + 1;
+    let (epochs, pox_constants) = make_test_epochs_pox_custom(PoxEpochOpts {
+        pox_4_activation_offset: 1,
+        ..PoxEpochOpts::default()
+    });
 
     let mut burnchain = Burnchain::default_unittest(
         0,
@@ -10460,3 +12715,180 @@ fn test_scenario_five(use_nakamoto: bool) {
     let carl_increase_err = receipts[1].clone().result;
     assert_eq!(carl_increase_err, Value::error(Value::Int(40)).unwrap());
 }
+
+// In this test case, Alice delegates to Bob and fixes her pox-addr, while Carol delegates to Bob
+//  without fixing one. Bob then tries to stack both of them to his own pox-addr.
+//
+// This test asserts that `delegate_stack_pox_addr_allowed` only allows Alice's fixed address for
+//  Alice, allows any address for Carol, and agrees with what pox-4 lets Bob do.
+#[test]
+fn delegate_stack_pox_addr_allowed_matches_pox_4() {
+    let lock_period: u128 = 2;
+    let (
+        _burnchain,
+        mut peer,
+        keys,
+        latest_block,
+        block_height,
+        mut coinbase_nonce,
+        mut test_signers,
+    ) = prepare_pox4_test(function_name!(), None, false);
+
+    let alice_key = &keys[0];
+    let bob_key = &keys[1];
+    let carol_key = &keys[2];
+    let alice_principal = PrincipalData::from(key_to_stacks_addr(alice_key));
+    let bob_principal = PrincipalData::from(key_to_stacks_addr(bob_key));
+    let carol_principal = PrincipalData::from(key_to_stacks_addr(carol_key));
+    let alice_pox_addr = pox_addr_from(alice_key);
+    let bob_pox_addr = pox_addr_from(bob_key);
+    let min_ustx = get_stacking_minimum(&mut peer, &latest_block);
+
+    let txs = vec![
+        make_pox_4_delegate_stx(
+            alice_key,
+            0,
+            min_ustx,
+            bob_principal.clone(),
+            None,
+            Some(alice_pox_addr.clone()),
+        ),
+        make_pox_4_delegate_stx(carol_key, 0, min_ustx, bob_principal, None, None),
+        make_pox_4_delegate_stack_stx(
+            bob_key,
+            0,
+            alice_principal.clone(),
+            min_ustx,
+            bob_pox_addr.clone(),
+            block_height as u128,
+            lock_period,
+        ),
+        make_pox_4_delegate_stack_stx(
+            bob_key,
+            1,
+            carol_principal.clone(),
+            min_ustx,
+            bob_pox_addr.clone(),
+            block_height as u128,
+            lock_period,
+        ),
+    ];
+    let latest_block = tenure_with_txs(&mut peer, &txs, &mut coinbase_nonce, &mut test_signers);
+
+    let alice_delegation = get_delegation_state_pox_4(&mut peer, &latest_block, &alice_principal)
+        .expect("No delegation state, Alice's delegate-stx failed")
+        .expect_tuple()
+        .unwrap();
+    let carol_delegation = get_delegation_state_pox_4(&mut peer, &latest_block, &carol_principal)
+        .expect("No delegation state, Carol's delegate-stx failed")
+        .expect_tuple()
+        .unwrap();
+
+    // Alice's delegation only allows her fixed pox-addr
+    assert!(delegate_stack_pox_addr_allowed(
+        &alice_delegation,
+        &alice_pox_addr
+    ));
+    assert!(!delegate_stack_pox_addr_allowed(
+        &alice_delegation,
+        &bob_pox_addr
+    ));
+
+    // Carol's delegation allows any pox-addr
+    assert!(delegate_stack_pox_addr_allowed(
+        &carol_delegation,
+        &alice_pox_addr
+    ));
+    assert!(delegate_stack_pox_addr_allowed(
+        &carol_delegation,
+        &bob_pox_addr
+    ));
+
+    // and pox-4 agrees: Bob could stack Carol's STX to his pox-addr, but not Alice's
+    assert!(get_stacking_state_pox_4(&mut peer, &latest_block, &alice_principal).is_none());
+    assert!(get_stacking_state_pox_4(&mut peer, &latest_block, &carol_principal).is_some());
+}
+
+// In this test case, Alice stacks with `stack-stx`.
+//
+// This test asserts that `TestPeer::pox_account_view()` reports Alice's balance, lock, and
+//  stacking state consistently with each other, and with the separate account and pox-4 map
+//  readers.
+#[test]
+fn pox_account_view_after_stack_stx() {
+    let lock_period: u128 = 2;
+    let (
+        _burnchain,
+        mut peer,
+        keys,
+        latest_block,
+        _block_height,
+        mut coinbase_nonce,
+        mut test_signers,
+    ) = prepare_pox4_test(function_name!(), None, false);
+
+    let alice_key = &keys[0];
+    let alice_principal = PrincipalData::from(key_to_stacks_addr(alice_key));
+    let min_ustx = get_stacking_minimum(&mut peer, &latest_block);
+
+    // before stacking, nothing is locked and there's no pox-4 state
+    let view = peer.pox_account_view(&latest_block, &alice_principal);
+    assert_eq!(view.locked, 0);
+    assert_eq!(view.unlock_height, 0);
+    assert!(view.stacking_state.is_none());
+    assert!(view.delegation_state.is_none());
+    let initial_balance = view.balance;
+
+    let alice_lockup = make_simple_pox_4_lock(alice_key, &mut peer, min_ustx, lock_period);
+    let latest_block = tenure_with_txs(
+        &mut peer,
+        &[alice_lockup],
+        &mut coinbase_nonce,
+        &mut test_signers,
+    );
+
+    let view = peer.pox_account_view(&latest_block, &alice_principal);
+    assert_eq!(view.locked, min_ustx);
+    // the locked uSTX came out of the balance, along with the tx fee
+    assert!(view.balance + view.locked <= initial_balance);
+    assert!(view.delegation_state.is_none());
+
+    // the view agrees with the account reader...
+    let stx_balance = get_stx_account_at(&mut peer, &latest_block, &alice_principal);
+    assert_eq!(view.balance, stx_balance.amount_unlocked());
+    assert_eq!(view.locked, stx_balance.amount_locked());
+    assert_eq!(view.unlock_height, stx_balance.unlock_height());
+
+    // ...and with the stacking-state reader
+    let stacking_state = view
+        .stacking_state
+        .expect("No stacking state, stack-stx failed");
+    let expected_stacking_state =
+        get_stacking_state_pox_4(&mut peer, &latest_block, &alice_principal)
+            .expect("No stacking state, stack-stx failed")
+            .expect_tuple()
+            .unwrap();
+    assert_eq!(stacking_state, expected_stacking_state);
+
+    // the lock ends when the stacking state's last reward cycle does
+    let first_reward_cycle = stacking_state
+        .get("first-reward-cycle")
+        .unwrap()
+        .clone()
+        .expect_u128()
+        .unwrap();
+    let stacked_lock_period = stacking_state
+        .get("lock-period")
+        .unwrap()
+        .clone()
+        .expect_u128()
+        .unwrap();
+    assert_eq!(stacked_lock_period, lock_period);
+    let unlock_cycle = u64::try_from(first_reward_cycle + lock_period).unwrap();
+    assert_eq!(
+        view.unlock_height,
+        peer.config
+            .burnchain
+            .reward_cycle_to_block_height(unlock_cycle)
+    );
+}
@@ -744,16 +744,18 @@ impl StacksChainState {
         Ok(rows)
     }
 
-    /// Get the scheduled miner rewards in a particular Stacks fork at a particular height.
+    /// Get the scheduled miner rewards in a particular Stacks fork that mature at the given tip,
+    /// i.e. the ones `miner_reward_maturity` blocks below it.
     pub fn get_scheduled_block_rewards(
         tx: &mut StacksDBTx,
         tip: &StacksHeaderInfo,
+        miner_reward_maturity: u64,
     ) -> Result<Vec<MinerPaymentSchedule>, Error> {
-        if tip.stacks_block_height < MINER_REWARD_MATURITY {
+        if tip.stacks_block_height < miner_reward_maturity {
             return Ok(vec![]);
         }
 
-        let block_height = tip.stacks_block_height - MINER_REWARD_MATURITY;
+        let block_height = tip.stacks_block_height - miner_reward_maturity;
         StacksChainState::get_scheduled_block_rewards_in_fork_at_height(tx, tip, block_height)
     }
 
@@ -988,12 +990,13 @@ impl StacksChainState {
         parent_miner: MinerPaymentSchedule,
     ) -> Result<Option<(MinerReward, Vec<MinerReward>, MinerReward, MinerRewardInfo)>, Error> {
         let mainnet = clarity_tx.config.mainnet;
-        if tip_stacks_height <= MINER_REWARD_MATURITY {
+        let miner_reward_maturity = clarity_tx.config.miner_reward_maturity;
+        if tip_stacks_height <= miner_reward_maturity {
             // no mature rewards exist
             return Ok(None);
         }
 
-        let reward_height = tip_stacks_height - MINER_REWARD_MATURITY;
+        let reward_height = tip_stacks_height - miner_reward_maturity;
 
         assert!(!latest_matured_miners.is_empty());
         assert!(latest_matured_miners[0].vtxindex == 0);
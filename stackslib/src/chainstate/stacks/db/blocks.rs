@@ -5058,9 +5058,11 @@ impl StacksChainState {
 
         // find matured miner rewards, so we can grant them within the Clarity DB tx.
         let (latest_matured_miners, matured_miner_parent) = {
+            let miner_reward_maturity = chainstate_tx.get_config().miner_reward_maturity;
             let latest_miners = StacksChainState::get_scheduled_block_rewards(
                 chainstate_tx.deref_mut(),
                 chain_tip,
+                miner_reward_maturity,
             )?;
             let parent_miner = StacksChainState::get_parent_matured_miner(
                 chainstate_tx.deref_mut(),
@@ -112,6 +112,7 @@ pub struct StacksChainState {
     pub unconfirmed_state: Option<UnconfirmedState>,
     pub fault_injection: StacksChainStateFaults,
     marf_opts: Option<MARFOpenOpts>,
+    miner_reward_maturity: u64,
 }
 
 #[derive(Debug, Clone, PartialEq)]
@@ -273,6 +274,10 @@ pub struct DBConfig {
     pub version: String,
     pub mainnet: bool,
     pub chain_id: u32,
+    /// How many Stacks blocks (or tenures, in Nakamoto) it takes for a miner reward to mature.
+    /// This is not stored in the DB: it's always `MINER_REWARD_MATURITY`, except in tests that
+    /// shorten it with `StacksChainState::set_miner_reward_maturity()`.
+    pub miner_reward_maturity: u64,
 }
 
 impl DBConfig {
@@ -409,6 +414,7 @@ impl FromRow<DBConfig> for DBConfig {
             version,
             mainnet,
             chain_id,
+            miner_reward_maturity: MINER_REWARD_MATURITY,
         })
     }
 }
@@ -1868,6 +1874,7 @@ impl StacksChainState {
             unconfirmed_state: None,
             fault_injection: StacksChainStateFaults::new(),
             marf_opts,
+            miner_reward_maturity: MINER_REWARD_MATURITY,
         };
 
         let mut receipts = vec![];
@@ -1893,9 +1900,19 @@ impl StacksChainState {
             mainnet: self.mainnet,
             chain_id: self.chain_id,
             version: CHAINSTATE_VERSION.to_string(),
+            miner_reward_maturity: self.miner_reward_maturity,
         }
     }
 
+    /// Override how many blocks (or tenures) it takes for miner rewards to mature when this
+    /// chainstate processes blocks, so that tests can reach matured rewards sooner.  This is
+    /// consensus-critical, so it must be the same for every chainstate that processes the same
+    /// chain.
+    #[cfg(any(test, feature = "testing"))]
+    pub fn set_miner_reward_maturity(&mut self, miner_reward_maturity: u64) {
+        self.miner_reward_maturity = miner_reward_maturity;
+    }
+
     /// Begin a transaction against the (indexed) stacks chainstate DB.
     /// Does not create a Clarity instance.
     pub fn index_tx_begin(&mut self) -> StacksDBTx<'_> {
@@ -2980,6 +2997,7 @@ pub mod test {
             version: CHAINSTATE_VERSION.to_string(),
             mainnet: true,
             chain_id: CHAIN_ID_MAINNET,
+            miner_reward_maturity: MINER_REWARD_MATURITY,
         };
         assert!(db.supports_epoch(StacksEpochId::latest()));
     }
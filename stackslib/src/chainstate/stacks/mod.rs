@@ -112,6 +112,11 @@ pub enum Error {
     PoxExtendNotLocked,
     PoxIncreaseOnV1,
     PoxInvalidIncrease,
+    /// Summing or scaling PoX stacked amounts overflowed
+    PoxAmountOverflow(String),
+    /// A reward cycle's reward set was read from a block other than the anchor block the
+    /// sortition DB selected for it: (reward cycle, selected anchor block, block read from)
+    PoxAnchorBlockMismatch(u64, Option<BlockHeaderHash>, StacksBlockId),
     DefunctPoxContract,
     ProblematicTransaction(Txid),
     MinerAborted,
@@ -204,6 +209,12 @@ impl fmt::Display for Error {
             ),
             Error::PoxIncreaseOnV1 => write!(f, "PoX increase only allowed for pox-2 locks"),
             Error::PoxInvalidIncrease => write!(f, "PoX increase was invalid"),
+            Error::PoxAmountOverflow(ref s) => write!(f, "PoX amount overflowed: {}", s),
+            Error::PoxAnchorBlockMismatch(ref cycle, ref recorded, ref source) => write!(
+                f,
+                "Reward set for cycle {} read from block {}, but the sortition DB's anchor block is {:?}",
+                cycle, source, recorded
+            ),
             Error::MinerAborted => write!(f, "Mining attempt aborted by signal"),
             Error::ChannelClosed(ref s) => write!(f, "Channel '{}' closed", s),
             Error::InvalidChildOfNakomotoBlock => write!(
@@ -259,6 +270,8 @@ impl error::Error for Error {
             Error::ProblematicTransaction(ref _txid) => None,
             Error::PoxIncreaseOnV1 => None,
             Error::PoxInvalidIncrease => None,
+            Error::PoxAmountOverflow(ref _s) => None,
+            Error::PoxAnchorBlockMismatch(..) => None,
             Error::MinerAborted => None,
             Error::ChannelClosed(ref _s) => None,
             Error::InvalidChildOfNakomotoBlock => None,
@@ -304,6 +317,8 @@ impl Error {
             Error::ProblematicTransaction(ref _txid) => "ProblematicTransaction",
             Error::PoxIncreaseOnV1 => "PoxIncreaseOnV1",
             Error::PoxInvalidIncrease => "PoxInvalidIncrease",
+            Error::PoxAmountOverflow(ref _s) => "PoxAmountOverflow",
+            Error::PoxAnchorBlockMismatch(..) => "PoxAnchorBlockMismatch",
             Error::MinerAborted => "MinerAborted",
             Error::ChannelClosed(ref _s) => "ChannelClosed",
             Error::InvalidChildOfNakomotoBlock => "InvalidChildOfNakomotoBlock",
@@ -1622,6 +1622,29 @@ pub struct BurnchainConfig {
     ///   - This value must be `<= 1024`.
     ///   - Only relevant if [`NodeConfig::miner`] is `true`.
     pub max_unspent_utxos: Option<u64>,
+    /// The number of threads that parse downloaded burnchain blocks in parallel while
+    /// the node syncs the burnchain. Parsed blocks are still stored in download order.
+    /// ---
+    /// @default: `1`
+    /// @notes:
+    ///   - This value must be at least `1`.
+    pub parse_workers: usize,
+    /// The maximum number of burnchain blocks to download per second while the node
+    /// syncs the burnchain. Up to one second's worth of blocks can be downloaded in a
+    /// burst before the rest are spaced out.
+    /// ---
+    /// @default: `None` (no limit)
+    /// @units: blocks per second
+    pub download_rate_limit: Option<u32>,
+    /// The maximum number of burnchain blocks to walk back from the chain tip when
+    /// searching for the common ancestor of a burnchain reorg. If the reorg is deeper
+    /// than this, the sync fails instead of searching further.
+    /// ---
+    /// @default: `None` (search back to the first burnchain block)
+    /// @units: blocks
+    /// @notes:
+    ///   - This value must be at least `1`.
+    pub reorg_lookback: Option<u64>,
 }
 
 impl BurnchainConfig {
@@ -1663,6 +1686,9 @@ impl BurnchainConfig {
             affirmation_overrides: HashMap::new(),
             fault_injection_burnchain_block_delay: 0,
             max_unspent_utxos: Some(1024),
+            parse_workers: 1,
+            download_rate_limit: None,
+            reorg_lookback: None,
         }
     }
     pub fn get_rpc_url(&self, wallet: Option<String>) -> String {
@@ -1763,6 +1789,9 @@ pub struct BurnchainConfigFile {
     pub affirmation_overrides: Option<Vec<AffirmationOverride>>,
     pub fault_injection_burnchain_block_delay: Option<u64>,
     pub max_unspent_utxos: Option<u64>,
+    pub parse_workers: Option<usize>,
+    pub download_rate_limit: Option<u32>,
+    pub reorg_lookback: Option<u64>,
 }
 
 impl BurnchainConfigFile {
@@ -1989,8 +2018,25 @@ impl BurnchainConfigFile {
                     assert!(val <= 1024, "Value for max_unspent_utxos should be <= 1024");
                 })
                 .or(default_burnchain_config.max_unspent_utxos),
+            parse_workers: self
+                .parse_workers
+                .unwrap_or(default_burnchain_config.parse_workers),
+            download_rate_limit: self
+                .download_rate_limit
+                .or(default_burnchain_config.download_rate_limit),
+            reorg_lookback: self
+                .reorg_lookback
+                .or(default_burnchain_config.reorg_lookback),
         };
 
+        if config.parse_workers == 0 {
+            return Err("burnchain.parse_workers must be at least 1".into());
+        }
+
+        if config.reorg_lookback == Some(0) {
+            return Err("burnchain.reorg_lookback must be at least 1".into());
+        }
+
         if let BitcoinNetworkType::Mainnet = config.get_bitcoin_network().1 {
             // check that pox_2_activation hasn't been set in mainnet
             if config.pox_2_activation.is_some()
@@ -4860,6 +4906,89 @@ mod tests {
         );
     }
 
+    #[test]
+    fn should_load_parse_workers() {
+        let config = Config::from_config_file(ConfigFile::from_str("").unwrap(), false).unwrap();
+        assert_eq!(config.burnchain.parse_workers, 1);
+
+        let config = Config::from_config_file(
+            ConfigFile::from_str(
+                r#"
+                [burnchain]
+                parse_workers = 4
+                "#,
+            )
+            .unwrap(),
+            false,
+        )
+        .expect("Expected to be able to parse burnchain.parse_workers from file");
+        assert_eq!(config.burnchain.parse_workers, 4);
+
+        let err = Config::from_config_file(
+            ConfigFile::from_str(
+                r#"
+                [burnchain]
+                parse_workers = 0
+                "#,
+            )
+            .unwrap(),
+            false,
+        )
+        .unwrap_err();
+        assert_eq!(err, "burnchain.parse_workers must be at least 1");
+    }
+
+    #[test]
+    fn should_load_download_rate_limit() {
+        let config = Config::from_config_file(ConfigFile::from_str("").unwrap(), false).unwrap();
+        assert_eq!(config.burnchain.download_rate_limit, None);
+
+        let config = Config::from_config_file(
+            ConfigFile::from_str(
+                r#"
+                [burnchain]
+                download_rate_limit = 20
+                "#,
+            )
+            .unwrap(),
+            false,
+        )
+        .expect("Expected to be able to parse burnchain.download_rate_limit from file");
+        assert_eq!(config.burnchain.download_rate_limit, Some(20));
+    }
+
+    #[test]
+    fn should_load_reorg_lookback() {
+        let config = Config::from_config_file(ConfigFile::from_str("").unwrap(), false).unwrap();
+        assert_eq!(config.burnchain.reorg_lookback, None);
+
+        let config = Config::from_config_file(
+            ConfigFile::from_str(
+                r#"
+                [burnchain]
+                reorg_lookback = 144
+                "#,
+            )
+            .unwrap(),
+            false,
+        )
+        .expect("Expected to be able to parse burnchain.reorg_lookback from file");
+        assert_eq!(config.burnchain.reorg_lookback, Some(144));
+
+        let err = Config::from_config_file(
+            ConfigFile::from_str(
+                r#"
+                [burnchain]
+                reorg_lookback = 0
+                "#,
+            )
+            .unwrap(),
+            false,
+        )
+        .unwrap_err();
+        assert_eq!(err, "burnchain.reorg_lookback must be at least 1");
+    }
+
     #[test]
     fn should_load_auth_token() {
         let config = Config::from_config_file(
@@ -21,12 +21,14 @@ use clarity::vm::costs::{ExecutionCost, LimitedCostTracker};
 use clarity::vm::errors::Error::Unchecked;
 use clarity::vm::errors::{Error as ClarityRuntimeError, InterpreterError};
 use clarity::vm::representations::{CONTRACT_NAME_REGEX_STRING, STANDARD_PRINCIPAL_REGEX_STRING};
-use clarity::vm::types::{PrincipalData, QualifiedContractIdentifier};
+use clarity::vm::types::{FunctionType, PrincipalData, QualifiedContractIdentifier, TypeSignature};
 use clarity::vm::{ClarityName, ContractName, SymbolicExpression, Value};
 use regex::{Captures, Regex};
-use stacks_common::types::chainstate::StacksAddress;
+use stacks_common::types::chainstate::{StacksAddress, StacksBlockId};
 use stacks_common::types::net::PeerHost;
+use stacks_common::types::StacksEpochId;
 
+use crate::chainstate::nakamoto::NakamotoChainState;
 use crate::net::http::{
     parse_json, Error, HttpContentType, HttpNotFound, HttpRequest, HttpRequestContents,
     HttpRequestPreamble, HttpResponse, HttpResponseContents, HttpResponsePayload,
@@ -38,6 +40,10 @@ use crate::net::httpcore::{
 };
 use crate::net::{Error as NetError, StacksNodeState, TipRequest};
 
+/// Maximum number of arguments a call-read request may pass. Larger requests are rejected
+/// before any argument is decoded.
+pub const MAX_CALL_READ_ARGUMENTS: usize = 256;
+
 #[derive(Clone, Serialize, Deserialize)]
 pub struct CallReadOnlyRequestBody {
     pub sender: String,
@@ -55,6 +61,128 @@ pub struct CallReadOnlyResponse {
     #[serde(default)]
     #[serde(skip_serializing_if = "Option::is_none")]
     pub cause: Option<String>,
+    /// Set instead of `result` when the call was made with `estimate=true`
+    #[serde(default)]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub estimate: Option<CallReadOnlyCostEstimate>,
+    /// The chain tip the call was evaluated against
+    #[serde(default)]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub tip: Option<CallReadOnlyTip>,
+}
+
+/// The chain tip a read-only call was evaluated against
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct CallReadOnlyTip {
+    /// Index block hash of the tip
+    pub index_block_hash: StacksBlockId,
+    /// Burnchain height of the tip's block. Not set if the tip is unconfirmed microblock state.
+    #[serde(default)]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub burn_block_height: Option<u64>,
+}
+
+/// Cost of evaluating a read-only call, reported in place of its result
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct CallReadOnlyCostEstimate {
+    /// Cost of the call. If the call exceeded the node's read-only budget, this is the cost
+    /// measured when evaluation stopped, so the full call costs at least this much.
+    pub cost: ExecutionCost,
+    /// Whether the call exceeds the node's read-only budget
+    pub exceeds_limit: bool,
+}
+
+/// Reasons to reject a read-only call before evaluating it
+#[derive(Debug, Clone, PartialEq)]
+pub enum CallReadParseError {
+    /// The request has no body
+    EmptyBody,
+    /// The body is not smaller than the node's maximum call argument size
+    BodyTooLarge { length: u32, max: u32 },
+    /// The body is not declared as JSON
+    InvalidContentType,
+    /// The body is not a JSON call-read request
+    MalformedJson,
+    /// The sender is not a principal
+    InvalidSender,
+    /// The sponsor is not a principal
+    InvalidSponsor,
+    /// There are more than `max` arguments
+    TooManyArguments { count: usize, max: usize },
+    /// The argument at this index is not a hex-encoded Clarity value
+    ArgumentDecode(usize),
+    /// The argument at `index` is not admitted by the function's declared argument type
+    ArgumentTypeMismatch {
+        index: usize,
+        expected: TypeSignature,
+        got: TypeSignature,
+    },
+}
+
+impl std::fmt::Display for CallReadParseError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            CallReadParseError::EmptyBody => write!(f, "EmptyBody: the request has no body"),
+            CallReadParseError::BodyTooLarge { length, max } => write!(
+                f,
+                "BodyTooLarge: body length {length} must be less than {max}"
+            ),
+            CallReadParseError::InvalidContentType => {
+                write!(f, "InvalidContentType: expected application/json")
+            }
+            CallReadParseError::MalformedJson => {
+                write!(f, "MalformedJson: failed to parse JSON body")
+            }
+            CallReadParseError::InvalidSender => {
+                write!(f, "InvalidSender: failed to parse sender principal")
+            }
+            CallReadParseError::InvalidSponsor => {
+                write!(f, "InvalidSponsor: failed to parse sponsor principal")
+            }
+            CallReadParseError::TooManyArguments { count, max } => write!(
+                f,
+                "TooManyArguments: {count} arguments, at most {max} allowed"
+            ),
+            CallReadParseError::ArgumentDecode(index) => {
+                write!(f, "ArgumentDecode: failed to deserialize argument {index}")
+            }
+            CallReadParseError::ArgumentTypeMismatch {
+                index,
+                expected,
+                got,
+            } => write!(
+                f,
+                "ArgumentTypeMismatch: argument {index} expected type {expected}, got {got}"
+            ),
+        }
+    }
+}
+
+impl CallReadParseError {
+    /// Name of this error, reported as `err_type` in the body of the HTTP 400 response, so that
+    /// clients can match on it without parsing the message
+    pub fn err_type(&self) -> &'static str {
+        match self {
+            CallReadParseError::EmptyBody => "EmptyBody",
+            CallReadParseError::BodyTooLarge { .. } => "BodyTooLarge",
+            CallReadParseError::InvalidContentType => "InvalidContentType",
+            CallReadParseError::MalformedJson => "MalformedJson",
+            CallReadParseError::InvalidSender => "InvalidSender",
+            CallReadParseError::InvalidSponsor => "InvalidSponsor",
+            CallReadParseError::TooManyArguments { .. } => "TooManyArguments",
+            CallReadParseError::ArgumentDecode(_) => "ArgumentDecode",
+            CallReadParseError::ArgumentTypeMismatch { .. } => "ArgumentTypeMismatch",
+        }
+    }
+
+    /// Body of the HTTP 400 response to a request rejected with this error
+    pub fn into_json(&self) -> serde_json::Value {
+        serde_json::json!({
+            "error": "Invalid CallReadOnly request",
+            "err_type": self.err_type(),
+            "err_msg": self.to_string(),
+        })
+    }
 }
 
 #[derive(Clone)]
@@ -68,6 +196,7 @@ pub struct RPCCallReadOnlyRequestHandler {
     pub sender: Option<PrincipalData>,
     pub sponsor: Option<PrincipalData>,
     pub arguments: Option<Vec<Value>>,
+    pub estimate: Option<bool>,
 }
 
 impl RPCCallReadOnlyRequestHandler {
@@ -80,7 +209,94 @@ impl RPCCallReadOnlyRequestHandler {
             sender: None,
             sponsor: None,
             arguments: None,
+            estimate: None,
+        }
+    }
+
+    /// Decode the sender, sponsor and arguments of a call-read request from its body.
+    pub fn parse_body(
+        &self,
+        preamble: &HttpRequestPreamble,
+        body: &[u8],
+    ) -> Result<(PrincipalData, Option<PrincipalData>, Vec<Value>), CallReadParseError> {
+        let content_len = preamble.get_content_length();
+        if content_len == 0 {
+            return Err(CallReadParseError::EmptyBody);
+        }
+        if content_len >= self.maximum_call_argument_size {
+            return Err(CallReadParseError::BodyTooLarge {
+                length: content_len,
+                max: self.maximum_call_argument_size,
+            });
         }
+
+        if preamble.content_type != Some(HttpContentType::JSON) {
+            return Err(CallReadParseError::InvalidContentType);
+        }
+
+        let body: CallReadOnlyRequestBody =
+            serde_json::from_slice(body).map_err(|_e| CallReadParseError::MalformedJson)?;
+
+        let sender =
+            PrincipalData::parse(&body.sender).map_err(|_e| CallReadParseError::InvalidSender)?;
+
+        let sponsor = body
+            .sponsor
+            .map(|sponsor| PrincipalData::parse(&sponsor))
+            .transpose()
+            .map_err(|_e| CallReadParseError::InvalidSponsor)?;
+
+        if body.arguments.len() > MAX_CALL_READ_ARGUMENTS {
+            return Err(CallReadParseError::TooManyArguments {
+                count: body.arguments.len(),
+                max: MAX_CALL_READ_ARGUMENTS,
+            });
+        }
+
+        // arguments must be valid Clarity values
+        let arguments = body
+            .arguments
+            .iter()
+            .enumerate()
+            .map(|(index, hex)| {
+                Value::try_deserialize_hex_untyped(hex)
+                    .map_err(|_e| CallReadParseError::ArgumentDecode(index))
+            })
+            .collect::<Result<Vec<Value>, _>>()?;
+
+        Ok((sender, sponsor, arguments))
+    }
+
+    /// Check each argument against the type the function declares for it, so that a
+    /// mis-typed call is rejected with a precise error instead of failing in evaluation.
+    /// Argument count mismatches are left for evaluation to report.
+    pub fn check_argument_types(
+        epoch: &StacksEpochId,
+        function_type: &FunctionType,
+        arguments: &[Value],
+    ) -> Result<(), CallReadParseError> {
+        let FunctionType::Fixed(function) = function_type else {
+            // public and read-only functions always have a fixed signature
+            return Ok(());
+        };
+        if function.args.len() != arguments.len() {
+            return Ok(());
+        }
+        for (index, (arg, value)) in function.args.iter().zip(arguments.iter()).enumerate() {
+            if arg.signature.admits(epoch, value).unwrap_or(false) {
+                continue;
+            }
+            let Ok(got) = TypeSignature::type_of(value) else {
+                // let evaluation report values that don't have a type
+                continue;
+            };
+            return Err(CallReadParseError::ArgumentTypeMismatch {
+                index,
+                expected: arg.signature.clone(),
+                got,
+            });
+        }
+        Ok(())
     }
 }
 
@@ -102,6 +318,10 @@ impl HttpRequest for RPCCallReadOnlyRequestHandler {
         "/v2/contracts/call-read/:principal/:contract_name/:func_name"
     }
 
+    fn strict_path_prefix(&self) -> Option<&'static str> {
+        Some("/v2/contracts/call-read/")
+    }
+
     /// Try to decode this request.
     fn try_parse_request(
         &mut self,
@@ -110,52 +330,31 @@ impl HttpRequest for RPCCallReadOnlyRequestHandler {
         query: Option<&str>,
         body: &[u8],
     ) -> Result<HttpRequestContents, Error> {
-        let content_len = preamble.get_content_length();
-        if !(content_len > 0 && content_len < self.maximum_call_argument_size) {
-            return Err(Error::DecodeError(format!(
-                "Invalid Http request: invalid body length for CallReadOnly ({})",
-                content_len
-            )));
-        }
-
-        if preamble.content_type != Some(HttpContentType::JSON) {
-            return Err(Error::DecodeError(
-                "Invalid content-type: expected application/json".to_string(),
-            ));
-        }
+        let (sender, sponsor, arguments) = self
+            .parse_body(preamble, body)
+            .map_err(|e| Error::BadRequestJson(e.into_json()))?;
 
         let contract_identifier = request::get_contract_address(captures, "address", "contract")?;
         let function = request::get_clarity_name(captures, "function")?;
-        let body: CallReadOnlyRequestBody = serde_json::from_slice(body)
-            .map_err(|_e| Error::DecodeError("Failed to parse JSON body".into()))?;
-
-        let sender = PrincipalData::parse(&body.sender)
-            .map_err(|_e| Error::DecodeError("Failed to parse sender principal".into()))?;
-
-        let sponsor = if let Some(sponsor) = body.sponsor {
-            Some(
-                PrincipalData::parse(&sponsor)
-                    .map_err(|_e| Error::DecodeError("Failed to parse sponsor principal".into()))?,
-            )
-        } else {
-            None
-        };
 
-        // arguments must be valid Clarity values
-        let arguments = body
-            .arguments
-            .into_iter()
-            .map(|hex| Value::try_deserialize_hex_untyped(&hex).ok())
-            .collect::<Option<Vec<Value>>>()
-            .ok_or_else(|| Error::DecodeError("Failed to deserialize argument value".into()))?;
+        let req_contents = HttpRequestContents::new().query_string(query);
+        let estimate = req_contents
+            .get_query_arg("estimate")
+            .map(|estimate| estimate.parse::<bool>())
+            .transpose()
+            .map_err(|e| {
+                Error::DecodeError(format!("Failed to parse estimate= query parameter: {e}"))
+            })?
+            .unwrap_or(false);
 
         self.contract_identifier = Some(contract_identifier);
         self.function = Some(function);
         self.sender = Some(sender);
         self.sponsor = sponsor;
         self.arguments = Some(arguments);
+        self.estimate = Some(estimate);
 
-        Ok(HttpRequestContents::new().query_string(query))
+        Ok(req_contents)
     }
 }
 
@@ -168,6 +367,7 @@ impl RPCRequestHandler for RPCCallReadOnlyRequestHandler {
         self.sender = None;
         self.sponsor = None;
         self.arguments = None;
+        self.estimate = None;
     }
 
     /// Make the response
@@ -201,6 +401,7 @@ impl RPCRequestHandler for RPCCallReadOnlyRequestHandler {
             .arguments
             .take()
             .ok_or(NetError::SendError("Missing `arguments`".into()))?;
+        let estimate = self.estimate.take().unwrap_or(false);
 
         // run the read-only call
         let data_resp =
@@ -242,7 +443,34 @@ impl RPCRequestHandler for RPCCallReadOnlyRequestHandler {
                                 )))
                             })?;
 
-                        clarity_tx.with_readonly_clarity_env(
+                        // if the contract interface has this function's signature, then
+                        // reject mis-typed arguments before evaluating anything
+                        let function_type = clarity_tx.with_analysis_db_readonly(|analysis_db| {
+                            match analysis_db.get_read_only_function_type(
+                                &contract_identifier,
+                                function.as_str(),
+                                &epoch,
+                            ) {
+                                Ok(Some(function_type)) => Some(function_type),
+                                _ => analysis_db
+                                    .get_public_function_type(
+                                        &contract_identifier,
+                                        function.as_str(),
+                                        &epoch,
+                                    )
+                                    .ok()
+                                    .flatten(),
+                            }
+                        });
+                        if let Some(function_type) = function_type {
+                            if let Err(e) =
+                                Self::check_argument_types(&epoch, &function_type, &arguments)
+                            {
+                                return Ok(Err(e));
+                            }
+                        }
+
+                        let call_result = clarity_tx.with_readonly_clarity_env(
                             mainnet,
                             chain_id,
                             clarity_version,
@@ -256,21 +484,33 @@ impl RPCRequestHandler for RPCCallReadOnlyRequestHandler {
                                 // can be called, and also circumvents limitations on `define-read-only`
                                 // functions that can not use `contrac-call?`, even when calling other
                                 // read-only functions
-                                env.execute_contract(
+                                let result = env.execute_contract(
                                     &contract_identifier,
                                     function.as_str(),
                                     &args,
                                     false,
-                                )
+                                )?;
+                                Ok((result, env.global_context.cost_track.get_total()))
                             },
-                        )
+                        );
+                        call_result.map(Ok)
                     },
                 )
             });
 
         // decode the response
-        let data_resp = match data_resp {
-            Ok(Some(Ok(data))) => {
+        let mut data_resp = match data_resp {
+            Ok(Some(Ok(Ok((_data, cost))))) if estimate => CallReadOnlyResponse {
+                okay: true,
+                result: None,
+                cause: None,
+                estimate: Some(CallReadOnlyCostEstimate {
+                    cost,
+                    exceeds_limit: false,
+                }),
+                tip: None,
+            },
+            Ok(Some(Ok(Ok((data, _cost))))) => {
                 let hex_result = data
                     .serialize_to_hex()
                     .map_err(|e| NetError::SerializeError(format!("{:?}", &e)))?;
@@ -279,8 +519,17 @@ impl RPCRequestHandler for RPCCallReadOnlyRequestHandler {
                     okay: true,
                     result: Some(format!("0x{}", hex_result)),
                     cause: None,
+                    estimate: None,
+                    tip: None,
                 }
             }
+            Ok(Some(Ok(Err(e)))) => CallReadOnlyResponse {
+                okay: false,
+                result: None,
+                cause: Some(e.to_string()),
+                estimate: None,
+                tip: None,
+            },
             Ok(Some(Err(e))) => match e {
                 Unchecked(CheckErrors::CostBalanceExceeded(actual_cost, _))
                     if actual_cost.write_count > 0 =>
@@ -289,12 +538,28 @@ impl RPCRequestHandler for RPCCallReadOnlyRequestHandler {
                         okay: false,
                         result: None,
                         cause: Some("NotReadOnly".to_string()),
+                        estimate: None,
+                        tip: None,
+                    }
+                }
+                Unchecked(CheckErrors::CostBalanceExceeded(actual_cost, _)) if estimate => {
+                    CallReadOnlyResponse {
+                        okay: true,
+                        result: None,
+                        cause: None,
+                        estimate: Some(CallReadOnlyCostEstimate {
+                            cost: actual_cost,
+                            exceeds_limit: true,
+                        }),
+                        tip: None,
                     }
                 }
                 _ => CallReadOnlyResponse {
                     okay: false,
                     result: None,
                     cause: Some(e.to_string()),
+                    estimate: None,
+                    tip: None,
                 },
             },
             Ok(None) | Err(_) => {
@@ -307,6 +572,19 @@ impl RPCRequestHandler for RPCCallReadOnlyRequestHandler {
             }
         };
 
+        // report the tip the call was evaluated against
+        let burn_block_height =
+            node.with_node_state(|_network, _sortdb, chainstate, _mempool, _rpc_args| {
+                NakamotoChainState::get_block_header(chainstate.db(), &tip)
+                    .ok()
+                    .flatten()
+                    .map(|header| u64::from(header.burn_header_height))
+            });
+        data_resp.tip = Some(CallReadOnlyTip {
+            index_block_hash: tip,
+            burn_block_height,
+        });
+
         let mut preamble = HttpResponsePreamble::ok_json(&preamble);
         preamble.set_canonical_stacks_tip_height(Some(node.canonical_stacks_tip_height()));
         let body = HttpResponseContents::try_from_json(&data_resp)?;
@@ -338,6 +616,61 @@ impl StacksHttpRequest {
         function_args: Vec<Value>,
         tip_req: TipRequest,
     ) -> StacksHttpRequest {
+        Self::new_callreadonlyfunction_request(
+            host,
+            contract_addr,
+            contract_name,
+            sender,
+            sponsor,
+            function_name,
+            function_args,
+            tip_req,
+            false,
+        )
+    }
+
+    /// Make a new request to estimate the cost of a read-only function call, without
+    /// returning its result
+    pub fn new_callreadonlyfunction_estimate(
+        host: PeerHost,
+        contract_addr: StacksAddress,
+        contract_name: ContractName,
+        sender: PrincipalData,
+        sponsor: Option<PrincipalData>,
+        function_name: ClarityName,
+        function_args: Vec<Value>,
+        tip_req: TipRequest,
+    ) -> StacksHttpRequest {
+        Self::new_callreadonlyfunction_request(
+            host,
+            contract_addr,
+            contract_name,
+            sender,
+            sponsor,
+            function_name,
+            function_args,
+            tip_req,
+            true,
+        )
+    }
+
+    /// Make a new read-only function call request, which only estimates the call's cost if
+    /// `estimate` is set
+    fn new_callreadonlyfunction_request(
+        host: PeerHost,
+        contract_addr: StacksAddress,
+        contract_name: ContractName,
+        sender: PrincipalData,
+        sponsor: Option<PrincipalData>,
+        function_name: ClarityName,
+        function_args: Vec<Value>,
+        tip_req: TipRequest,
+        estimate: bool,
+    ) -> StacksHttpRequest {
+        let mut contents = HttpRequestContents::new().for_tip(tip_req);
+        if estimate {
+            contents = contents.query_arg("estimate".into(), "true".into());
+        }
         StacksHttpRequest::new_for_peer(
             host,
             "POST".into(),
@@ -345,7 +678,7 @@ impl StacksHttpRequest {
                 "/v2/contracts/call-read/{}/{}/{}",
                 &contract_addr, &contract_name, &function_name
             ),
-            HttpRequestContents::new().for_tip(tip_req).payload_json(
+            contents.payload_json(
                 serde_json::to_value(CallReadOnlyRequestBody {
                     sender: sender.to_string(),
                     sponsor: sponsor.map(|s| s.to_string()),
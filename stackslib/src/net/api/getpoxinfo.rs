@@ -342,13 +342,13 @@ impl RPCPoxInfoData {
             total_liquid_supply_ustx as u128,
             cur_cycle_stacked_ustx,
             reward_slots as u128,
-        ) as u64;
+        )? as u64;
 
         let next_threshold = StacksChainState::get_threshold_from_participation(
             total_liquid_supply_ustx as u128,
             next_cycle_stacked_ustx,
             reward_slots as u128,
-        ) as u64;
+        )? as u64;
 
         let pox_activation_threshold_ustx = (total_liquid_supply_ustx as u128)
             .checked_mul(pox_consts.pox_participation_threshold_pct as u128)
@@ -0,0 +1,246 @@
+// Copyright (C) 2025 Stacks Open Internet Foundation
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with this program.  If not, see <http://www.gnu.org/licenses/>.
+use regex::{Captures, Regex};
+use serde_json::json;
+use stacks_common::types::net::PeerHost;
+use stacks_common::util::hash::to_hex;
+
+use crate::chainstate::stacks::address::{PoxAddress, PoxAddressType};
+use crate::chainstate::stacks::boot::{NakamotoSignerEntry, RewardSet};
+use crate::net::api::getstackers::GetStackersResponse;
+use crate::net::http::{
+    parse_json, Error, HttpBadRequest, HttpRequest, HttpRequestContents, HttpRequestPreamble,
+    HttpResponse, HttpResponseContents, HttpResponsePayload, HttpResponsePreamble,
+};
+use crate::net::httpcore::{
+    HttpPreambleExtensions, HttpRequestContentsExtensions, RPCRequestHandler, StacksHttpRequest,
+    StacksHttpResponse,
+};
+use crate::net::{Error as NetError, StacksNodeState, TipRequest};
+
+#[derive(Clone, Default)]
+pub struct RPCGetRewardSetHandler {
+    pub cycle_number: Option<u64>,
+}
+
+impl RPCGetRewardSetHandler {
+    pub fn new() -> Self {
+        Self::default()
+    }
+}
+
+/// A PoX address as the PoX contract's `{ version, hashbytes }` tuple, with both buffers
+/// hex-encoded
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct RPCPoxAddressTuple {
+    pub version: String,
+    pub hashbytes: String,
+}
+
+/// One reward slot of a reward set
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct RPCRewardedAddress {
+    pub pox_addr: RPCPoxAddressTuple,
+    pub bitcoin_address: String,
+}
+
+impl From<&PoxAddress> for RPCRewardedAddress {
+    fn from(addr: &PoxAddress) -> Self {
+        // reward set addresses come from the PoX contract, so their hash modes are known, but
+        // coerce one just in case so that every address has a version byte
+        let version = match addr.clone().coerce_hash_mode().address_type() {
+            Some(PoxAddressType::Standard(hashmode)) => hashmode as u8,
+            Some(PoxAddressType::Addr20(addrtype)) => addrtype.to_u8(),
+            Some(PoxAddressType::Addr32(addrtype)) => addrtype.to_u8(),
+            None => unreachable!("BUG: standard PoX address has no hash mode after coercion"),
+        };
+        Self {
+            pox_addr: RPCPoxAddressTuple {
+                version: format!("0x{}", to_hex(&[version])),
+                hashbytes: format!("0x{}", to_hex(&addr.bytes())),
+            },
+            bitcoin_address: addr.clone().to_b58(),
+        }
+    }
+}
+
+/// The reward set of a reward cycle.  `rewarded_addresses` has one entry per reward slot, so an
+/// address that earned several slots appears several times.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct RPCRewardSetData {
+    pub cycle_number: u64,
+    pub pox_ustx_threshold: Option<u128>,
+    pub rewarded_addresses: Vec<RPCRewardedAddress>,
+    pub signers: Option<Vec<NakamotoSignerEntry>>,
+}
+
+impl RPCRewardSetData {
+    pub fn from_reward_set(cycle_number: u64, reward_set: &RewardSet) -> Self {
+        Self {
+            cycle_number,
+            pox_ustx_threshold: reward_set.pox_ustx_threshold,
+            rewarded_addresses: reward_set
+                .rewarded_addresses
+                .iter()
+                .map(RPCRewardedAddress::from)
+                .collect(),
+            signers: reward_set.signers.clone(),
+        }
+    }
+}
+
+/// Decode the HTTP request
+impl HttpRequest for RPCGetRewardSetHandler {
+    fn verb(&self) -> &'static str {
+        "GET"
+    }
+
+    fn path_regex(&self) -> Regex {
+        Regex::new(r#"^/v2/pox/reward-set/(?P<cycle_num>[0-9]{1,10})$"#).unwrap()
+    }
+
+    fn metrics_identifier(&self) -> &str {
+        "/v2/pox/reward-set/:cycle_num"
+    }
+
+    /// Try to decode this request.
+    /// There's nothing to load here, so just make sure the request is well-formed.
+    fn try_parse_request(
+        &mut self,
+        preamble: &HttpRequestPreamble,
+        captures: &Captures,
+        query: Option<&str>,
+        _body: &[u8],
+    ) -> Result<HttpRequestContents, Error> {
+        if preamble.get_content_length() != 0 {
+            return Err(Error::DecodeError(
+                "Invalid Http request: expected 0-length body".into(),
+            ));
+        }
+
+        let Some(cycle_num_str) = captures.name("cycle_num") else {
+            return Err(Error::DecodeError(
+                "Missing in request path: `cycle_num`".into(),
+            ));
+        };
+        let cycle_num = u64::from_str_radix(cycle_num_str.into(), 10)
+            .map_err(|e| Error::DecodeError(format!("Failed to parse cycle number: {e}")))?;
+
+        self.cycle_number = Some(cycle_num);
+
+        Ok(HttpRequestContents::new().query_string(query))
+    }
+}
+
+impl RPCRequestHandler for RPCGetRewardSetHandler {
+    /// Reset internal state
+    fn restart(&mut self) {
+        self.cycle_number = None;
+    }
+
+    /// Make the response
+    fn try_handle_request(
+        &mut self,
+        preamble: HttpRequestPreamble,
+        contents: HttpRequestContents,
+        node: &mut StacksNodeState,
+    ) -> Result<(HttpResponsePreamble, HttpResponseContents), NetError> {
+        let tip = match node.load_stacks_chain_tip(&preamble, &contents) {
+            Ok(tip) => tip,
+            Err(error_resp) => {
+                return error_resp.try_into_contents().map_err(NetError::from);
+            }
+        };
+        let Some(cycle_number) = self.cycle_number else {
+            return StacksHttpResponse::new_error(
+                &preamble,
+                &HttpBadRequest::new_json(
+                    json!({"response": "error", "err_msg": "Failed to read cycle number in request"}),
+                ),
+            )
+            .try_into_contents()
+            .map_err(NetError::from);
+        };
+
+        let reward_set_response =
+            node.with_node_state(|network, sortdb, chainstate, _mempool, _rpc_args| {
+                GetStackersResponse::load(
+                    sortdb,
+                    chainstate,
+                    &tip,
+                    network.get_burnchain(),
+                    cycle_number,
+                )
+            });
+
+        let response = match reward_set_response {
+            Ok(response) => RPCRewardSetData::from_reward_set(cycle_number, &response.stacker_set),
+            Err(error) => {
+                return StacksHttpResponse::new_error(
+                    &preamble,
+                    &HttpBadRequest::new_json(json!({
+                        "response": "error",
+                        "err_type": error.error_type_string(),
+                        "err_msg": error.to_string()})),
+                )
+                .try_into_contents()
+                .map_err(NetError::from)
+            }
+        };
+
+        let mut preamble = HttpResponsePreamble::ok_json(&preamble);
+        preamble.set_canonical_stacks_tip_height(Some(node.canonical_stacks_tip_height()));
+        let body = HttpResponseContents::try_from_json(&response)?;
+        Ok((preamble, body))
+    }
+}
+
+impl HttpResponse for RPCGetRewardSetHandler {
+    fn try_parse_response(
+        &self,
+        preamble: &HttpResponsePreamble,
+        body: &[u8],
+    ) -> Result<HttpResponsePayload, Error> {
+        let response: RPCRewardSetData = parse_json(preamble, body)?;
+        Ok(HttpResponsePayload::try_from_json(response)?)
+    }
+}
+
+impl StacksHttpRequest {
+    /// Make a new request for a reward cycle's reward set
+    pub fn new_getrewardset(
+        host: PeerHost,
+        cycle_num: u64,
+        tip_req: TipRequest,
+    ) -> StacksHttpRequest {
+        StacksHttpRequest::new_for_peer(
+            host,
+            "GET".into(),
+            format!("/v2/pox/reward-set/{cycle_num}"),
+            HttpRequestContents::new().for_tip(tip_req),
+        )
+        .expect("FATAL: failed to construct request from infallible data")
+    }
+}
+
+impl StacksHttpResponse {
+    pub fn decode_reward_set(self) -> Result<RPCRewardSetData, NetError> {
+        let contents = self.get_http_payload_ok()?;
+        let response_json: serde_json::Value = contents.try_into()?;
+        let response: RPCRewardSetData = serde_json::from_value(response_json)
+            .map_err(|_e| Error::DecodeError("Failed to decode JSON".to_string()))?;
+        Ok(response)
+    }
+}
@@ -41,6 +41,7 @@ pub mod getmicroblocks_indexed;
 pub mod getmicroblocks_unconfirmed;
 pub mod getneighbors;
 pub mod getpoxinfo;
+pub mod getrewardset;
 pub mod getsigner;
 pub mod getsortition;
 pub mod getstackerdbchunk;
@@ -105,6 +106,7 @@ impl StacksHttp {
         self.register_rpc_endpoint(getstxtransfercost::RPCGetStxTransferCostRequestHandler::new());
         self.register_rpc_endpoint(getstackerdbchunk::RPCGetStackerDBChunkRequestHandler::new());
         self.register_rpc_endpoint(getpoxinfo::RPCPoxInfoRequestHandler::new());
+        self.register_rpc_endpoint(getrewardset::RPCGetRewardSetHandler::new());
         self.register_rpc_endpoint(
             getstackerdbmetadata::RPCGetStackerDBMetadataRequestHandler::new(),
         );
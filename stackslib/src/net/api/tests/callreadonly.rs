@@ -17,19 +17,28 @@
 use std::net::{IpAddr, Ipv4Addr, SocketAddr};
 
 use clarity::types::chainstate::StacksBlockId;
-use clarity::vm::types::{PrincipalData, QualifiedContractIdentifier, StacksAddressExtensions};
+use clarity::vm::clarity::ClarityConnection;
+use clarity::vm::costs::LimitedCostTracker;
+use clarity::vm::types::{
+    PrincipalData, QualifiedContractIdentifier, StacksAddressExtensions, TypeSignature,
+};
+use clarity::vm::{ClarityVersion, Value};
 use stacks_common::types::chainstate::StacksAddress;
 use stacks_common::types::Address;
 
-use super::test_rpc;
+use super::{test_rpc, TestRPC};
+use crate::chainstate::burn::db::sortdb::SortitionDB;
+use crate::chainstate::nakamoto::NakamotoChainState;
+use crate::chainstate::stacks::StacksBlockHeader;
 use crate::core::BLOCK_LIMIT_MAINNET_21;
 use crate::net::api::*;
 use crate::net::connection::ConnectionOptions;
+use crate::net::http::{Error as HttpError, HttpRequestContents, HttpResponsePayload};
 use crate::net::httpcore::{
     HttpPreambleExtensions, HttpRequestContentsExtensions, RPCRequestHandler, StacksHttp,
     StacksHttpRequest,
 };
-use crate::net::{ProtocolFamily, TipRequest};
+use crate::net::{Error as NetError, ProtocolFamily, TipRequest};
 
 #[test]
 fn test_try_parse_request() {
@@ -85,6 +94,7 @@ fn test_try_parse_request() {
     );
     assert_eq!(handler.sponsor, None);
     assert_eq!(handler.arguments, Some(vec![]));
+    assert_eq!(handler.estimate, Some(false));
 
     // parsed request consumes headers that would not be in a constructed reqeuest
     parsed_request.clear_headers();
@@ -99,6 +109,311 @@ fn test_try_parse_request() {
     assert!(handler.sender.is_none());
     assert!(handler.sponsor.is_none());
     assert!(handler.arguments.is_none());
+    assert!(handler.estimate.is_none());
+}
+
+#[test]
+fn test_try_parse_request_ambiguous_path() {
+    let addr = SocketAddr::new(IpAddr::V4(Ipv4Addr::new(127, 0, 0, 1)), 33333);
+    let mut http = StacksHttp::new(addr.clone(), &ConnectionOptions::default());
+
+    let request = StacksHttpRequest::new_callreadonlyfunction(
+        addr.into(),
+        StacksAddress::from_string("ST2DS4MSWSGJ3W9FBC6BVT0Y92S345HY8N3T6AV7R").unwrap(),
+        "hello-world".try_into().unwrap(),
+        StacksAddress::from_string("ST2DS4MSWSGJ3W9FBC6BVT0Y92S345HY8N3T6AV7R")
+            .unwrap()
+            .to_account_principal(),
+        None,
+        "ro-test".try_into().unwrap(),
+        vec![],
+        TipRequest::UseLatestAnchoredTip,
+    );
+    let bytes = request.try_serialize().unwrap();
+    let request_str = std::str::from_utf8(&bytes).unwrap();
+
+    for (contract_name, reason) in [
+        ("hello%2Fworld", "encoded path separator"),
+        ("hello%5cworld", "encoded path separator"),
+        ("hello%252Fworld", "double percent-encoding"),
+        ("hello%00world", "encoded control character"),
+        ("hello%C0%AFworld", "over-long UTF-8 encoding"),
+        ("hello%2world", "malformed percent-encoding"),
+    ] {
+        let ambiguous_str = request_str.replacen("hello-world", contract_name, 1);
+        let ambiguous_bytes = ambiguous_str.as_bytes();
+        let (parsed_preamble, offset) = http.read_preamble(ambiguous_bytes).unwrap();
+        let mut handler =
+            callreadonly::RPCCallReadOnlyRequestHandler::new(4096, BLOCK_LIMIT_MAINNET_21);
+        let err = http
+            .handle_try_parse_request(
+                &mut handler,
+                &parsed_preamble.expect_request(),
+                &ambiguous_bytes[offset..],
+            )
+            .unwrap_err();
+        match err {
+            NetError::Http(HttpError::AmbiguousPath(msg)) => {
+                assert!(msg.contains(reason), "{contract_name}: {msg}")
+            }
+            e => panic!("Expected an ambiguous path error for {contract_name}, got {e:?}"),
+        }
+    }
+}
+
+#[test]
+fn test_try_parse_request_estimate() {
+    let addr = SocketAddr::new(IpAddr::V4(Ipv4Addr::new(127, 0, 0, 1)), 33333);
+    let mut http = StacksHttp::new(addr.clone(), &ConnectionOptions::default());
+
+    let request = StacksHttpRequest::new_callreadonlyfunction_estimate(
+        addr.into(),
+        StacksAddress::from_string("ST2DS4MSWSGJ3W9FBC6BVT0Y92S345HY8N3T6AV7R").unwrap(),
+        "hello-world".try_into().unwrap(),
+        StacksAddress::from_string("ST2DS4MSWSGJ3W9FBC6BVT0Y92S345HY8N3T6AV7R")
+            .unwrap()
+            .to_account_principal(),
+        None,
+        "ro-confirmed".try_into().unwrap(),
+        vec![],
+        TipRequest::UseLatestAnchoredTip,
+    );
+    assert_eq!(
+        request.contents().get_query_arg("estimate"),
+        Some(&"true".to_string())
+    );
+
+    let bytes = request.try_serialize().unwrap();
+    let (parsed_preamble, offset) = http.read_preamble(&bytes).unwrap();
+    let mut handler =
+        callreadonly::RPCCallReadOnlyRequestHandler::new(4096, BLOCK_LIMIT_MAINNET_21);
+    http.handle_try_parse_request(
+        &mut handler,
+        &parsed_preamble.expect_request(),
+        &bytes[offset..],
+    )
+    .unwrap();
+    assert_eq!(handler.estimate, Some(true));
+
+    handler.restart();
+    assert!(handler.estimate.is_none());
+
+    // the flag must be a boolean
+    let request = StacksHttpRequest::new_for_peer(
+        addr.into(),
+        "POST".into(),
+        "/v2/contracts/call-read/ST2DS4MSWSGJ3W9FBC6BVT0Y92S345HY8N3T6AV7R/hello-world/ro-confirmed"
+            .into(),
+        HttpRequestContents::new()
+            .query_arg("estimate".into(), "yes".into())
+            .payload_json(
+                serde_json::to_value(callreadonly::CallReadOnlyRequestBody {
+                    sender: "ST2DS4MSWSGJ3W9FBC6BVT0Y92S345HY8N3T6AV7R".into(),
+                    sponsor: None,
+                    arguments: vec![],
+                })
+                .unwrap(),
+            ),
+    )
+    .unwrap();
+
+    let bytes = request.try_serialize().unwrap();
+    let (parsed_preamble, offset) = http.read_preamble(&bytes).unwrap();
+    assert!(http
+        .handle_try_parse_request(
+            &mut handler,
+            &parsed_preamble.expect_request(),
+            &bytes[offset..],
+        )
+        .is_err());
+}
+
+/// A raw call-read request with the given content type and body
+fn raw_call_read_request(content_type: &str, body: &str) -> String {
+    format!(
+        "POST /v2/contracts/call-read/ST2DS4MSWSGJ3W9FBC6BVT0Y92S345HY8N3T6AV7R/hello-world/ro-test HTTP/1.1\r\n\
+         Host: 127.0.0.1:33333\r\n\
+         Content-Type: {content_type}\r\n\
+         Content-Length: {}\r\n\
+         \r\n\
+         {body}",
+        body.len()
+    )
+}
+
+/// Parse the body of a raw call-read request with the given content type and body
+fn parse_call_read_body(
+    content_type: &str,
+    body: &str,
+) -> Result<(PrincipalData, Option<PrincipalData>, Vec<Value>), callreadonly::CallReadParseError> {
+    let addr = SocketAddr::new(IpAddr::V4(Ipv4Addr::new(127, 0, 0, 1)), 33333);
+    let mut http = StacksHttp::new(addr, &ConnectionOptions::default());
+    let request = raw_call_read_request(content_type, body);
+    let (preamble, offset) = http.read_preamble(request.as_bytes()).unwrap();
+    let handler = callreadonly::RPCCallReadOnlyRequestHandler::new(4096, BLOCK_LIMIT_MAINNET_21);
+    handler.parse_body(&preamble.expect_request(), &request.as_bytes()[offset..])
+}
+
+/// Malformed call-read requests: name, content type, body, and the error they are rejected with
+fn malformed_call_read_cases() -> Vec<(
+    &'static str,
+    &'static str,
+    String,
+    callreadonly::CallReadParseError,
+)> {
+    use callreadonly::CallReadParseError;
+
+    let sender = "ST2DS4MSWSGJ3W9FBC6BVT0Y92S345HY8N3T6AV7R";
+    let good_arg = Value::UInt(1).serialize_to_hex().unwrap();
+    let json = "application/json";
+    let too_large = format!(
+        r#"{{"sender":"{sender}","arguments":["{}"]}}"#,
+        "00".repeat(4096)
+    );
+    vec![
+        (
+            "empty body",
+            json,
+            String::new(),
+            CallReadParseError::EmptyBody,
+        ),
+        (
+            "body too large",
+            json,
+            too_large.clone(),
+            CallReadParseError::BodyTooLarge {
+                length: too_large.len().try_into().unwrap(),
+                max: 4096,
+            },
+        ),
+        (
+            "not JSON",
+            "text/plain",
+            format!(r#"{{"sender":"{sender}","arguments":[]}}"#),
+            CallReadParseError::InvalidContentType,
+        ),
+        (
+            "malformed JSON",
+            json,
+            format!(r#"{{"sender":"{sender}","arguments":"#),
+            CallReadParseError::MalformedJson,
+        ),
+        (
+            "missing arguments",
+            json,
+            format!(r#"{{"sender":"{sender}"}}"#),
+            CallReadParseError::MalformedJson,
+        ),
+        (
+            "bad sender",
+            json,
+            r#"{"sender":"not-a-principal","arguments":[]}"#.to_string(),
+            CallReadParseError::InvalidSender,
+        ),
+        (
+            "bad sponsor",
+            json,
+            format!(r#"{{"sender":"{sender}","sponsor":"nope","arguments":[]}}"#),
+            CallReadParseError::InvalidSponsor,
+        ),
+        (
+            "non-hex argument",
+            json,
+            format!(r#"{{"sender":"{sender}","arguments":["{good_arg}","zz"]}}"#),
+            CallReadParseError::ArgumentDecode(1),
+        ),
+        (
+            "truncated argument",
+            json,
+            format!(r#"{{"sender":"{sender}","arguments":["{good_arg}","{good_arg}","0c"]}}"#),
+            CallReadParseError::ArgumentDecode(2),
+        ),
+    ]
+}
+
+#[test]
+fn test_try_parse_request_malformed() {
+    let sender = "ST2DS4MSWSGJ3W9FBC6BVT0Y92S345HY8N3T6AV7R";
+    let good_arg = Value::UInt(1).serialize_to_hex().unwrap();
+
+    let (parsed_sender, sponsor, arguments) = parse_call_read_body(
+        "application/json",
+        &format!(r#"{{"sender":"{sender}","arguments":["{good_arg}"]}}"#),
+    )
+    .unwrap();
+    assert_eq!(parsed_sender, PrincipalData::parse(sender).unwrap());
+    assert_eq!(sponsor, None);
+    assert_eq!(arguments, vec![Value::UInt(1)]);
+
+    for (name, content_type, body, expected) in malformed_call_read_cases() {
+        assert_eq!(
+            parse_call_read_body(content_type, &body).unwrap_err(),
+            expected,
+            "{name}"
+        );
+    }
+}
+
+/// A malformed request is rejected with a 400 whose JSON body names the parse error
+#[test]
+fn test_try_parse_request_malformed_http_error() {
+    let addr = SocketAddr::new(IpAddr::V4(Ipv4Addr::new(127, 0, 0, 1)), 33333);
+    let mut http = StacksHttp::new(addr, &ConnectionOptions::default());
+
+    for (name, content_type, body, expected) in malformed_call_read_cases() {
+        let request = raw_call_read_request(content_type, &body);
+        let (preamble, offset) = http.read_preamble(request.as_bytes()).unwrap();
+        let mut handler =
+            callreadonly::RPCCallReadOnlyRequestHandler::new(4096, BLOCK_LIMIT_MAINNET_21);
+        let err = http
+            .handle_try_parse_request(
+                &mut handler,
+                &preamble.expect_request(),
+                &request.as_bytes()[offset..],
+            )
+            .unwrap_err();
+        let http_error = match err {
+            NetError::Http(http_error) => http_error,
+            e => panic!("{name}: expected an HTTP error, got {e:?}"),
+        };
+        let HttpError::BadRequestJson(ref error_body) = http_error else {
+            panic!("{name}: expected a JSON bad request, got {http_error:?}");
+        };
+        assert_eq!(error_body["err_type"], expected.err_type(), "{name}");
+        assert_eq!(error_body["err_msg"], expected.to_string(), "{name}");
+
+        let response = http_error.into_http_error();
+        assert_eq!(response.code(), 400, "{name}");
+        assert_eq!(
+            response.payload(),
+            HttpResponsePayload::JSON(expected.into_json()),
+            "{name}"
+        );
+    }
+}
+
+#[test]
+fn test_try_parse_request_max_arguments() {
+    let sender = "ST2DS4MSWSGJ3W9FBC6BVT0Y92S345HY8N3T6AV7R";
+    let body_with_args = |count: usize, arg: &str| {
+        let arguments = vec![format!("\"{arg}\""); count].join(",");
+        format!(r#"{{"sender":"{sender}","arguments":[{arguments}]}}"#)
+    };
+    let max = callreadonly::MAX_CALL_READ_ARGUMENTS;
+
+    let true_hex = Value::Bool(true).serialize_to_hex().unwrap();
+    let (_, _, arguments) =
+        parse_call_read_body("application/json", &body_with_args(max, &true_hex)).unwrap();
+    assert_eq!(arguments, vec![Value::Bool(true); max]);
+
+    // rejected by count, before the (undecodable) arguments are looked at
+    assert_eq!(
+        parse_call_read_body("application/json", &body_with_args(max + 1, "")).unwrap_err(),
+        callreadonly::CallReadParseError::TooManyArguments {
+            count: max + 1,
+            max,
+        }
+    );
 }
 
 #[test]
@@ -269,3 +584,217 @@ fn test_try_make_response() {
     let (preamble, payload) = response.destruct();
     assert_eq!(preamble.status_code, 404);
 }
+
+#[test]
+fn test_try_make_response_argument_type_mismatch() {
+    let addr = SocketAddr::new(IpAddr::V4(Ipv4Addr::new(127, 0, 0, 1)), 33333);
+
+    // `set-bar` takes two ints, but the second argument is a uint
+    let arguments = [Value::Int(1), Value::UInt(2)];
+    let request = StacksHttpRequest::new_for_peer(
+        addr.into(),
+        "POST".into(),
+        "/v2/contracts/call-read/ST2DS4MSWSGJ3W9FBC6BVT0Y92S345HY8N3T6AV7R/hello-world/set-bar"
+            .into(),
+        HttpRequestContents::new()
+            .for_tip(TipRequest::UseLatestAnchoredTip)
+            .payload_json(
+                serde_json::to_value(callreadonly::CallReadOnlyRequestBody {
+                    sender: "ST2DS4MSWSGJ3W9FBC6BVT0Y92S345HY8N3T6AV7R".into(),
+                    sponsor: None,
+                    arguments: arguments
+                        .iter()
+                        .map(|arg| arg.serialize_to_hex().unwrap())
+                        .collect(),
+                })
+                .unwrap(),
+            ),
+    )
+    .unwrap();
+
+    let mut responses = test_rpc(function_name!(), vec![request]);
+
+    let response = responses.remove(0);
+    debug!(
+        "Response:\n{}\n",
+        std::str::from_utf8(&response.try_serialize().unwrap()).unwrap()
+    );
+
+    let resp = response.decode_call_readonly_response().unwrap();
+
+    assert!(!resp.okay);
+    assert!(resp.result.is_none());
+    assert_eq!(
+        resp.cause,
+        Some(
+            callreadonly::CallReadParseError::ArgumentTypeMismatch {
+                index: 1,
+                expected: TypeSignature::IntType,
+                got: TypeSignature::UIntType,
+            }
+            .to_string()
+        )
+    );
+}
+
+#[test]
+fn test_try_make_response_estimate() {
+    let addr = SocketAddr::new(IpAddr::V4(Ipv4Addr::new(127, 0, 0, 1)), 33333);
+    let mut rpc_test = TestRPC::setup(function_name!());
+
+    let contract_addr =
+        StacksAddress::from_string("ST2DS4MSWSGJ3W9FBC6BVT0Y92S345HY8N3T6AV7R").unwrap();
+    let contract_id =
+        QualifiedContractIdentifier::parse("ST2DS4MSWSGJ3W9FBC6BVT0Y92S345HY8N3T6AV7R.hello-world")
+            .unwrap();
+    let sender = contract_addr.to_account_principal();
+
+    // measure the cost of actually calling `ro-confirmed` at the canonical tip
+    let peer = &mut rpc_test.peer_1;
+    let sortdb = peer.sortdb.take().unwrap();
+    let (consensus_hash, block_hash) =
+        SortitionDB::get_canonical_stacks_chain_tip_hash(sortdb.conn()).unwrap();
+    let tip = StacksBlockId::new(&consensus_hash, &block_hash);
+    let chainstate = peer.chainstate();
+    let mainnet = chainstate.mainnet;
+    let chain_id = chainstate.chain_id;
+    let actual_cost = chainstate
+        .with_read_only_clarity_tx(
+            &sortdb.index_handle_at_block(chainstate, &tip).unwrap(),
+            &tip,
+            |clarity_tx| {
+                let epoch = clarity_tx.get_epoch();
+                let cost_track = clarity_tx
+                    .with_clarity_db_readonly(|clarity_db| {
+                        LimitedCostTracker::new_mid_block(
+                            mainnet,
+                            chain_id,
+                            BLOCK_LIMIT_MAINNET_21,
+                            clarity_db,
+                            epoch,
+                        )
+                    })
+                    .unwrap();
+                clarity_tx
+                    .with_readonly_clarity_env(
+                        mainnet,
+                        chain_id,
+                        ClarityVersion::default_for_epoch(epoch),
+                        sender.clone(),
+                        None,
+                        cost_track,
+                        |env| {
+                            env.execute_contract(&contract_id, "ro-confirmed", &[], false)?;
+                            Ok(env.global_context.cost_track.get_total())
+                        },
+                    )
+                    .unwrap()
+            },
+        )
+        .unwrap();
+    peer.sortdb = Some(sortdb);
+
+    let request = StacksHttpRequest::new_callreadonlyfunction_estimate(
+        addr.into(),
+        contract_addr,
+        "hello-world".try_into().unwrap(),
+        sender,
+        None,
+        "ro-confirmed".try_into().unwrap(),
+        vec![],
+        TipRequest::UseLatestAnchoredTip,
+    );
+
+    let mut responses = rpc_test.run(vec![request]);
+
+    let response = responses.remove(0);
+    debug!(
+        "Response:\n{}\n",
+        std::str::from_utf8(&response.try_serialize().unwrap()).unwrap()
+    );
+
+    let resp = response.decode_call_readonly_response().unwrap();
+
+    assert!(resp.okay);
+    assert!(resp.result.is_none());
+    assert!(resp.cause.is_none());
+
+    let estimate = resp.estimate.unwrap();
+    assert!(!estimate.exceeds_limit);
+    assert!(actual_cost.runtime > 0);
+    assert_eq!(estimate.cost, actual_cost);
+}
+
+#[test]
+fn test_try_make_response_tip() {
+    let addr = SocketAddr::new(IpAddr::V4(Ipv4Addr::new(127, 0, 0, 1)), 33333);
+    let mut rpc_test = TestRPC::setup(function_name!());
+
+    // advance the canonical tip past the block that deployed `hello-world`, so that the
+    // canonical tip and the requested tip differ
+    let requested_tip = rpc_test.canonical_tip.clone();
+    let (next_consensus_hash, next_block) = rpc_test.next_block.take().unwrap();
+    rpc_test
+        .peer_1
+        .process_stacks_epoch_at_tip(&next_block, &[]);
+    rpc_test
+        .peer_2
+        .process_stacks_epoch_at_tip(&next_block, &[]);
+    let canonical_tip =
+        StacksBlockHeader::make_index_block_hash(&next_consensus_hash, &next_block.block_hash());
+
+    let chainstate = rpc_test.peer_2.chainstate();
+    let [canonical_burn_height, requested_burn_height] =
+        [&canonical_tip, &requested_tip].map(|tip| {
+            let header = NakamotoChainState::get_block_header(chainstate.db(), tip)
+                .unwrap()
+                .unwrap();
+            u64::from(header.burn_header_height)
+        });
+    assert!(canonical_burn_height > requested_burn_height);
+
+    let make_request = |tip_req| {
+        StacksHttpRequest::new_callreadonlyfunction(
+            addr.into(),
+            StacksAddress::from_string("ST2DS4MSWSGJ3W9FBC6BVT0Y92S345HY8N3T6AV7R").unwrap(),
+            "hello-world".try_into().unwrap(),
+            StacksAddress::from_string("ST2DS4MSWSGJ3W9FBC6BVT0Y92S345HY8N3T6AV7R")
+                .unwrap()
+                .to_account_principal(),
+            None,
+            "ro-confirmed".try_into().unwrap(),
+            vec![],
+            tip_req,
+        )
+    };
+
+    let requests = vec![
+        // no tip given: evaluated at the canonical tip
+        make_request(TipRequest::UseLatestAnchoredTip),
+        // evaluated at the requested tip
+        make_request(TipRequest::SpecificTip(requested_tip.clone())),
+    ];
+    let mut responses = rpc_test.run(requests);
+
+    let response = responses.remove(0);
+    let resp = response.decode_call_readonly_response().unwrap();
+    assert!(resp.okay);
+    assert_eq!(
+        resp.tip,
+        Some(callreadonly::CallReadOnlyTip {
+            index_block_hash: canonical_tip,
+            burn_block_height: Some(canonical_burn_height),
+        })
+    );
+
+    let response = responses.remove(0);
+    let resp = response.decode_call_readonly_response().unwrap();
+    assert!(resp.okay);
+    assert_eq!(
+        resp.tip,
+        Some(callreadonly::CallReadOnlyTip {
+            index_block_hash: requested_tip,
+            burn_block_height: Some(requested_burn_height),
+        })
+    );
+}
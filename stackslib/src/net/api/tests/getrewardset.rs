@@ -0,0 +1,169 @@
+// Copyright (C) 2025 Stacks Open Internet Foundation
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with this program.  If not, see <http://www.gnu.org/licenses/>.
+
+use std::collections::{BTreeMap, HashMap};
+use std::net::{IpAddr, Ipv4Addr, SocketAddr};
+
+use stacks_common::types::net::PeerHost;
+
+use crate::chainstate::burn::db::sortdb::SortitionDB;
+use crate::chainstate::stacks::address::PoxAddress;
+use crate::chainstate::stacks::boot::pox_2_tests::get_reward_set_entries_at;
+use crate::net::api::getrewardset::{self, RPCRewardedAddress};
+use crate::net::api::tests::TestRPC;
+use crate::net::connection::ConnectionOptions;
+use crate::net::http::{Error as HttpError, HttpRequestPreamble, HttpVersion};
+use crate::net::httpcore::{RPCRequestHandler, StacksHttp, StacksHttpRequest, TipRequest};
+use crate::net::test::TestEventObserver;
+use crate::net::Error as NetError;
+
+fn make_preamble(query: &str) -> HttpRequestPreamble {
+    HttpRequestPreamble {
+        version: HttpVersion::Http11,
+        verb: "GET".into(),
+        path_and_query_str: format!("/v2/pox/reward-set{query}"),
+        host: PeerHost::DNS("localhost".into(), 0),
+        content_type: None,
+        content_length: Some(0),
+        keep_alive: false,
+        headers: BTreeMap::new(),
+        set_cookie: Vec::new(),
+    }
+}
+
+#[test]
+fn test_try_parse_request() {
+    let addr = SocketAddr::new(IpAddr::V4(Ipv4Addr::new(127, 0, 0, 1)), 33333);
+    let http = StacksHttp::new(addr.clone(), &ConnectionOptions::default());
+
+    let mut handler = getrewardset::RPCGetRewardSetHandler::new();
+    let mut bad_content_length_preamble = make_preamble("/5");
+    bad_content_length_preamble.content_length = Some(1);
+    let tests = vec![
+        (make_preamble("/5"), Ok(Some(5))),
+        (make_preamble("/4294967295"), Ok(Some(4294967295))),
+        (make_preamble("/foo"), Err(NetError::NotFoundError)),
+        (make_preamble("/12345678901"), Err(NetError::NotFoundError)),
+        (make_preamble(""), Err(NetError::NotFoundError)),
+        (
+            bad_content_length_preamble,
+            Err(
+                HttpError::DecodeError("Invalid Http request: expected 0-length body".into())
+                    .into(),
+            ),
+        ),
+    ];
+
+    for (inp, expected_result) in tests.into_iter() {
+        handler.restart();
+        let parsed_request = http.handle_try_parse_request(&mut handler, &inp, &[]);
+        match expected_result {
+            Ok(cycle) => {
+                assert!(parsed_request.is_ok());
+                assert_eq!(handler.cycle_number, cycle);
+            }
+            Err(e) => {
+                assert_eq!(e, parsed_request.unwrap_err());
+            }
+        }
+    }
+
+    // restart clears the request state
+    handler.restart();
+    assert!(handler.cycle_number.is_none());
+}
+
+#[test]
+fn test_try_make_response() {
+    let addr = SocketAddr::new(IpAddr::V4(Ipv4Addr::new(127, 0, 0, 1)), 33333);
+
+    let test_observer = TestEventObserver::new();
+    let mut rpc_test = TestRPC::setup_nakamoto(function_name!(), &test_observer);
+
+    // the boot plan has the test signers stack, so the current cycle has a reward set
+    let nakamoto_chain_tip = rpc_test.canonical_tip.clone();
+    let burnchain = rpc_test.peer_1.config.burnchain.clone();
+    let sort_tip =
+        SortitionDB::get_canonical_burn_chain_tip(rpc_test.peer_1.sortdb().conn()).unwrap();
+    let cycle_num = burnchain
+        .block_height_to_reward_cycle(sort_tip.block_height)
+        .unwrap();
+    let entries = get_reward_set_entries_at(
+        &mut rpc_test.peer_1,
+        &nakamoto_chain_tip,
+        burnchain.reward_cycle_to_block_height(cycle_num),
+    );
+    assert!(!entries.is_empty());
+
+    let requests = vec![
+        StacksHttpRequest::new_getrewardset(
+            addr.into(),
+            cycle_num,
+            TipRequest::SpecificTip(nakamoto_chain_tip.clone()),
+        ),
+        // pox-4 wasn't active yet in cycle 0
+        StacksHttpRequest::new_getrewardset(
+            addr.into(),
+            0,
+            TipRequest::SpecificTip(nakamoto_chain_tip),
+        ),
+    ];
+    let mut responses = rpc_test.run(requests);
+
+    let response = responses.remove(0);
+    info!("response: {:?}", &response);
+    let reward_set = response.decode_reward_set().unwrap();
+    assert_eq!(reward_set.cycle_number, cycle_num);
+    let threshold = reward_set.pox_ustx_threshold.unwrap();
+
+    // each reward address gets one slot per `threshold` uSTX stacked to it
+    let mut stacked_per_addr: HashMap<PoxAddress, u128> = HashMap::new();
+    let mut stacked_per_signer: BTreeMap<[u8; 33], u128> = BTreeMap::new();
+    for entry in entries.iter() {
+        *stacked_per_addr
+            .entry(entry.reward_address.clone())
+            .or_default() += entry.amount_stacked;
+        *stacked_per_signer.entry(entry.signer.unwrap()).or_default() += entry.amount_stacked;
+    }
+    let mut expected_addresses: Vec<RPCRewardedAddress> = stacked_per_addr
+        .iter()
+        .flat_map(|(addr, stacked)| {
+            let slots = usize::try_from(stacked / threshold).unwrap();
+            std::iter::repeat(RPCRewardedAddress::from(addr)).take(slots)
+        })
+        .collect();
+    let mut rewarded_addresses = reward_set.rewarded_addresses.clone();
+    expected_addresses.sort_by(|a, b| a.bitcoin_address.cmp(&b.bitcoin_address));
+    rewarded_addresses.sort_by(|a, b| a.bitcoin_address.cmp(&b.bitcoin_address));
+    assert_eq!(rewarded_addresses, expected_addresses);
+
+    // signers are ordered by signing key, and weighted by their share of the threshold
+    let signers = reward_set.signers.unwrap();
+    let expected_signers: Vec<_> = stacked_per_signer
+        .into_iter()
+        .filter(|(_, stacked)| *stacked >= threshold)
+        .map(|(signing_key, stacked)| (signing_key, stacked, (stacked / threshold) as u32))
+        .collect();
+    let signers: Vec<_> = signers
+        .into_iter()
+        .map(|signer| (signer.signing_key, signer.stacked_amt, signer.weight))
+        .collect();
+    assert_eq!(signers, expected_signers);
+
+    let response = responses.remove(0);
+    info!("response: {:?}", &response);
+    let (preamble, _body) = response.destruct();
+    assert_eq!(preamble.status_code, 400);
+}
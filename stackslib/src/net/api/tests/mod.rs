@@ -80,6 +80,7 @@ mod getmicroblocks_indexed;
 mod getmicroblocks_unconfirmed;
 mod getneighbors;
 mod getpoxinfo;
+mod getrewardset;
 mod getsigner;
 mod getsortition;
 mod getstackerdbchunk;
@@ -66,6 +66,10 @@ pub enum Error {
     Http(u16, String),
     /// Application error
     AppError(String),
+    /// The request path has an encoding that could be read more than one way
+    AmbiguousPath(String),
+    /// The request is malformed, as described by this JSON error body
+    BadRequestJson(serde_json::Value),
 }
 
 impl fmt::Display for Error {
@@ -79,6 +83,8 @@ impl fmt::Display for Error {
             Error::UnderflowError(msg) => write!(f, "{}", msg),
             Error::Http(code, msg) => write!(f, "code={}, msg={}", code, msg),
             Error::AppError(msg) => write!(f, "{}", &msg),
+            Error::AmbiguousPath(msg) => write!(f, "Ambiguous request path: {msg}"),
+            Error::BadRequestJson(body) => write!(f, "Bad request: {body}"),
         }
     }
 }
@@ -94,6 +100,8 @@ impl std::error::Error for Error {
             Error::UnderflowError(_) => None,
             Error::Http(..) => None,
             Error::AppError(_) => None,
+            Error::AmbiguousPath(_) => None,
+            Error::BadRequestJson(_) => None,
         }
     }
 }
@@ -142,6 +150,10 @@ impl Error {
                 "Unhandled application error: {:?}",
                 &x
             ))),
+            Error::AmbiguousPath(x) => {
+                Box::new(HttpBadRequest::new(format!("Ambiguous request path: {x}")))
+            }
+            Error::BadRequestJson(x) => Box::new(HttpBadRequest::new_json(x)),
         }
     }
 }
@@ -703,4 +703,10 @@ pub trait HttpRequest: Send + HttpRequestClone {
     ) -> Result<HttpRequestContents, Error>;
     /// Get identifier from finite set to be used in metrics
     fn metrics_identifier(&self) -> &str;
+    /// If set, request paths that start with this prefix are decoded with
+    /// `decode_request_path_strict()`, so that paths which could be read more than one way are
+    /// rejected instead of being matched against `path_regex()`.
+    fn strict_path_prefix(&self) -> Option<&'static str> {
+        None
+    }
 }
@@ -1029,17 +1029,53 @@ impl StacksHttp {
         preamble: &HttpRequestPreamble,
         body: &[u8],
     ) -> Result<StacksHttpRequest, NetError> {
-        let (decoded_path, query) = decode_request_path(&preamble.path_and_query_str)?;
-        let captures = if let Some(caps) = handler.path_regex().captures(&decoded_path) {
-            caps
-        } else {
-            return Err(NetError::NotFoundError);
+        let decoded = decode_request_path(&preamble.path_and_query_str);
+        let regex = handler.path_regex();
+        match Self::parse_request_with_handler(
+            handler,
+            &regex,
+            preamble,
+            decoded.as_ref().ok(),
+            body,
+        )? {
+            Some(request) => Ok(request),
+            None => {
+                decoded?;
+                Err(NetError::NotFoundError)
+            }
+        }
+    }
+
+    /// Try to parse an inbound HTTP request with one handler, given the leniently-decoded
+    /// (path, query) pair (`None` if the path could not be decoded).
+    /// If the handler opts into strict path decoding for this path, the raw path is checked with
+    /// `decode_request_path_strict()` first, since the lenient decoding could have failed with a
+    /// less specific error, or decoded a separator that keeps the path from matching at all.
+    /// Returns Ok(None) if the handler's path regex does not match the decoded path.
+    fn parse_request_with_handler(
+        handler: &mut dyn RPCRequestHandler,
+        regex: &Regex,
+        preamble: &HttpRequestPreamble,
+        decoded: Option<&(String, String)>,
+        body: &[u8],
+    ) -> Result<Option<StacksHttpRequest>, NetError> {
+        if handler
+            .strict_path_prefix()
+            .is_some_and(|prefix| preamble.path_and_query_str.starts_with(prefix))
+        {
+            decode_request_path_strict(&preamble.path_and_query_str)?;
+        }
+        let Some((decoded_path, query)) = decoded else {
+            return Ok(None);
+        };
+        let Some(captures) = regex.captures(decoded_path) else {
+            return Ok(None);
         };
 
         let payload = match handler.try_parse_request(
             preamble,
             &captures,
-            if query.is_empty() { None } else { Some(&query) },
+            if query.is_empty() { None } else { Some(query) },
             body,
         ) {
             Ok(p) => p,
@@ -1049,8 +1085,7 @@ impl StacksHttp {
             }
         };
 
-        let request = StacksHttpRequest::new(preamble.clone(), payload);
-        Ok(request)
+        Ok(Some(StacksHttpRequest::new(preamble.clone(), payload)))
     }
 
     /// Try to parse an inbound HTTP request, given its decoded HTTP preamble.
@@ -1061,8 +1096,11 @@ impl StacksHttp {
         preamble: &HttpRequestPreamble,
         body: &[u8],
     ) -> Result<StacksHttpRequest, NetError> {
-        let (decoded_path, query) = decode_request_path(&preamble.path_and_query_str)?;
-        test_debug!("decoded_path: '{}', query: '{}'", &decoded_path, &query);
+        // a decoding failure is only reported if no handler rejects the path more specifically
+        let decoded = decode_request_path(&preamble.path_and_query_str);
+        if let Ok((decoded_path, query)) = decoded.as_ref() {
+            test_debug!("decoded_path: '{}', query: '{}'", decoded_path, query);
+        }
 
         // NOTE: This loop starts out like `find_response_handler()`, but `captures`'s lifetime is
         // bound to `regex` so we can't just return it from `find_response_handler()`.  Thus, it's
@@ -1071,28 +1109,22 @@ impl StacksHttp {
             if &preamble.verb != verb {
                 continue;
             }
-            let Some(captures) = regex.captures(&decoded_path) else {
-                continue;
-            };
-
-            let payload = match request.try_parse_request(
+            let Some(parsed) = Self::parse_request_with_handler(
+                request.as_mut(),
+                regex,
                 preamble,
-                &captures,
-                if query.is_empty() { None } else { Some(&query) },
+                decoded.as_ref().ok(),
                 body,
-            ) {
-                Ok(p) => p,
-                Err(e) => {
-                    request.restart();
-                    return Err(e.into());
-                }
+            )?
+            else {
+                continue;
             };
 
-            debug!("Handle StacksHttpRequest"; "verb" => %verb, "peer_addr" => %self.peer_addr, "path" => %decoded_path, "query" => %query);
-            let request = StacksHttpRequest::new(preamble.clone(), payload);
-            return Ok(request);
+            debug!("Handle StacksHttpRequest"; "verb" => %verb, "peer_addr" => %self.peer_addr, "path" => %preamble.path_and_query_str);
+            return Ok(parsed);
         }
 
+        decoded?;
         test_debug!("Failed to parse '{}'", &preamble.path_and_query_str);
         Err(NetError::Http(HttpError::Http(
             404,
@@ -1753,14 +1785,67 @@ impl PeerNetwork {
     }
 }
 
+/// A byte of a raw request path, as yielded by `scan_path_bytes()`
+enum PathByte {
+    /// A byte that was not percent-encoded
+    Raw(u8),
+    /// The value of a `%XX` percent-encoding
+    Escaped(u8),
+}
+
+/// Walk the bytes of a raw request path, decoding each `%XX` percent-encoding.
+/// Yields each byte with its offset in `raw_path`, or `Err(offset)` (and then stops) at the first
+/// malformed percent-encoding (e.g. `%ZZ`, or a trailing `%4`).
+fn scan_path_bytes(raw_path: &[u8]) -> impl Iterator<Item = Result<(usize, PathByte), usize>> + '_ {
+    let mut i = 0;
+    std::iter::from_fn(move || {
+        let offset = i;
+        let byte = *raw_path.get(offset)?;
+        if byte != b'%' {
+            i += 1;
+            return Some(Ok((offset, PathByte::Raw(byte))));
+        }
+        let value = raw_path
+            .get(offset + 1..offset + 3)
+            .and_then(|hex| std::str::from_utf8(hex).ok())
+            .filter(|hex| hex.chars().all(|c| c.is_ascii_hexdigit()))
+            .and_then(|hex| u8::from_str_radix(hex, 16).ok());
+        match value {
+            Some(value) => {
+                i += 3;
+                Some(Ok((offset, PathByte::Escaped(value))))
+            }
+            None => {
+                i = raw_path.len();
+                Some(Err(offset))
+            }
+        }
+    })
+}
+
 /// Given a raw path, decode it (i.e. if it's url-encoded)
 /// Return the (decoded-path, query-string) on success
+/// Fails if the path has a malformed percent-encoding (e.g. `%ZZ`), or a percent-encoded `/`,
+/// which would otherwise split a path segment (such as a contract name) in two once decoded.
 pub fn decode_request_path(path: &str) -> Result<(String, String), NetError> {
     let local_url = format!("http://local{}", path);
     let url = Url::parse(&local_url).map_err(|_e| {
         NetError::DeserializeError("Http request path could not be parsed".to_string())
     })?;
 
+    for scanned in scan_path_bytes(url.path().as_bytes()) {
+        let (i, byte) = scanned.map_err(|i| {
+            NetError::DeserializeError(format!(
+                "Http request path has a malformed percent-encoding at offset {i}"
+            ))
+        })?;
+        if let PathByte::Escaped(b'/') = byte {
+            return Err(NetError::DeserializeError(format!(
+                "Http request path has an encoded path separator at offset {i}"
+            )));
+        }
+    }
+
     let decoded_path = percent_decode_str(url.path()).decode_utf8().map_err(|_e| {
         NetError::DeserializeError("Http request path could not be parsed as UTF-8".to_string())
     })?;
@@ -1772,6 +1857,66 @@ pub fn decode_request_path(path: &str) -> Result<(String, String), NetError> {
     ))
 }
 
+/// Like `decode_request_path`, but reject paths whose encoding could be read more than one way:
+/// * raw or percent-encoded control characters
+/// * malformed percent-encodings, double percent-encodings (e.g. `%252F`), and over-long UTF-8
+///   encodings (e.g. `%C0%AF` for `/`)
+/// * percent-encoded path separators (`%2F` and `%5C`), which could smuggle a separator into a
+///   principal or contract name
+///
+/// Returns NetError::Http(HttpError::AmbiguousPath(..)) if the path is rejected.
+pub fn decode_request_path_strict(path: &str) -> Result<(String, String), NetError> {
+    let ambiguous = |msg: String| NetError::Http(HttpError::AmbiguousPath(msg));
+    let raw_path = path.split_once('?').map_or(path, |(raw_path, _)| raw_path);
+    let bytes = raw_path.as_bytes();
+
+    let mut decoded = Vec::with_capacity(bytes.len());
+    for scanned in scan_path_bytes(bytes) {
+        let (i, byte) =
+            scanned.map_err(|i| ambiguous(format!("malformed percent-encoding at offset {i}")))?;
+        let value = match byte {
+            PathByte::Raw(byte) => {
+                if byte.is_ascii_control() {
+                    return Err(ambiguous(format!("control character at offset {i}")));
+                }
+                decoded.push(byte);
+                continue;
+            }
+            PathByte::Escaped(value) => value,
+        };
+        if value.is_ascii_control() {
+            return Err(ambiguous(format!(
+                "encoded control character at offset {i}"
+            )));
+        }
+        if value == b'/' || value == b'\\' {
+            return Err(ambiguous(format!("encoded path separator at offset {i}")));
+        }
+        if value == b'%' {
+            return Err(ambiguous(format!("double percent-encoding at offset {i}")));
+        }
+        decoded.push(value);
+    }
+
+    // over-long UTF-8 encodings are the lead bytes 0xC0 and 0xC1, and the three- and four-byte
+    // sequences that could have been shorter
+    for (offset, pair) in decoded.windows(2).enumerate() {
+        let overlong = match (pair[0], pair[1]) {
+            (0xC0 | 0xC1, _) => true,
+            (0xE0, 0x80..=0x9F) => true,
+            (0xF0, 0x80..=0x8F) => true,
+            _ => false,
+        };
+        if overlong {
+            return Err(ambiguous(format!(
+                "over-long UTF-8 encoding at decoded offset {offset}"
+            )));
+        }
+    }
+
+    decode_request_path(path)
+}
+
 /// Convert a NetError into an io::Error if appropriate.
 fn handle_net_error(e: NetError, msg: &str) -> io::Error {
     match e {
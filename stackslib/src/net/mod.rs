@@ -2273,7 +2273,9 @@ pub mod test {
     use crate::chainstate::stacks::boot::*;
     use crate::chainstate::stacks::db::accounts::MinerReward;
     use crate::chainstate::stacks::db::{StacksChainState, *};
-    use crate::chainstate::stacks::events::{StacksBlockEventData, StacksTransactionReceipt};
+    use crate::chainstate::stacks::events::{
+        StacksBlockEventData, StacksTransactionReceipt, TransactionOrigin,
+    };
     use crate::chainstate::stacks::tests::chain_histories::mine_smart_contract_block_contract_call_microblock;
     use crate::chainstate::stacks::tests::*;
     use crate::chainstate::stacks::{StacksMicroblockHeader, *};
@@ -2290,7 +2292,7 @@ pub mod test {
     use crate::net::relay::*;
     use crate::net::stackerdb::{StackerDBSync, StackerDBs};
     use crate::net::Error as net_error;
-    use crate::util_lib::boot::{boot_code_test_addr, boot_code_tx_auth};
+    use crate::util_lib::boot::{boot_code_id, boot_code_test_addr, boot_code_tx_auth};
     use crate::util_lib::strings::*;
 
     impl StacksMessageCodec for BlockstackOperationType {
@@ -2503,6 +2505,50 @@ pub mod test {
                 blocks: Mutex::new(vec![]),
             }
         }
+
+        /// Receipts of the Stacks transactions sent by `addr` across all observed blocks, keyed
+        /// by origin nonce
+        pub fn receipts_by_sender(
+            &self,
+            addr: StacksAddress,
+        ) -> HashMap<u64, StacksTransactionReceipt> {
+            self.get_blocks()
+                .into_iter()
+                .flat_map(|block| block.receipts)
+                .filter_map(|receipt| {
+                    let nonce = Self::sender_nonce(&receipt, &addr)?;
+                    Some((nonce, receipt))
+                })
+                .collect()
+        }
+
+        /// Receipts of the Stacks transactions sent by `addr` in the last observed block, in
+        /// block order
+        pub fn receipts_in_last_block_by_sender(
+            &self,
+            addr: StacksAddress,
+        ) -> Vec<StacksTransactionReceipt> {
+            self.blocks
+                .lock()
+                .unwrap()
+                .last()
+                .expect("No blocks observed")
+                .receipts
+                .iter()
+                .filter(|receipt| Self::sender_nonce(receipt, &addr).is_some())
+                .cloned()
+                .collect()
+        }
+
+        /// The origin nonce of `receipt`'s transaction, if it is a Stacks transaction sent by
+        /// `addr`
+        fn sender_nonce(receipt: &StacksTransactionReceipt, addr: &StacksAddress) -> Option<u64> {
+            let TransactionOrigin::Stacks(ref tx) = receipt.transaction else {
+                return None;
+            };
+            let origin = tx.auth.origin();
+            (origin.address_testnet() == *addr).then(|| origin.nonce())
+        }
     }
 
     impl BlockEventDispatcher for TestEventObserver {
@@ -2662,6 +2708,9 @@ pub mod test {
         pub test_stackers: Option<Vec<TestStacker>>,
         pub test_signers: Option<TestSigners>,
         pub txindex: bool,
+        /// How many blocks (or tenures) it takes for a miner reward to mature on this peer's
+        /// chain.  Defaults to `MINER_REWARD_MATURITY`.
+        pub miner_reward_maturity: u64,
     }
 
     impl TestPeerConfig {
@@ -2715,6 +2764,7 @@ pub mod test {
                 test_stackers: None,
                 test_signers: None,
                 txindex: false,
+                miner_reward_maturity: MINER_REWARD_MATURITY,
             }
         }
 
@@ -2817,6 +2867,21 @@ pub mod test {
         thread_handle.join().unwrap();
     }
 
+    /// An account's STX balance and pox-4 state, as read by `TestPeer::pox_account_view()`
+    #[derive(Debug, Clone, PartialEq)]
+    pub struct PoxAccountView {
+        /// Spendable (unlocked) uSTX
+        pub balance: u128,
+        /// Locked uSTX
+        pub locked: u128,
+        /// Burn height at which the locked uSTX unlock, or 0 if nothing is locked
+        pub unlock_height: u64,
+        /// The account's pox-4 `stacking-state` entry, if it has one
+        pub stacking_state: Option<TupleData>,
+        /// The account's pox-4 `delegation-state` entry, if it has one
+        pub delegation_state: Option<TupleData>,
+    }
+
     pub struct TestPeer<'a> {
         pub config: TestPeerConfig,
         pub network: PeerNetwork,
@@ -3096,7 +3161,7 @@ pub mod test {
                     Some(Box::new(move || Box::new(lockups.into_iter())));
             }
 
-            let (chainstate, _) = StacksChainState::open_and_exec(
+            let (mut chainstate, _) = StacksChainState::open_and_exec(
                 false,
                 config.network_id,
                 &chainstate_path,
@@ -3104,6 +3169,7 @@ pub mod test {
                 None,
             )
             .unwrap();
+            chainstate.set_miner_reward_maturity(config.miner_reward_maturity);
 
             let indexer = BitcoinIndexer::new_unit_test(&config.burnchain.working_dir);
             let mut coord = ChainsCoordinator::test_new_full(
@@ -3116,6 +3182,11 @@ pub mod test {
                 None,
                 config.txindex,
             );
+            // the coordinator processes blocks with its own chainstate, so it must agree with
+            // the miner's about when rewards mature
+            coord
+                .chain_state_db
+                .set_miner_reward_maturity(config.miner_reward_maturity);
             coord.handle_new_burnchain_block().unwrap();
 
             let mut stacks_node = TestStacksNode::from_chainstate(chainstate);
@@ -4339,6 +4410,76 @@ pub mod test {
             tip_id
         }
 
+        /// Like `tenure_with_txs()`, but if `self.config.check_pox_invariants` is set, a failed
+        /// invariant check panics with a message naming the reward cycle whose invariants failed
+        /// and the burn height they were checked at, instead of with the bare assertion.
+        pub fn advance_checked(
+            &mut self,
+            txs: &[StacksTransaction],
+            coinbase_nonce: &mut usize,
+        ) -> StacksBlockId {
+            let check_pox_invariants = self.config.check_pox_invariants.take();
+            let tip_id = self.tenure_with_txs(txs, coinbase_nonce);
+            self.config.check_pox_invariants = check_pox_invariants;
+
+            if let Some((start_check_cycle, end_check_cycle)) = check_pox_invariants {
+                if let Err(msg) = pox_2_tests::try_check_all_stacker_link_invariants(
+                    self,
+                    &tip_id,
+                    start_check_cycle,
+                    end_check_cycle,
+                ) {
+                    panic!("{msg}");
+                }
+            }
+
+            tip_id
+        }
+
+        /// Read `principal`'s balance, lock, and pox-4 stacking and delegation state at `tip`, all
+        /// in one read-only Clarity connection so that they're consistent with each other.
+        pub fn pox_account_view(
+            &mut self,
+            tip: &StacksBlockId,
+            principal: &PrincipalData,
+        ) -> PoxAccountView {
+            pox_2_tests::with_clarity_db_ro(self, tip, |db| {
+                let (balance, stx_balance) = {
+                    let mut snapshot = db.get_stx_balance_snapshot(principal).unwrap();
+                    let balance = snapshot.get_available_balance().unwrap();
+                    (balance, snapshot.canonical_balance_repr().unwrap())
+                };
+
+                let epoch = db.get_clarity_epoch_version().unwrap();
+                let lookup_tuple = Value::Tuple(
+                    TupleData::from_data(vec![("stacker".into(), principal.clone().into())])
+                        .unwrap(),
+                );
+                let mut fetch_pox_4_entry = |map_name: &str| {
+                    db.fetch_entry_unknown_descriptor(
+                        &boot_code_id(POX_4_NAME, false),
+                        map_name,
+                        &lookup_tuple,
+                        &epoch,
+                    )
+                    .unwrap()
+                    .expect_optional()
+                    .unwrap()
+                    .map(|entry| entry.expect_tuple().unwrap())
+                };
+                let stacking_state = fetch_pox_4_entry("stacking-state");
+                let delegation_state = fetch_pox_4_entry("delegation-state");
+
+                PoxAccountView {
+                    balance,
+                    locked: stx_balance.amount_locked(),
+                    unlock_height: stx_balance.unlock_height(),
+                    stacking_state,
+                    delegation_state,
+                }
+            })
+        }
+
         /// Make a tenure, using `tenure_builder` to generate a Stacks block and a list of
         /// microblocks.
         pub fn make_tenure<F>(
@@ -4504,6 +4645,7 @@ pub mod test {
 
             let network_id = self.config.network_id;
             let chainstate_path = self.chainstate_path.clone();
+            let miner_reward_maturity = self.config.miner_reward_maturity;
             let burn_block_height = burn_block.block_height;
 
             let (stacks_block, microblocks, block_commit_op) = stacks_node.mine_stacks_block(
@@ -4516,6 +4658,7 @@ pub mod test {
                 |mut builder, ref mut miner, sortdb| {
                     let (mut miner_chainstate, _) =
                         StacksChainState::open(false, network_id, &chainstate_path, None).unwrap();
+                    miner_chainstate.set_miner_reward_maturity(miner_reward_maturity);
                     let sort_iconn = sortdb.index_handle_at_tip();
 
                     let mut miner_epoch_info = builder
@@ -36,15 +36,17 @@ use crate::chainstate::stacks::{
 use crate::net::api::getneighbors::{RPCNeighbor, RPCNeighborsInfo};
 use crate::net::connection::ConnectionOptions;
 use crate::net::http::{
-    http_error_from_code_and_text, http_reason, HttpContentType, HttpRequestContents,
-    HttpRequestPreamble, HttpResponsePayload, HttpResponsePreamble, HttpVersion,
+    http_error_from_code_and_text, http_reason, Error as HttpError, HttpContentType,
+    HttpRequestContents, HttpRequestPreamble, HttpResponsePayload, HttpResponsePreamble,
+    HttpVersion,
 };
 use crate::net::httpcore::{
-    send_http_request, HttpPreambleExtensions, HttpRequestContentsExtensions, StacksHttp,
-    StacksHttpMessage, StacksHttpPreamble, StacksHttpRequest, StacksHttpResponse,
+    decode_request_path, decode_request_path_strict, send_http_request, HttpPreambleExtensions,
+    HttpRequestContentsExtensions, StacksHttp, StacksHttpMessage, StacksHttpPreamble,
+    StacksHttpRequest, StacksHttpResponse,
 };
 use crate::net::rpc::ConversationHttp;
-use crate::net::{ProtocolFamily, TipRequest};
+use crate::net::{Error as NetError, ProtocolFamily, TipRequest};
 
 #[test]
 fn test_parse_stacks_http_preamble_request_err() {
@@ -140,6 +142,136 @@ fn test_parse_stacks_http_preamble_response_err() {
     }
 }
 
+#[test]
+fn test_decode_request_path() {
+    let prefix = "/v2/contracts/call-read/ST2DS4MSWSGJ3W9FBC6BVT0Y92S345HY8N3T6AV7R";
+    assert_eq!(
+        decode_request_path(&format!("{prefix}/hello-world/ro-test?tip=latest")).unwrap(),
+        (
+            format!("{prefix}/hello-world/ro-test"),
+            "tip=latest".to_string()
+        )
+    );
+
+    // encoded dash and underscore decode within the segment
+    assert_eq!(
+        decode_request_path(&format!("{prefix}/hello%2Dworld%5f2/ro%2dtest")).unwrap(),
+        (format!("{prefix}/hello-world_2/ro-test"), String::new())
+    );
+
+    for (path, reason) in [
+        (
+            format!("{prefix}/hello%ZZworld/ro-test"),
+            "malformed percent-encoding at offset 71",
+        ),
+        (
+            format!("{prefix}/hello-world%2"),
+            "malformed percent-encoding",
+        ),
+        (
+            format!("{prefix}/hello-world%"),
+            "malformed percent-encoding",
+        ),
+        (
+            format!("{prefix}/hello%2Fworld/ro-test"),
+            "encoded path separator",
+        ),
+        (
+            format!("{prefix}/hello%2fworld/ro-test"),
+            "encoded path separator",
+        ),
+    ] {
+        match decode_request_path(&path) {
+            Err(NetError::DeserializeError(msg)) => {
+                assert!(msg.contains(reason), "{path}: {msg}")
+            }
+            res => panic!("Expected a decode error for {path}, got {res:?}"),
+        }
+    }
+}
+
+#[test]
+fn test_decode_request_path_strict() {
+    // valid paths decode just like they do leniently
+    for path in [
+        "/v2/contracts/call-read/ST2DS4MSWSGJ3W9FBC6BVT0Y92S345HY8N3T6AV7R/hello-world/ro-test",
+        "/v2/contracts/call-read/ST2DS4MSWSGJ3W9FBC6BVT0Y92S345HY8N3T6AV7R/hello-world/ro-test?tip=latest",
+        "/v2/contracts/call-read/ST2DS4MSWSGJ3W9FBC6BVT0Y92S345HY8N3T6AV7R/hello%2Dworld/ro-test",
+        "/v2/info",
+    ] {
+        assert_eq!(
+            decode_request_path_strict(path).unwrap(),
+            decode_request_path(path).unwrap()
+        );
+    }
+
+    for (path, reason) in [
+        (
+            "/v2/contracts/call-read/ST1/hello\tworld/f",
+            "control character",
+        ),
+        (
+            "/v2/contracts/call-read/ST1/hello\x7fworld/f",
+            "control character",
+        ),
+        (
+            "/v2/contracts/call-read/ST1/hello%0aworld/f",
+            "encoded control character",
+        ),
+        (
+            "/v2/contracts/call-read/ST1/hello%7Fworld/f",
+            "encoded control character",
+        ),
+        (
+            "/v2/contracts/call-read/ST1/hello%2/f",
+            "malformed percent-encoding",
+        ),
+        (
+            "/v2/contracts/call-read/ST1/hello%zzworld/f",
+            "malformed percent-encoding",
+        ),
+        (
+            "/v2/contracts/call-read/ST1/hello%",
+            "malformed percent-encoding",
+        ),
+        (
+            "/v2/contracts/call-read/ST1/hello%252Fworld/f",
+            "double percent-encoding",
+        ),
+        (
+            "/v2/contracts/call-read/ST1/hello%C0%AFworld/f",
+            "over-long UTF-8 encoding",
+        ),
+        (
+            "/v2/contracts/call-read/ST1/hello%E0%80%AFworld/f",
+            "over-long UTF-8 encoding",
+        ),
+        (
+            "/v2/contracts/call-read/ST1/hello%F0%80%80%AFworld/f",
+            "over-long UTF-8 encoding",
+        ),
+        (
+            "/v2/contracts/call-read/ST1/hello%2Fworld/f",
+            "encoded path separator",
+        ),
+        (
+            "/v2/contracts/call-read/ST1%2fST2/hello-world/f",
+            "encoded path separator",
+        ),
+        (
+            "/v2/contracts/call-read/ST1/hello%5Cworld/f",
+            "encoded path separator",
+        ),
+    ] {
+        match decode_request_path_strict(path) {
+            Err(NetError::Http(HttpError::AmbiguousPath(msg))) => {
+                assert!(msg.contains(reason), "{path}: {msg}")
+            }
+            res => panic!("Expected an ambiguous path error for {path}, got {res:?}"),
+        }
+    }
+}
+
 fn make_test_transaction() -> StacksTransaction {
     let privk = StacksPrivateKey::from_hex(
         "6d430bb91222408e7706c9001cfaeb91b08c2be6d5ac95779ab52c6b431950e001",
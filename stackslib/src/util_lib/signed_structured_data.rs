@@ -16,10 +16,10 @@
 
 use clarity::vm::types::TupleData;
 use clarity::vm::Value;
-use stacks_common::types::chainstate::StacksPrivateKey;
+use stacks_common::types::chainstate::{StacksPrivateKey, StacksPublicKey};
 use stacks_common::types::PrivateKey;
 use stacks_common::util::hash::Sha256Sum;
-use stacks_common::util::secp256k1::{MessageSignature, Secp256k1PrivateKey};
+use stacks_common::util::secp256k1::{secp256k1_recover, MessageSignature, Secp256k1PrivateKey};
 
 use crate::chainstate::stacks::address::PoxAddress;
 
@@ -76,8 +76,9 @@ pub fn make_structured_data_domain(name: &str, version: &str, chain_id: u32) ->
 
 pub mod pox4 {
     use super::{
-        make_structured_data_domain, structured_data_message_hash, MessageSignature, PoxAddress,
-        PrivateKey, Sha256Sum, StacksPrivateKey, TupleData, Value,
+        make_structured_data_domain, secp256k1_recover, structured_data_message_hash,
+        MessageSignature, PoxAddress, PrivateKey, Sha256Sum, StacksPrivateKey, StacksPublicKey,
+        TupleData, Value,
     };
     define_named_enum!(Pox4SignatureTopic {
         StackStx("stack-stx"),
@@ -87,6 +88,14 @@ pub mod pox4 {
         StackIncrease("stack-increase"),
     });
 
+    impl Pox4SignatureTopic {
+        /// Parse a topic from the string used for it in `verify-signer-key-sig`.
+        /// This is the inverse of `get_name_str()`.
+        pub fn from_name_str(name: &str) -> Option<Self> {
+            Self::lookup_by_name(name)
+        }
+    }
+
     pub fn make_pox_4_signed_data_domain(chain_id: u32) -> Value {
         make_structured_data_domain("pox-4-signer", "1.0.0", chain_id)
     }
@@ -129,13 +138,7 @@ pub mod pox4 {
     impl Into<Pox4SignatureTopic> for &'static str {
         #[cfg_attr(test, mutants::skip)]
         fn into(self) -> Pox4SignatureTopic {
-            match self {
-                "stack-stx" => Pox4SignatureTopic::StackStx,
-                "agg-commit" => Pox4SignatureTopic::AggregationCommit,
-                "stack-extend" => Pox4SignatureTopic::StackExtend,
-                "stack-increase" => Pox4SignatureTopic::StackIncrease,
-                _ => panic!("Invalid pox-4 signature topic"),
-            }
+            Pox4SignatureTopic::from_name_str(self).expect("Invalid pox-4 signature topic")
         }
     }
 
@@ -162,6 +165,76 @@ pub mod pox4 {
         signer_key.sign(msg_hash.as_bytes())
     }
 
+    /// Check, without the Clarity VM, that `signature` (in RSV form) is `signing_key`'s signature
+    /// of the pox-4 signer key message for the given fields, and that `amount` is within
+    /// `max_amount`. This is what pox-4's `verify-signer-key-sig` checks when given a signature,
+    /// except for whether the authorization was already used, which needs the chain state.
+    pub fn verify_signer_key_signature(
+        signature: &[u8],
+        signing_key: &StacksPublicKey,
+        pox_addr: &PoxAddress,
+        reward_cycle: u128,
+        topic: &Pox4SignatureTopic,
+        chain_id: u32,
+        period: u128,
+        amount: u128,
+        max_amount: u128,
+        auth_id: u128,
+    ) -> bool {
+        if amount > max_amount || signature.len() != 65 {
+            return false;
+        }
+        let msg_hash = make_pox_4_signer_key_message_hash(
+            pox_addr,
+            reward_cycle,
+            topic,
+            chain_id,
+            period,
+            max_amount,
+            auth_id,
+        );
+        secp256k1_recover(msg_hash.as_bytes(), signature)
+            .is_ok_and(|recovered| recovered[..] == signing_key.to_bytes_compressed()[..])
+    }
+
+    /// Sign each of `entries` with `signer_key`, as `make_pox_4_signer_key_signature` does.
+    /// Each entry is `(pox_addr, reward_cycle, topic, period, max_amount, auth_id)`.
+    /// Returns the signatures in RSV form, in the order of `entries`.
+    /// Stops at the first entry with a zero period, since pox-4 rejects that for every topic.
+    pub fn make_signer_key_signatures_batch(
+        signer_key: &StacksPrivateKey,
+        chain_id: u32,
+        entries: Vec<(PoxAddress, u128, Pox4SignatureTopic, u128, u128, u128)>,
+    ) -> Result<Vec<Vec<u8>>, String> {
+        entries
+            .iter()
+            .enumerate()
+            .map(
+                |(index, (pox_addr, reward_cycle, topic, period, max_amount, auth_id))| {
+                    if *period == 0 {
+                        return Err(format!(
+                            "Entry {index} ({} for reward cycle {reward_cycle}) has a zero period",
+                            topic.get_name_str()
+                        ));
+                    }
+                    let msg_hash = make_pox_4_signer_key_message_hash(
+                        pox_addr,
+                        *reward_cycle,
+                        topic,
+                        chain_id,
+                        *period,
+                        *max_amount,
+                        *auth_id,
+                    );
+                    let signature = signer_key
+                        .sign(msg_hash.as_bytes())
+                        .map_err(|e| format!("Failed to sign entry {index}: {e}"))?;
+                    Ok(signature.to_rsv())
+                },
+            )
+            .collect()
+    }
+
     #[cfg(test)]
     mod tests {
         use clarity::vm::ast::ASTRules;
@@ -405,6 +478,186 @@ pub mod pox4 {
 
             assert_eq!(to_hex(message_hash.as_bytes()), fixture);
         }
+
+        #[test]
+        fn test_topic_from_name_str_round_trip() {
+            for topic in Pox4SignatureTopic::ALL {
+                assert_eq!(
+                    Pox4SignatureTopic::from_name_str(topic.get_name_str()),
+                    Some(*topic)
+                );
+            }
+            assert_eq!(
+                Pox4SignatureTopic::from_name_str("agg-increase"),
+                Some(Pox4SignatureTopic::AggregationIncrease)
+            );
+        }
+
+        /// Arguments of `verify_signer_key_signature`
+        #[derive(Clone)]
+        struct SignedFields {
+            signature: Vec<u8>,
+            signing_key: Secp256k1PublicKey,
+            pox_addr: PoxAddress,
+            reward_cycle: u128,
+            topic: Pox4SignatureTopic,
+            chain_id: u32,
+            period: u128,
+            amount: u128,
+            max_amount: u128,
+            auth_id: u128,
+        }
+
+        impl SignedFields {
+            fn verify(&self) -> bool {
+                verify_signer_key_signature(
+                    &self.signature,
+                    &self.signing_key,
+                    &self.pox_addr,
+                    self.reward_cycle,
+                    &self.topic,
+                    self.chain_id,
+                    self.period,
+                    self.amount,
+                    self.max_amount,
+                    self.auth_id,
+                )
+            }
+        }
+
+        #[test]
+        fn test_verify_signer_key_signature() {
+            let signer_key = StacksPrivateKey::from_seed(&[1]);
+            let pox_addr = PoxAddress::standard_burn_address(false);
+            let signature = make_pox_4_signer_key_signature(
+                &pox_addr,
+                &signer_key,
+                5,
+                &Pox4SignatureTopic::StackStx,
+                CHAIN_ID_TESTNET,
+                2,
+                2000,
+                7,
+            )
+            .unwrap()
+            .to_rsv();
+            let signed = SignedFields {
+                signature,
+                signing_key: Secp256k1PublicKey::from_private(&signer_key),
+                pox_addr,
+                reward_cycle: 5,
+                topic: Pox4SignatureTopic::StackStx,
+                chain_id: CHAIN_ID_TESTNET,
+                period: 2,
+                amount: 1000,
+                max_amount: 2000,
+                auth_id: 7,
+            };
+            assert!(signed.verify());
+            // any amount up to `max_amount` is allowed
+            assert!(SignedFields {
+                amount: 2000,
+                ..signed.clone()
+            }
+            .verify());
+
+            let mut tampered_signature = signed.signature.clone();
+            tampered_signature[10] ^= 0xff;
+            let tampered = [
+                (
+                    "signing key",
+                    SignedFields {
+                        signing_key: Secp256k1PublicKey::from_private(
+                            &StacksPrivateKey::from_seed(&[2]),
+                        ),
+                        ..signed.clone()
+                    },
+                ),
+                (
+                    "pox addr",
+                    SignedFields {
+                        pox_addr: PoxAddress::standard_burn_address(true),
+                        ..signed.clone()
+                    },
+                ),
+                (
+                    "reward cycle",
+                    SignedFields {
+                        reward_cycle: 6,
+                        ..signed.clone()
+                    },
+                ),
+                (
+                    "topic",
+                    SignedFields {
+                        topic: Pox4SignatureTopic::StackExtend,
+                        ..signed.clone()
+                    },
+                ),
+                (
+                    "chain id",
+                    SignedFields {
+                        chain_id: CHAIN_ID_TESTNET + 1,
+                        ..signed.clone()
+                    },
+                ),
+                (
+                    "period",
+                    SignedFields {
+                        period: 3,
+                        ..signed.clone()
+                    },
+                ),
+                (
+                    "amount above max amount",
+                    SignedFields {
+                        amount: 2001,
+                        ..signed.clone()
+                    },
+                ),
+                (
+                    "max amount",
+                    SignedFields {
+                        max_amount: 3000,
+                        ..signed.clone()
+                    },
+                ),
+                (
+                    "auth id",
+                    SignedFields {
+                        auth_id: 8,
+                        ..signed.clone()
+                    },
+                ),
+                (
+                    "signature",
+                    SignedFields {
+                        signature: tampered_signature,
+                        ..signed.clone()
+                    },
+                ),
+                (
+                    "truncated signature",
+                    SignedFields {
+                        signature: signed.signature[..64].to_vec(),
+                        ..signed.clone()
+                    },
+                ),
+            ];
+            for (name, fields) in tampered {
+                assert!(
+                    !fields.verify(),
+                    "Signature should not verify with a wrong {name}"
+                );
+            }
+        }
+
+        #[test]
+        fn test_topic_from_name_str_unknown() {
+            for name in ["", "stack_stx", "Stack-Stx", "stack-stx ", "agg-decrease"] {
+                assert_eq!(Pox4SignatureTopic::from_name_str(name), None);
+            }
+        }
     }
 }
 
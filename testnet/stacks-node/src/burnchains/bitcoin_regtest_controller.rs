@@ -181,6 +181,9 @@ pub fn make_bitcoin_indexer(
             first_block: burnchain_params.first_block_height,
             magic_bytes: burnchain_config.magic_bytes,
             epochs: burnchain_config.epochs,
+            rate_limit: burnchain_config.download_rate_limit,
+            reorg_lookback: burnchain_config.reorg_lookback,
+            parse_workers: burnchain_config.parse_workers,
         }
     };
 
@@ -190,6 +193,7 @@ pub fn make_bitcoin_indexer(
         config: indexer_config,
         runtime: indexer_runtime,
         should_keep_running,
+        unparsed_tx_sampler: None,
     }
 }
 
@@ -342,6 +346,9 @@ impl BitcoinRegtestController {
                 first_block: burnchain_params.first_block_height,
                 magic_bytes: burnchain_config.magic_bytes,
                 epochs: burnchain_config.epochs,
+                rate_limit: burnchain_config.download_rate_limit,
+                reorg_lookback: burnchain_config.reorg_lookback,
+                parse_workers: burnchain_config.parse_workers,
             }
         };
 
@@ -351,6 +358,7 @@ impl BitcoinRegtestController {
             config: indexer_config,
             runtime: indexer_runtime,
             should_keep_running: should_keep_running.clone(),
+            unparsed_tx_sampler: None,
         };
 
         Self {
@@ -388,6 +396,9 @@ impl BitcoinRegtestController {
                 first_block: burnchain_params.first_block_height,
                 magic_bytes: burnchain_config.magic_bytes,
                 epochs: burnchain_config.epochs,
+                rate_limit: burnchain_config.download_rate_limit,
+                reorg_lookback: burnchain_config.reorg_lookback,
+                parse_workers: burnchain_config.parse_workers,
             }
         };
 
@@ -397,6 +408,7 @@ impl BitcoinRegtestController {
             config: indexer_config,
             runtime: indexer_runtime,
             should_keep_running: None,
+            unparsed_tx_sampler: None,
         };
 
         Self {
@@ -574,7 +586,7 @@ impl BitcoinRegtestController {
                 Err(e) => {
                     // keep trying
                     error!("Unable to sync with burnchain: {e}");
-                    match e {
+                    match e.root_cause() {
                         burnchain_error::CoordinatorClosed => {
                             return Err(BurnchainControllerError::CoordinatorClosed)
                         }
@@ -5,6 +5,8 @@ use proptest::strategy::Strategy;
 use tracing::info;
 
 use super::context::{SignerTestContext, SignerTestState};
+use super::CommandSelection;
+use crate::tests::neon_integrations::get_chain_info;
 
 /// Command to mine a single Bitcoin block in the test environment and wait for its confirmation.
 /// This command simulates the process of mining a new Bitcoin block in the Stacks blockchain
@@ -49,6 +51,9 @@ impl Command<SignerTestState, SignerTestContext> for MinerMineBitcoinBlocks {
             .unwrap()
             .mine_bitcoin_blocks_and_confirm(&sortdb, self.num_blocks, 30)
             .expect("Failed to mine BTC block");
+
+        state.last_burn_block_height =
+            Some(get_chain_info(&self.ctx.get_node_config(1)).burn_block_height);
     }
 
     fn label(&self) -> String {
@@ -66,6 +71,8 @@ impl Command<SignerTestState, SignerTestContext> for MinerMineBitcoinBlocks {
     }
 }
 
+impl CommandSelection<SignerTestState, SignerTestContext> for MinerMineBitcoinBlocks {}
+
 /// Command to generate a specified number of Bitcoin blocks in the regtest environment.
 /// Unlike other mining commands, this command directly instructs the Bitcoin regtest
 /// controller to generate between 1-5 blocks without waiting for confirmations or
@@ -127,3 +134,5 @@ impl Command<SignerTestState, SignerTestContext> for ChainGenerateBitcoinBlocks
         })
     }
 }
+
+impl CommandSelection<SignerTestState, SignerTestContext> for ChainGenerateBitcoinBlocks {}
@@ -4,6 +4,7 @@ use madhouse::{Command, CommandWrapper};
 use proptest::prelude::Strategy;
 
 use super::context::{SignerTestContext, SignerTestState};
+use super::CommandSelection;
 
 /// Command to manually trigger a block commit submission for a specified miner
 pub struct MinerSubmitNakaBlockCommit {
@@ -81,3 +82,5 @@ impl Command<SignerTestState, SignerTestContext> for MinerSubmitNakaBlockCommit
         })
     }
 }
+
+impl CommandSelection<SignerTestState, SignerTestContext> for MinerSubmitNakaBlockCommit {}
@@ -4,6 +4,7 @@ use madhouse::{Command, CommandWrapper};
 use proptest::prelude::Strategy;
 
 use super::context::{SignerTestContext, SignerTestState};
+use super::CommandSelection;
 
 /// Command to verify that a specified miner has produced the expected number of blocks
 /// based on different height calculation strategies.
@@ -131,3 +132,5 @@ impl Command<SignerTestState, SignerTestContext> for ChainVerifyMinerNakaBlockCo
         })
     }
 }
+
+impl CommandSelection<SignerTestState, SignerTestContext> for ChainVerifyMinerNakaBlockCount {}
@@ -8,6 +8,7 @@ use proptest::prop_oneof;
 use stacks::chainstate::stacks::{TenureChangeCause, TenureChangePayload, TransactionPayload};
 
 use super::context::{SignerTestContext, SignerTestState};
+use super::CommandSelection;
 use crate::tests::neon_integrations::get_chain_info;
 use crate::tests::signer::v0::{
     wait_for_block_global_rejection_with_reject_reason, wait_for_block_proposal,
@@ -185,6 +186,8 @@ impl Command<SignerTestState, SignerTestContext> for ChainExpectNakaBlock {
     }
 }
 
+impl CommandSelection<SignerTestState, SignerTestContext> for ChainExpectNakaBlock {}
+
 /// Command to wait for a block proposal from a specific miner in the Nakamoto consensus protocol.
 /// This command monitors the blockchain until the specified miner submits a block proposal at the expected height.
 /// Can optionally wait for the proposed block to be rejected or accepted based on the expectation.
@@ -328,6 +331,8 @@ impl Command<SignerTestState, SignerTestContext> for ChainExpectNakaBlockProposa
     }
 }
 
+impl CommandSelection<SignerTestState, SignerTestContext> for ChainExpectNakaBlockProposal {}
+
 /// Command to wait for a tenure change block from a specific miner.
 /// This command waits for a block that contains:
 /// 1. A TenureChange transaction with cause BlockFound
@@ -411,3 +416,5 @@ impl Command<SignerTestState, SignerTestContext> for ChainExpectStacksTenureChan
         })
     }
 }
+
+impl CommandSelection<SignerTestState, SignerTestContext> for ChainExpectStacksTenureChange {}
@@ -4,6 +4,7 @@ use madhouse::{Command, CommandWrapper};
 use proptest::prelude::{Just, Strategy};
 
 use super::context::{SignerTestContext, SignerTestState};
+use super::CommandSelection;
 use crate::tests::neon_integrations::get_chain_info;
 
 /// Command to advance the test environment's burn chain and Stacks chain
@@ -56,3 +57,5 @@ impl Command<SignerTestState, SignerTestContext> for ChainBootToEpoch3 {
         Just(CommandWrapper::new(ChainBootToEpoch3::new(ctx.clone())))
     }
 }
+
+impl CommandSelection<SignerTestState, SignerTestContext> for ChainBootToEpoch3 {}
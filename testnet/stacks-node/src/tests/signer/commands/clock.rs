@@ -0,0 +1,225 @@
+use std::fmt::Debug;
+use std::sync::{Arc, Mutex};
+use std::thread;
+use std::time::{Duration, Instant};
+
+use super::scenario::is_random_mode;
+
+/// Source of time for commands that wait on timeouts. Commands reach it through their test
+/// context (see `SignerTestContext::clock`), so a scenario can run them against a
+/// `VirtualClock` and control exactly when each timeout passes.
+pub trait Clock: Debug + Send + Sync {
+    fn now(&self) -> Instant;
+    /// Move the clock forward by `duration`
+    fn advance(&self, duration: Duration);
+}
+
+/// Wall-clock time. Advancing it sleeps.
+#[derive(Debug, Clone, Default)]
+pub struct WallClock;
+
+impl Clock for WallClock {
+    fn now(&self) -> Instant {
+        Instant::now()
+    }
+
+    fn advance(&self, duration: Duration) {
+        thread::sleep(duration)
+    }
+}
+
+/// Clock that only moves when it is advanced, so that timeouts play out the same way on every
+/// run
+#[derive(Debug)]
+pub struct VirtualClock {
+    start: Instant,
+    elapsed: Mutex<Duration>,
+}
+
+impl Default for VirtualClock {
+    fn default() -> Self {
+        Self {
+            start: Instant::now(),
+            elapsed: Mutex::new(Duration::ZERO),
+        }
+    }
+}
+
+impl VirtualClock {
+    /// How far this clock has been advanced
+    pub fn elapsed(&self) -> Duration {
+        *self.elapsed.lock().unwrap()
+    }
+}
+
+impl Clock for VirtualClock {
+    fn now(&self) -> Instant {
+        self.start + self.elapsed()
+    }
+
+    fn advance(&self, duration: Duration) {
+        *self.elapsed.lock().unwrap() += duration;
+    }
+}
+
+/// The clock for the mode scenarios run in: see `clock_for`
+pub fn clock_for_mode() -> Arc<dyn Clock> {
+    clock_for(is_random_mode())
+}
+
+/// Wall-clock time in random (`MADHOUSE=1`) mode, whose runs differ anyway, and a
+/// `VirtualClock` in deterministic mode, so that timeouts pass the same way on every run
+fn clock_for(random_mode: bool) -> Arc<dyn Clock> {
+    if random_mode {
+        Arc::new(WallClock)
+    } else {
+        Arc::new(VirtualClock::default())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Arc;
+    use std::time::{Duration, Instant};
+
+    use madhouse::{Command, CommandWrapper, State, TestContext};
+    use proptest::prelude::{Just, Strategy};
+
+    use super::{clock_for, Clock, VirtualClock, WallClock};
+    use crate::tests::signer::commands::execution::{execute_commands, StateSnapshot};
+
+    #[derive(Debug, Default)]
+    struct EmptyState;
+
+    impl State for EmptyState {}
+
+    impl StateSnapshot for EmptyState {}
+
+    #[derive(Debug, Default)]
+    struct ClockContext {
+        clock: VirtualClock,
+    }
+
+    impl TestContext for ClockContext {}
+
+    /// Command that advances the context's clock
+    struct AdvanceClock {
+        ctx: Arc<ClockContext>,
+        secs: u64,
+    }
+
+    impl Command<EmptyState, ClockContext> for AdvanceClock {
+        fn check(&self, _state: &EmptyState) -> bool {
+            true
+        }
+
+        fn apply(&self, _state: &mut EmptyState) {
+            self.ctx.clock.advance(Duration::from_secs(self.secs));
+        }
+
+        fn label(&self) -> String {
+            format!("ADVANCE_CLOCK_{}", self.secs)
+        }
+
+        fn build(
+            ctx: Arc<ClockContext>,
+        ) -> impl Strategy<Value = CommandWrapper<EmptyState, ClockContext>> {
+            Just(CommandWrapper::new(AdvanceClock { ctx, secs: 1 }))
+        }
+    }
+
+    /// Command that can only run once its timeout has passed on the context's clock
+    struct WaitForTimeout {
+        ctx: Arc<ClockContext>,
+        deadline: Instant,
+    }
+
+    impl WaitForTimeout {
+        fn new(ctx: Arc<ClockContext>, timeout: Duration) -> Self {
+            let deadline = ctx.clock.now() + timeout;
+            Self { ctx, deadline }
+        }
+    }
+
+    impl Command<EmptyState, ClockContext> for WaitForTimeout {
+        fn check(&self, _state: &EmptyState) -> bool {
+            self.ctx.clock.now() >= self.deadline
+        }
+
+        fn apply(&self, _state: &mut EmptyState) {}
+
+        fn label(&self) -> String {
+            "WAIT_FOR_TIMEOUT".into()
+        }
+
+        fn build(
+            ctx: Arc<ClockContext>,
+        ) -> impl Strategy<Value = CommandWrapper<EmptyState, ClockContext>> {
+            Just(CommandWrapper::new(WaitForTimeout::new(
+                ctx,
+                Duration::from_secs(10),
+            )))
+        }
+    }
+
+    #[test]
+    fn virtual_clock_only_moves_when_advanced() {
+        let clock = VirtualClock::default();
+        let start = clock.now();
+        std::thread::sleep(Duration::from_millis(10));
+        assert_eq!(clock.now(), start);
+
+        clock.advance(Duration::from_secs(3));
+        assert_eq!(clock.now(), start + Duration::from_secs(3));
+        assert_eq!(clock.elapsed(), Duration::from_secs(3));
+    }
+
+    #[test]
+    fn wall_clock_sleeps_when_advanced() {
+        let clock = WallClock;
+        let start = clock.now();
+        clock.advance(Duration::from_millis(50));
+        assert!(clock.now() >= start + Duration::from_millis(50));
+    }
+
+    #[test]
+    fn clock_follows_the_mode() {
+        // deterministic mode: only advancing moves the clock
+        let clock = clock_for(false);
+        let start = clock.now();
+        std::thread::sleep(Duration::from_millis(10));
+        assert_eq!(clock.now(), start);
+        clock.advance(Duration::from_secs(10));
+        assert_eq!(clock.now(), start + Duration::from_secs(10));
+
+        // random mode: the clock moves on its own
+        let clock = clock_for(true);
+        let start = clock.now();
+        std::thread::sleep(Duration::from_millis(10));
+        assert!(clock.now() >= start + Duration::from_millis(10));
+    }
+
+    #[test]
+    fn timeout_passes_once_the_clock_is_advanced() {
+        let ctx = Arc::new(ClockContext::default());
+        let wait =
+            || CommandWrapper::new(WaitForTimeout::new(ctx.clone(), Duration::from_secs(10)));
+        let advance = |secs| {
+            CommandWrapper::new(AdvanceClock {
+                ctx: ctx.clone(),
+                secs,
+            })
+        };
+        let commands = vec![wait(), advance(4), wait(), advance(6), wait()];
+
+        let executed: Vec<_> = execute_commands(&commands, &mut EmptyState)
+            .into_iter()
+            .map(|cmd| cmd.command.label())
+            .collect();
+        assert_eq!(
+            executed,
+            vec!["ADVANCE_CLOCK_4", "ADVANCE_CLOCK_6", "WAIT_FOR_TIMEOUT"]
+        );
+        assert_eq!(ctx.clock.elapsed(), Duration::from_secs(10));
+    }
+}
@@ -4,6 +4,7 @@ use madhouse::{Command, CommandWrapper};
 use proptest::prelude::Strategy;
 
 use super::context::{SignerTestContext, SignerTestState};
+use super::CommandSelection;
 
 /// Command to enable or disable the block commit operations for a specific
 /// miner within the test environment.
@@ -85,3 +86,5 @@ impl Command<SignerTestState, SignerTestContext> for ChainMinerCommitOp {
         })
     }
 }
+
+impl CommandSelection<SignerTestState, SignerTestContext> for ChainMinerCommitOp {}
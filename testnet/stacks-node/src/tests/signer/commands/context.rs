@@ -1,22 +1,47 @@
+use std::any::Any;
 use std::fmt::Debug;
 use std::sync::{Arc, Mutex};
 use std::time::Duration;
 
+use clarity::vm::Value;
 use madhouse::{State, TestContext};
+use stacks::address::AddressHashMode;
+use stacks::burnchains::Burnchain;
 use stacks::chainstate::burn::db::sortdb::SortitionDB;
+use stacks::chainstate::stacks::address::PoxAddress;
+use stacks::chainstate::stacks::boot::RewardSet;
 use stacks::config::Config as NeonConfig;
-use stacks::types::chainstate::StacksPublicKey;
+use stacks::core::test_util::{make_contract_call, to_addr};
+use stacks::types::chainstate::{StacksAddress, StacksPrivateKey, StacksPublicKey};
 use stacks::util::hash::Hash160;
+use stacks::util_lib::signed_structured_data::pox4::{
+    make_pox_4_signer_key_signature, Pox4SignatureTopic,
+};
 
+use super::clock::{clock_for_mode, Clock};
+use super::execution::StateSnapshot;
 use crate::neon::Counters;
 use crate::stacks_common::types::PublicKey;
+use crate::tests::nakamoto_integrations::POX_4_DEFAULT_STACKER_BALANCE;
+use crate::tests::neon_integrations::get_chain_info;
 use crate::tests::signer::v0::{get_nakamoto_headers, MultipleMinerTest};
 
+/// Shared context for the signer commands.
+///
+/// Besides the running miners, it carries the `Burnchain` the nodes were booted with, so that
+/// commands which build PoX transactions get reward cycles and signer signatures from the same
+/// `PoxConstants` as the nodes, instead of re-deriving the burnchain math themselves. See
+/// `StackerStackStx` for an example.
 #[derive(Clone)]
 pub struct SignerTestContext {
     pub miners: Arc<Mutex<MultipleMinerTest>>,
+    burnchain: Arc<Burnchain>,
+    /// An account funded at genesis with enough STX to clear the pox-4 stacking minimum
+    stacker_sk: StacksPrivateKey,
     num_signers: usize,
     num_transfer_txs: u64,
+    /// The clock commands wait on. See `clock_for_mode`.
+    clock: Arc<dyn Clock>,
 }
 
 impl Debug for SignerTestContext {
@@ -24,6 +49,7 @@ impl Debug for SignerTestContext {
         f.debug_struct("SignerTestContext")
             .field("num_signers", &self.num_signers)
             .field("num_transfer_txs", &self.num_transfer_txs)
+            .field("clock", &self.clock)
             .finish()
     }
 }
@@ -32,6 +58,8 @@ impl TestContext for SignerTestContext {}
 
 impl SignerTestContext {
     pub fn new(num_signers: usize, num_transfer_txs: u64) -> Self {
+        let stacker_sk = StacksPrivateKey::random();
+        let stacker_addr = to_addr(&stacker_sk).to_string();
         let miners = MultipleMinerTest::new_with_config_modifications(
             num_signers,
             num_transfer_txs,
@@ -42,16 +70,24 @@ impl SignerTestContext {
             },
             |config| {
                 config.miner.block_commit_delay = Duration::from_secs(0);
+                // Node 2's config is cloned from node 1's, so both share this genesis balance
+                config.add_initial_balance(stacker_addr.clone(), POX_4_DEFAULT_STACKER_BALANCE);
             },
             |config| {
                 config.miner.block_commit_delay = Duration::from_secs(0);
             },
         );
 
+        // Both miners are booted from the same burnchain config
+        let burnchain = Arc::new(miners.get_node_configs().0.get_burnchain());
+
         Self {
             miners: Arc::new(Mutex::new(miners)),
+            burnchain,
+            stacker_sk,
             num_signers,
             num_transfer_txs,
+            clock: clock_for_mode(),
         }
     }
 
@@ -66,6 +102,98 @@ impl SignerTestContext {
         self.num_transfer_txs
     }
 
+    /// The clock that commands which wait on timeouts measure them against: the wall clock in
+    /// random (`MADHOUSE=1`) mode, and a `VirtualClock` in deterministic mode
+    pub fn clock(&self) -> &dyn Clock {
+        self.clock.as_ref()
+    }
+
+    pub fn burnchain(&self) -> &Burnchain {
+        &self.burnchain
+    }
+
+    pub fn stacker_sk(&self) -> &StacksPrivateKey {
+        &self.stacker_sk
+    }
+
+    /// The last burn block height recorded in `state`, or the current burnchain tip height if
+    /// no height has been recorded yet.
+    pub fn current_burn_block_height(&self, state: &SignerTestState) -> u64 {
+        state
+            .last_burn_block_height
+            .unwrap_or_else(|| get_chain_info(&self.get_node_config(1)).burn_block_height)
+    }
+
+    /// The reward cycle containing `current_burn_block_height`
+    pub fn current_reward_cycle(&self, state: &SignerTestState) -> u64 {
+        self.burnchain
+            .block_height_to_reward_cycle(self.current_burn_block_height(state))
+            .expect("Burn block height is before the first burnchain block")
+    }
+
+    /// Build a `stack-stx` contract call from `stacker_sk`, stacking `amount_ustx` for
+    /// `lock_period` cycles starting in the current reward cycle. The stacker is its own signer,
+    /// so the signer key signature is made with `stacker_sk` over the current reward cycle.
+    ///
+    /// pox-4 rejects a start height outside the current reward cycle, so the height and cycle are
+    /// read from the burnchain tip rather than from the (possibly stale) test state.
+    pub fn make_stack_stx_tx(
+        &self,
+        stacker_sk: &StacksPrivateKey,
+        nonce: u64,
+        amount_ustx: u128,
+        lock_period: u128,
+    ) -> Vec<u8> {
+        let conf = self.get_node_config(1);
+        let chain_id = conf.burnchain.chain_id;
+        let burn_block_height = get_chain_info(&conf).burn_block_height;
+        let reward_cycle = self
+            .burnchain
+            .block_height_to_reward_cycle(burn_block_height)
+            .expect("Burn block height is before the first burnchain block");
+
+        let pox_addr = PoxAddress::from_legacy(
+            AddressHashMode::SerializeP2PKH,
+            to_addr(stacker_sk).bytes().clone(),
+        );
+        let pox_addr_tuple: Value = pox_addr.clone().as_clarity_tuple().unwrap().into();
+        let max_amount = u128::MAX;
+        let auth_id = 1;
+        let signature = make_pox_4_signer_key_signature(
+            &pox_addr,
+            stacker_sk,
+            reward_cycle.into(),
+            &Pox4SignatureTopic::StackStx,
+            chain_id,
+            lock_period,
+            max_amount,
+            auth_id,
+        )
+        .unwrap()
+        .to_rsv();
+        let signer_pk = StacksPublicKey::from_private(stacker_sk);
+
+        make_contract_call(
+            stacker_sk,
+            nonce,
+            1000,
+            chain_id,
+            &StacksAddress::burn_address(false),
+            "pox-4",
+            "stack-stx",
+            &[
+                Value::UInt(amount_ustx),
+                pox_addr_tuple,
+                Value::UInt(burn_block_height.into()),
+                Value::UInt(lock_period),
+                Value::some(Value::buff_from(signature).unwrap()).unwrap(),
+                Value::buff_from(signer_pk.to_bytes_compressed()).unwrap(),
+                Value::UInt(max_amount),
+                Value::UInt(auth_id),
+            ],
+        )
+    }
+
     pub fn get_counters_for_miner(&self, miner_index: usize) -> Counters {
         self.miners
             .lock()
@@ -135,14 +263,34 @@ impl SignerTestContext {
     }
 }
 
-#[derive(Debug, Default)]
+#[derive(Debug, Default, Clone)]
 pub struct SignerTestState {
     pub is_booted_to_nakamoto: bool,
     pub mining_stalled: bool,
     pub epoch_3_start_block_height: Option<u64>,
     pub last_stacks_block_height: Option<u64>,
+    pub last_burn_block_height: Option<u64>,
+    /// The reward cycle and reward set recorded by `ChainAssertRewardSetStable`
+    pub observed_reward_set: Option<(u64, RewardSet)>,
+    /// The reward cycle in which the context's stacker unlocks, once `StackerStackStx` has
+    /// locked its STX
+    pub stacker_unlock_cycle: Option<u64>,
 }
 
 impl SignerTestState {}
 
 impl State for SignerTestState {}
+
+/// The whole state is cheap to clone, so a snapshot is a clone of it. A command that fails
+/// partway through `apply` leaves the state exactly as it was before that command.
+impl StateSnapshot for SignerTestState {
+    fn snapshot(&self) -> Box<dyn Any> {
+        Box::new(self.clone())
+    }
+
+    fn restore(&mut self, snapshot: Box<dyn Any>) {
+        *self = *snapshot
+            .downcast()
+            .expect("Snapshot should be a SignerTestState");
+    }
+}
@@ -0,0 +1,157 @@
+use std::collections::BTreeMap;
+
+use madhouse::{CommandWrapper, State, TestContext};
+
+/// Count how many times each command label appears among the executed commands
+fn executed_counts<S: State, C: TestContext>(
+    executed: &[&CommandWrapper<S, C>],
+) -> BTreeMap<String, usize> {
+    let mut counts = BTreeMap::new();
+    for cmd in executed.iter() {
+        *counts.entry(cmd.command.label()).or_insert(0) += 1;
+    }
+    counts
+}
+
+/// Assert that the command labeled `name` ran at least `min_count` times, where `executed` is
+/// what `execute_commands` returned. Commands whose `check` failed are not in `executed`, so
+/// this catches a scenario that still generates a command but silently stopped running it.
+pub fn assert_executed_at_least<S: State, C: TestContext>(
+    executed: &[&CommandWrapper<S, C>],
+    name: &str,
+    min_count: usize,
+) {
+    let counts = executed_counts(executed);
+    let count = counts.get(name).copied().unwrap_or(0);
+    assert!(
+        count >= min_count,
+        "Expected command {name} to be executed at least {min_count} time(s), but it was executed {count} time(s). Executed commands: {counts:?}"
+    );
+}
+
+/// Assert that every command labeled in `names` ran at least once, where `executed` is what
+/// `execute_commands` returned. The failure message lists all of the commands that never ran.
+pub fn assert_all_types_executed<S: State, C: TestContext>(
+    executed: &[&CommandWrapper<S, C>],
+    names: &[&str],
+) {
+    let counts = executed_counts(executed);
+    let missing: Vec<_> = names
+        .iter()
+        .filter(|name| !counts.contains_key(**name))
+        .collect();
+    assert!(
+        missing.is_empty(),
+        "Expected commands were never executed: {missing:?}. Executed commands: {counts:?}"
+    );
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Arc;
+
+    use madhouse::Command;
+    use proptest::prelude::{Just, Strategy};
+
+    use super::*;
+    use crate::tests::signer::commands::execution::{execute_commands, StateSnapshot};
+
+    #[derive(Debug, Default)]
+    struct RunCountState {
+        runs: usize,
+    }
+
+    impl State for RunCountState {}
+
+    impl StateSnapshot for RunCountState {}
+
+    #[derive(Debug, Clone)]
+    struct NoContext;
+
+    impl TestContext for NoContext {}
+
+    /// Command that always runs
+    struct AlwaysRuns;
+
+    impl Command<RunCountState, NoContext> for AlwaysRuns {
+        fn check(&self, _state: &RunCountState) -> bool {
+            true
+        }
+
+        fn apply(&self, state: &mut RunCountState) {
+            state.runs += 1;
+        }
+
+        fn label(&self) -> String {
+            "ALWAYS_RUNS".to_string()
+        }
+
+        fn build(
+            _ctx: Arc<NoContext>,
+        ) -> impl Strategy<Value = CommandWrapper<RunCountState, NoContext>> {
+            Just(CommandWrapper::new(AlwaysRuns))
+        }
+    }
+
+    /// Command that is generated, but whose `check` never passes
+    struct NeverRuns;
+
+    impl Command<RunCountState, NoContext> for NeverRuns {
+        fn check(&self, _state: &RunCountState) -> bool {
+            false
+        }
+
+        fn apply(&self, state: &mut RunCountState) {
+            state.runs += 1;
+        }
+
+        fn label(&self) -> String {
+            "NEVER_RUNS".to_string()
+        }
+
+        fn build(
+            _ctx: Arc<NoContext>,
+        ) -> impl Strategy<Value = CommandWrapper<RunCountState, NoContext>> {
+            Just(CommandWrapper::new(NeverRuns))
+        }
+    }
+
+    fn commands() -> Vec<CommandWrapper<RunCountState, NoContext>> {
+        vec![
+            CommandWrapper::new(AlwaysRuns),
+            CommandWrapper::new(NeverRuns),
+            CommandWrapper::new(AlwaysRuns),
+        ]
+    }
+
+    #[test]
+    fn executed_commands_are_counted() {
+        let commands = commands();
+        let mut state = RunCountState::default();
+        let executed = execute_commands(&commands, &mut state);
+
+        assert_eq!(state.runs, 2);
+        assert_executed_at_least(&executed, "ALWAYS_RUNS", 2);
+        assert_all_types_executed(&executed, &["ALWAYS_RUNS"]);
+    }
+
+    #[test]
+    #[should_panic(expected = "Expected commands were never executed: [\"NEVER_RUNS\"]")]
+    fn skipped_command_is_detected() {
+        let commands = commands();
+        let mut state = RunCountState::default();
+        let executed = execute_commands(&commands, &mut state);
+
+        assert_all_types_executed(&executed, &["ALWAYS_RUNS", "NEVER_RUNS"]);
+    }
+
+    #[test]
+    #[should_panic(expected = "Expected command NEVER_RUNS to be executed at least 1 time(s)")]
+    fn skipped_command_is_below_minimum() {
+        let commands = commands();
+        let mut state = RunCountState::default();
+        let executed = execute_commands(&commands, &mut state);
+
+        assert_executed_at_least(&executed, "NEVER_RUNS", 1);
+    }
+}
@@ -0,0 +1,278 @@
+use std::any::Any;
+use std::fmt::{self, Debug};
+use std::panic::{self, AssertUnwindSafe};
+
+use madhouse::{CommandWrapper, State, TestContext};
+
+use super::henceforth::{check_properties, reset_properties};
+
+/// A `State` that `execute_commands` can put back the way it was before a command failed.
+/// Both methods default to doing nothing, so most states only need an empty impl; a failure is
+/// then reported with the state as the failing command left it.
+pub trait StateSnapshot: State + Debug {
+    /// Capture whatever `restore` needs to put the state back as it is now
+    fn snapshot(&self) -> Box<dyn Any> {
+        Box::new(())
+    }
+
+    /// Put the state back as it was when `snapshot` returned `snapshot`
+    fn restore(&mut self, _snapshot: Box<dyn Any>) {}
+}
+
+/// A command whose `apply` panicked in `try_execute_commands`
+#[derive(Debug)]
+pub struct CommandFailure {
+    /// Index in the commands of the command that failed
+    pub index: usize,
+    /// Label of the command that failed
+    pub label: String,
+    /// Panic message of the command that failed
+    pub message: String,
+    /// Indices in the commands of the commands that ran before it, in order
+    pub executed: Vec<usize>,
+    /// Labels of the commands that ran before it, in order
+    pub executed_labels: Vec<String>,
+    /// The state as it was before the command ran
+    pub state: String,
+}
+
+impl fmt::Display for CommandFailure {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "Command {} at index {} failed: {}\nPreviously executed commands: [{}]\nState before the command: {}",
+            self.label,
+            self.index,
+            self.message,
+            self.executed_labels.join(", "),
+            self.state
+        )
+    }
+}
+
+/// Like `madhouse::execute_commands`, but if a command's `apply` panics, restore the state to its
+/// snapshot from before the command ran, and return the failure with the command's label, its
+/// index in `commands`, the commands that ran before it, and the restored state.
+/// A failed assertion partway through `apply` therefore never leaves a half-applied state behind.
+/// After each command, the properties that `Henceforth` commands registered so far in this run
+/// are checked too, and a property that no longer holds fails that command.
+/// Returns the commands that ran, in order.
+pub fn try_execute_commands<'a, S: StateSnapshot + 'static, C: TestContext>(
+    commands: &'a [CommandWrapper<S, C>],
+    state: &mut S,
+) -> Result<Vec<&'a CommandWrapper<S, C>>, CommandFailure> {
+    reset_properties();
+    let mut executed: Vec<usize> = Vec::with_capacity(commands.len());
+    for (index, cmd) in commands.iter().enumerate() {
+        if !cmd.command.check(state) {
+            continue;
+        }
+        let pre_state = state.snapshot();
+        let result = panic::catch_unwind(AssertUnwindSafe(|| {
+            cmd.command.apply(state);
+            check_properties(state);
+        }));
+        if let Err(payload) = result {
+            state.restore(pre_state);
+            let message = payload
+                .downcast_ref::<&str>()
+                .map(|msg| msg.to_string())
+                .or_else(|| payload.downcast_ref::<String>().cloned())
+                .unwrap_or_else(|| "<non-string panic payload>".into());
+            let executed_labels = executed
+                .iter()
+                .map(|i| commands[*i].command.label())
+                .collect();
+            return Err(CommandFailure {
+                index,
+                label: cmd.command.label(),
+                message,
+                executed,
+                executed_labels,
+                state: format!("{state:?}"),
+            });
+        }
+        executed.push(index);
+    }
+    Ok(executed.into_iter().map(|i| &commands[i]).collect())
+}
+
+/// Like `try_execute_commands`, but panic with the failure if a command fails
+pub fn execute_commands<'a, S: StateSnapshot + 'static, C: TestContext>(
+    commands: &'a [CommandWrapper<S, C>],
+    state: &mut S,
+) -> Vec<&'a CommandWrapper<S, C>> {
+    try_execute_commands(commands, state).unwrap_or_else(|failure| panic!("{failure}"))
+}
+
+#[cfg(test)]
+mod tests {
+    use std::any::Any;
+    use std::panic::{self, AssertUnwindSafe};
+    use std::sync::Arc;
+
+    use madhouse::{Command, CommandWrapper, State, TestContext};
+    use proptest::prelude::{Just, Strategy};
+
+    use super::{execute_commands, StateSnapshot};
+
+    #[derive(Debug, Default)]
+    struct EmptyState;
+
+    impl State for EmptyState {}
+
+    impl StateSnapshot for EmptyState {}
+
+    #[derive(Debug, Clone)]
+    struct NoContext;
+
+    impl TestContext for NoContext {}
+
+    /// Command that does nothing
+    struct Noop(&'static str);
+
+    impl Command<EmptyState, NoContext> for Noop {
+        fn check(&self, _state: &EmptyState) -> bool {
+            true
+        }
+
+        fn apply(&self, _state: &mut EmptyState) {}
+
+        fn label(&self) -> String {
+            self.0.to_string()
+        }
+
+        fn build(
+            _ctx: Arc<NoContext>,
+        ) -> impl Strategy<Value = CommandWrapper<EmptyState, NoContext>> {
+            Just(CommandWrapper::new(Noop("NOOP")))
+        }
+    }
+
+    /// Command whose `apply` always fails
+    struct Fail;
+
+    impl Command<EmptyState, NoContext> for Fail {
+        fn check(&self, _state: &EmptyState) -> bool {
+            true
+        }
+
+        fn apply(&self, _state: &mut EmptyState) {
+            panic!("boom");
+        }
+
+        fn label(&self) -> String {
+            "FAIL".into()
+        }
+
+        fn build(
+            _ctx: Arc<NoContext>,
+        ) -> impl Strategy<Value = CommandWrapper<EmptyState, NoContext>> {
+            Just(CommandWrapper::new(Fail))
+        }
+    }
+
+    #[derive(Debug, Default)]
+    struct CounterState {
+        count: u64,
+    }
+
+    impl State for CounterState {}
+
+    impl StateSnapshot for CounterState {
+        fn snapshot(&self) -> Box<dyn Any> {
+            Box::new(self.count)
+        }
+
+        fn restore(&mut self, snapshot: Box<dyn Any>) {
+            self.count = *snapshot.downcast().expect("Snapshot should be a count");
+        }
+    }
+
+    /// Command that increments the counter. If `fail` is set, it panics after incrementing.
+    struct Increment {
+        fail: bool,
+    }
+
+    impl Command<CounterState, NoContext> for Increment {
+        fn check(&self, _state: &CounterState) -> bool {
+            true
+        }
+
+        fn apply(&self, state: &mut CounterState) {
+            state.count += 1;
+            assert!(!self.fail, "increment failed at count {}", state.count);
+        }
+
+        fn label(&self) -> String {
+            if self.fail {
+                "INCREMENT_THEN_FAIL".into()
+            } else {
+                "INCREMENT".into()
+            }
+        }
+
+        fn build(
+            _ctx: Arc<NoContext>,
+        ) -> impl Strategy<Value = CommandWrapper<CounterState, NoContext>> {
+            Just(CommandWrapper::new(Increment { fail: false }))
+        }
+    }
+
+    #[test]
+    fn runs_all_commands() {
+        let commands = vec![
+            CommandWrapper::new(Noop("FIRST")),
+            CommandWrapper::new(Noop("SECOND")),
+        ];
+        let executed = execute_commands(&commands, &mut EmptyState);
+        let labels: Vec<_> = executed.iter().map(|cmd| cmd.command.label()).collect();
+        assert_eq!(labels, vec!["FIRST", "SECOND"]);
+    }
+
+    #[test]
+    fn failing_command_reports_its_context() {
+        let commands = vec![
+            CommandWrapper::new(Noop("FIRST")),
+            CommandWrapper::new(Noop("SECOND")),
+            CommandWrapper::new(Noop("THIRD")),
+            CommandWrapper::new(Fail),
+            CommandWrapper::new(Noop("FOURTH")),
+        ];
+        let payload = panic::catch_unwind(AssertUnwindSafe(|| {
+            execute_commands(&commands, &mut EmptyState)
+        }))
+        .expect_err("FAIL should have panicked");
+        let message = payload
+            .downcast_ref::<String>()
+            .expect("Panic message should be a String");
+        assert!(message.contains("FAIL"), "{message}");
+        assert!(message.contains("index 3"), "{message}");
+        assert!(message.contains("boom"), "{message}");
+        assert!(message.contains("[FIRST, SECOND, THIRD]"), "{message}");
+    }
+
+    #[test]
+    fn failing_command_restores_its_pre_state() {
+        let commands = vec![
+            CommandWrapper::new(Increment { fail: false }),
+            CommandWrapper::new(Increment { fail: false }),
+            CommandWrapper::new(Increment { fail: true }),
+        ];
+        let mut state = CounterState::default();
+        let payload =
+            panic::catch_unwind(AssertUnwindSafe(|| execute_commands(&commands, &mut state)))
+                .expect_err("INCREMENT_THEN_FAIL should have panicked");
+        let message = payload
+            .downcast_ref::<String>()
+            .expect("Panic message should be a String");
+        // the command saw its own increment before failing...
+        assert!(message.contains("increment failed at count 3"), "{message}");
+        // ...but the state is reported, and left, as it was before the command ran
+        assert!(
+            message.contains("State before the command: CounterState { count: 2 }"),
+            "{message}"
+        );
+        assert_eq!(state.count, 2);
+    }
+}
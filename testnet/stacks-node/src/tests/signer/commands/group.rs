@@ -0,0 +1,291 @@
+use std::sync::Arc;
+
+use madhouse::{Command, CommandWrapper, State, TestContext};
+use proptest::prelude::Strategy;
+use proptest::strategy::LazyJust;
+
+/// Separator between the children's labels in a group's label
+const GROUP_LABEL_SEPARATOR: &str = "→";
+
+/// Constructors that `madhouse::CommandWrapper` doesn't have
+pub trait CommandWrapperExtension<S: State, C: TestContext> {
+    /// Wrap `cmds` into a single command that runs them back to back, in order, so that the
+    /// scheduler can never interleave other commands between them. See `CommandGroup`.
+    fn group(cmds: Vec<CommandWrapper<S, C>>) -> CommandWrapper<S, C>;
+}
+
+impl<S: State + 'static, C: TestContext + 'static> CommandWrapperExtension<S, C>
+    for CommandWrapper<S, C>
+{
+    fn group(cmds: Vec<CommandWrapper<S, C>>) -> CommandWrapper<S, C> {
+        assert!(!cmds.is_empty(), "Cannot group zero commands");
+        CommandWrapper::new(CommandGroup { cmds })
+    }
+}
+
+/// A fixed sequence of commands that runs as one unit. Built with `CommandWrapper::group`.
+///
+/// The group can run only if every child's `check` passes against the state the group starts
+/// from. `check` never applies a child, so no child mines blocks or sends transactions just
+/// to be checked; this also means that a child can't rely on an earlier child's effects to
+/// pass its own `check`. `apply` checks each child again against the state the children
+/// before it left, and panics if one fails: that means an earlier child undid what a later one
+/// needs, and the group should not have been built that way.
+pub struct CommandGroup<S: State, C: TestContext> {
+    cmds: Vec<CommandWrapper<S, C>>,
+}
+
+impl<S: State + 'static, C: TestContext + 'static> Command<S, C> for CommandGroup<S, C> {
+    fn check(&self, state: &S) -> bool {
+        self.cmds.iter().all(|cmd| cmd.command.check(state))
+    }
+
+    fn apply(&self, state: &mut S) {
+        for (index, cmd) in self.cmds.iter().enumerate() {
+            assert!(
+                index == 0 || cmd.command.check(state),
+                "Command {} in group {} cannot run after the commands before it",
+                cmd.command.label(),
+                self.label()
+            );
+            cmd.command.apply(state);
+        }
+    }
+
+    fn label(&self) -> String {
+        self.cmds
+            .iter()
+            .map(|cmd| cmd.command.label())
+            .collect::<Vec<_>>()
+            .join(GROUP_LABEL_SEPARATOR)
+    }
+
+    fn build(_ctx: Arc<C>) -> impl Strategy<Value = CommandWrapper<S, C>> {
+        LazyJust::new(|| -> CommandWrapper<S, C> {
+            panic!("A CommandGroup has no strategy of its own: build it with CommandWrapper::group")
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::env;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    use std::sync::Arc;
+
+    use madhouse::{Command, CommandWrapper, State, TestContext};
+    use proptest::prelude::{Just, Strategy};
+    use proptest::strategy::{Union, ValueTree};
+    use proptest::test_runner::TestRunner;
+
+    use super::CommandWrapperExtension;
+    use crate::tests::neon_integrations::get_chain_info;
+    use crate::tests::signer::commands::context::SignerTestState;
+    use crate::tests::signer::commands::execution::{execute_commands, StateSnapshot};
+    use crate::tests::signer::commands::{
+        ChainBootToEpoch3, ChainShutdownMiners, MinerMineBitcoinBlocks, SignerTestContext,
+    };
+
+    #[derive(Debug, Default, Clone)]
+    struct LogState {
+        log: Vec<&'static str>,
+    }
+
+    impl State for LogState {}
+
+    impl StateSnapshot for LogState {}
+
+    #[derive(Debug, Clone)]
+    struct NoContext;
+
+    impl TestContext for NoContext {}
+
+    /// Command that appends its name to the log
+    struct Log(&'static str);
+
+    impl Command<LogState, NoContext> for Log {
+        fn check(&self, _state: &LogState) -> bool {
+            true
+        }
+
+        fn apply(&self, state: &mut LogState) {
+            state.log.push(self.0);
+        }
+
+        fn label(&self) -> String {
+            self.0.to_string()
+        }
+
+        fn build(
+            _ctx: Arc<NoContext>,
+        ) -> impl Strategy<Value = CommandWrapper<LogState, NoContext>> {
+            Just(CommandWrapper::new(Log("LOG")))
+        }
+    }
+
+    /// Command that can't run right after `CLOSE`. Counts how many times it was applied in
+    /// `applied`, which stands for a side effect outside the state.
+    struct Commit {
+        applied: Arc<AtomicUsize>,
+    }
+
+    impl Commit {
+        fn new() -> Self {
+            Self {
+                applied: Arc::new(AtomicUsize::new(0)),
+            }
+        }
+    }
+
+    impl Command<LogState, NoContext> for Commit {
+        fn check(&self, state: &LogState) -> bool {
+            state.log.last() != Some(&"CLOSE")
+        }
+
+        fn apply(&self, state: &mut LogState) {
+            self.applied.fetch_add(1, Ordering::SeqCst);
+            state.log.push("COMMIT");
+        }
+
+        fn label(&self) -> String {
+            "COMMIT".into()
+        }
+
+        fn build(
+            _ctx: Arc<NoContext>,
+        ) -> impl Strategy<Value = CommandWrapper<LogState, NoContext>> {
+            Just(CommandWrapper::new(Commit::new()))
+        }
+    }
+
+    fn stack_then_commit() -> CommandWrapper<LogState, NoContext> {
+        CommandWrapper::group(vec![
+            CommandWrapper::new(Log("STACK")),
+            CommandWrapper::new(Commit::new()),
+        ])
+    }
+
+    #[test]
+    fn group_label_joins_children() {
+        assert_eq!(stack_then_commit().command.label(), "STACK→COMMIT");
+    }
+
+    #[test]
+    fn group_members_execute_adjacently_and_in_order() {
+        // pick commands at random, as MADHOUSE=1 does
+        let strategy = Union::new([
+            Just(CommandWrapper::new(Log("OTHER"))).boxed(),
+            Just(CommandWrapper::new(Log("STACK"))).boxed(),
+            Just(stack_then_commit()).boxed(),
+        ]);
+        let mut runner = TestRunner::default();
+
+        let mut total_groups_run = 0;
+        for _ in 0..50 {
+            let commands: Vec<_> = (0..20)
+                .map(|_| strategy.new_tree(&mut runner).unwrap().current())
+                .collect();
+            let mut state = LogState::default();
+            let executed = execute_commands(&commands, &mut state);
+            assert_eq!(executed.len(), commands.len());
+            let groups_run = executed
+                .iter()
+                .filter(|cmd| cmd.command.label() == "STACK→COMMIT")
+                .count();
+            total_groups_run += groups_run;
+
+            // COMMIT only runs in the group, right after the group's STACK
+            let commits = state.log.iter().filter(|name| **name == "COMMIT").count();
+            let commits_after_stack = state
+                .log
+                .windows(2)
+                .filter(|pair| pair == &["STACK", "COMMIT"])
+                .count();
+            assert_eq!(commits, groups_run, "{:?}", state.log);
+            assert_eq!(commits_after_stack, groups_run, "{:?}", state.log);
+        }
+        assert!(total_groups_run > 0);
+    }
+
+    #[test]
+    fn group_check_does_not_apply_children() {
+        let commit = Commit::new();
+        let applied = commit.applied.clone();
+        let group = CommandWrapper::group(vec![
+            CommandWrapper::new(Log("STACK")),
+            CommandWrapper::new(commit),
+        ]);
+
+        let mut state = LogState::default();
+        assert!(group.command.check(&state));
+        assert_eq!(applied.load(Ordering::SeqCst), 0);
+
+        // every child is checked against the state the group starts from
+        state.log.push("CLOSE");
+        assert!(!group.command.check(&state));
+        assert_eq!(applied.load(Ordering::SeqCst), 0);
+
+        state.log.clear();
+        group.command.apply(&mut state);
+        assert_eq!(state.log, vec!["STACK", "COMMIT"]);
+        assert_eq!(applied.load(Ordering::SeqCst), 1);
+    }
+
+    #[test]
+    #[should_panic(expected = "Command COMMIT in group CLOSE→COMMIT cannot run")]
+    fn group_rechecks_each_child_when_applied() {
+        let group = CommandWrapper::group(vec![
+            CommandWrapper::new(Log("CLOSE")),
+            CommandWrapper::new(Commit::new()),
+        ]);
+        let mut state = LogState::default();
+        assert!(group.command.check(&state));
+        group.command.apply(&mut state);
+    }
+
+    #[test]
+    fn group_that_cannot_run_is_skipped() {
+        let commands = vec![
+            CommandWrapper::new(Log("CLOSE")),
+            stack_then_commit(),
+            CommandWrapper::new(Log("OPEN")),
+            stack_then_commit(),
+        ];
+        let mut state = LogState::default();
+        let executed = execute_commands(&commands, &mut state);
+        assert_eq!(executed.len(), 3);
+        assert_eq!(state.log, vec!["CLOSE", "OPEN", "STACK", "COMMIT"]);
+    }
+
+    /// Checking a group of commands that mine bitcoin blocks doesn't mine any; applying it mines
+    /// each child's block once.
+    #[test]
+    #[ignore]
+    fn group_of_mining_commands_only_mines_when_applied() {
+        if env::var("BITCOIND_TEST") != Ok("1".into()) {
+            return;
+        }
+
+        let test_context = Arc::new(SignerTestContext::new(5, 3));
+        let burn_block_height =
+            || get_chain_info(&test_context.get_node_config(1)).burn_block_height;
+
+        let mut state = SignerTestState::default();
+        ChainBootToEpoch3::new(test_context.clone()).apply(&mut state);
+
+        let group = CommandWrapper::group(vec![
+            CommandWrapper::new(MinerMineBitcoinBlocks::one(test_context.clone())),
+            CommandWrapper::new(MinerMineBitcoinBlocks::one(test_context.clone())),
+        ]);
+        let height_before = burn_block_height();
+        assert!(group.command.check(&state));
+        assert_eq!(burn_block_height(), height_before);
+
+        group.command.apply(&mut state);
+        assert_eq!(burn_block_height(), height_before + 2);
+        assert_eq!(state.last_burn_block_height, Some(height_before + 2));
+
+        drop(group);
+        ChainShutdownMiners::new(test_context).apply(&mut state);
+    }
+}
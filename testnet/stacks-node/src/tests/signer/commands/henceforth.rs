@@ -0,0 +1,195 @@
+use std::any::Any;
+use std::cell::RefCell;
+use std::sync::Arc;
+
+use madhouse::{Command, CommandWrapper, State, TestContext};
+use proptest::prelude::Strategy;
+use proptest::strategy::LazyJust;
+
+/// Predicate on the state that a `Henceforth` command asserts
+type Predicate<S> = Arc<dyn Fn(&S) -> bool + Send + Sync>;
+
+/// A property that a `Henceforth` command registered: its label, and the predicate that must
+/// keep holding
+struct Property<S> {
+    label: String,
+    holds: Predicate<S>,
+}
+
+impl<S> Clone for Property<S> {
+    fn clone(&self) -> Self {
+        Self {
+            label: self.label.clone(),
+            holds: self.holds.clone(),
+        }
+    }
+}
+
+thread_local! {
+    /// Properties registered by the `Henceforth` commands of the current `try_execute_commands`
+    /// run. Each is a `Property<S>` for the state type `S` of that run.
+    static PROPERTIES: RefCell<Vec<Box<dyn Any>>> = RefCell::new(vec![]);
+}
+
+/// Forget the properties registered by a previous run. `try_execute_commands` calls this before
+/// running any command.
+pub(crate) fn reset_properties() {
+    PROPERTIES.with(|properties| properties.borrow_mut().clear());
+}
+
+/// Panic, naming the property, if any of the properties registered in this run does not hold
+/// for `state`. `try_execute_commands` calls this after each command it applies, so the
+/// violation is reported as a failure of that command.
+pub(crate) fn check_properties<S: 'static>(state: &S) {
+    let registered: Vec<Property<S>> = PROPERTIES.with(|properties| {
+        properties
+            .borrow()
+            .iter()
+            .filter_map(|property| property.downcast_ref::<Property<S>>())
+            .cloned()
+            .collect()
+    });
+    for property in registered {
+        assert!(
+            (property.holds)(state),
+            "Property {} no longer holds",
+            property.label
+        );
+    }
+}
+
+/// A command that asserts a property of the state now, and makes `try_execute_commands` assert
+/// it again after every later command of the run, so that a command that breaks the property
+/// fails with its own label. Built with `Henceforth::new`.
+pub struct Henceforth<S> {
+    label: String,
+    holds: Predicate<S>,
+}
+
+impl<S> Henceforth<S> {
+    /// The property called `label`, which holds for a state if `holds` returns true for it
+    pub fn new(label: &str, holds: impl Fn(&S) -> bool + Send + Sync + 'static) -> Self {
+        Self {
+            label: label.to_string(),
+            holds: Arc::new(holds),
+        }
+    }
+}
+
+impl<S: State + 'static, C: TestContext + 'static> Command<S, C> for Henceforth<S> {
+    fn check(&self, _state: &S) -> bool {
+        true
+    }
+
+    fn apply(&self, state: &mut S) {
+        assert!((self.holds)(state), "Property {} does not hold", self.label);
+        let property = Property {
+            label: self.label.clone(),
+            holds: self.holds.clone(),
+        };
+        PROPERTIES.with(|properties| properties.borrow_mut().push(Box::new(property)));
+    }
+
+    fn label(&self) -> String {
+        format!("HENCEFORTH_{}", self.label)
+    }
+
+    fn build(_ctx: Arc<C>) -> impl Strategy<Value = CommandWrapper<S, C>> {
+        LazyJust::new(|| -> CommandWrapper<S, C> {
+            panic!("A Henceforth has no strategy of its own: build it with Henceforth::new")
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Arc;
+
+    use madhouse::{Command, CommandWrapper, State, TestContext};
+    use proptest::prelude::{Just, Strategy};
+
+    use super::Henceforth;
+    use crate::tests::signer::commands::execution::{try_execute_commands, StateSnapshot};
+
+    #[derive(Debug, Default)]
+    struct CounterState {
+        count: i64,
+    }
+
+    impl State for CounterState {}
+
+    impl StateSnapshot for CounterState {}
+
+    #[derive(Debug, Clone)]
+    struct NoContext;
+
+    impl TestContext for NoContext {}
+
+    /// Command that adds its amount to the counter
+    struct Add(i64);
+
+    impl Command<CounterState, NoContext> for Add {
+        fn check(&self, _state: &CounterState) -> bool {
+            true
+        }
+
+        fn apply(&self, state: &mut CounterState) {
+            state.count += self.0;
+        }
+
+        fn label(&self) -> String {
+            format!("ADD_{}", self.0)
+        }
+
+        fn build(
+            _ctx: Arc<NoContext>,
+        ) -> impl Strategy<Value = CommandWrapper<CounterState, NoContext>> {
+            Just(CommandWrapper::new(Add(1)))
+        }
+    }
+
+    fn positive() -> CommandWrapper<CounterState, NoContext> {
+        CommandWrapper::new(Henceforth::new("POSITIVE", |state: &CounterState| {
+            state.count > 0
+        }))
+    }
+
+    #[test]
+    fn violation_names_the_later_command() {
+        let commands = vec![
+            CommandWrapper::new(Add(1)),
+            positive(),
+            CommandWrapper::new(Add(2)),
+            CommandWrapper::new(Add(-5)),
+            CommandWrapper::new(Add(10)),
+        ];
+        let mut state = CounterState::default();
+        let failure = try_execute_commands(&commands, &mut state)
+            .expect_err("ADD_-5 should have broken the property");
+        assert_eq!(failure.label, "ADD_-5");
+        assert_eq!(failure.index, 3);
+        assert_eq!(failure.message, "Property POSITIVE no longer holds");
+    }
+
+    #[test]
+    fn property_must_hold_when_asserted() {
+        let commands = vec![positive(), CommandWrapper::new(Add(1))];
+        let mut state = CounterState::default();
+        let failure = try_execute_commands(&commands, &mut state)
+            .expect_err("POSITIVE does not hold for the initial state");
+        assert_eq!(failure.label, "HENCEFORTH_POSITIVE");
+        assert_eq!(failure.message, "Property POSITIVE does not hold");
+    }
+
+    #[test]
+    fn properties_do_not_outlive_their_run() {
+        let commands = vec![CommandWrapper::new(Add(1)), positive()];
+        let mut state = CounterState::default();
+        try_execute_commands(&commands, &mut state).unwrap();
+
+        let commands = vec![CommandWrapper::new(Add(-1))];
+        let mut state = CounterState::default();
+        let executed = try_execute_commands(&commands, &mut state).unwrap();
+        assert_eq!(executed.len(), 1);
+    }
+}
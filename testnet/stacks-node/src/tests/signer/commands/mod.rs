@@ -5,9 +5,20 @@ mod block_commit;
 mod block_verify;
 mod block_wait;
 mod boot;
+pub mod clock;
 mod commit_ops;
+pub mod coverage;
+pub mod execution;
+pub mod group;
+pub mod henceforth;
+mod pause;
+mod registry;
+pub mod replay;
+mod reward_set;
+pub mod scenario;
 mod shutdown;
 mod sortition;
+mod stacking;
 mod stacks_mining;
 mod transfer;
 
@@ -20,7 +31,24 @@ pub use block_wait::{
 pub use boot::ChainBootToEpoch3;
 pub use commit_ops::ChainMinerCommitOp;
 pub use context::SignerTestContext;
+use context::SignerTestState;
+pub use pause::ChainPause;
+pub(crate) use registry::register_commands;
+pub use registry::CommandSelection;
+pub use reward_set::ChainAssertRewardSetStable;
+pub(crate) use scenario::{scenario, scenario_n};
 pub use shutdown::ChainShutdownMiners;
 pub use sortition::{ChainExpectSortitionWinner, ChainVerifyLastSortitionWinnerReorged};
+pub use stacking::StackerStackStx;
 pub use stacks_mining::ChainStacksMining;
 pub use transfer::MinerSendAndMineStacksTransferTx;
+
+register_commands! {
+    /// Commands that advance the chain. Each use of the set in `scenario!` runs one of them, picked
+    /// by its strategy.
+    pub ChainAdvanceCommands<SignerTestState, SignerTestContext> {
+        MinerMineBitcoinBlocks,
+        ChainGenerateBitcoinBlocks,
+        MinerSendAndMineStacksTransferTx,
+    }
+}
@@ -0,0 +1,90 @@
+use std::sync::Arc;
+use std::time::Duration;
+
+use madhouse::{Command, CommandWrapper};
+use proptest::prelude::Strategy;
+
+use super::context::{SignerTestContext, SignerTestState};
+use super::CommandSelection;
+
+/// Command to let some seconds pass on the test context's clock before the next command runs.
+///
+/// In random (`MADHOUSE=1`) mode the context's clock is the wall clock, so this sleeps and gives
+/// the nodes and signers that much time to make progress. In deterministic mode it only advances
+/// the `VirtualClock`, so the run takes no longer, and commands that measure timeouts against
+/// `SignerTestContext::clock` see exactly that much time pass.
+pub struct ChainPause {
+    ctx: Arc<SignerTestContext>,
+    secs: u64,
+}
+
+impl ChainPause {
+    pub fn new(ctx: Arc<SignerTestContext>, secs: u64) -> Self {
+        Self { ctx, secs }
+    }
+}
+
+impl Command<SignerTestState, SignerTestContext> for ChainPause {
+    fn check(&self, _state: &SignerTestState) -> bool {
+        info!(
+            "Checking: Pausing for {} seconds. Result: {}",
+            self.secs, true
+        );
+        true
+    }
+
+    fn apply(&self, _state: &mut SignerTestState) {
+        info!("Applying: Pausing for {} seconds", self.secs);
+        self.ctx.clock().advance(Duration::from_secs(self.secs));
+    }
+
+    fn label(&self) -> String {
+        format!("PAUSE_{}_SECS", self.secs)
+    }
+
+    fn build(
+        ctx: Arc<SignerTestContext>,
+    ) -> impl Strategy<Value = CommandWrapper<SignerTestState, SignerTestContext>> {
+        (1u64..=10u64).prop_map(move |secs| CommandWrapper::new(ChainPause::new(ctx.clone(), secs)))
+    }
+}
+
+impl CommandSelection<SignerTestState, SignerTestContext> for ChainPause {}
+
+#[cfg(test)]
+mod tests {
+    use std::env;
+    use std::sync::Arc;
+    use std::time::Duration;
+
+    use madhouse::Command;
+
+    use super::ChainPause;
+    use crate::tests::signer::commands::context::SignerTestState;
+    use crate::tests::signer::commands::scenario::is_random_mode;
+    use crate::tests::signer::commands::{ChainShutdownMiners, SignerTestContext};
+
+    /// A pause moves the context's clock by exactly its length in deterministic mode, and by at
+    /// least that much in random mode, where it sleeps.
+    #[test]
+    #[ignore]
+    fn pause_advances_the_context_clock() {
+        if env::var("BITCOIND_TEST") != Ok("1".into()) {
+            return;
+        }
+
+        let test_context = Arc::new(SignerTestContext::new(5, 3));
+        let mut state = SignerTestState::default();
+
+        let before = test_context.clock().now();
+        ChainPause::new(test_context.clone(), 3).apply(&mut state);
+        let paused = test_context.clock().now() - before;
+        if is_random_mode() {
+            assert!(paused >= Duration::from_secs(3));
+        } else {
+            assert_eq!(paused, Duration::from_secs(3));
+        }
+
+        ChainShutdownMiners::new(test_context).apply(&mut state);
+    }
+}
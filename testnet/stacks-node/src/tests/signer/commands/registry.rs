@@ -0,0 +1,322 @@
+/// How `register_commands!` and `scenario!` pick commands. Every command that is registered in
+/// a set, or listed by type in a `scenario!`, implements this; most keep the defaults.
+pub trait CommandSelection<S, C> {
+    /// How often this command is picked in random (`MADHOUSE=1`) mode, relative to the other
+    /// commands it is picked from, given the test context. Must be at least 1. Deterministic
+    /// mode ignores weights.
+    fn weight(_ctx: &C) -> u32 {
+        1
+    }
+
+    /// Whether this command can be picked at all. Unlike `Command::check`, which skips a
+    /// command when it is about to run, this keeps the command from being generated.
+    /// proptest generates every command before any of them runs, so this only ever sees the
+    /// state's initial `Default` value, never the state of a run in progress.
+    fn can_generate(_state: &S) -> bool {
+        true
+    }
+}
+
+/// Define a named set of madhouse commands, so that the set is listed in one place.
+///
+/// The set is a type that can't be instantiated, and that implements `Command`, so `scenario!`
+/// accepts the set name wherever it accepts a command type. Its `build` picks one of the
+/// registered commands that `CommandSelection::can_generate`. In random (`MADHOUSE=1`) mode,
+/// each command is picked with probability proportional to its `CommandSelection::weight`; in
+/// deterministic mode, weights are ignored and every command is equally likely. The set also has
+/// a map from each command's name to its constructor, for replaying a recorded run.
+///
+/// ```ignore
+/// register_commands! {
+///     /// Commands that mine blocks
+///     pub MiningCommands<SignerTestState, SignerTestContext> {
+///         MinerMineBitcoinBlocks,
+///         ChainGenerateBitcoinBlocks,
+///     }
+/// }
+/// ```
+macro_rules! register_commands {
+    (
+        $(#[$meta:meta])*
+        $vis:vis $name:ident<$state:ty, $ctx:ty> {
+            $($cmd:ident),+ $(,)?
+        }
+    ) => {
+        $(#[$meta])*
+        $vis enum $name {}
+
+        impl $name {
+            /// Names of the commands in this set, in registration order
+            #[allow(dead_code)]
+            pub const COMMANDS: &'static [&'static str] = &[$(stringify!($cmd)),+];
+
+            /// Constructor of each command in this set, by command name
+            #[allow(dead_code)]
+            pub fn constructors(
+            ) -> $crate::tests::signer::commands::replay::CommandConstructors<$state, $ctx> {
+                let mut constructors = ::std::collections::BTreeMap::new();
+                $(
+                    let build: $crate::tests::signer::commands::replay::CommandConstructor<
+                        $state,
+                        $ctx,
+                    > = ::std::sync::Arc::new(|ctx: ::std::sync::Arc<$ctx>| {
+                        ::proptest::strategy::Strategy::boxed(
+                            <$cmd as ::madhouse::Command<$state, $ctx>>::build(ctx),
+                        )
+                    });
+                    constructors.insert(stringify!($cmd), build);
+                )+
+                constructors
+            }
+
+            /// Strategy that picks one of this set's commands that can be generated. If
+            /// `weighted_by_selection`, each command is picked with probability proportional to
+            /// its `CommandSelection::weight`; otherwise every command is equally likely.
+            #[allow(dead_code)]
+            pub fn strategy(
+                ctx: ::std::sync::Arc<$ctx>,
+                weighted_by_selection: bool,
+            ) -> ::proptest::strategy::Union<
+                ::proptest::strategy::BoxedStrategy<::madhouse::CommandWrapper<$state, $ctx>>,
+            > {
+                let initial_state = <$state as ::std::default::Default>::default();
+                let mut weighted = vec![];
+                $(
+                    if <$cmd as $crate::tests::signer::commands::CommandSelection<$state, $ctx>>::can_generate(
+                        &initial_state,
+                    ) {
+                        weighted.push((
+                            if weighted_by_selection {
+                                <$cmd as $crate::tests::signer::commands::CommandSelection<$state, $ctx>>::weight(
+                                    &ctx,
+                                )
+                            } else {
+                                1
+                            },
+                            ::proptest::strategy::Strategy::boxed(
+                                <$cmd as ::madhouse::Command<$state, $ctx>>::build(ctx.clone()),
+                            ),
+                        ));
+                    }
+                )+
+                assert!(
+                    !weighted.is_empty(),
+                    "None of the commands in {} can be generated",
+                    stringify!($name)
+                );
+                ::proptest::strategy::Union::new_weighted(weighted)
+            }
+        }
+
+        impl $crate::tests::signer::commands::CommandSelection<$state, $ctx> for $name {}
+
+        impl ::madhouse::Command<$state, $ctx> for $name {
+            fn check(&self, _state: &$state) -> bool {
+                match *self {}
+            }
+
+            fn apply(&self, _state: &mut $state) {
+                match *self {}
+            }
+
+            fn label(&self) -> String {
+                match *self {}
+            }
+
+            fn build(
+                ctx: ::std::sync::Arc<$ctx>,
+            ) -> impl ::proptest::strategy::Strategy<Value = ::madhouse::CommandWrapper<$state, $ctx>>
+            {
+                Self::strategy(ctx, $crate::tests::signer::commands::scenario::is_random_mode())
+            }
+        }
+    };
+}
+
+pub(crate) use register_commands;
+
+#[cfg(test)]
+mod tests {
+    use std::collections::{BTreeMap, BTreeSet};
+    use std::sync::Arc;
+
+    use madhouse::{Command, CommandWrapper, State, TestContext};
+    use proptest::prelude::{Just, Strategy};
+    use proptest::strategy::ValueTree;
+    use proptest::test_runner::TestRunner;
+
+    use super::CommandSelection;
+    use crate::tests::signer::commands::execution::{execute_commands, StateSnapshot};
+
+    #[derive(Debug, Default)]
+    struct EmptyState;
+
+    impl State for EmptyState {}
+
+    impl StateSnapshot for EmptyState {}
+
+    #[derive(Debug, Clone)]
+    struct NoContext;
+
+    impl TestContext for NoContext {}
+
+    /// Command that does nothing, labeled `$label`
+    macro_rules! noop_command {
+        ($cmd:ident, $label:literal) => {
+            struct $cmd;
+
+            impl Command<EmptyState, NoContext> for $cmd {
+                fn check(&self, _state: &EmptyState) -> bool {
+                    true
+                }
+
+                fn apply(&self, _state: &mut EmptyState) {}
+
+                fn label(&self) -> String {
+                    $label.to_string()
+                }
+
+                fn build(
+                    _ctx: Arc<NoContext>,
+                ) -> impl Strategy<Value = CommandWrapper<EmptyState, NoContext>> {
+                    Just(CommandWrapper::new($cmd))
+                }
+            }
+        };
+    }
+
+    noop_command!(First, "FIRST");
+    noop_command!(Second, "SECOND");
+    noop_command!(Third, "THIRD");
+    noop_command!(Frequent, "FREQUENT");
+    noop_command!(Impossible, "IMPOSSIBLE");
+
+    impl CommandSelection<EmptyState, NoContext> for First {}
+    impl CommandSelection<EmptyState, NoContext> for Second {}
+    impl CommandSelection<EmptyState, NoContext> for Third {}
+
+    impl CommandSelection<EmptyState, NoContext> for Frequent {
+        fn weight(_ctx: &NoContext) -> u32 {
+            10
+        }
+    }
+
+    impl CommandSelection<EmptyState, NoContext> for Impossible {
+        fn can_generate(_state: &EmptyState) -> bool {
+            false
+        }
+    }
+
+    register_commands! {
+        TestCommands<EmptyState, NoContext> {
+            First,
+            Second,
+            Third,
+        }
+    }
+
+    register_commands! {
+        WeightedCommands<EmptyState, NoContext> {
+            First,
+            Frequent,
+        }
+    }
+
+    register_commands! {
+        PartlyImpossibleCommands<EmptyState, NoContext> {
+            First,
+            Impossible,
+        }
+    }
+
+    #[test]
+    fn registered_commands_are_listed() {
+        assert_eq!(TestCommands::COMMANDS, &["First", "Second", "Third"]);
+        let names: Vec<_> = TestCommands::constructors().into_keys().collect();
+        assert_eq!(names, vec!["First", "Second", "Third"]);
+    }
+
+    #[test]
+    fn all_registered_commands_are_generated() {
+        let ctx = Arc::new(NoContext);
+        let strategy = TestCommands::build(ctx);
+        let mut runner = TestRunner::deterministic();
+
+        let mut labels = BTreeSet::new();
+        for _ in 0..100 {
+            let command = strategy.new_tree(&mut runner).unwrap().current();
+            labels.insert(command.command.label());
+        }
+        assert_eq!(
+            labels,
+            BTreeSet::from(["FIRST".to_string(), "SECOND".into(), "THIRD".into()])
+        );
+    }
+
+    #[test]
+    fn constructors_build_their_command() {
+        let ctx = Arc::new(NoContext);
+        let mut runner = TestRunner::deterministic();
+        for (name, build) in TestCommands::constructors() {
+            let command = build(ctx.clone()).new_tree(&mut runner).unwrap().current();
+            assert_eq!(command.command.label(), name.to_uppercase());
+        }
+    }
+
+    #[test]
+    fn commands_are_picked_by_weight() {
+        let ctx = Arc::new(NoContext);
+        let strategy = WeightedCommands::strategy(ctx, true);
+        let mut runner = TestRunner::deterministic();
+
+        let mut counts = BTreeMap::new();
+        for _ in 0..11_000 {
+            let command = strategy.new_tree(&mut runner).unwrap().current();
+            *counts.entry(command.command.label()).or_insert(0u32) += 1;
+        }
+        // weight 10 vs. weight 1, so about 10000 vs. 1000 picks
+        let frequent = counts["FREQUENT"];
+        let first = counts["FIRST"];
+        assert_eq!(frequent + first, 11_000);
+        assert!(
+            (8..=12).contains(&(frequent / first)),
+            "Expected about 10x more picks of FREQUENT than FIRST: {counts:?}"
+        );
+    }
+
+    #[test]
+    fn unweighted_strategy_ignores_weights() {
+        let ctx = Arc::new(NoContext);
+        let strategy = WeightedCommands::strategy(ctx, false);
+        let mut runner = TestRunner::deterministic();
+
+        let mut counts = BTreeMap::new();
+        for _ in 0..10_000 {
+            let command = strategy.new_tree(&mut runner).unwrap().current();
+            *counts.entry(command.command.label()).or_insert(0u32) += 1;
+        }
+        // equally likely, so about 5000 picks each
+        let frequent = counts["FREQUENT"];
+        let first = counts["FIRST"];
+        assert!(
+            (4_500..=5_500).contains(&frequent) && (4_500..=5_500).contains(&first),
+            "Expected FREQUENT and FIRST to be picked equally often: {counts:?}"
+        );
+    }
+
+    #[test]
+    fn commands_that_cannot_generate_are_never_executed() {
+        let ctx = Arc::new(NoContext);
+        let strategy = PartlyImpossibleCommands::build(ctx);
+        let mut runner = TestRunner::deterministic();
+
+        for _ in 0..50 {
+            let commands: Vec<_> = (0..20)
+                .map(|_| strategy.new_tree(&mut runner).unwrap().current())
+                .collect();
+            let executed = execute_commands(&commands, &mut EmptyState);
+            assert_eq!(executed.len(), commands.len());
+            assert!(executed.iter().all(|cmd| cmd.command.label() == "FIRST"));
+        }
+    }
+}
@@ -0,0 +1,251 @@
+use std::collections::BTreeMap;
+use std::fs;
+use std::path::Path;
+use std::sync::Arc;
+
+use madhouse::{CommandWrapper, State, TestContext};
+use proptest::prelude::RngCore;
+use proptest::strategy::{BoxedStrategy, Strategy, ValueTree};
+use proptest::test_runner::{Config, RngAlgorithm, TestRng, TestRunner};
+use stacks_common::util::hash::{hex_bytes, to_hex};
+
+use super::execution::{execute_commands, StateSnapshot};
+
+/// Env var naming the file that `scenario!` records the commands of its run to
+pub const SEED_FILE_ENV: &str = "MADHOUSE_SEED_FILE";
+
+/// Env var naming a seed file that `scenario!` replays instead of building new commands
+pub const REPLAY_FILE_ENV: &str = "MADHOUSE_REPLAY_FILE";
+
+/// Builds the strategy for a command
+pub type CommandConstructor<S, C> = Arc<dyn Fn(Arc<C>) -> BoxedStrategy<CommandWrapper<S, C>>>;
+
+/// Constructor of each command in a set, by command name. See `register_commands!`.
+pub type CommandConstructors<S, C> = BTreeMap<&'static str, CommandConstructor<S, C>>;
+
+/// How a command was built: the name of its constructor, and the seed of the RNG its strategy
+/// drew from
+#[derive(Debug, Clone, PartialEq)]
+pub struct CommandSeed {
+    pub name: String,
+    pub seed: [u8; 32],
+}
+
+/// Build the command named `name` from `seed`. The same name and seed always build the same
+/// command.
+pub fn build_seeded<S: State, C: TestContext>(
+    constructors: &CommandConstructors<S, C>,
+    ctx: Arc<C>,
+    name: &str,
+    seed: &[u8; 32],
+) -> CommandWrapper<S, C> {
+    let build = constructors
+        .get(name)
+        .unwrap_or_else(|| panic!("No command named {name} in the command set"));
+    let rng = TestRng::from_seed(RngAlgorithm::ChaCha, seed);
+    let mut runner = TestRunner::new_with_rng(Config::default(), rng);
+    build(ctx)
+        .new_tree(&mut runner)
+        .unwrap_or_else(|e| panic!("Failed to build command {name}: {e}"))
+        .current()
+}
+
+/// Build the commands named `names`, in order, drawing each command's seed from `runner`.
+/// Returns the commands, and how each was built.
+pub fn build_commands<S: State, C: TestContext>(
+    constructors: &CommandConstructors<S, C>,
+    ctx: Arc<C>,
+    names: &[&str],
+    runner: &mut TestRunner,
+) -> (Vec<CommandWrapper<S, C>>, Vec<CommandSeed>) {
+    let mut commands = Vec::with_capacity(names.len());
+    let mut seeds = Vec::with_capacity(names.len());
+    for name in names {
+        let mut seed = [0u8; 32];
+        runner.rng().fill_bytes(&mut seed);
+        commands.push(build_seeded(constructors, ctx.clone(), name, &seed));
+        seeds.push(CommandSeed {
+            name: name.to_string(),
+            seed,
+        });
+    }
+    (commands, seeds)
+}
+
+/// Record `commands` to `seed_file`, so `replay_commands` can run them again. `seeds` says how
+/// each of `commands` was built, as returned by `build_commands`. This is written before the
+/// commands run, so a run that fails partway is recorded too.
+pub fn record_commands<S: State, C: TestContext>(
+    seed_file: &Path,
+    commands: &[CommandWrapper<S, C>],
+    seeds: &[CommandSeed],
+) {
+    assert_eq!(commands.len(), seeds.len(), "Every command needs a seed");
+    // one line per command: constructor name, seed, and label
+    let mut lines = String::new();
+    for (cmd, CommandSeed { name, seed }) in commands.iter().zip(seeds) {
+        lines.push_str(&format!(
+            "{name}\t{}\t{}\n",
+            to_hex(seed),
+            cmd.command.label()
+        ));
+    }
+    fs::write(seed_file, lines)
+        .unwrap_or_else(|e| panic!("Failed to write seed file {}: {e}", seed_file.display()));
+}
+
+/// Rebuild the commands recorded in `seed_file` by `record_commands`, and run them against
+/// `state` in the same order. Returns the labels of the commands that ran, which are those of
+/// the recorded run: `check` skips the same commands, since it sees the same states.
+/// Panics if a rebuilt command's label differs from the recorded one.
+pub fn replay_commands<S: StateSnapshot + 'static, C: TestContext>(
+    seed_file: &Path,
+    constructors: &CommandConstructors<S, C>,
+    ctx: Arc<C>,
+    state: &mut S,
+) -> Vec<String> {
+    let contents = fs::read_to_string(seed_file)
+        .unwrap_or_else(|e| panic!("Failed to read seed file {}: {e}", seed_file.display()));
+
+    let mut commands = vec![];
+    for line in contents.lines() {
+        let mut fields = line.splitn(3, '\t');
+        let (Some(name), Some(seed_hex), Some(label)) =
+            (fields.next(), fields.next(), fields.next())
+        else {
+            panic!("Malformed seed file line: {line:?}");
+        };
+        let seed: [u8; 32] = hex_bytes(seed_hex)
+            .ok()
+            .and_then(|bytes| bytes.try_into().ok())
+            .unwrap_or_else(|| panic!("Malformed seed in seed file line: {line:?}"));
+        let command = build_seeded(constructors, ctx.clone(), name, &seed);
+        assert_eq!(
+            command.command.label(),
+            label,
+            "Command {name} rebuilt from its seed does not match the recorded command"
+        );
+        commands.push(command);
+    }
+
+    execute_commands(&commands, state)
+        .into_iter()
+        .map(|cmd| cmd.command.label())
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Arc;
+
+    use madhouse::{Command, CommandWrapper, State, TestContext};
+    use proptest::prelude::Strategy;
+    use proptest::test_runner::TestRunner;
+
+    use super::*;
+    use crate::tests::signer::commands::{register_commands, CommandSelection};
+
+    #[derive(Debug, Default)]
+    struct CounterState {
+        total: u64,
+    }
+
+    impl State for CounterState {}
+
+    impl StateSnapshot for CounterState {}
+
+    #[derive(Debug, Clone)]
+    struct NoContext;
+
+    impl TestContext for NoContext {}
+
+    /// Command that adds its amount to the counter
+    struct AddAmount(u64);
+
+    impl Command<CounterState, NoContext> for AddAmount {
+        fn check(&self, _state: &CounterState) -> bool {
+            true
+        }
+
+        fn apply(&self, state: &mut CounterState) {
+            state.total += self.0;
+        }
+
+        fn label(&self) -> String {
+            format!("ADD_{}", self.0)
+        }
+
+        fn build(
+            _ctx: Arc<NoContext>,
+        ) -> impl Strategy<Value = CommandWrapper<CounterState, NoContext>> {
+            (1u64..1000).prop_map(|amount| CommandWrapper::new(AddAmount(amount)))
+        }
+    }
+
+    /// Command that subtracts its amount from the counter, if the counter is high enough
+    struct SubtractAmount(u64);
+
+    impl Command<CounterState, NoContext> for SubtractAmount {
+        fn check(&self, state: &CounterState) -> bool {
+            state.total >= self.0
+        }
+
+        fn apply(&self, state: &mut CounterState) {
+            state.total -= self.0;
+        }
+
+        fn label(&self) -> String {
+            format!("SUBTRACT_{}", self.0)
+        }
+
+        fn build(
+            _ctx: Arc<NoContext>,
+        ) -> impl Strategy<Value = CommandWrapper<CounterState, NoContext>> {
+            (1u64..1000).prop_map(|amount| CommandWrapper::new(SubtractAmount(amount)))
+        }
+    }
+
+    impl CommandSelection<CounterState, NoContext> for AddAmount {}
+    impl CommandSelection<CounterState, NoContext> for SubtractAmount {}
+
+    register_commands! {
+        CounterCommands<CounterState, NoContext> {
+            AddAmount,
+            SubtractAmount,
+        }
+    }
+
+    #[test]
+    fn replay_runs_the_recorded_commands() {
+        let ctx = Arc::new(NoContext);
+        let constructors = CounterCommands::constructors();
+        let mut runner = TestRunner::deterministic();
+        let names = CounterCommands::COMMANDS.repeat(25);
+        let (commands, seeds) = build_commands(&constructors, ctx.clone(), &names, &mut runner);
+
+        let seed_file = tempfile::NamedTempFile::new().unwrap();
+        record_commands(seed_file.path(), &commands, &seeds);
+        let mut state = CounterState::default();
+        let labels: Vec<_> = execute_commands(&commands, &mut state)
+            .into_iter()
+            .map(|cmd| cmd.command.label())
+            .collect();
+        assert!(!labels.is_empty());
+
+        let mut replayed_state = CounterState::default();
+        let replayed_labels =
+            replay_commands(seed_file.path(), &constructors, ctx, &mut replayed_state);
+        assert_eq!(labels, replayed_labels);
+        assert_eq!(state.total, replayed_state.total);
+    }
+
+    #[test]
+    fn same_seed_builds_same_command() {
+        let ctx = Arc::new(NoContext);
+        let constructors = CounterCommands::constructors();
+        let seed = [7u8; 32];
+        let first = build_seeded(&constructors, ctx.clone(), "AddAmount", &seed);
+        let second = build_seeded(&constructors, ctx, "AddAmount", &seed);
+        assert_eq!(first.command.label(), second.command.label());
+    }
+}
@@ -0,0 +1,122 @@
+use std::sync::Arc;
+
+use madhouse::{Command, CommandWrapper};
+use proptest::prelude::{Just, Strategy};
+use stacks::chainstate::stacks::boot::RewardSet;
+
+use super::context::{SignerTestContext, SignerTestState};
+use super::CommandSelection;
+use crate::tests::nakamoto_integrations::get_stacker_set;
+
+/// Command to check that the reward set does not change within a reward cycle.
+///
+/// The first time it runs in a reward cycle, it records that cycle's reward set in the test
+/// state. Every later run in the same cycle asserts that the node still reports the recorded
+/// reward set. The current cycle comes from the context's `Burnchain`.
+pub struct ChainAssertRewardSetStable {
+    ctx: Arc<SignerTestContext>,
+}
+
+impl ChainAssertRewardSetStable {
+    pub fn new(ctx: Arc<SignerTestContext>) -> Self {
+        Self { ctx }
+    }
+}
+
+/// Record `reward_set` as the reward set of `reward_cycle` if `state` has none recorded for that
+/// cycle yet, and otherwise assert that it matches the recorded one.
+fn record_or_check_reward_set(
+    state: &mut SignerTestState,
+    reward_cycle: u64,
+    reward_set: RewardSet,
+) {
+    match state.observed_reward_set {
+        Some((observed_cycle, ref observed_reward_set)) if observed_cycle == reward_cycle => {
+            assert_eq!(
+                observed_reward_set, &reward_set,
+                "Reward set of cycle {reward_cycle} changed within the cycle"
+            );
+        }
+        _ => {
+            info!("Recording the reward set of cycle {reward_cycle}");
+            state.observed_reward_set = Some((reward_cycle, reward_set));
+        }
+    }
+}
+
+impl Command<SignerTestState, SignerTestContext> for ChainAssertRewardSetStable {
+    fn check(&self, state: &SignerTestState) -> bool {
+        info!(
+            "Checking: Asserting reward set stability. Result: {:?}",
+            state.is_booted_to_nakamoto
+        );
+        // the node only serves Nakamoto reward sets
+        state.is_booted_to_nakamoto
+    }
+
+    fn apply(&self, state: &mut SignerTestState) {
+        let reward_cycle = self.ctx.current_reward_cycle(state);
+        info!("Applying: Asserting the reward set of cycle {reward_cycle} is stable");
+
+        let conf = self.ctx.get_node_config(1);
+        let http_origin = format!("http://{}", &conf.node.rpc_bind);
+        let reward_set = get_stacker_set(&http_origin, reward_cycle)
+            .expect("Failed to get the reward set")
+            .stacker_set;
+
+        record_or_check_reward_set(state, reward_cycle, reward_set);
+    }
+
+    fn label(&self) -> String {
+        "ASSERT_REWARD_SET_STABLE".to_string()
+    }
+
+    fn build(
+        ctx: Arc<SignerTestContext>,
+    ) -> impl Strategy<Value = CommandWrapper<SignerTestState, SignerTestContext>> {
+        Just(CommandWrapper::new(ChainAssertRewardSetStable::new(ctx)))
+    }
+}
+
+impl CommandSelection<SignerTestState, SignerTestContext> for ChainAssertRewardSetStable {}
+
+#[cfg(test)]
+mod tests {
+    use stacks::chainstate::stacks::address::PoxAddress;
+
+    use super::*;
+
+    #[test]
+    fn unchanged_reward_set_is_accepted() {
+        let mut state = SignerTestState::default();
+        record_or_check_reward_set(&mut state, 5, RewardSet::empty());
+        record_or_check_reward_set(&mut state, 5, RewardSet::empty());
+        assert_eq!(state.observed_reward_set, Some((5, RewardSet::empty())));
+    }
+
+    #[test]
+    fn new_cycle_records_new_reward_set() {
+        let mut state = SignerTestState::default();
+        record_or_check_reward_set(&mut state, 5, RewardSet::empty());
+
+        let mut next_reward_set = RewardSet::empty();
+        next_reward_set
+            .rewarded_addresses
+            .push(PoxAddress::standard_burn_address(false));
+        record_or_check_reward_set(&mut state, 6, next_reward_set.clone());
+        assert_eq!(state.observed_reward_set, Some((6, next_reward_set)));
+    }
+
+    #[test]
+    #[should_panic(expected = "Reward set of cycle 5 changed within the cycle")]
+    fn mutated_reward_set_is_caught() {
+        let mut state = SignerTestState::default();
+        record_or_check_reward_set(&mut state, 5, RewardSet::empty());
+
+        let mut mutated_reward_set = RewardSet::empty();
+        mutated_reward_set
+            .rewarded_addresses
+            .push(PoxAddress::standard_burn_address(false));
+        record_or_check_reward_set(&mut state, 5, mutated_reward_set);
+    }
+}
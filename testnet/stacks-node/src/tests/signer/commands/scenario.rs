@@ -0,0 +1,706 @@
+use std::cell::RefCell;
+use std::env;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+
+use madhouse::{Command, CommandWrapper, State, TestContext};
+use proptest::collection::vec;
+use proptest::prelude::any;
+use proptest::strategy::{BoxedStrategy, Strategy};
+use proptest::test_runner::{Config, RngAlgorithm, TestCaseError, TestError, TestRng, TestRunner};
+
+use super::execution::{execute_commands, try_execute_commands, StateSnapshot};
+use super::registry::CommandSelection;
+use super::replay::{
+    build_commands, build_seeded, record_commands, replay_commands, CommandConstructors,
+    CommandSeed, REPLAY_FILE_ENV, SEED_FILE_ENV,
+};
+
+/// Env var that puts scenarios in random mode when set to `1`
+const MADHOUSE_ENV: &str = "MADHOUSE";
+
+/// Most commands that a random-mode scenario runs
+const MAX_RANDOM_COMMANDS: usize = 16;
+
+/// Whether scenarios run in random (`MADHOUSE=1`) mode rather than deterministic mode
+pub fn is_random_mode() -> bool {
+    env::var(MADHOUSE_ENV).as_deref() == Ok("1")
+}
+
+/// The commands listed in a `scenario!`. Built by `scenario!`.
+pub struct ScenarioCommands<S: State, C: TestContext> {
+    /// Name and weight of each listed command, in the order they are listed
+    items: Vec<(&'static str, u32)>,
+    /// Constructor of each listed command, by name
+    constructors: CommandConstructors<S, C>,
+}
+
+impl<S: State, C: TestContext> Default for ScenarioCommands<S, C> {
+    fn default() -> Self {
+        Self {
+            items: vec![],
+            constructors: CommandConstructors::new(),
+        }
+    }
+}
+
+impl<S: StateSnapshot + Default + 'static, C: TestContext> ScenarioCommands<S, C> {
+    /// List the command called `name`, whose strategy `build` makes, picked with weight
+    /// `weight` in random mode. Items with the same name are the same command, so only the
+    /// first one's `build` is kept.
+    pub fn push(
+        &mut self,
+        name: &'static str,
+        weight: u32,
+        build: impl Fn(Arc<C>) -> BoxedStrategy<CommandWrapper<S, C>> + 'static,
+    ) {
+        assert!(weight > 0, "Command {name} has weight 0");
+        self.items.push((name, weight));
+        self.constructors
+            .entry(name)
+            .or_insert_with(|| Arc::new(build));
+    }
+
+    /// List the command type `T`, built with its `Command::build` and picked with its
+    /// `CommandSelection::weight` in random mode. `T` is left out, in both modes, unless it
+    /// `CommandSelection::can_generate` from the default state every case starts from.
+    pub fn push_command<T>(&mut self, name: &'static str, ctx: &C)
+    where
+        T: Command<S, C> + CommandSelection<S, C> + 'static,
+    {
+        if !T::can_generate(&S::default()) {
+            info!("Leaving out scenario command {name}, which cannot be generated");
+            return;
+        }
+        self.push(name, T::weight(ctx), |ctx| T::build(ctx).boxed());
+    }
+
+    /// Strategy for the commands of a random run: up to `MAX_RANDOM_COMMANDS` picks, each a
+    /// point in the total weight of the items (see `name_at`) and the seed to build it from
+    fn random_strategy(&self) -> impl Strategy<Value = Vec<(u64, [u8; 32])>> {
+        let total_weight: u64 = self
+            .items
+            .iter()
+            .map(|(_, weight)| u64::from(*weight))
+            .sum();
+        assert!(
+            total_weight > 0,
+            "A random scenario needs at least one command that can be generated"
+        );
+        vec(
+            (0..total_weight, any::<[u8; 32]>()),
+            1..=MAX_RANDOM_COMMANDS,
+        )
+    }
+
+    /// The name of the item at `point` in the total weight of the items, so that each item is
+    /// picked with probability proportional to its weight
+    fn name_at(&self, mut point: u64) -> &'static str {
+        for (name, weight) in self.items.iter() {
+            if point < u64::from(*weight) {
+                return *name;
+            }
+            point -= u64::from(*weight);
+        }
+        panic!("BUG: point is past the total weight of the items");
+    }
+
+    /// Run `cases` independent cases of the scenario, each on a fresh default state. If
+    /// `MADHOUSE_REPLAY_FILE` is set, run the commands recorded in that file once instead of
+    /// building new ones. Otherwise, in deterministic mode, each case runs each listed item once,
+    /// in order, with commands built from a seed that only depends on the case's index. In random
+    /// (`MADHOUSE=1`) mode, each case runs a random sequence of the items. If
+    /// `MADHOUSE_SEED_FILE` is set, runs are recorded to it so that they can be replayed: each
+    /// case's whole sequence in deterministic mode, which leaves the last case that ran, and the
+    /// minimal failing sequence if a random case fails.
+    pub fn run(&self, ctx: Arc<C>, cases: u32) {
+        assert!(cases > 0, "A scenario needs at least one case");
+        let seed_file = env::var(SEED_FILE_ENV).ok().map(PathBuf::from);
+        if let Ok(replay_file) = env::var(REPLAY_FILE_ENV) {
+            self.run_replay(ctx, Path::new(&replay_file));
+        } else if is_random_mode() {
+            let config = Config {
+                cases,
+                failure_persistence: None,
+                ..Config::default()
+            };
+            self.run_random(ctx, seed_file.as_deref(), TestRunner::new(config));
+        } else {
+            for case in 0..cases {
+                info!("Running scenario case {} of {cases}", case + 1);
+                self.run_listed(ctx.clone(), seed_file.as_deref(), case);
+            }
+        }
+    }
+
+    /// Run the commands recorded in `replay_file`
+    fn run_replay(&self, ctx: Arc<C>, replay_file: &Path) {
+        info!("Replaying scenario commands from {}", replay_file.display());
+        let mut state = S::default();
+        let executed = replay_commands(replay_file, &self.constructors, ctx, &mut state);
+        info!("Replayed scenario commands: {executed:?}");
+    }
+
+    /// Run each listed item once, in order, whatever its weight, with commands built from the
+    /// seed of case `case`. A command that fails panics with the context `execute_commands` adds.
+    fn run_listed(&self, ctx: Arc<C>, seed_file: Option<&Path>, case: u32) {
+        let mut case_seed = [0u8; 32];
+        case_seed[..4].copy_from_slice(&case.to_be_bytes());
+        let rng = TestRng::from_seed(RngAlgorithm::ChaCha, &case_seed);
+        let mut runner = TestRunner::new_with_rng(Config::default(), rng);
+        let names: Vec<_> = self.items.iter().map(|(name, _)| *name).collect();
+        let (commands, seeds) = build_commands(&self.constructors, ctx, &names, &mut runner);
+        let labels: Vec<_> = commands.iter().map(|cmd| cmd.command.label()).collect();
+        info!("Running scenario commands: {labels:?}");
+        if let Some(seed_file) = seed_file {
+            record_commands(seed_file, &commands, &seeds);
+        }
+
+        let mut state = S::default();
+        let executed = execute_commands(&commands, &mut state);
+        info!(
+            "Executed {} of {} scenario commands",
+            executed.len(),
+            commands.len()
+        );
+    }
+
+    /// Run the random sequences of items that `runner` generates. If one fails, proptest
+    /// shrinks it by running simpler sequences against the same test context, and this panics
+    /// with the minimal failing sequence: only the commands that executed, in order, then the one
+    /// that failed. Commands that `check` skipped are left out, since they have no effect.
+    fn run_random(&self, ctx: Arc<C>, seed_file: Option<&Path>, mut runner: TestRunner) {
+        // proptest reports the last failing case it ran, which is the minimal one
+        let last_failure = RefCell::new(None);
+        let result = runner.run(&self.random_strategy(), |picks| {
+            let seeds: Vec<_> = picks
+                .into_iter()
+                .map(|(point, seed)| CommandSeed {
+                    name: self.name_at(point).to_string(),
+                    seed,
+                })
+                .collect();
+            let commands: Vec<_> = seeds
+                .iter()
+                .map(|CommandSeed { name, seed }| {
+                    build_seeded(&self.constructors, ctx.clone(), name, seed)
+                })
+                .collect();
+            let labels: Vec<_> = commands.iter().map(|cmd| cmd.command.label()).collect();
+            info!("Running scenario commands: {labels:?}");
+
+            let mut state = S::default();
+            match try_execute_commands(&commands, &mut state) {
+                Ok(executed) => {
+                    info!(
+                        "Executed {} of {} scenario commands",
+                        executed.len(),
+                        commands.len()
+                    );
+                    Ok(())
+                }
+                Err(failure) => {
+                    let message = failure.to_string();
+                    *last_failure.borrow_mut() = Some((commands, seeds, failure));
+                    Err(TestCaseError::fail(message))
+                }
+            }
+        });
+
+        match result {
+            Ok(()) => {}
+            Err(TestError::Fail(..)) => {
+                let (commands, seeds, failure) = last_failure
+                    .into_inner()
+                    .expect("BUG: proptest reported a failure that was not recorded");
+                let mut report = String::from("Minimal failing sequence:");
+                for index in failure.executed.iter() {
+                    report.push_str(&format!(
+                        "\n  executed: {}",
+                        commands[*index].command.label()
+                    ));
+                }
+                report.push_str(&format!("\n  failed: {}", failure.label));
+
+                if let Some(seed_file) = seed_file {
+                    let kept: Vec<_> = failure
+                        .executed
+                        .iter()
+                        .chain([&failure.index])
+                        .copied()
+                        .collect();
+                    let kept_commands: Vec<_> =
+                        kept.iter().map(|index| commands[*index].clone()).collect();
+                    let kept_seeds: Vec<_> =
+                        kept.iter().map(|index| seeds[*index].clone()).collect();
+                    record_commands(seed_file, &kept_commands, &kept_seeds);
+                }
+                panic!("{report}\n{failure}");
+            }
+            Err(TestError::Abort(reason)) => panic!("Scenario aborted: {reason}"),
+        }
+    }
+}
+
+/// Run a scenario: build the listed commands and execute them against the test context `ctx`
+/// with this module's `execute_commands`, so that a failing command reports its index and the
+/// commands before it, and the state is restored to how it was before that command.
+///
+/// Each item is either a command type, built with its `Command::build` (a set defined with
+/// `register_commands!` is a command type too), or a command instance in parentheses. A command
+/// type that does not `CommandSelection::can_generate` from the default state is left out. In
+/// deterministic mode every item runs once, in the order listed, and weights are ignored. In
+/// random (`MADHOUSE=1`) mode, a random sequence of the items runs instead, where a command type
+/// is picked with probability proportional to its `CommandSelection::weight`, and a command
+/// instance with weight 1. A failing random run is shrunk, and reported as the minimal failing
+/// sequence of the commands that executed. Set `MADHOUSE_SEED_FILE` to record the run, and
+/// `MADHOUSE_REPLAY_FILE` to run a recorded run again.
+///
+/// ```ignore
+/// scenario![
+///     test_context,
+///     ChainBootToEpoch3,
+///     (MinerMineBitcoinBlocks::one(test_context.clone())),
+///     ChainShutdownMiners
+/// ]
+/// ```
+macro_rules! scenario {
+    (@push $commands:ident, $ctx:ident, ($cmd:expr)) => {
+        let command = ::madhouse::CommandWrapper::new($cmd);
+        $commands.push(stringify!($cmd), 1, move |_ctx| {
+            ::proptest::strategy::Strategy::boxed(::proptest::strategy::Just(command.clone()))
+        });
+    };
+    (@push $commands:ident, $ctx:ident, $cmd:ident) => {
+        $commands.push_command::<$cmd>(stringify!($cmd), &*$ctx);
+    };
+    (@run $ctx:expr, $cases:expr, $($item:tt),+) => {{
+        let ctx = ::std::sync::Arc::clone(&$ctx);
+        let mut commands = $crate::tests::signer::commands::scenario::ScenarioCommands::default();
+        $($crate::tests::signer::commands::scenario!(@push commands, ctx, $item);)+
+        commands.run(ctx, $cases);
+    }};
+    [$ctx:expr, $($item:tt),+ $(,)?] => {
+        $crate::tests::signer::commands::scenario!(@run $ctx, 1, $($item),+)
+    };
+}
+
+/// Like `scenario!`, but run `$cases` independent cases, each on a fresh default state. In
+/// deterministic mode, every case runs each listed item once, in order, and commands that draw
+/// from their strategy (such as a `register_commands!` set) are built from a seed that only
+/// depends on the case's index, so cases differ from each other but not from run to run. In
+/// random (`MADHOUSE=1`) mode, every case runs its own random sequence of the items. Every case
+/// runs against the same test context.
+///
+/// ```ignore
+/// scenario_n![test_context, 3, ChainBootToEpoch3, ChainAdvanceCommands, ChainShutdownMiners]
+/// ```
+macro_rules! scenario_n {
+    [$ctx:expr, $cases:expr, $($item:tt),+ $(,)?] => {
+        $crate::tests::signer::commands::scenario!(@run $ctx, $cases, $($item),+)
+    };
+}
+
+pub(crate) use {scenario, scenario_n};
+
+#[cfg(test)]
+mod tests {
+    use std::fs;
+    use std::panic::{self, AssertUnwindSafe};
+    use std::sync::{Arc, Mutex};
+
+    use madhouse::{Command, CommandWrapper, State, TestContext};
+    use proptest::prelude::{any, Just, Strategy};
+    use proptest::strategy::ValueTree;
+    use proptest::test_runner::{Config, RngAlgorithm, TestRng, TestRunner};
+
+    use super::ScenarioCommands;
+    use crate::tests::signer::commands::execution::StateSnapshot;
+    use crate::tests::signer::commands::CommandSelection;
+
+    #[derive(Debug, Default)]
+    struct EmptyState;
+
+    impl State for EmptyState {}
+
+    impl StateSnapshot for EmptyState {}
+
+    /// Context that records the label of each command that ran
+    #[derive(Debug, Clone, Default)]
+    struct LogContext {
+        log: Arc<Mutex<Vec<String>>>,
+    }
+
+    impl TestContext for LogContext {}
+
+    /// Command that logs its name to the context
+    struct Log {
+        ctx: Arc<LogContext>,
+        name: &'static str,
+    }
+
+    impl Command<EmptyState, LogContext> for Log {
+        fn check(&self, _state: &EmptyState) -> bool {
+            true
+        }
+
+        fn apply(&self, _state: &mut EmptyState) {
+            self.ctx.log.lock().unwrap().push(self.name.to_string());
+        }
+
+        fn label(&self) -> String {
+            self.name.to_string()
+        }
+
+        fn build(
+            ctx: Arc<LogContext>,
+        ) -> impl Strategy<Value = CommandWrapper<EmptyState, LogContext>> {
+            Just(CommandWrapper::new(Log { ctx, name: "LOG" }))
+        }
+    }
+
+    impl CommandSelection<EmptyState, LogContext> for Log {
+        fn weight(_ctx: &LogContext) -> u32 {
+            10
+        }
+    }
+
+    /// Command whose `apply` always fails
+    struct Fail;
+
+    impl Command<EmptyState, LogContext> for Fail {
+        fn check(&self, _state: &EmptyState) -> bool {
+            true
+        }
+
+        fn apply(&self, _state: &mut EmptyState) {
+            panic!("boom");
+        }
+
+        fn label(&self) -> String {
+            "FAIL".into()
+        }
+
+        fn build(
+            _ctx: Arc<LogContext>,
+        ) -> impl Strategy<Value = CommandWrapper<EmptyState, LogContext>> {
+            Just(CommandWrapper::new(Fail))
+        }
+    }
+
+    impl CommandSelection<EmptyState, LogContext> for Fail {}
+
+    #[derive(Debug, Default)]
+    struct BumpState {
+        bumps: u32,
+    }
+
+    impl State for BumpState {}
+
+    impl StateSnapshot for BumpState {}
+
+    /// Command that bumps the state
+    struct Bump;
+
+    impl Command<BumpState, LogContext> for Bump {
+        fn check(&self, _state: &BumpState) -> bool {
+            true
+        }
+
+        fn apply(&self, state: &mut BumpState) {
+            state.bumps += 1;
+        }
+
+        fn label(&self) -> String {
+            "BUMP".into()
+        }
+
+        fn build(
+            _ctx: Arc<LogContext>,
+        ) -> impl Strategy<Value = CommandWrapper<BumpState, LogContext>> {
+            Just(CommandWrapper::new(Bump))
+        }
+    }
+
+    impl CommandSelection<BumpState, LogContext> for Bump {}
+
+    /// Command that `check` always skips
+    struct Never;
+
+    impl Command<BumpState, LogContext> for Never {
+        fn check(&self, _state: &BumpState) -> bool {
+            false
+        }
+
+        fn apply(&self, _state: &mut BumpState) {
+            unreachable!("NEVER is never applied");
+        }
+
+        fn label(&self) -> String {
+            "NEVER".into()
+        }
+
+        fn build(
+            _ctx: Arc<LogContext>,
+        ) -> impl Strategy<Value = CommandWrapper<BumpState, LogContext>> {
+            Just(CommandWrapper::new(Never))
+        }
+    }
+
+    impl CommandSelection<BumpState, LogContext> for Never {}
+
+    /// Command that runs once the state was bumped, and then fails
+    struct FailAfterBump;
+
+    impl Command<BumpState, LogContext> for FailAfterBump {
+        fn check(&self, state: &BumpState) -> bool {
+            state.bumps > 0
+        }
+
+        fn apply(&self, state: &mut BumpState) {
+            panic!("failed after {} bumps", state.bumps);
+        }
+
+        fn label(&self) -> String {
+            "FAIL_AFTER_BUMP".into()
+        }
+
+        fn build(
+            _ctx: Arc<LogContext>,
+        ) -> impl Strategy<Value = CommandWrapper<BumpState, LogContext>> {
+            Just(CommandWrapper::new(FailAfterBump))
+        }
+    }
+
+    impl CommandSelection<BumpState, LogContext> for FailAfterBump {}
+
+    /// Command that logs the number it was built with, and asserts that it runs on a fresh state
+    struct LogFreshRun {
+        ctx: Arc<LogContext>,
+        number: u64,
+    }
+
+    impl Command<BumpState, LogContext> for LogFreshRun {
+        fn check(&self, _state: &BumpState) -> bool {
+            true
+        }
+
+        fn apply(&self, state: &mut BumpState) {
+            assert_eq!(state.bumps, 0, "State was not fresh");
+            state.bumps += 1;
+            self.ctx.log.lock().unwrap().push(self.number.to_string());
+        }
+
+        fn label(&self) -> String {
+            format!("LOG_FRESH_RUN_{}", self.number)
+        }
+
+        fn build(
+            ctx: Arc<LogContext>,
+        ) -> impl Strategy<Value = CommandWrapper<BumpState, LogContext>> {
+            any::<u64>().prop_map(move |number| {
+                CommandWrapper::new(LogFreshRun {
+                    ctx: ctx.clone(),
+                    number,
+                })
+            })
+        }
+    }
+
+    impl CommandSelection<BumpState, LogContext> for LogFreshRun {}
+
+    /// Command that logs its name to the context, but is never generated
+    struct Ungenerable {
+        ctx: Arc<LogContext>,
+    }
+
+    impl Command<EmptyState, LogContext> for Ungenerable {
+        fn check(&self, _state: &EmptyState) -> bool {
+            true
+        }
+
+        fn apply(&self, _state: &mut EmptyState) {
+            self.ctx.log.lock().unwrap().push("UNGENERABLE".into());
+        }
+
+        fn label(&self) -> String {
+            "UNGENERABLE".into()
+        }
+
+        fn build(
+            ctx: Arc<LogContext>,
+        ) -> impl Strategy<Value = CommandWrapper<EmptyState, LogContext>> {
+            Just(CommandWrapper::new(Ungenerable { ctx }))
+        }
+    }
+
+    impl CommandSelection<EmptyState, LogContext> for Ungenerable {
+        fn weight(_ctx: &LogContext) -> u32 {
+            100
+        }
+
+        fn can_generate(_state: &EmptyState) -> bool {
+            false
+        }
+    }
+
+    #[test]
+    fn scenario_runs_items_in_order() {
+        let ctx = Arc::new(LogContext::default());
+        scenario![
+            ctx,
+            (Log {
+                ctx: ctx.clone(),
+                name: "FIRST"
+            }),
+            Log,
+            (Log {
+                ctx: ctx.clone(),
+                name: "LAST"
+            }),
+        ];
+        assert_eq!(*ctx.log.lock().unwrap(), vec!["FIRST", "LOG", "LAST"]);
+    }
+
+    #[test]
+    fn scenario_replays_its_seed_file() {
+        let ctx = Arc::new(LogContext::default());
+        let mut commands = ScenarioCommands::<EmptyState, LogContext>::default();
+        commands.push_command::<Log>("Log", &ctx);
+        commands.push("First", 1, {
+            let ctx = ctx.clone();
+            move |_| {
+                Just(CommandWrapper::new(Log {
+                    ctx: ctx.clone(),
+                    name: "FIRST",
+                }))
+                .boxed()
+            }
+        });
+        commands.push_command::<Log>("Log", &ctx);
+
+        let seed_file = tempfile::NamedTempFile::new().unwrap();
+        commands.run_listed(ctx.clone(), Some(seed_file.path()), 0);
+        let recorded = ctx.log.lock().unwrap().drain(..).collect::<Vec<_>>();
+        assert_eq!(recorded, vec!["LOG", "FIRST", "LOG"]);
+
+        commands.run_replay(ctx.clone(), seed_file.path());
+        assert_eq!(*ctx.log.lock().unwrap(), recorded);
+    }
+
+    #[test]
+    fn random_picks_follow_weights() {
+        let ctx = Arc::new(LogContext::default());
+        let mut commands = ScenarioCommands::<EmptyState, LogContext>::default();
+        commands.push_command::<Log>("Log", &ctx);
+        commands.push_command::<Fail>("Fail", &ctx);
+        let mut runner = TestRunner::deterministic();
+
+        let strategy = commands.random_strategy();
+
+        let mut logs = 0;
+        let mut fails = 0;
+        while logs + fails < 11_000 {
+            for (point, _seed) in strategy.new_tree(&mut runner).unwrap().current() {
+                match commands.name_at(point) {
+                    "Log" => logs += 1,
+                    _ => fails += 1,
+                }
+            }
+        }
+        // weight 10 vs. weight 1
+        assert!(
+            (8..=12).contains(&(logs / fails)),
+            "Expected about 10x more picks of Log than Fail: {logs} vs. {fails}"
+        );
+    }
+
+    #[test]
+    fn scenario_leaves_out_commands_that_cannot_be_generated() {
+        let ctx = Arc::new(LogContext::default());
+        scenario![ctx, Log, Ungenerable, Log];
+        assert_eq!(*ctx.log.lock().unwrap(), vec!["LOG", "LOG"]);
+
+        // in random mode too, however heavily it would be picked
+        let mut commands = ScenarioCommands::<EmptyState, LogContext>::default();
+        commands.push_command::<Log>("Log", &ctx);
+        commands.push_command::<Ungenerable>("Ungenerable", &ctx);
+        let config = Config {
+            cases: 256,
+            failure_persistence: None,
+            ..Config::default()
+        };
+        let runner =
+            TestRunner::new_with_rng(config, TestRng::deterministic_rng(RngAlgorithm::ChaCha));
+        ctx.log.lock().unwrap().clear();
+        commands.run_random(ctx.clone(), None, runner);
+        let log = ctx.log.lock().unwrap();
+        assert!(!log.is_empty());
+        assert!(log.iter().all(|name| name == "LOG"), "{log:?}");
+    }
+
+    #[test]
+    fn random_failure_reports_only_executed_commands() {
+        let ctx = Arc::new(LogContext::default());
+        let mut commands = ScenarioCommands::<BumpState, LogContext>::default();
+        commands.push_command::<Bump>("Bump", &ctx);
+        commands.push_command::<Never>("Never", &ctx);
+        commands.push_command::<FailAfterBump>("FailAfterBump", &ctx);
+        let config = Config {
+            cases: 256,
+            failure_persistence: None,
+            ..Config::default()
+        };
+        let runner =
+            TestRunner::new_with_rng(config, TestRng::deterministic_rng(RngAlgorithm::ChaCha));
+
+        let seed_file = tempfile::NamedTempFile::new().unwrap();
+        let payload = panic::catch_unwind(AssertUnwindSafe(|| {
+            commands.run_random(ctx.clone(), Some(seed_file.path()), runner)
+        }))
+        .expect_err("FAIL_AFTER_BUMP should have failed the run");
+        let message = payload
+            .downcast_ref::<String>()
+            .expect("Panic message should be a String");
+        assert!(
+            message.starts_with(
+                "Minimal failing sequence:\n  executed: BUMP\n  failed: FAIL_AFTER_BUMP\n"
+            ),
+            "{message}"
+        );
+        assert!(!message.contains("NEVER"), "{message}");
+
+        // the seed file holds the same minimal sequence
+        let recorded = fs::read_to_string(seed_file.path()).unwrap();
+        let labels: Vec<_> = recorded
+            .lines()
+            .map(|line| line.rsplit('\t').next().unwrap())
+            .collect();
+        assert_eq!(labels, vec!["BUMP", "FAIL_AFTER_BUMP"]);
+    }
+
+    #[test]
+    fn scenario_n_runs_independent_cases() {
+        let ctx = Arc::new(LogContext::default());
+        crate::tests::signer::commands::scenario_n![ctx, 3, LogFreshRun];
+        let runs = ctx.log.lock().unwrap().drain(..).collect::<Vec<_>>();
+        assert_eq!(runs.len(), 3);
+        // each case is built from its own seed...
+        assert_ne!(runs[0], runs[1]);
+        assert_ne!(runs[1], runs[2]);
+        assert_ne!(runs[0], runs[2]);
+
+        // ...which is the same from run to run
+        crate::tests::signer::commands::scenario_n![ctx, 3, LogFreshRun];
+        assert_eq!(*ctx.log.lock().unwrap(), runs);
+    }
+
+    #[test]
+    #[should_panic(
+        expected = "Command FAIL at index 1 failed: boom\nPreviously executed commands: [LOG]"
+    )]
+    fn scenario_failure_reports_its_context() {
+        let ctx = Arc::new(LogContext::default());
+        scenario![ctx, Log, Fail, Log];
+    }
+}
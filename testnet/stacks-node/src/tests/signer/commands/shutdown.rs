@@ -4,6 +4,7 @@ use madhouse::{Command, CommandWrapper};
 use proptest::prelude::{Just, Strategy};
 
 use super::context::{SignerTestContext, SignerTestState};
+use super::CommandSelection;
 
 /// Command to attempt to shut down the miner instances managed in the test context.
 /// This command is typically intended for use at the end of a test scenario or
@@ -57,3 +58,5 @@ impl Command<SignerTestState, SignerTestContext> for ChainShutdownMiners {
         Just(CommandWrapper::new(ChainShutdownMiners::new(ctx.clone())))
     }
 }
+
+impl CommandSelection<SignerTestState, SignerTestContext> for ChainShutdownMiners {}
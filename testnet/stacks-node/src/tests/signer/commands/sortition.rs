@@ -4,7 +4,7 @@ use madhouse::{Command, CommandWrapper};
 use proptest::prelude::{Just, Strategy};
 
 use super::context::SignerTestState;
-use super::SignerTestContext;
+use super::{CommandSelection, SignerTestContext};
 use crate::tests::signer::v0::verify_sortition_winner;
 
 /// Command to verify that a specific miner is correctly recorded as
@@ -57,6 +57,8 @@ impl Command<SignerTestState, SignerTestContext> for ChainExpectSortitionWinner
     }
 }
 
+impl CommandSelection<SignerTestState, SignerTestContext> for ChainExpectSortitionWinner {}
+
 /// Command to verify that a Stacks chain reorganization has occurred by comparing consensus hashes.
 /// This checks if the last sortition's consensus hash differs from the current Stacks parent consensus hash,
 /// indicating that the previously selected sortition winner is no longer part of the canonical chain.
@@ -100,3 +102,8 @@ impl Command<SignerTestState, SignerTestContext> for ChainVerifyLastSortitionWin
         ))
     }
 }
+
+impl CommandSelection<SignerTestState, SignerTestContext>
+    for ChainVerifyLastSortitionWinnerReorged
+{
+}
@@ -0,0 +1,102 @@
+use std::sync::Arc;
+
+use madhouse::{Command, CommandWrapper};
+use proptest::prelude::Strategy;
+use stacks::core::test_util::to_addr;
+use tracing::info;
+
+use super::context::{SignerTestContext, SignerTestState};
+use super::CommandSelection;
+use crate::tests::nakamoto_integrations::{wait_for, POX_4_DEFAULT_STACKER_STX_AMT};
+use crate::tests::neon_integrations::{get_account, get_chain_info, submit_tx};
+
+/// Example of a command that builds a PoX transaction through the context.
+///
+/// It has the context's stacker, funded at genesis above the stacking minimum, lock
+/// `POX_4_DEFAULT_STACKER_STX_AMT` with a `stack-stx` call signed for the reward cycle of the
+/// burnchain tip. Once the call is mined, it asserts that it returned `(ok ...)` by checking that
+/// the stacker's STX are locked. It can't run again until the previous lock-up has expired.
+pub struct StackerStackStx {
+    ctx: Arc<SignerTestContext>,
+    lock_period: u128,
+    timeout_secs: u64,
+}
+
+impl StackerStackStx {
+    pub fn new(ctx: Arc<SignerTestContext>, lock_period: u128, timeout_secs: u64) -> Self {
+        Self {
+            ctx,
+            lock_period,
+            timeout_secs,
+        }
+    }
+}
+
+impl Command<SignerTestState, SignerTestContext> for StackerStackStx {
+    fn check(&self, state: &SignerTestState) -> bool {
+        // pox-4 is only active once the chain has booted into Epoch 3.0
+        let result = state.is_booted_to_nakamoto
+            && state.stacker_unlock_cycle.map_or(true, |unlock_cycle| {
+                self.ctx.current_reward_cycle(state) >= unlock_cycle
+            });
+        info!(
+            "Checking: Stack-stx for {} cycle(s). Result: {:?}",
+            self.lock_period, result
+        );
+        result
+    }
+
+    fn apply(&self, state: &mut SignerTestState) {
+        info!("Applying: Stack-stx for {} cycle(s)", self.lock_period);
+
+        let conf = self.ctx.get_node_config(1);
+        let http_origin = format!("http://{}", &conf.node.rpc_bind);
+        let stacker_sk = self.ctx.stacker_sk();
+        let stacker_addr = to_addr(stacker_sk);
+        let nonce = get_account(&http_origin, &stacker_addr).nonce;
+
+        let stack_stx_tx = self.ctx.make_stack_stx_tx(
+            stacker_sk,
+            nonce,
+            POX_4_DEFAULT_STACKER_STX_AMT,
+            self.lock_period,
+        );
+        submit_tx(&http_origin, &stack_stx_tx);
+
+        wait_for(self.timeout_secs, || {
+            Ok(get_account(&http_origin, &stacker_addr).nonce > nonce)
+        })
+        .expect("Timed out waiting for the stack-stx tx to be mined");
+
+        // pox-4 locks the STX as soon as `stack-stx` returns `(ok ...)`, and leaves them
+        // unlocked on an `(err ...)`
+        let account = get_account(&http_origin, &stacker_addr);
+        assert_eq!(
+            account.locked, POX_4_DEFAULT_STACKER_STX_AMT,
+            "stack-stx did not return (ok ...): expected {} uSTX locked, got {}",
+            POX_4_DEFAULT_STACKER_STX_AMT, account.locked
+        );
+
+        // The lock-up starts in the cycle after the one the call was made in
+        let reward_cycle = self
+            .ctx
+            .burnchain()
+            .block_height_to_reward_cycle(get_chain_info(&conf).burn_block_height)
+            .expect("Burn block height is before the first burnchain block");
+        state.stacker_unlock_cycle = Some(reward_cycle + 1 + self.lock_period as u64);
+    }
+
+    fn label(&self) -> String {
+        format!("STACK_STX_FOR_{}_CYCLE(S)", self.lock_period)
+    }
+
+    fn build(
+        ctx: Arc<SignerTestContext>,
+    ) -> impl Strategy<Value = CommandWrapper<SignerTestState, SignerTestContext>> {
+        (1u128..=12u128).prop_map(move |lock_period| {
+            CommandWrapper::new(StackerStackStx::new(ctx.clone(), lock_period, 30))
+        })
+    }
+}
+
+impl CommandSelection<SignerTestState, SignerTestContext> for StackerStackStx {}
@@ -4,6 +4,7 @@ use madhouse::{Command, CommandWrapper};
 use proptest::prelude::{prop_oneof, Just, Strategy};
 
 use super::context::{SignerTestContext, SignerTestState};
+use super::CommandSelection;
 
 /// Command to globally pause or resume Stacks block mining within the test environment.
 /// This command is used to simulate network-wide conditions where Stacks block production might halt or resume.
@@ -67,3 +68,5 @@ impl Command<SignerTestState, SignerTestContext> for ChainStacksMining {
         ]
     }
 }
+
+impl CommandSelection<SignerTestState, SignerTestContext> for ChainStacksMining {}
@@ -4,6 +4,7 @@ use madhouse::{Command, CommandWrapper};
 use proptest::prelude::Strategy;
 
 use super::context::{SignerTestContext, SignerTestState};
+use super::CommandSelection;
 use crate::tests::neon_integrations::get_chain_info;
 
 // This command simulates sending a Stacks transfer transaction and then mining a block to confirm it.
@@ -69,3 +70,5 @@ impl Command<SignerTestState, SignerTestContext> for MinerSendAndMineStacksTrans
         })
     }
 }
+
+impl CommandSelection<SignerTestState, SignerTestContext> for MinerSendAndMineStacksTransferTx {}
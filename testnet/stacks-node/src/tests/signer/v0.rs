@@ -30,9 +30,7 @@ use libsigner::{
     BlockProposal, BlockProposalData, SignerSession, StackerDBSession, StacksBlockEvent,
     VERSION_STRING,
 };
-use madhouse::{execute_commands, prop_allof, scenario, Command, CommandWrapper};
 use pinny::tag;
-use proptest::prelude::Strategy;
 use rand::{thread_rng, Rng};
 use rusqlite::Connection;
 use stacks::address::AddressHashMode;
@@ -836,6 +834,11 @@ impl MultipleMinerTest {
         )
     }
 
+    /// The funded account used to send transactions
+    pub fn sender_sk(&self) -> &Secp256k1PrivateKey {
+        &self.sender_sk
+    }
+
     /// Sends a transfer tx to the stacks node and returns the txid and nonce used
     pub fn send_transfer_tx(&self) -> (String, u64) {
         self.signer_test
@@ -13401,6 +13404,84 @@ fn disallow_reorg_within_first_proposal_burn_block_timing_secs_but_more_than_one
     ]
 }
 
+/// Test a scenario where the context's stacker sends a `stack-stx` call built by the test context,
+/// once the chain has booted to Nakamoto, and the call locks its STX.
+#[test]
+#[ignore]
+fn stack_stx_from_context_scenario() {
+    if env::var("BITCOIND_TEST") != Ok("1".into()) {
+        return;
+    }
+
+    let num_signers = 5;
+    let num_transfer_txs = 3;
+
+    let test_context = Arc::new(SignerTestContext::new(num_signers, num_transfer_txs));
+
+    scenario![
+        test_context,
+        ChainBootToEpoch3,
+        (MinerMineBitcoinBlocks::one(test_context.clone())),
+        (StackerStackStx::new(test_context.clone(), 1, 30)), // Signs for the tip's reward cycle
+        ChainShutdownMiners
+    ]
+}
+
+/// Test a scenario where the reward set is queried repeatedly as the chain advances, and must not
+/// change within a reward cycle.
+#[test]
+#[ignore]
+fn reward_set_stable_within_cycle_scenario() {
+    if env::var("BITCOIND_TEST") != Ok("1".into()) {
+        return;
+    }
+
+    let num_signers = 5;
+    let num_transfer_txs = 3;
+
+    let test_context = Arc::new(SignerTestContext::new(num_signers, num_transfer_txs));
+
+    scenario![
+        test_context,
+        ChainBootToEpoch3,
+        ChainAssertRewardSetStable, // Records the reward set of the current cycle
+        (MinerMineBitcoinBlocks::one(test_context.clone())),
+        ChainAssertRewardSetStable,
+        (StackerStackStx::new(test_context.clone(), 1, 30)), // Only affects later cycles
+        ChainAssertRewardSetStable,
+        ChainShutdownMiners
+    ]
+}
+
+/// Test a scenario where the chain advances by commands picked from `ChainAdvanceCommands`, and
+/// pauses on the test context's clock, and the reward set must not change within a reward cycle
+/// in between.
+#[test]
+#[ignore]
+fn reward_set_stable_as_chain_advances_scenario() {
+    if env::var("BITCOIND_TEST") != Ok("1".into()) {
+        return;
+    }
+
+    let num_signers = 5;
+    let num_transfer_txs = 3;
+
+    let test_context = Arc::new(SignerTestContext::new(num_signers, num_transfer_txs));
+
+    scenario![
+        test_context,
+        ChainBootToEpoch3,
+        ChainAssertRewardSetStable,
+        ChainAdvanceCommands,
+        ChainAssertRewardSetStable,
+        ChainPause,
+        ChainAssertRewardSetStable,
+        ChainAdvanceCommands,
+        ChainAssertRewardSetStable,
+        ChainShutdownMiners
+    ]
+}
+
 #[test]
 #[ignore]
 /// This test verifies that a miner will produce a TenureExtend transaction